@@ -0,0 +1,463 @@
+use crate::output::{Output, OutputFormat, Table, Tabular};
+use anyhow::{anyhow, bail, Context, Result};
+use elf_lib::{
+    detectors::ecg::{run_beat_hrv_pipeline, EcgPipelineConfig},
+    io::loader,
+    metrics::hrv::HRVTime,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Instant, UNIX_EPOCH};
+
+pub const STATE_FILE: &str = "batch_state.json";
+
+/// Settings shared by every file; a state file written with different settings is not resumed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSettings {
+    pub signal: Option<String>,
+    pub fs: Option<f64>,
+    pub pipeline: EcgPipelineConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub input: PathBuf,
+    /// Per-file result, relative to the output directory.
+    pub output: PathBuf,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    /// Size and modification time of the input when it was processed.
+    pub input_bytes: Option<u64>,
+    pub input_modified: Option<u64>,
+    pub fs: Option<f64>,
+    pub duration_s: Option<f64>,
+    pub beats: Option<usize>,
+    pub hrv: Option<HRVTime>,
+    pub elapsed_s: Option<f64>,
+}
+
+impl BatchJob {
+    fn new(input: PathBuf, output: PathBuf) -> Self {
+        Self {
+            input,
+            output,
+            status: JobStatus::Pending,
+            error: None,
+            input_bytes: None,
+            input_modified: None,
+            fs: None,
+            duration_s: None,
+            beats: None,
+            hrv: None,
+            elapsed_s: None,
+        }
+    }
+}
+
+impl Tabular for BatchJob {
+    /// One summary row: input, status, failure reason, recording info and HRV.
+    fn table(&self) -> Result<Table> {
+        let hrv = self.hrv.as_ref();
+        let done = self.status == JobStatus::Done;
+        let mut table = Table::default();
+        table.push_row([
+            ("input".to_string(), json!(self.input.display().to_string())),
+            ("status".to_string(), json!(self.status)),
+            ("error".to_string(), json!(self.error)),
+            ("fs".to_string(), json!(self.fs)),
+            ("duration_s".to_string(), json!(self.duration_s)),
+            ("beats".to_string(), json!(self.beats)),
+            ("n".to_string(), json!(hrv.map(|h| h.n))),
+            ("avnn".to_string(), json!(hrv.map(|h| h.avnn))),
+            ("sdnn".to_string(), json!(hrv.map(|h| h.sdnn))),
+            ("rmssd".to_string(), json!(hrv.map(|h| h.rmssd))),
+            ("pnn50".to_string(), json!(hrv.map(|h| h.pnn50))),
+            (
+                "output".to_string(),
+                json!(done.then(|| self.output.display().to_string())),
+            ),
+        ]);
+        Ok(table)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchState {
+    pub version: String,
+    pub settings: BatchSettings,
+    pub jobs: Vec<BatchJob>,
+}
+
+impl BatchState {
+    fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(Some(serde_json::from_str(&text).with_context(|| {
+            format!("{} is not a batch state file", path.display())
+        })?))
+    }
+
+    /// Written to a temporary file first, so an interrupted write leaves the old state intact.
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("failed to replace {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub out_dir: PathBuf,
+    pub jobs: usize,
+    pub settings: BatchSettings,
+    /// Summary table; the format follows the extension.
+    pub summary: PathBuf,
+    pub retry_failed: bool,
+    /// Discard an existing state file instead of resuming it.
+    pub restart: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFailure {
+    pub input: PathBuf,
+    pub error: String,
+}
+
+/// What `elf batch` prints once the pool is drained.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub total: usize,
+    pub processed: usize,
+    pub resumed: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub state: PathBuf,
+    pub summary: PathBuf,
+    pub failures: Vec<BatchFailure>,
+}
+
+impl Tabular for BatchReport {}
+
+/// Recordings matching a glob pattern, in sorted order.
+pub fn inputs_from_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for entry in glob::glob(pattern).with_context(|| format!("invalid glob '{pattern}'"))? {
+        let path = entry?;
+        if path.is_file() {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// One recording per line (`#` starts a comment), or a CSV/TSV whose header has a `path`
+/// column. Relative paths are resolved against the manifest's directory.
+pub fn inputs_from_manifest(path: &Path) -> Result<Vec<PathBuf>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .peekable();
+    let delimiter = lines
+        .peek()
+        .and_then(|header| [',', '\t'].into_iter().find(|d| header.contains(*d)));
+    let column = match (delimiter, lines.peek()) {
+        (Some(d), Some(header)) => header
+            .split(d)
+            .position(|c| c.trim().eq_ignore_ascii_case("path")),
+        _ => None,
+    };
+    let entries: Vec<String> = match (delimiter, column) {
+        (Some(d), Some(idx)) => lines
+            .skip(1)
+            .filter_map(|l| l.split(d).nth(idx).map(|c| c.trim().to_string()))
+            .filter(|c| !c.is_empty())
+            .collect(),
+        (Some(_), None) => bail!("{} has no `path` column", path.display()),
+        (None, _) => lines.map(str::to_string).collect(),
+    };
+    Ok(entries.into_iter().map(|e| base.join(e)).collect())
+}
+
+/// Runs (or resumes) the batch and writes the state file, per-file results and summary.
+pub fn run_batch(inputs: &[PathBuf], options: &BatchOptions) -> Result<BatchReport> {
+    if inputs.is_empty() {
+        bail!("no recordings to process");
+    }
+    fs::create_dir_all(&options.out_dir)
+        .with_context(|| format!("failed to create {}", options.out_dir.display()))?;
+    let state_path = options.out_dir.join(STATE_FILE);
+    let mut state = match BatchState::load(&state_path)? {
+        Some(state) if !options.restart => {
+            if state.settings != options.settings {
+                bail!(
+                    "{} was written with different settings; pass --restart to start over",
+                    state_path.display()
+                );
+            }
+            state
+        }
+        _ => BatchState {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            settings: options.settings.clone(),
+            jobs: Vec::new(),
+        },
+    };
+    // Absolute paths, so a batch can be resumed from another working directory.
+    let inputs: Vec<PathBuf> = inputs
+        .iter()
+        .map(|p| {
+            fs::canonicalize(p)
+                .or_else(|_| std::path::absolute(p))
+                .unwrap_or_else(|_| p.clone())
+        })
+        .collect();
+    add_jobs(&mut state, &inputs);
+
+    let mut resumed = 0;
+    let mut queue = Vec::new();
+    for (idx, job) in state.jobs.iter_mut().enumerate() {
+        let unchanged = input_stamp(&job.input) == (job.input_bytes, job.input_modified);
+        let finished = match job.status {
+            JobStatus::Done => unchanged && options.out_dir.join(&job.output).exists(),
+            JobStatus::Failed => unchanged && !options.retry_failed,
+            JobStatus::Pending => false,
+        };
+        if finished {
+            resumed += 1;
+        } else {
+            job.status = JobStatus::Pending;
+            queue.push(idx);
+        }
+    }
+    state.save(&state_path)?;
+
+    let work: Vec<(usize, BatchJob)> = queue
+        .iter()
+        .map(|&idx| (idx, state.jobs[idx].clone()))
+        .collect();
+    let workers = options.jobs.max(1).min(work.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel::<(usize, BatchJob)>();
+    std::thread::scope(|scope| -> Result<()> {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || {
+                while let Some((idx, job)) = work.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if tx.send((*idx, process(job, options))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+        for (idx, job) in rx {
+            if let Some(error) = &job.error {
+                eprintln!("{}: {error}", job.input.display());
+            }
+            state.jobs[idx] = job;
+            state.save(&state_path)?;
+        }
+        Ok(())
+    })?;
+
+    let summary_path = options.out_dir.join(&options.summary);
+    let format = OutputFormat::from_path(&summary_path).ok_or_else(|| {
+        anyhow!(
+            "cannot tell the summary format of {}",
+            summary_path.display()
+        )
+    })?;
+    Output::new(Some(format), Some(summary_path.clone()))?.records(&state.jobs)?;
+
+    let failures: Vec<BatchFailure> = state
+        .jobs
+        .iter()
+        .filter(|j| j.status == JobStatus::Failed)
+        .map(|j| BatchFailure {
+            input: j.input.clone(),
+            error: j.error.clone().unwrap_or_default(),
+        })
+        .collect();
+    Ok(BatchReport {
+        total: state.jobs.len(),
+        processed: queue.len(),
+        resumed,
+        done: state
+            .jobs
+            .iter()
+            .filter(|j| j.status == JobStatus::Done)
+            .count(),
+        failed: failures.len(),
+        state: state_path,
+        summary: summary_path,
+        failures,
+    })
+}
+
+/// Appends inputs the state does not know yet, giving each a unique output name.
+fn add_jobs(state: &mut BatchState, inputs: &[PathBuf]) {
+    let known: HashSet<PathBuf> = state.jobs.iter().map(|j| j.input.clone()).collect();
+    let mut taken: HashSet<PathBuf> = state.jobs.iter().map(|j| j.output.clone()).collect();
+    for input in inputs {
+        if known.contains(input) {
+            continue;
+        }
+        let stem = input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("recording");
+        let mut output = PathBuf::from(format!("{stem}.json"));
+        let mut n = 2;
+        while taken.contains(&output) {
+            output = PathBuf::from(format!("{stem}-{n}.json"));
+            n += 1;
+        }
+        taken.insert(output.clone());
+        state.jobs.push(BatchJob::new(input.clone(), output));
+    }
+}
+
+fn input_stamp(path: &Path) -> (Option<u64>, Option<u64>) {
+    let Ok(meta) = fs::metadata(path) else {
+        return (None, None);
+    };
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    (Some(meta.len()), modified)
+}
+
+fn process(job: &BatchJob, options: &BatchOptions) -> BatchJob {
+    let started = Instant::now();
+    let mut job = job.clone();
+    (job.input_bytes, job.input_modified) = input_stamp(&job.input);
+    // A loader or detector that panics on one file fails that file, not the whole batch.
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| analyze(&mut job, options)))
+        .unwrap_or_else(|payload| Err(anyhow!("panicked: {}", panic_message(&*payload))));
+    match outcome {
+        Ok(()) => {
+            job.status = JobStatus::Done;
+            job.error = None;
+        }
+        Err(err) => {
+            job.status = JobStatus::Failed;
+            job.error = Some(format!("{err:#}"));
+            job.fs = None;
+            job.duration_s = None;
+            job.beats = None;
+            job.hrv = None;
+        }
+    }
+    job.elapsed_s = Some(started.elapsed().as_secs_f64());
+    job
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+fn analyze(job: &mut BatchJob, options: &BatchOptions) -> Result<()> {
+    let settings = &options.settings;
+    let load = loader::LoadOptions {
+        fallback_fs: settings.fs,
+    };
+    let ts = loader::load_recording(&job.input, &load)?.time_series(settings.signal.as_deref())?;
+    if !ts.fs.is_finite() || ts.fs <= 0.0 {
+        bail!("no sampling rate; pass --fs");
+    }
+    let result = run_beat_hrv_pipeline(&ts, &settings.pipeline);
+    if result.events.indices.len() < 2 {
+        bail!("fewer than two beats detected");
+    }
+    let path = options.out_dir.join(&job.output);
+    fs::write(&path, serde_json::to_string(&result)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    job.fs = Some(ts.fs);
+    job.duration_s = Some(ts.duration());
+    job.beats = Some(result.events.indices.len());
+    job.hrv = Some(result.hrv);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elf_lib::io::loader::{FormatLoader, LoadOptions, Probe, Recording};
+
+    struct PanickingLoader;
+
+    impl FormatLoader for PanickingLoader {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn sniff(&self, probe: &Probe) -> u8 {
+            if probe.extension == "boom" {
+                100
+            } else {
+                0
+            }
+        }
+
+        fn load(&self, _path: &Path, _options: &LoadOptions) -> Result<Recording> {
+            panic!("loader bug")
+        }
+    }
+
+    #[test]
+    fn a_panicking_file_fails_without_stopping_the_batch() -> Result<()> {
+        loader::register_loader(PanickingLoader);
+        let dir = tempfile::tempdir()?;
+        let boom = dir.path().join("a.boom");
+        fs::write(&boom, "1\n2\n")?;
+        let edf = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test_data/synth_30s.edf");
+        let options = BatchOptions {
+            out_dir: dir.path().join("out"),
+            jobs: 2,
+            settings: BatchSettings {
+                signal: None,
+                fs: None,
+                pipeline: EcgPipelineConfig::default(),
+            },
+            summary: PathBuf::from("summary.csv"),
+            retry_failed: false,
+            restart: false,
+        };
+        let report = run_batch(&[boom, edf], &options)?;
+        assert_eq!((report.done, report.failed), (1, 1));
+        assert_eq!(report.failures[0].error, "panicked: loader bug");
+        Ok(())
+    }
+}
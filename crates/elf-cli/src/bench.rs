@@ -0,0 +1,307 @@
+use crate::output::{Table, Tabular};
+use anyhow::{bail, Context, Result};
+use elf_lib::{
+    detectors::{
+        bench::{match_beats, BeatMatch, QrsDetector},
+        ecg::EcgPipelineConfig,
+    },
+    io::wfdb as wfdb_io,
+    signal::TimeSeries,
+    synth::{add_noise, NoiseConfig, NoiseKind},
+};
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Record name used for the aggregate rows.
+const ALL_RECORDS: &str = "all";
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub dir: PathBuf,
+    /// Extension of the reference annotation files.
+    pub annotator: String,
+    pub lead: usize,
+    pub detectors: Vec<QrsDetector>,
+    pub pipeline: EcgPipelineConfig,
+    /// Noise-stress levels; every record is also scored clean.
+    pub snr_db: Vec<f64>,
+    pub noise: Vec<NoiseKind>,
+    pub powerline_hz: f64,
+    pub seed: u64,
+    pub tolerance_s: f64,
+}
+
+/// Scores of one detector on one record (or on all of them) at one noise level.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchRow {
+    /// Position on the leaderboard of its noise level; aggregate rows only.
+    pub rank: Option<usize>,
+    pub record: String,
+    pub detector: QrsDetector,
+    /// `None` for the clean signal.
+    pub snr_db: Option<f64>,
+    pub reference: usize,
+    pub detected: usize,
+    pub tp: usize,
+    pub fp: usize,
+    #[serde(rename = "fn")]
+    pub fn_: usize,
+    pub se: f64,
+    pub ppv: f64,
+    pub f1: f64,
+    /// Mean absolute timing error of the matched beats.
+    pub timing_error_ms: f64,
+    /// Mean signed timing error; positive when detections come late.
+    pub timing_bias_ms: f64,
+    /// Length of the signal the detector ran over.
+    pub duration_s: f64,
+    pub runtime_ms: f64,
+    /// Seconds of signal processed per second of runtime.
+    pub speed_x: f64,
+}
+
+impl BenchRow {
+    fn new(
+        record: &str,
+        detector: QrsDetector,
+        snr_db: Option<f64>,
+        m: &BeatMatch,
+        duration_s: f64,
+        runtime_s: f64,
+    ) -> Self {
+        Self {
+            rank: None,
+            record: record.to_string(),
+            detector,
+            snr_db,
+            reference: m.reference,
+            detected: m.detected,
+            tp: m.true_positives,
+            fp: m.false_positives,
+            fn_: m.false_negatives,
+            se: m.sensitivity(),
+            ppv: m.positive_predictivity(),
+            f1: m.f1(),
+            timing_error_ms: m.mean_abs_error_s() * 1e3,
+            timing_bias_ms: m.mean_error_s() * 1e3,
+            duration_s,
+            runtime_ms: runtime_s * 1e3,
+            speed_x: if runtime_s > 0.0 {
+                duration_s / runtime_s
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchSkip {
+    pub record: PathBuf,
+    pub reason: String,
+}
+
+/// What `elf bench-detectors` prints.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub records: Vec<String>,
+    pub skipped: Vec<BenchSkip>,
+    pub tolerance_ms: f64,
+    pub noise: Vec<NoiseKind>,
+    /// Gross statistics per detector and noise level, best F1 first within each level.
+    pub leaderboard: Vec<BenchRow>,
+    pub results: Vec<BenchRow>,
+}
+
+impl Tabular for BenchReport {
+    /// The leaderboard rows (record `all`) followed by the per-record rows.
+    fn table(&self) -> Result<Table> {
+        let mut table = Table::default();
+        for row in self.leaderboard.iter().chain(&self.results) {
+            table.push_row([
+                ("rank".to_string(), json!(row.rank)),
+                ("record".to_string(), json!(row.record)),
+                ("detector".to_string(), json!(row.detector)),
+                ("snr_db".to_string(), json!(row.snr_db)),
+                ("reference".to_string(), json!(row.reference)),
+                ("detected".to_string(), json!(row.detected)),
+                ("tp".to_string(), json!(row.tp)),
+                ("fp".to_string(), json!(row.fp)),
+                ("fn".to_string(), json!(row.fn_)),
+                ("se".to_string(), json!(row.se)),
+                ("ppv".to_string(), json!(row.ppv)),
+                ("f1".to_string(), json!(row.f1)),
+                ("timing_error_ms".to_string(), json!(row.timing_error_ms)),
+                ("timing_bias_ms".to_string(), json!(row.timing_bias_ms)),
+                ("duration_s".to_string(), json!(row.duration_s)),
+                ("runtime_ms".to_string(), json!(row.runtime_ms)),
+                ("speed_x".to_string(), json!(row.speed_x)),
+            ]);
+        }
+        Ok(table)
+    }
+}
+
+/// Headers in `dir` (recursively) that have a reference annotation file, sorted by path.
+fn find_records(dir: &Path, annotator: &str) -> Result<(Vec<PathBuf>, Vec<BenchSkip>)> {
+    let pattern = dir.join("**").join("*.hea");
+    let pattern = pattern
+        .to_str()
+        .context("records directory is not valid UTF-8")?;
+    let mut headers: Vec<PathBuf> = glob::glob(pattern)
+        .with_context(|| format!("invalid records directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .collect();
+    if headers.is_empty() {
+        bail!("no WFDB headers (.hea) under {}", dir.display());
+    }
+    headers.sort();
+    let (records, missing): (Vec<_>, Vec<_>) = headers
+        .into_iter()
+        .partition(|hea| hea.with_extension(annotator).is_file());
+    let skipped = missing
+        .into_iter()
+        .map(|record| BenchSkip {
+            reason: format!("no .{annotator} annotations"),
+            record,
+        })
+        .collect();
+    Ok((records, skipped))
+}
+
+pub fn run_bench(options: &BenchOptions) -> Result<BenchReport> {
+    let (headers, mut skipped) = find_records(&options.dir, &options.annotator)?;
+    let mut levels: Vec<Option<f64>> = vec![None];
+    if !options.noise.is_empty() {
+        levels.extend(options.snr_db.iter().map(|&snr| Some(snr)));
+    }
+    let mut records = Vec::new();
+    let mut results = Vec::new();
+    for (index, header) in headers.iter().enumerate() {
+        let loaded = wfdb_io::load_wfdb_lead(header, options.lead).and_then(|ts| {
+            let reference = wfdb_io::load_wfdb_events(&header.with_extension(&options.annotator))?;
+            Ok((ts, reference.indices))
+        });
+        let (clean, reference) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                eprintln!("{}: {err:#}", header.display());
+                skipped.push(BenchSkip {
+                    record: header.clone(),
+                    reason: format!("{err:#}"),
+                });
+                continue;
+            }
+        };
+        let name = header
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let signal_s = clean.len() as f64 / clean.fs;
+        for &snr_db in &levels {
+            let ts = match snr_db {
+                None => clean.clone(),
+                Some(snr_db) => {
+                    let mut data = clean.data.clone();
+                    let noise = NoiseConfig {
+                        kinds: options.noise.clone(),
+                        snr_db,
+                        powerline_hz: options.powerline_hz,
+                    };
+                    add_noise(
+                        &mut data,
+                        clean.fs,
+                        &noise,
+                        options.seed.wrapping_add(index as u64),
+                    );
+                    TimeSeries { fs: clean.fs, data }
+                }
+            };
+            for &detector in &options.detectors {
+                let started = Instant::now();
+                let detected = detector.detect(&ts, &options.pipeline);
+                let runtime_s = started.elapsed().as_secs_f64();
+                let m = match_beats(&reference, &detected.indices, ts.fs, options.tolerance_s);
+                results.push((
+                    m,
+                    BenchRow::new(&name, detector, snr_db, &m, signal_s, runtime_s),
+                ));
+            }
+        }
+        records.push(name);
+    }
+    let leaderboard = leaderboard(&results);
+    Ok(BenchReport {
+        records,
+        skipped,
+        tolerance_ms: options.tolerance_s * 1e3,
+        noise: options.noise.clone(),
+        leaderboard,
+        results: results.into_iter().map(|(_, row)| row).collect(),
+    })
+}
+
+/// Sums the per-record matches of each detector and noise level. Clean comes first, then
+/// decreasing SNR; within a level, rows are ranked by F1, with runtime breaking ties.
+fn leaderboard(results: &[(BeatMatch, BenchRow)]) -> Vec<BenchRow> {
+    struct Total {
+        snr_db: Option<f64>,
+        detector: QrsDetector,
+        matches: BeatMatch,
+        duration_s: f64,
+        runtime_s: f64,
+    }
+    let mut totals: Vec<Total> = Vec::new();
+    for (m, row) in results {
+        let idx = totals
+            .iter()
+            .position(|t| t.snr_db == row.snr_db && t.detector == row.detector)
+            .unwrap_or_else(|| {
+                totals.push(Total {
+                    snr_db: row.snr_db,
+                    detector: row.detector,
+                    matches: BeatMatch::default(),
+                    duration_s: 0.0,
+                    runtime_s: 0.0,
+                });
+                totals.len() - 1
+            });
+        let total = &mut totals[idx];
+        total.matches.merge(m);
+        total.duration_s += row.duration_s;
+        total.runtime_s += row.runtime_ms / 1e3;
+    }
+    let mut board: Vec<BenchRow> = totals
+        .iter()
+        .map(|t| {
+            BenchRow::new(
+                ALL_RECORDS,
+                t.detector,
+                t.snr_db,
+                &t.matches,
+                t.duration_s,
+                t.runtime_s,
+            )
+        })
+        .collect();
+    // `None` (clean) sorts above every SNR.
+    let level = |snr: Option<f64>| snr.map_or(f64::INFINITY, |s| s);
+    board.sort_by(|a, b| {
+        level(b.snr_db)
+            .total_cmp(&level(a.snr_db))
+            .then(b.f1.total_cmp(&a.f1))
+            .then(a.runtime_ms.total_cmp(&b.runtime_ms))
+    });
+    let mut rank = 0;
+    for i in 0..board.len() {
+        rank = if i > 0 && board[i - 1].snr_db == board[i].snr_db {
+            rank + 1
+        } else {
+            1
+        };
+        board[i].rank = Some(rank);
+    }
+    board
+}
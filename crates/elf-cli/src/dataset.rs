@@ -0,0 +1,1036 @@
+use crate::output::{Output, Table, Tabular};
+use crate::pipeline::StageOp;
+use anyhow::{anyhow, bail, Context, Result};
+use elf_lib::{
+    detectors::{
+        bench::{match_beats, DEFAULT_TOLERANCE_S},
+        ecg::{bandpass_filter, run_beat_hrv_pipeline, EcgPipelineConfig},
+    },
+    io::{
+        bitalino as bitalino_io,
+        eye::{Eye, PupilSample},
+        loader::{self, LoadOptions},
+        openbci as openbci_io, text as text_io, wfdb as wfdb_io,
+    },
+    metrics::{
+        hrv::{clean_rr, hrv_nonlinear, hrv_psd, hrv_time},
+        pupil::{preprocess_pupil, PupilPipelineConfig},
+        sqi::evaluate_sqi,
+    },
+    signal::{Events, RRSeries, TimeSeries},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::Command,
+    time::Instant,
+};
+
+const DEFAULT_TOLERANCE: f64 = 0.5;
+
+/// Progress lines go to stderr; the case results are the command's output.
+pub fn cmd_dataset_validate(
+    out: &Output,
+    spec_path: &Path,
+    json: Option<&Path>,
+    junit: Option<&Path>,
+    update_spec: bool,
+) -> Result<()> {
+    let (spec, mut spec_value) = load_dataset_spec(spec_path)?;
+    let repo_root = workspace_root();
+    let mode = if update_spec {
+        DatasetValidatorMode::Update
+    } else {
+        DatasetValidatorMode::Validate
+    };
+
+    let report = run_dataset_spec(&spec, &mut spec_value, &repo_root, mode)?;
+
+    if update_spec {
+        let file = File::create(spec_path)
+            .with_context(|| format!("failed to write spec {}", spec_path.display()))?;
+        serde_json::to_writer_pretty(file, &spec_value)?;
+        eprintln!("dataset spec updated at {}", spec_path.display());
+    }
+
+    for case in &report.cases {
+        eprintln!("{}", case.message());
+    }
+    let failed = report.failed();
+    if let Some(suite_summary) = &report.suite {
+        let status = match mode {
+            DatasetValidatorMode::Update => "processed",
+            DatasetValidatorMode::Validate if failed > 0 => "failed",
+            DatasetValidatorMode::Validate => "validated",
+        };
+        eprintln!(
+            "suite {} {} ({} cases, {} failed)",
+            suite_summary.name, status, suite_summary.cases, failed
+        );
+    }
+    if let Some(path) = json {
+        let file = File::create(path)
+            .with_context(|| format!("failed to write report {}", path.display()))?;
+        serde_json::to_writer_pretty(file, &report.cases)?;
+        eprintln!("dataset report written to {}", path.display());
+    } else {
+        out.value(&CaseResults(&report.cases))?;
+    }
+    if let Some(path) = junit {
+        fs::write(path, junit_xml(&report))
+            .with_context(|| format!("failed to write JUnit report {}", path.display()))?;
+        eprintln!("JUnit report written to {}", path.display());
+    }
+    if failed > 0 {
+        bail!("{} of {} dataset cases failed", failed, report.cases.len());
+    }
+    Ok(())
+}
+
+fn load_dataset_spec(spec_path: &Path) -> Result<(DatasetSpec, Value)> {
+    let spec_src = fs::read_to_string(spec_path)
+        .with_context(|| format!("failed to open spec {}", spec_path.display()))?;
+    let spec_value: Value =
+        serde_json::from_str(&spec_src).context("failed to parse dataset spec")?;
+    // A suite is told apart by its `cases`, so serde reports the error of the right shape.
+    let spec = if spec_value.get("cases").is_some() {
+        serde_json::from_value(spec_value.clone()).map(DatasetSpec::Suite)
+    } else {
+        serde_json::from_value(spec_value.clone()).map(|case| DatasetSpec::Case(Box::new(case)))
+    }
+    .context("failed to deserialize dataset spec")?;
+    Ok((spec, spec_value))
+}
+
+fn run_dataset_spec(
+    spec: &DatasetSpec,
+    spec_value: &mut Value,
+    repo_root: &Path,
+    mode: DatasetValidatorMode,
+) -> Result<DatasetReport> {
+    let mut report = DatasetReport::default();
+    match (spec, spec_value) {
+        (DatasetSpec::Case(case), value) => {
+            report.cases.push(run_case(
+                case,
+                value,
+                repo_root,
+                &CaseDefaults::default(),
+                mode,
+            )?);
+        }
+        (DatasetSpec::Suite(suite), Value::Object(map)) => {
+            let defaults = CaseDefaults {
+                fs: suite.fs,
+                interp_fs: suite.interp_fs,
+                tolerance: suite.tolerance,
+                relative_tolerance: suite.relative_tolerance,
+            };
+            let cases_value = map
+                .get_mut("cases")
+                .and_then(|v| v.as_array_mut())
+                .ok_or_else(|| anyhow!("dataset suite {} missing cases array", suite.name))?;
+            if cases_value.len() != suite.cases.len() {
+                bail!(
+                    "dataset suite {} cases count mismatch between spec and JSON",
+                    suite.name
+                );
+            }
+            for (case, case_value) in suite.cases.iter().zip(cases_value.iter_mut()) {
+                report
+                    .cases
+                    .push(run_case(case, case_value, repo_root, &defaults, mode)?);
+            }
+            report.suite = Some(DatasetSuiteSummary {
+                name: suite.name.clone(),
+                cases: suite.cases.len(),
+            });
+        }
+        _ => bail!("dataset spec had unexpected shape"),
+    }
+    Ok(report)
+}
+
+/// Validates or updates one case. Errors fail the case when validating, but abort an update so
+/// the spec is never half rewritten.
+fn run_case(
+    case: &DatasetCase,
+    case_value: &mut Value,
+    repo_root: &Path,
+    defaults: &CaseDefaults,
+    mode: DatasetValidatorMode,
+) -> Result<DatasetResult> {
+    let started = Instant::now();
+    let computed = compute_case(case, repo_root, defaults, mode);
+    let elapsed_s = started.elapsed().as_secs_f64();
+    let mut result = DatasetResult {
+        name: case.name.clone(),
+        status: CaseStatus::Passed,
+        error: None,
+        elapsed_s,
+        metrics: BTreeMap::new(),
+        checks: Vec::new(),
+    };
+    let sections = match (computed, mode) {
+        (Ok(sections), _) => sections,
+        (Err(err), DatasetValidatorMode::Update) => {
+            return Err(err.context(format!("dataset {} could not be updated", case.name)))
+        }
+        (Err(err), DatasetValidatorMode::Validate) => {
+            result.status = CaseStatus::Error;
+            result.error = Some(format!("{err:#}"));
+            return Ok(result);
+        }
+    };
+    match mode {
+        DatasetValidatorMode::Validate => {
+            for section in &sections {
+                if let Some(spec) = case.section(section.name) {
+                    result
+                        .checks
+                        .extend(check_section(case, defaults, section, spec));
+                }
+            }
+            if result.checks.is_empty() {
+                result.status = CaseStatus::Error;
+                result.error = Some("no stored metrics to check (run with --update-spec)".into());
+            } else if result.checks.iter().any(|check| !check.passed) {
+                result.status = CaseStatus::Failed;
+            }
+        }
+        DatasetValidatorMode::Update => {
+            update_case_value(case_value, case, defaults, &sections)?;
+            result.status = CaseStatus::Updated;
+        }
+    }
+    result.metrics = sections
+        .into_iter()
+        .map(|section| (section.name, section.values))
+        .collect();
+    Ok(result)
+}
+
+#[derive(Default)]
+struct DatasetReport {
+    cases: Vec<DatasetResult>,
+    suite: Option<DatasetSuiteSummary>,
+}
+
+impl DatasetReport {
+    fn failed(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| matches!(case.status, CaseStatus::Failed | CaseStatus::Error))
+            .count()
+    }
+}
+
+struct DatasetSuiteSummary {
+    name: String,
+    cases: usize,
+}
+
+enum DatasetSpec {
+    Suite(DatasetSuite),
+    Case(Box<DatasetCase>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DatasetSuite {
+    name: String,
+    #[serde(default)]
+    fs: Option<f64>,
+    #[serde(default)]
+    interp_fs: Option<f64>,
+    #[serde(default)]
+    tolerance: Option<f64>,
+    #[serde(default)]
+    relative_tolerance: Option<f64>,
+    cases: Vec<DatasetCase>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct CaseDefaults {
+    fs: Option<f64>,
+    interp_fs: Option<f64>,
+    tolerance: Option<f64>,
+    relative_tolerance: Option<f64>,
+}
+
+#[derive(Clone, Copy)]
+enum DatasetValidatorMode {
+    Validate,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CaseStatus {
+    Passed,
+    /// At least one metric is out of tolerance.
+    Failed,
+    /// The case could not be computed (missing file, bad spec, ...).
+    Error,
+    Updated,
+}
+
+/// One metric compared with its stored value.
+#[derive(Debug, Clone, Serialize)]
+struct MetricCheck {
+    /// `<section>.<metric>`, e.g. `hrv_time.sdnn`.
+    metric: String,
+    expected: f64,
+    /// `None` when the metric was not computed or is not finite.
+    actual: Option<f64>,
+    /// Actual minus expected.
+    delta: Option<f64>,
+    /// Largest `|delta|` that passes: `tolerance + relative_tolerance * |expected|`.
+    tolerance: f64,
+    passed: bool,
+}
+
+/// Report entry of one case.
+#[derive(Serialize)]
+struct DatasetResult {
+    name: String,
+    status: CaseStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    elapsed_s: f64,
+    /// Computed metrics per section.
+    metrics: BTreeMap<&'static str, BTreeMap<String, f64>>,
+    checks: Vec<MetricCheck>,
+}
+
+impl DatasetResult {
+    fn failures(&self) -> impl Iterator<Item = &MetricCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    fn message(&self) -> String {
+        match self.status {
+            CaseStatus::Passed => format!("dataset {} validated", self.name),
+            CaseStatus::Updated => format!("dataset {} updated", self.name),
+            CaseStatus::Error => format!(
+                "dataset {} error: {}",
+                self.name,
+                self.error.as_deref().unwrap_or_default()
+            ),
+            CaseStatus::Failed => {
+                let mut message = format!(
+                    "dataset {} failed ({} of {} metrics out of tolerance)",
+                    self.name,
+                    self.failures().count(),
+                    self.checks.len()
+                );
+                for check in self.failures() {
+                    message.push_str("\n  ");
+                    message.push_str(&check.describe());
+                }
+                message
+            }
+        }
+    }
+}
+
+/// The case results as one JSON array, or one row per metric check in the row formats.
+#[derive(Serialize)]
+#[serde(transparent)]
+struct CaseResults<'a>(&'a [DatasetResult]);
+
+impl Tabular for CaseResults<'_> {
+    fn table(&self) -> Result<Table> {
+        let mut table = Table::default();
+        for result in self.0 {
+            result.push_rows(&mut table);
+        }
+        Ok(table)
+    }
+}
+
+impl DatasetResult {
+    /// One row per metric check (a single row for a case without checks).
+    fn push_rows(&self, table: &mut Table) {
+        let case = [
+            ("case".to_string(), json!(self.name)),
+            ("status".to_string(), json!(self.status)),
+            ("error".to_string(), json!(self.error)),
+            ("elapsed_s".to_string(), json!(self.elapsed_s)),
+        ];
+        if self.checks.is_empty() {
+            table.push_row(case.clone());
+        }
+        for check in &self.checks {
+            table.push_row(case.iter().cloned().chain([
+                ("metric".to_string(), json!(check.metric)),
+                ("expected".to_string(), json!(check.expected)),
+                ("actual".to_string(), json!(check.actual)),
+                ("delta".to_string(), json!(check.delta)),
+                ("tolerance".to_string(), json!(check.tolerance)),
+                ("passed".to_string(), json!(check.passed)),
+            ]));
+        }
+    }
+}
+
+impl MetricCheck {
+    fn describe(&self) -> String {
+        match (self.actual, self.delta) {
+            (Some(actual), Some(delta)) => format!(
+                "{}: expected {}, got {} (delta {:+e}, tolerance {:e})",
+                self.metric, self.expected, actual, delta, self.tolerance
+            ),
+            _ => format!("{}: expected {}, not computed", self.metric, self.expected),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct DatasetCase {
+    name: String,
+    /// Any file the loader registry reads; RR intervals with `format: "rr"`.
+    #[serde(default)]
+    input: Option<String>,
+    /// `rr`, or the name of a registered loader (`edf`, `xdf`, `pupil-labs`, ...) to skip
+    /// format detection.
+    #[serde(default)]
+    format: Option<String>,
+    /// Channel label or index of `input`; the first channel otherwise.
+    #[serde(default)]
+    signal: Option<String>,
+    #[serde(default)]
+    fs: Option<f64>,
+    #[serde(default)]
+    interp_fs: Option<f64>,
+    #[serde(default)]
+    tolerance: Option<f64>,
+    #[serde(default)]
+    relative_tolerance: Option<f64>,
+    #[serde(default)]
+    wfdb_header: Option<String>,
+    #[serde(default)]
+    wfdb_lead: Option<usize>,
+    #[serde(default)]
+    annotations: Option<String>,
+    #[serde(default)]
+    bids_events: Option<String>,
+    #[serde(default)]
+    bitalino_input: Option<String>,
+    #[serde(default)]
+    bitalino_signal: Option<String>,
+    #[serde(default)]
+    openbci_input: Option<String>,
+    #[serde(default)]
+    openbci_channel: Option<String>,
+    #[serde(default)]
+    hrv_time: Option<MetricSpec>,
+    #[serde(default)]
+    hrv_psd: Option<MetricSpec>,
+    #[serde(default)]
+    hrv_nonlinear: Option<MetricSpec>,
+    #[serde(default)]
+    sqi: Option<MetricSpec>,
+    #[serde(default)]
+    beats: Option<BeatsSpec>,
+    #[serde(default)]
+    pupil: Option<MetricSpec>,
+    /// Beat detector settings.
+    #[serde(default)]
+    pipeline: EcgPipelineConfig,
+    /// Pupil preprocessing settings.
+    #[serde(default)]
+    pupil_pipeline: PupilPipelineConfig,
+    /// `filter` stages run on the signal before beat detection and `rr-clean` stages on the
+    /// RR intervals, in order.
+    #[serde(default)]
+    preprocess: Vec<StageOp>,
+}
+
+impl DatasetCase {
+    fn section(&self, name: &str) -> Option<&MetricSpec> {
+        match name {
+            "hrv_time" => self.hrv_time.as_ref(),
+            "hrv_psd" => self.hrv_psd.as_ref(),
+            "hrv_nonlinear" => self.hrv_nonlinear.as_ref(),
+            "sqi" => self.sqi.as_ref(),
+            "beats" => self.beats.as_ref().map(|beats| &beats.metrics),
+            "pupil" => self.pupil.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// SQIs and beat scores are computed on the signal, so those cases load it even when the RR
+    /// intervals come from annotations.
+    fn needs_signal(&self) -> bool {
+        self.sqi.is_some() || self.beats.is_some()
+    }
+
+    fn check_preprocess(&self) -> Result<()> {
+        for op in &self.preprocess {
+            if !matches!(op, StageOp::Filter { .. } | StageOp::RrClean(_)) {
+                bail!(
+                    "dataset {}: {} cannot preprocess a case; use filter or rr-clean",
+                    self.name,
+                    op.kind()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn preprocess_signal(&self, ts: TimeSeries) -> TimeSeries {
+        self.preprocess.iter().fold(ts, |ts, op| match op {
+            StageOp::Filter {
+                lowcut_hz,
+                highcut_hz,
+            } => bandpass_filter(&ts, *lowcut_hz, *highcut_hz),
+            _ => ts,
+        })
+    }
+
+    fn preprocess_rr(&self, rr: RRSeries) -> RRSeries {
+        self.preprocess.iter().fold(rr, |rr, op| match op {
+            StageOp::RrClean(cfg) => clean_rr(&rr, cfg).rr,
+            _ => rr,
+        })
+    }
+}
+
+/// Expected values of one section, keyed by metric name.
+#[derive(Default, Deserialize, Clone)]
+struct MetricSpec {
+    #[serde(default)]
+    tolerance: Option<f64>,
+    #[serde(default)]
+    relative_tolerance: Option<f64>,
+    #[serde(flatten)]
+    expected: BTreeMap<String, f64>,
+}
+
+/// Detected beats, scored against reference annotations when there are any.
+#[derive(Default, Deserialize, Clone)]
+struct BeatsSpec {
+    /// Reference beats; the case's `annotations` or `bids_events` otherwise.
+    #[serde(default)]
+    annotations: Option<String>,
+    /// Matching window; EC57's 150 ms by default.
+    #[serde(default)]
+    window_ms: Option<f64>,
+    #[serde(flatten)]
+    metrics: MetricSpec,
+}
+
+/// Metrics computed for one section.
+struct SectionMetrics {
+    name: &'static str,
+    values: BTreeMap<String, f64>,
+}
+
+/// The numeric fields of a metrics struct; vectors and non-finite values are left out.
+fn metric_values<T: Serialize>(metrics: &T) -> BTreeMap<String, f64> {
+    match serde_json::to_value(metrics) {
+        Ok(Value::Object(map)) => map
+            .into_iter()
+            .filter_map(|(key, value)| value.as_f64().map(|v| (key, v)))
+            .filter(|(_, v)| v.is_finite())
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+/// Computes the sections the case declares. An update also fills in every HRV section when the
+/// case has RR intervals, and the pupil section when it has eye-tracker samples.
+fn compute_case(
+    case: &DatasetCase,
+    repo_root: &Path,
+    defaults: &CaseDefaults,
+    mode: DatasetValidatorMode,
+) -> Result<Vec<SectionMetrics>> {
+    case.check_preprocess()?;
+    let interp_fs = case.interp_fs.or(defaults.interp_fs).unwrap_or(4.0);
+    let inputs = load_case_inputs(case, repo_root, defaults)?;
+    let update = matches!(mode, DatasetValidatorMode::Update);
+    let hrv = |declared: bool| declared || (update && inputs.rr.is_some());
+    let mut sections = Vec::new();
+    if hrv(case.hrv_time.is_some()) {
+        sections.push(SectionMetrics {
+            name: "hrv_time",
+            values: metric_values(&hrv_time(inputs.rr(case)?)),
+        });
+    }
+    if hrv(case.hrv_psd.is_some()) {
+        sections.push(SectionMetrics {
+            name: "hrv_psd",
+            values: metric_values(&hrv_psd(inputs.rr(case)?, interp_fs)),
+        });
+    }
+    if hrv(case.hrv_nonlinear.is_some()) {
+        sections.push(SectionMetrics {
+            name: "hrv_nonlinear",
+            values: metric_values(&hrv_nonlinear(inputs.rr(case)?)),
+        });
+    }
+    if case.needs_signal() {
+        let (ts, detected) = inputs
+            .signal
+            .as_ref()
+            .ok_or_else(|| anyhow!("dataset {} has no signal for sqi and beats", case.name))?;
+        if case.sqi.is_some() {
+            sections.push(SectionMetrics {
+                name: "sqi",
+                values: metric_values(&evaluate_sqi(ts, inputs.rr(case)?)),
+            });
+        }
+        if let Some(spec) = &case.beats {
+            sections.push(SectionMetrics {
+                name: "beats",
+                values: beat_metrics(case, spec, ts, detected, repo_root)?,
+            });
+        }
+    }
+    let has_pupil = inputs.eye.iter().any(|sample| sample.pupil_mm.is_some());
+    if case.pupil.is_some() || (update && has_pupil) {
+        if inputs.eye.is_empty() {
+            bail!("dataset {} has no eye-tracker samples for pupil", case.name);
+        }
+        if !has_pupil {
+            bail!("dataset {} has no pupil diameter samples", case.name);
+        }
+        sections.push(SectionMetrics {
+            name: "pupil",
+            values: pupil_metrics(&inputs.eye, &case.pupil_pipeline),
+        });
+    }
+    Ok(sections)
+}
+
+/// Gap report and mean diameter of every eye, keyed `<eye>_<metric>` (e.g. `left_blinks`).
+fn pupil_metrics(samples: &[PupilSample], cfg: &PupilPipelineConfig) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+    for trace in preprocess_pupil(samples, cfg) {
+        let eye = match trace.eye {
+            Eye::Left => "left",
+            Eye::Right => "right",
+            Eye::Binocular => "binocular",
+        };
+        let mut metrics = metric_values(&trace.report);
+        let valid: Vec<f64> = trace
+            .series
+            .data
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .collect();
+        if !valid.is_empty() {
+            metrics.insert(
+                "mean_mm".into(),
+                valid.iter().sum::<f64>() / valid.len() as f64,
+            );
+        }
+        values.extend(
+            metrics
+                .into_iter()
+                .map(|(metric, value)| (format!("{eye}_{metric}"), value)),
+        );
+    }
+    values
+}
+
+/// `count` of detected beats; with reference annotations also `reference`, `se`, `ppv`, `f1`
+/// and `timing_error_ms`.
+fn beat_metrics(
+    case: &DatasetCase,
+    spec: &BeatsSpec,
+    ts: &TimeSeries,
+    detected: &Events,
+    repo_root: &Path,
+) -> Result<BTreeMap<String, f64>> {
+    let mut values = BTreeMap::from([("count".to_string(), detected.indices.len() as f64)]);
+    let (annotations, bids_events) = match &spec.annotations {
+        Some(path) => (Some(path), None),
+        None => (case.annotations.as_ref(), case.bids_events.as_ref()),
+    };
+    let annotations = annotations.map(|value| resolve_path(repo_root, value));
+    let bids_events = bids_events.map(|value| resolve_path(repo_root, value));
+    if let Some(reference) =
+        crate::load_annotation_events(annotations.as_deref(), bids_events.as_deref(), ts.fs)?
+    {
+        let window_s = spec
+            .window_ms
+            .map_or(DEFAULT_TOLERANCE_S, |window| window / 1e3);
+        let m = match_beats(&reference.indices, &detected.indices, ts.fs, window_s);
+        values.insert("reference".into(), m.reference as f64);
+        values.insert("se".into(), m.sensitivity());
+        values.insert("ppv".into(), m.positive_predictivity());
+        values.insert("f1".into(), m.f1());
+        values.insert("timing_error_ms".into(), m.mean_abs_error_s() * 1e3);
+    }
+    Ok(values)
+}
+
+fn check_section(
+    case: &DatasetCase,
+    defaults: &CaseDefaults,
+    section: &SectionMetrics,
+    spec: &MetricSpec,
+) -> Vec<MetricCheck> {
+    let (tolerance, relative_tolerance) = section_tolerances(case, defaults, spec);
+    spec.expected
+        .iter()
+        .map(|(metric, &expected)| {
+            let actual = section.values.get(metric).copied();
+            let delta = actual.map(|actual| actual - expected);
+            let tolerance = tolerance + relative_tolerance * expected.abs();
+            MetricCheck {
+                metric: format!("{}.{}", section.name, metric),
+                expected,
+                actual,
+                delta,
+                tolerance,
+                passed: delta.is_some_and(|delta| delta.abs() <= tolerance),
+            }
+        })
+        .collect()
+}
+
+/// Absolute and relative tolerance of a section: its own, else the case's, else the suite's.
+/// The absolute tolerance defaults to 0.5 and the relative one to 0.
+fn section_tolerances(
+    case: &DatasetCase,
+    defaults: &CaseDefaults,
+    spec: &MetricSpec,
+) -> (f64, f64) {
+    let tolerance = spec
+        .tolerance
+        .or(case.tolerance)
+        .or(defaults.tolerance)
+        .unwrap_or(DEFAULT_TOLERANCE)
+        .abs();
+    let relative_tolerance = spec
+        .relative_tolerance
+        .or(case.relative_tolerance)
+        .or(defaults.relative_tolerance)
+        .unwrap_or(0.0)
+        .abs();
+    (tolerance, relative_tolerance)
+}
+
+/// Rewrites the computed sections into the case JSON. Keys the update does not compute (the
+/// relative tolerance, the beat reference, ...) are kept.
+fn update_case_value(
+    case_value: &mut Value,
+    case: &DatasetCase,
+    defaults: &CaseDefaults,
+    sections: &[SectionMetrics],
+) -> Result<()> {
+    let map = case_value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("dataset spec case is not an object"))?;
+    for section in sections {
+        let spec = case.section(section.name).cloned().unwrap_or_default();
+        let (tolerance, _) = section_tolerances(case, defaults, &spec);
+        let entry = map
+            .entry(section.name)
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(entry) = entry else {
+            bail!("dataset {} {} is not an object", case.name, section.name);
+        };
+        entry.insert("tolerance".into(), json!(tolerance));
+        for (metric, value) in &section.values {
+            entry.insert(metric.clone(), json!(value));
+        }
+    }
+    Ok(())
+}
+
+/// What a case provides to compute its metrics from.
+struct CaseInputs {
+    /// From an RR file, annotations or beats detected on the signal, after `rr-clean` stages.
+    rr: Option<RRSeries>,
+    /// The preprocessed signal and the beats detected on it.
+    signal: Option<(TimeSeries, Events)>,
+    /// Pupil samples of eye-tracker exports.
+    eye: Vec<PupilSample>,
+}
+
+impl CaseInputs {
+    fn rr(&self, case: &DatasetCase) -> Result<&RRSeries> {
+        self.rr.as_ref().ok_or_else(|| {
+            anyhow!(
+                "dataset {} has no RR intervals (no RR file, annotations or ECG signal)",
+                case.name
+            )
+        })
+    }
+}
+
+fn load_case_inputs(
+    case: &DatasetCase,
+    repo_root: &Path,
+    defaults: &CaseDefaults,
+) -> Result<CaseInputs> {
+    if case.format.as_deref() == Some("rr") {
+        if case.needs_signal() || case.pupil.is_some() {
+            bail!(
+                "dataset {} has RR intervals only; sqi, beats and pupil need a signal",
+                case.name
+            );
+        }
+        let input = case
+            .input
+            .as_ref()
+            .ok_or_else(|| anyhow!("dataset {} missing RR input path", case.name))?;
+        let path = resolve_path(repo_root, input);
+        let rr = text_io::read_f64_series(&path)?;
+        return Ok(CaseInputs {
+            rr: Some(case.preprocess_rr(RRSeries { rr })),
+            signal: None,
+            eye: Vec::new(),
+        });
+    }
+
+    let annotated = annotated_rr(case, repo_root, defaults)?;
+    let source = if annotated.is_none() || case.needs_signal() || case.pupil.is_some() {
+        case_source(case, repo_root, defaults)?
+    } else {
+        None
+    };
+    let Some(source) = source else {
+        return match annotated {
+            Some(rr) => Ok(CaseInputs {
+                rr: Some(case.preprocess_rr(rr)),
+                signal: None,
+                eye: Vec::new(),
+            }),
+            None => bail!("dataset {} missing time series input path", case.name),
+        };
+    };
+    let detected = source.signal.map(|ts| {
+        let ts = case.preprocess_signal(ts);
+        let result = run_beat_hrv_pipeline(&ts, &case.pipeline);
+        (ts, result)
+    });
+    let rr = annotated
+        .or_else(|| detected.as_ref().map(|(_, result)| result.rr.clone()))
+        .map(|rr| case.preprocess_rr(rr));
+    Ok(CaseInputs {
+        rr,
+        signal: detected.map(|(ts, result)| (ts, result.events)),
+        eye: source.eye,
+    })
+}
+
+/// RR intervals from the case's `annotations` or `bids_events`, if it has either.
+fn annotated_rr(
+    case: &DatasetCase,
+    repo_root: &Path,
+    defaults: &CaseDefaults,
+) -> Result<Option<RRSeries>> {
+    let annotation_path = case
+        .annotations
+        .as_ref()
+        .map(|value| resolve_path(repo_root, value));
+    let bids_events_path = case
+        .bids_events
+        .as_ref()
+        .map(|value| resolve_path(repo_root, value));
+    if annotation_path.is_none() && bids_events_path.is_none() {
+        return Ok(None);
+    }
+    let fs = case.fs.or(defaults.fs).ok_or_else(|| {
+        anyhow!(
+            "dataset {} requires fs when providing event annotations",
+            case.name
+        )
+    })?;
+    if annotation_path
+        .as_deref()
+        .map(|p| p.components().any(|comp| comp.as_os_str() == "run_bundle"))
+        .unwrap_or(false)
+    {
+        ensure_run_bundle(repo_root, fs)?;
+    }
+    match crate::load_annotation_events(
+        annotation_path.as_deref(),
+        bids_events_path.as_deref(),
+        fs,
+    )? {
+        Some(events) => Ok(Some(RRSeries::from_events(&events, fs))),
+        None => bail!("dataset {} produced no events from annotations", case.name),
+    }
+}
+
+/// The signal and eye-tracker samples of a case's input file.
+struct CaseSource {
+    signal: Option<TimeSeries>,
+    eye: Vec<PupilSample>,
+}
+
+impl From<TimeSeries> for CaseSource {
+    fn from(ts: TimeSeries) -> Self {
+        Self {
+            signal: Some(ts),
+            eye: Vec::new(),
+        }
+    }
+}
+
+/// Loads the case's input, if it has one. Eye-tracker exports only give a signal when the case
+/// names a `signal` channel.
+fn case_source(
+    case: &DatasetCase,
+    repo_root: &Path,
+    defaults: &CaseDefaults,
+) -> Result<Option<CaseSource>> {
+    if let Some(bitalino_input) = &case.bitalino_input {
+        let signal = case.bitalino_signal.as_deref().unwrap_or("analog0");
+        let path = resolve_path(repo_root, bitalino_input);
+        return Ok(Some(bitalino_io::read_bitalino_csv(&path, signal)?.into()));
+    }
+    if let Some(openbci_input) = &case.openbci_input {
+        let channel = case.openbci_channel.as_deref().unwrap_or("Ch1");
+        let path = resolve_path(repo_root, openbci_input);
+        return Ok(Some(openbci_io::read_openbci_csv(&path, channel)?.into()));
+    }
+    if let Some(header) = &case.wfdb_header {
+        let lead = case.wfdb_lead.unwrap_or(0);
+        let path = resolve_path(repo_root, header);
+        return Ok(Some(wfdb_io::load_wfdb_lead(&path, lead)?.into()));
+    }
+    let Some(input) = &case.input else {
+        return Ok(None);
+    };
+    let path = resolve_path(repo_root, input);
+    let options = LoadOptions {
+        fallback_fs: case.fs.or(defaults.fs),
+    };
+    let recording = match case.format.as_deref() {
+        Some(format) => loader::load_recording_as(format, &path, &options)?,
+        None => loader::load_recording(&path, &options)?,
+    };
+    let signal = if recording.eye.is_empty() || case.signal.is_some() {
+        Some(recording.time_series(case.signal.as_deref())?)
+    } else {
+        None
+    };
+    Ok(Some(CaseSource {
+        signal,
+        eye: recording.eye,
+    }))
+}
+
+/// JUnit XML with one testcase per dataset case; out-of-tolerance metrics are listed in the
+/// `<failure>` body.
+fn junit_xml(report: &DatasetReport) -> String {
+    let suite = report
+        .suite
+        .as_ref()
+        .map(|suite| suite.name.as_str())
+        .or_else(|| report.cases.first().map(|case| case.name.as_str()))
+        .unwrap_or("dataset");
+    let count = |status| {
+        report
+            .cases
+            .iter()
+            .filter(|case| case.status == status)
+            .count()
+    };
+    let elapsed_s: f64 = report.cases.iter().map(|case| case.elapsed_s).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+        xml_escape(suite),
+        report.cases.len(),
+        count(CaseStatus::Failed),
+        count(CaseStatus::Error),
+        elapsed_s
+    );
+    for case in &report.cases {
+        let _ = write!(
+            xml,
+            "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            xml_escape(suite),
+            xml_escape(&case.name),
+            case.elapsed_s
+        );
+        match case.status {
+            CaseStatus::Failed => {
+                let body: Vec<String> = case.failures().map(MetricCheck::describe).collect();
+                let _ = write!(
+                    xml,
+                    ">\n    <failure message=\"{} of {} metrics out of tolerance\">{}</failure>\n  </testcase>\n",
+                    body.len(),
+                    case.checks.len(),
+                    xml_escape(&body.join("\n"))
+                );
+            }
+            CaseStatus::Error => {
+                let error = case.error.as_deref().unwrap_or_default();
+                let _ = write!(
+                    xml,
+                    ">\n    <error message=\"{}\"/>\n  </testcase>\n",
+                    xml_escape(error)
+                );
+            }
+            CaseStatus::Passed | CaseStatus::Updated => xml.push_str("/>\n"),
+        }
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn ensure_run_bundle(repo_root: &Path, fs: f64) -> Result<()> {
+    let bundle_dir = repo_root.join("test_data/run_bundle");
+    if bundle_dir.join("events.idx").exists() {
+        return Ok(());
+    }
+    let script = repo_root.join("scripts/generate_run_bundle.sh");
+    let status = Command::new(script)
+        .arg(fs.to_string())
+        .current_dir(repo_root)
+        .status()
+        .context("running run bundle generator")?;
+    if !status.success() {
+        bail!("run bundle generator failed")
+    }
+    Ok(())
+}
+
+fn resolve_path(repo_root: &Path, input: &str) -> PathBuf {
+    let path = Path::new(input);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        repo_root.join(path)
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("workspace root")
+        .to_path_buf()
+}
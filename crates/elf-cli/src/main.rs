@@ -1,50 +1,61 @@
-use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand, ValueEnum};
+mod batch;
+mod bench;
+mod dataset;
+mod output;
+mod pipeline;
+mod report;
+mod synth;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use elf_lib::{
-    detectors::ecg::{
-        detect_r_peaks, run_beat_hrv_pipeline, BeatHrvPipelineResult, EcgPipelineConfig,
+    detectors::{
+        bench::{QrsDetector, DEFAULT_TOLERANCE_S},
+        ecg::{
+            detect_r_peaks, detect_r_peaks_with_config, run_beat_hrv_pipeline,
+            BeatHrvPipelineResult, EcgPipelineConfig,
+        },
+        streaming::run_beat_hrv_pipeline_chunked,
     },
     io::{
-        bitalino as bitalino_io, eeg as eeg_io, eye as eye_io, openbci as openbci_io,
-        text as text_io, wfdb as wfdb_io,
+        bids as bids_io, bitalino as bitalino_io, chunked as chunked_io, edf as edf_io,
+        eeg as eeg_io, eye as eye_io, loader, openbci as openbci_io, parquet as parquet_io,
+        rr as rr_io, text as text_io, wfdb as wfdb_io, xdf as xdf_io,
     },
     metrics::{
-        hrv::{hrv_nonlinear, hrv_psd, hrv_time, HRVPsd, HRVTime},
+        eda::{analyze_eda, EdaConfig, ScrEvent},
+        gaze::{
+            detect_gaze_events, gaze_points, FixationMethod, GazeEventConfig, GazeUnits,
+            ScreenGeometry,
+        },
+        hrv::{hrv_nonlinear, hrv_psd, hrv_rsa, hrv_time},
+        pupil::{
+            preprocess_pupil, pupil_erp, BaselineCorrection, PupilEpochEvent, PupilErpConfig,
+            PupilInterpolation, PupilPipelineConfig,
+        },
+        resp::{analyze_resp, rsa_peak_valley, RespConfig},
         sqi::evaluate_sqi,
     },
-    plot::{figure_from_rr, Figure, Series},
+    plot::{
+        figure_from_ecg, figure_from_psd, figure_from_rr, rr_histogram_figure, Figure, PlotBackend,
+        PlottersBackend, Subplots,
+    },
     signal::{Events, RRSeries, TimeSeries},
+    synth::{generate, NoiseConfig, NoiseKind, SynthConfig},
 };
 use elf_run::{
-    read_design, read_trials, simulate_run, write_events_json, write_events_tsv, write_manifest,
+    read_design, read_events_tsv, read_manifest, read_trials, simulate_run, write_events_json,
+    write_events_tsv, write_manifest,
 };
-use plotters::prelude::*;
+use output::{Output, OutputFormat, Tabular};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Map, Value};
+use serde_json::{json, Value};
 use std::{
     env,
     fs::{self, File},
     io::{self, Read},
     path::{Path, PathBuf},
-    process::Command,
 };
-fn ensure_run_bundle(repo_root: &Path, fs: f64) -> Result<()> {
-    let bundle_dir = repo_root.join("test_data/run_bundle");
-    if bundle_dir.join("events.idx").exists() {
-        return Ok(());
-    }
-    let script = repo_root.join("scripts/generate_run_bundle.sh");
-    let status = Command::new(script)
-        .arg(fs.to_string())
-        .current_dir(repo_root)
-        .status()
-        .context("running run bundle generator")?;
-    if !status.success() {
-        anyhow::bail!("run bundle generator failed")
-    }
-    Ok(())
-}
-
 #[derive(Parser)]
 #[command(
     name = "elf",
@@ -52,10 +63,40 @@ fn ensure_run_bundle(repo_root: &Path, fs: f64) -> Result<()> {
     about = "ELF: Extensible Lab Framework CLI tools"
 )]
 struct Cli {
+    /// Result format (json, ndjson, csv, parquet, table); each command's JSON document (or
+    /// JSON lines) when omitted. The eye commands also take their layout here (pupil-labs,
+    /// tobii), as before `--eye-format`.
+    #[arg(long, global = true, value_parser = parse_format_arg)]
+    format: Option<FormatArg>,
+    /// Write results to this file instead of stdout
+    #[arg(long, global = true)]
+    output: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Value of the global `--format`: a result format, or an eye export layout, which is what
+/// `--format` meant for the eye commands before result formats existed.
+#[derive(Copy, Clone, Debug)]
+enum FormatArg {
+    Output(OutputFormat),
+    Eye(EyeFormat),
+}
+
+fn parse_format_arg(value: &str) -> Result<FormatArg, String> {
+    if let Ok(format) = OutputFormat::from_str(value, true) {
+        return Ok(FormatArg::Output(format));
+    }
+    EyeFormat::from_str(value, true)
+        .map(FormatArg::Eye)
+        .map_err(|_| {
+            format!(
+                "unknown format '{value}' (expected json, ndjson, csv, parquet or table; \
+                 pupil-labs or tobii for the eye commands)"
+            )
+        })
+}
+
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum EyeFormat {
     #[value(name = "pupil-labs")]
@@ -65,34 +106,159 @@ enum EyeFormat {
 }
 
 impl EyeFormat {
-    fn columns(
-        &self,
-    ) -> (
-        &'static str,
-        &'static str,
-        Option<&'static str>,
-        Option<&'static str>,
-        u8,
-    ) {
+    fn layout(&self) -> eye_io::EyeCsvLayout<'static> {
+        match self {
+            EyeFormat::PupilLabs => eye_io::EyeCsvLayout::PUPIL_LABS,
+            EyeFormat::Tobii => eye_io::EyeCsvLayout::TOBII,
+        }
+    }
+
+    fn gaze_units(&self) -> GazeUnits {
         match self {
-            EyeFormat::PupilLabs => (
-                "timestamp",
-                "diameter",
-                Some("confidence"),
-                Some("eye"),
-                b',',
-            ),
-            EyeFormat::Tobii => (
-                "system_time_stamp",
-                "pupil_diameter_2d",
-                Some("confidence"),
-                Some("eye"),
-                b'\t',
-            ),
+            EyeFormat::PupilLabs => GazeUnits::NormalizedBottomLeft,
+            EyeFormat::Tobii => GazeUnits::Normalized,
+        }
+    }
+}
+
+/// Figures `elf plot` exports, drawn as in the GUI's HRV tab.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum PlotKind {
+    Ecg,
+    Rr,
+    RrHistogram,
+    Psd,
+}
+
+/// JSON accepted by `elf plot --figure`: a single figure or stacked panels.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PlotSpec {
+    Subplots(Subplots),
+    Figure(Figure),
+}
+
+/// Noise sources `elf synth` mixes into the ECG.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum NoiseArg {
+    BaselineWander,
+    Emg,
+    ElectrodeMotion,
+    Powerline,
+}
+
+impl From<NoiseArg> for NoiseKind {
+    fn from(value: NoiseArg) -> Self {
+        match value {
+            NoiseArg::BaselineWander => NoiseKind::BaselineWander,
+            NoiseArg::Emg => NoiseKind::Emg,
+            NoiseArg::ElectrodeMotion => NoiseKind::ElectrodeMotion,
+            NoiseArg::Powerline => NoiseKind::Powerline,
+        }
+    }
+}
+
+/// QRS detectors `elf bench-detectors` can run.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum DetectorArg {
+    PanTompkins,
+    Streaming,
+    LocalMaxima,
+}
+
+impl From<DetectorArg> for QrsDetector {
+    fn from(value: DetectorArg) -> Self {
+        match value {
+            DetectorArg::PanTompkins => QrsDetector::PanTompkins,
+            DetectorArg::Streaming => QrsDetector::Streaming,
+            DetectorArg::LocalMaxima => QrsDetector::LocalMaxima,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum FixationMethodArg {
+    Ivt,
+    Idt,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum PupilInterpolationArg {
+    Linear,
+    Cubic,
+}
+
+impl From<PupilInterpolationArg> for PupilInterpolation {
+    fn from(value: PupilInterpolationArg) -> Self {
+        match value {
+            PupilInterpolationArg::Linear => PupilInterpolation::Linear,
+            PupilInterpolationArg::Cubic => PupilInterpolation::Cubic,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum BaselineArg {
+    None,
+    Subtractive,
+    Divisive,
+}
+
+impl From<BaselineArg> for BaselineCorrection {
+    fn from(value: BaselineArg) -> Self {
+        match value {
+            BaselineArg::None => BaselineCorrection::None,
+            BaselineArg::Subtractive => BaselineCorrection::Subtractive,
+            BaselineArg::Divisive => BaselineCorrection::Divisive,
+        }
+    }
+}
+
+/// R-peak detector settings shared by the commands that run the beat/HRV pipeline.
+#[derive(Args, Debug, Clone, Copy)]
+struct EcgPipelineArgs {
+    #[arg(long, default_value_t = 5.0)]
+    lowcut_hz: f64,
+    #[arg(long, default_value_t = 15.0)]
+    highcut_hz: f64,
+    #[arg(long, default_value_t = 0.150)]
+    integration_window_s: f64,
+    #[arg(long, default_value_t = 0.12)]
+    min_rr_s: f64,
+    #[arg(long, default_value_t = 0.6)]
+    threshold_scale: f64,
+    #[arg(long, default_value_t = 0.150)]
+    search_back_s: f64,
+}
+
+impl From<EcgPipelineArgs> for EcgPipelineConfig {
+    fn from(args: EcgPipelineArgs) -> Self {
+        Self {
+            lowcut_hz: args.lowcut_hz,
+            highcut_hz: args.highcut_hz,
+            integration_window_s: args.integration_window_s,
+            min_rr_s: args.min_rr_s,
+            threshold_scale: args.threshold_scale,
+            search_back_s: args.search_back_s,
         }
     }
 }
 
+#[derive(Subcommand)]
+enum PipelineCommand {
+    /// Run every stage in-process and write the sinks plus a provenance record
+    Run {
+        /// Pipeline file (TOML)
+        file: PathBuf,
+        /// Directory for relative sink and provenance paths (defaults to the pipeline's own)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        /// Provenance record path, overriding `[pipeline] provenance`
+        #[arg(long)]
+        provenance: Option<PathBuf>,
+    },
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Detect R-peaks from newline-delimited samples read from stdin or --input file
@@ -112,7 +278,7 @@ enum Commands {
         #[arg(long, default_value_t = 0)]
         eeg_channel: usize,
     },
-    /// Compute time-domain HRV from newline-delimited RR intervals (seconds)
+    /// Compute time-domain HRV from RR intervals (text in s or ms, Polar HRM, Garmin FIT, table)
     HrvTime {
         #[arg(long)]
         input: Option<PathBuf>,
@@ -121,18 +287,8 @@ enum Commands {
     BeatHrvPipeline {
         #[arg(long, default_value_t = 250.0)]
         fs: f64,
-        #[arg(long, default_value_t = 5.0)]
-        lowcut_hz: f64,
-        #[arg(long, default_value_t = 15.0)]
-        highcut_hz: f64,
-        #[arg(long, default_value_t = 0.150)]
-        integration_window_s: f64,
-        #[arg(long, default_value_t = 0.12)]
-        min_rr_s: f64,
-        #[arg(long, default_value_t = 0.6)]
-        threshold_scale: f64,
-        #[arg(long, default_value_t = 0.150)]
-        search_back_s: f64,
+        #[command(flatten)]
+        pipeline: EcgPipelineArgs,
         #[arg(long)]
         input: Option<PathBuf>,
         #[arg(long)]
@@ -147,15 +303,100 @@ enum Commands {
         eeg_channel: usize,
         #[arg(long)]
         bids_events: Option<PathBuf>,
+        /// Read the signal in blocks and detect beats incrementally (constant memory)
+        #[arg(long, conflicts_with_all = ["annotations", "bids_events"])]
+        stream: bool,
+        /// Samples per block with --stream
+        #[arg(long, default_value_t = chunked_io::DEFAULT_BLOCK_SIZE)]
+        block_size: usize,
     },
     /// Normalize pupil exports (Pupil Labs/Tobii) and filter by confidence
     PupilNormalize {
         #[arg(long)]
         input: PathBuf,
-        #[arg(long, default_value = "pupil-labs")]
-        format: EyeFormat,
+        /// Export layout; detected from the header when omitted
+        #[arg(long)]
+        eye_format: Option<EyeFormat>,
+        #[arg(long, default_value_t = 0.5)]
+        min_confidence: f32,
+    },
+    /// Clean pupil exports: blink removal, interpolation, outlier rejection and resampling
+    PupilPreprocess {
+        #[arg(long)]
+        input: PathBuf,
+        /// Export layout; detected from the header when omitted
+        #[arg(long)]
+        eye_format: Option<EyeFormat>,
+        #[arg(long, default_value_t = 0.6)]
+        min_confidence: f32,
+        #[arg(long, default_value_t = 60.0)]
+        resample_hz: f64,
+        #[arg(long, value_enum, default_value = "linear")]
+        interpolation: PupilInterpolationArg,
+        #[arg(long, default_value_t = 1.0)]
+        max_gap_s: f64,
+        #[arg(long, default_value_t = 0.05)]
+        smoothing_window_s: f64,
+    },
+    /// Event-locked pupil dilation responses for a run bundle's events
+    PupilErp {
+        /// Run bundle directory containing events.tsv (and run.json)
+        #[arg(long)]
+        bundle: PathBuf,
+        #[arg(long)]
+        eye: PathBuf,
+        /// Export layout; detected from the header when omitted
+        #[arg(long)]
+        eye_format: Option<EyeFormat>,
+        /// Event type to epoch (e.g. stim, response); `any` keeps every event
+        #[arg(long, default_value = "stim")]
+        event_type: String,
         #[arg(long, default_value_t = 0.5)]
+        pre_s: f64,
+        #[arg(long, default_value_t = 3.0)]
+        post_s: f64,
+        #[arg(long, value_enum, default_value = "subtractive")]
+        baseline: BaselineArg,
+        #[arg(long, default_value_t = 0.5)]
+        max_missing_fraction: f64,
+        /// Eye-tracker timestamp of bundle onset zero (defaults to the first eye sample)
+        #[arg(long, conflicts_with = "manifest_clock")]
+        offset_s: Option<f64>,
+        /// Eye timestamps are unix seconds; align using run.json start_time_unix
+        #[arg(long)]
+        manifest_clock: bool,
+        #[arg(long, default_value_t = 0.6)]
+        min_confidence: f32,
+        #[arg(long, default_value_t = 60.0)]
+        resample_hz: f64,
+    },
+    /// Detect fixations (I-VT or I-DT), saccades and blinks from gaze exports
+    GazeEvents {
+        #[arg(long)]
+        input: PathBuf,
+        /// Export layout; detected from the header when omitted
+        #[arg(long)]
+        eye_format: Option<EyeFormat>,
+        #[arg(long, value_enum, default_value = "ivt")]
+        method: FixationMethodArg,
+        #[arg(long, default_value_t = 30.0)]
+        velocity_threshold_deg_s: f64,
+        #[arg(long, default_value_t = 1.0)]
+        dispersion_deg: f64,
+        #[arg(long, default_value_t = 0.08)]
+        min_fixation_s: f64,
+        #[arg(long, default_value_t = 0.6)]
         min_confidence: f32,
+        #[arg(long, default_value_t = 1920.0)]
+        screen_width_px: f64,
+        #[arg(long, default_value_t = 1080.0)]
+        screen_height_px: f64,
+        #[arg(long, default_value_t = 531.0)]
+        screen_width_mm: f64,
+        #[arg(long, default_value_t = 299.0)]
+        screen_height_mm: f64,
+        #[arg(long, default_value_t = 600.0)]
+        distance_mm: f64,
     },
     /// Frequency-domain HRV (Welch PSD)
     HrvPsd {
@@ -169,12 +410,225 @@ enum Commands {
         #[arg(long)]
         input: Option<PathBuf>,
     },
-    /// Render RR series to a PNG via plotters
+    /// Render RR series to a PNG or SVG image
     HrvPlot {
         #[arg(long)]
         input: Option<PathBuf>,
+        /// Image to write; the format follows the extension (.png or .svg)
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Export a GUI figure, or any figure saved as JSON, to PNG or SVG
+    Plot {
+        /// Figure to draw from the inputs
+        #[arg(value_enum, required_unless_present = "figure")]
+        kind: Option<PlotKind>,
+        /// Figure JSON (`elf_lib::plot::Figure` or `Subplots`) to draw instead
+        #[arg(long, conflicts_with = "kind")]
+        figure: Option<PathBuf>,
+        /// Image to write; the format follows the extension (.png or .svg)
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, default_value_t = 800)]
+        width: u32,
+        #[arg(long, default_value_t = 480)]
+        height: u32,
+        /// RR intervals for rr, rr-histogram and psd instead of beats detected in a signal
+        #[arg(long)]
+        rr: Option<PathBuf>,
+        #[arg(long, default_value_t = 250.0)]
+        fs: f64,
+        #[arg(long)]
+        input: Option<PathBuf>,
+        #[arg(long)]
+        wfdb_header: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        wfdb_lead: usize,
+        #[arg(long)]
+        eeg_edf: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        eeg_channel: usize,
+        #[arg(long)]
+        annotations: Option<PathBuf>,
+        #[arg(long)]
+        bids_events: Option<PathBuf>,
+        /// Bins of the RR histogram
+        #[arg(long, default_value_t = 12)]
+        bins: usize,
+        /// RR resampling rate for the PSD
+        #[arg(long, default_value_t = 4.0)]
+        interp_fs: f64,
+    },
+    /// Self-contained HTML HRV report for one recording; print it for a PDF
+    HrvReport {
+        /// HTML report to write
+        #[arg(long)]
+        out: PathBuf,
+        /// Report heading (defaults to the input name)
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long, default_value_t = 250.0)]
+        fs: f64,
+        #[arg(long)]
+        input: Option<PathBuf>,
+        #[arg(long)]
+        wfdb_header: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        wfdb_lead: usize,
+        #[arg(long)]
+        eeg_edf: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        eeg_channel: usize,
+        /// Take the beats from annotations instead of the detector
+        #[arg(long)]
+        annotations: Option<PathBuf>,
+        #[arg(long)]
+        bids_events: Option<PathBuf>,
+        #[command(flatten)]
+        pipeline: EcgPipelineArgs,
+        /// Number of signal excerpts spread over the recording
+        #[arg(long, default_value_t = 3)]
+        excerpts: usize,
+        /// Length of each excerpt in seconds
+        #[arg(long, default_value_t = 10.0)]
+        excerpt_s: f64,
+        /// RR resampling rate for the PSD
+        #[arg(long, default_value_t = 4.0)]
+        interp_fs: f64,
+    },
+    /// Beat/HRV pipeline over many recordings in parallel, resumable from its job state
+    Batch {
+        /// Glob of recordings (quote it so the shell does not expand it)
+        #[arg(
+            long,
+            required_unless_present = "manifest",
+            conflicts_with = "manifest"
+        )]
+        glob: Option<String>,
+        /// File listing one recording per line, or a CSV/TSV with a `path` column
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        /// Per-file results, summary table and batch_state.json go here
+        #[arg(long)]
+        out_dir: PathBuf,
+        /// Worker threads (defaults to the number of CPUs)
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// Summary table inside --out-dir; format from the extension
+        #[arg(long, default_value = "summary.csv")]
+        summary: PathBuf,
+        /// Channel label or index (first channel when omitted)
+        #[arg(long)]
+        signal: Option<String>,
+        /// Sampling rate for plain sample files, which do not store one
+        #[arg(long)]
+        fs: Option<f64>,
+        #[command(flatten)]
+        pipeline: EcgPipelineArgs,
+        /// Run files that failed in an earlier attempt again
+        #[arg(long)]
+        retry_failed: bool,
+        /// Ignore an existing batch_state.json and process everything
+        #[arg(long)]
+        restart: bool,
+    },
+    /// Declarative pipelines: sources, stages and sinks from a TOML file
+    Pipeline {
+        #[command(subcommand)]
+        command: PipelineCommand,
+    },
+    /// Score every QRS detector against the reference beats of a directory of WFDB records,
+    /// clean and with added noise
+    BenchDetectors {
+        /// Directory searched recursively for <record>.hea with a reference annotation file
+        #[arg(long)]
+        dir: PathBuf,
+        /// Extension of the reference annotation files
+        #[arg(long, default_value = "atr")]
+        annotator: String,
+        #[arg(long, default_value_t = 0)]
+        lead: usize,
+        /// Detectors to compare, comma-separated (default: all)
+        #[arg(long, value_delimiter = ',')]
+        detectors: Vec<DetectorArg>,
+        /// Noise-stress SNRs in dB, comma-separated; records are always scored clean too
+        #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+        snr_db: Vec<f64>,
+        /// Noise sources for the stress test, comma-separated
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "baseline-wander,emg,electrode-motion"
+        )]
+        noise: Vec<NoiseArg>,
+        #[arg(long, default_value_t = 50.0)]
+        powerline_hz: f64,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Matching window between a detection and a reference beat
+        #[arg(long, default_value_t = DEFAULT_TOLERANCE_S * 1e3)]
+        tolerance_ms: f64,
+        #[command(flatten)]
+        pipeline: EcgPipelineArgs,
+    },
+    /// Generate a seeded synthetic ECG, PPG, respiration, EDA and pupil recording with ground
+    /// truth
+    Synth {
+        /// Recording to write; the extension picks the format (.hea, .edf, .parquet, .arrow,
+        /// .txt)
         #[arg(long)]
         out: PathBuf,
+        /// Channel written to a .txt recording: ecg, ppg, resp, eda or pupil
+        #[arg(long, default_value = "ecg")]
+        signal: String,
+        /// Ground-truth events as a BIDS events.tsv
+        #[arg(long)]
+        truth: Option<PathBuf>,
+        /// True R-peak sample indices, one per line
+        #[arg(long)]
+        beats: Option<PathBuf>,
+        /// Pupil trace as a Pupil Labs CSV (for pupil-preprocess and friends)
+        #[arg(long)]
+        pupil_csv: Option<PathBuf>,
+        #[arg(long, default_value_t = 250.0)]
+        fs: f64,
+        #[arg(long, default_value_t = 60.0)]
+        duration_s: f64,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        #[arg(long, default_value_t = 60.0)]
+        heart_rate_bpm: f64,
+        /// Standard deviation of the heart rate
+        #[arg(long, default_value_t = 1.0)]
+        heart_rate_std_bpm: f64,
+        #[arg(long, default_value_t = 0.1)]
+        lf_hz: f64,
+        #[arg(long, default_value_t = 0.25)]
+        hf_hz: f64,
+        #[arg(long, default_value_t = 0.5)]
+        lf_hf_ratio: f64,
+        /// Probability that a beat is a premature ventricular beat (0-0.5)
+        #[arg(long, default_value_t = 0.0)]
+        ectopic_rate: f64,
+        /// Noise sources mixed into the ECG, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        noise: Vec<NoiseArg>,
+        /// Signal-to-noise ratio of the noise mix in dB
+        #[arg(long, default_value_t = 20.0)]
+        snr_db: f64,
+        #[arg(long, default_value_t = 50.0)]
+        powerline_hz: f64,
+        /// Breathing rate (defaults to the HF centre frequency)
+        #[arg(long)]
+        resp_rate_bpm: Option<f64>,
+        /// Skin conductance responses per minute
+        #[arg(long, default_value_t = 2.0)]
+        scr_per_min: f64,
+        #[arg(long, default_value_t = 12.0)]
+        blink_per_min: f64,
+        /// Spacing of the stimuli that evoke pupil dilations; 0 disables them
+        #[arg(long, default_value_t = 10.0)]
+        stimulus_interval_s: f64,
     },
     /// Simulate a run from design + trial specs and emit events/manifest bundle
     RunSimulate {
@@ -188,33 +642,155 @@ enum Commands {
         ses: String,
         #[arg(long, default_value = "01")]
         run: String,
+        /// Bundle directory to write (events.tsv, events.json, run.json)
         #[arg(long)]
         out: PathBuf,
     },
-    /// Load a BITalino / OpenSignals CSV and run the ECG HRV pipeline
+    /// Electrodermal activity: tonic/phasic split, SCR table and event-related SCRs
+    Eda {
+        /// Plain sample file (one value per line, µS) or BITalino CSV with --signal
+        #[arg(long)]
+        input: PathBuf,
+        /// BITalino / OpenSignals column holding EDA
+        #[arg(long)]
+        signal: Option<String>,
+        #[arg(long)]
+        fs: Option<f64>,
+        /// Run bundle directory whose events.tsv onsets define SCR windows
+        #[arg(long)]
+        bundle: Option<PathBuf>,
+        #[arg(long, default_value = "stim")]
+        event_type: String,
+        /// EDA time (s) of bundle onset zero
+        #[arg(long, default_value_t = 0.0)]
+        offset_s: f64,
+        #[arg(long, default_value_t = 10.0)]
+        tonic_window_s: f64,
+        #[arg(long, default_value_t = 0.02)]
+        min_amplitude: f64,
+        #[arg(long, default_value_t = 1.0)]
+        latency_min_s: f64,
+        #[arg(long, default_value_t = 4.0)]
+        latency_max_s: f64,
+    },
+    /// Respiration: breath-by-breath rate, Ti/Te, tidal variability and RSA
+    Resp {
+        /// Plain sample file or BITalino CSV with --signal
+        #[arg(long)]
+        input: PathBuf,
+        /// BITalino / OpenSignals column holding the respiration belt
+        #[arg(long)]
+        signal: Option<String>,
+        #[arg(long)]
+        fs: Option<f64>,
+        /// ECG column in the same CSV; enables spectral and peak-valley RSA
+        #[arg(long)]
+        ecg_signal: Option<String>,
+        /// RR intervals (seconds, one per line); enables spectral RSA
+        #[arg(long, conflicts_with = "ecg_signal")]
+        rr: Option<PathBuf>,
+        #[arg(long, default_value_t = 4.0)]
+        interp_fs: f64,
+        #[arg(long, default_value_t = 0.05)]
+        rsa_half_width_hz: f64,
+        #[arg(long, default_value_t = 1.0)]
+        min_breath_s: f64,
+    },
+    /// Run the ECG → HRV pipeline over a BIDS dataset and write derivatives/elf
+    BidsAnalyze {
+        /// Dataset root (folder holding dataset_description.json)
+        #[arg(long)]
+        root: PathBuf,
+        #[arg(long)]
+        subject: Option<String>,
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long)]
+        task: Option<String>,
+        /// Physio column holding the ECG (BIDS recommends `cardiac`)
+        #[arg(long, default_value = "cardiac")]
+        column: String,
+        /// Case-insensitive part of the EDF channel label used as ECG in *_eeg.edf files
+        #[arg(long, default_value = "ECG")]
+        eeg_channel_label: String,
+        #[arg(long, default_value_t = 4.0)]
+        interp_fs: f64,
+    },
+    /// Print EDF/EDF+/BDF header, channels and EDF+ annotations as JSON
+    EdfInfo {
+        #[arg(long)]
+        input: PathBuf,
+        /// Omit the annotation list (reading it touches every data record)
+        #[arg(long)]
+        no_annotations: bool,
+    },
+    /// Print the streams of an XDF (LabRecorder) file, with clock-corrected times and markers
+    XdfInfo {
+        #[arg(long)]
+        input: PathBuf,
+    },
+    /// Write a signal as a WFDB record (.hea/.dat) plus detected beats as an annotation file
+    WfdbExport {
+        #[arg(long, default_value_t = 250.0)]
+        fs: f64,
+        #[arg(long)]
+        input: Option<PathBuf>,
+        #[arg(long)]
+        wfdb_header: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        wfdb_lead: usize,
+        #[arg(long)]
+        eeg_edf: Option<PathBuf>,
+        #[arg(long, default_value_t = 0)]
+        eeg_channel: usize,
+        /// Directory receiving <record>.hea, <record>.dat and <record>.<annotator>
+        #[arg(long)]
+        out_dir: PathBuf,
+        #[arg(long, default_value = "elf")]
+        record: String,
+        /// WFDB storage format: 16, 61, 80, 160, 212, 310, 311, 24 or 32
+        #[arg(long, default_value_t = 16)]
+        wfdb_format: u32,
+        #[arg(long, default_value = "ECG")]
+        description: String,
+        #[arg(long, default_value = "mV")]
+        units: String,
+        /// Annotation file extension for detected beats
+        #[arg(long, default_value = "atr")]
+        annotator: String,
+        /// Skip beat detection and write only the signal
+        #[arg(long)]
+        no_beats: bool,
+    },
+    /// Load a BITalino CSV or OpenSignals text export and run the ECG HRV pipeline
     Bitalino {
         #[arg(long)]
         input: PathBuf,
+        /// Column, channel label or sensor type (e.g. analog0, A2, ECG)
         #[arg(long, default_value = "analog0")]
         signal: String,
         #[arg(long)]
         fs: Option<f64>,
     },
-    /// Load OpenBCI CSV and run the ECG HRV pipeline
+    /// Load an OpenBCI CSV or OpenBCI GUI recording and run the ECG HRV pipeline
     OpenBci {
         #[arg(long)]
         input: PathBuf,
+        /// Column name, or ChN for the N-th EXG channel
         #[arg(long, default_value = "Ch1")]
         channel: String,
         #[arg(long)]
         fs: Option<f64>,
     },
-    /// Validate a dataset spec JSON against the computed HRV summaries
+    /// Validate a dataset spec JSON against recomputed HRV, SQI and beat metrics
     DatasetValidate {
         #[arg(long)]
         spec: PathBuf,
         #[arg(long)]
         json: Option<PathBuf>,
+        /// Also write the results as JUnit XML, one testcase per dataset case.
+        #[arg(long)]
+        junit: Option<PathBuf>,
         #[arg(long)]
         update_spec: bool,
     },
@@ -227,9 +803,31 @@ enum Commands {
     },
 }
 
+impl Commands {
+    fn takes_eye_format(&self) -> bool {
+        matches!(
+            self,
+            Commands::PupilNormalize { .. }
+                | Commands::PupilPreprocess { .. }
+                | Commands::PupilErp { .. }
+                | Commands::GazeEvents { .. }
+        )
+    }
+}
+
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    let (format, legacy_eye_format) = match cli.format {
+        Some(FormatArg::Eye(_)) if !cli.command.takes_eye_format() => bail!(
+            "--format pupil-labs|tobii only applies to pupil-normalize, pupil-preprocess, \
+             pupil-erp and gaze-events"
+        ),
+        Some(FormatArg::Eye(layout)) => (None, Some(layout)),
+        Some(FormatArg::Output(format)) => (Some(format), None),
+        None => (None, None),
+    };
+    let output = Output::new(format, cli.output)?;
     match cli.command {
         Commands::EcgFindRpeaks {
             fs,
@@ -240,6 +838,7 @@ fn main() -> Result<()> {
             eeg_edf,
             eeg_channel,
         } => cmd_ecg_find_rpeaks(
+            &output,
             fs,
             min_rr_s,
             input.as_deref(),
@@ -248,15 +847,10 @@ fn main() -> Result<()> {
             eeg_edf.as_deref(),
             eeg_channel,
         )?,
-        Commands::HrvTime { input } => cmd_hrv_time(input.as_deref())?,
+        Commands::HrvTime { input } => cmd_hrv_time(&output, input.as_deref())?,
         Commands::BeatHrvPipeline {
             fs,
-            lowcut_hz,
-            highcut_hz,
-            integration_window_s,
-            min_rr_s,
-            threshold_scale,
-            search_back_s,
+            pipeline,
             input,
             wfdb_header,
             wfdb_lead,
@@ -264,14 +858,12 @@ fn main() -> Result<()> {
             eeg_edf,
             eeg_channel,
             bids_events,
+            stream,
+            block_size,
         } => cmd_beat_hrv_pipeline(
+            &output,
             fs,
-            lowcut_hz,
-            highcut_hz,
-            integration_window_s,
-            min_rr_s,
-            threshold_scale,
-            search_back_s,
+            pipeline.into(),
             input.as_deref(),
             wfdb_header.as_deref(),
             wfdb_lead,
@@ -279,41 +871,543 @@ fn main() -> Result<()> {
             eeg_edf.as_deref(),
             eeg_channel,
             bids_events.as_deref(),
+            stream.then_some(block_size),
         )?,
         Commands::PupilNormalize {
             input,
-            format,
+            eye_format: format,
             min_confidence,
-        } => cmd_pupil_normalize(&input, format, min_confidence)?,
-        Commands::HrvPsd { input, interp_fs } => cmd_hrv_psd(input.as_deref(), interp_fs)?,
-        Commands::HrvNonlinear { input } => cmd_hrv_nonlinear(input.as_deref())?,
-        Commands::HrvPlot { input, out } => cmd_hrv_plot(input.as_deref(), &out)?,
-        Commands::Bitalino { input, signal, fs } => {
-            cmd_bitalino_hrv(&input, &signal, fs.unwrap_or(0.0))?
+        } => cmd_pupil_normalize(
+            &output,
+            &input,
+            eye_format(&input, format.or(legacy_eye_format))?,
+            min_confidence,
+        )?,
+        Commands::PupilPreprocess {
+            input,
+            eye_format: format,
+            min_confidence,
+            resample_hz,
+            interpolation,
+            max_gap_s,
+            smoothing_window_s,
+        } => {
+            let cfg = PupilPipelineConfig {
+                min_confidence,
+                resample_hz,
+                interpolation: interpolation.into(),
+                max_gap_s,
+                smoothing_window_s,
+                ..PupilPipelineConfig::default()
+            };
+            cmd_pupil_preprocess(
+                &output,
+                &input,
+                eye_format(&input, format.or(legacy_eye_format))?,
+                &cfg,
+            )?
         }
-        Commands::OpenBci { input, channel, fs } => {
-            cmd_openbci_hrv(&input, &channel, fs.unwrap_or(0.0))?
+        Commands::GazeEvents {
+            input,
+            eye_format: format,
+            method,
+            velocity_threshold_deg_s,
+            dispersion_deg,
+            min_fixation_s,
+            min_confidence,
+            screen_width_px,
+            screen_height_px,
+            screen_width_mm,
+            screen_height_mm,
+            distance_mm,
+        } => {
+            let screen = ScreenGeometry {
+                width_px: screen_width_px,
+                height_px: screen_height_px,
+                width_mm: screen_width_mm,
+                height_mm: screen_height_mm,
+                distance_mm,
+            };
+            let cfg = GazeEventConfig {
+                method: match method {
+                    FixationMethodArg::Ivt => FixationMethod::Ivt {
+                        velocity_threshold_deg_s,
+                    },
+                    FixationMethodArg::Idt => FixationMethod::Idt { dispersion_deg },
+                },
+                min_fixation_s,
+                ..GazeEventConfig::default()
+            };
+            cmd_gaze_events(
+                &output,
+                &input,
+                eye_format(&input, format.or(legacy_eye_format))?,
+                min_confidence,
+                &screen,
+                &cfg,
+            )?
         }
-        Commands::DatasetValidate {
-            spec,
-            json,
-            update_spec,
-        } => cmd_dataset_validate(&spec, json.as_deref(), update_spec)?,
-        Commands::Sqi { input, fs } => cmd_sqi(input.as_deref(), fs)?,
-        Commands::RunSimulate {
-            design,
-            trials,
-            sub,
-            ses,
-            run,
+        Commands::PupilErp {
+            bundle,
+            eye,
+            eye_format: format,
+            event_type,
+            pre_s,
+            post_s,
+            baseline,
+            max_missing_fraction,
+            offset_s,
+            manifest_clock,
+            min_confidence,
+            resample_hz,
+        } => {
+            let preprocess = PupilPipelineConfig {
+                min_confidence,
+                resample_hz,
+                ..PupilPipelineConfig::default()
+            };
+            let erp = PupilErpConfig {
+                pre_s,
+                post_s,
+                baseline: baseline.into(),
+                max_missing_fraction,
+            };
+            cmd_pupil_erp(
+                &output,
+                &bundle,
+                &eye,
+                eye_format(&eye, format.or(legacy_eye_format))?,
+                &event_type,
+                offset_s,
+                manifest_clock,
+                &preprocess,
+                &erp,
+            )?
+        }
+        Commands::HrvPsd { input, interp_fs } => cmd_hrv_psd(&output, input.as_deref(), interp_fs)?,
+        Commands::HrvNonlinear { input } => cmd_hrv_nonlinear(&output, input.as_deref())?,
+        Commands::HrvPlot { input, out } => cmd_hrv_plot(&output, input.as_deref(), out)?,
+        Commands::Plot {
+            kind,
+            figure,
             out,
-        } => cmd_run_simulate(&design, &trials, &sub, &ses, &run, &out)?,
-    }
-    Ok(())
-}
-
+            width,
+            height,
+            rr,
+            fs,
+            input,
+            wfdb_header,
+            wfdb_lead,
+            eeg_edf,
+            eeg_channel,
+            annotations,
+            bids_events,
+            bins,
+            interp_fs,
+        } => {
+            let spec = match (figure, kind) {
+                (Some(path), _) => {
+                    let file =
+                        File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+                    serde_json::from_reader(io::BufReader::new(file))
+                        .with_context(|| format!("parsing figure {}", path.display()))?
+                }
+                (None, Some(kind)) => {
+                    let signal = || {
+                        load_time_series(
+                            fs,
+                            input.as_deref(),
+                            wfdb_header.as_deref(),
+                            wfdb_lead,
+                            eeg_edf.as_deref(),
+                            eeg_channel,
+                        )
+                    };
+                    let rr_series = || -> Result<RRSeries> {
+                        if rr.is_some() {
+                            return rr_series_from_input(rr.as_deref());
+                        }
+                        let ts = signal()?;
+                        let events = match load_annotation_events(
+                            annotations.as_deref(),
+                            bids_events.as_deref(),
+                            ts.fs,
+                        )? {
+                            Some(events) => events,
+                            None => detect_r_peaks_with_config(&ts, &EcgPipelineConfig::default()),
+                        };
+                        Ok(RRSeries::from_events(&events, ts.fs))
+                    };
+                    PlotSpec::Figure(match kind {
+                        PlotKind::Ecg => {
+                            let ts = signal()?;
+                            let beats = load_annotation_events(
+                                annotations.as_deref(),
+                                bids_events.as_deref(),
+                                ts.fs,
+                            )?;
+                            figure_from_ecg(&ts, beats.as_ref())
+                        }
+                        PlotKind::Rr => figure_from_rr(&rr_series()?),
+                        PlotKind::RrHistogram => rr_histogram_figure(&rr_series()?, bins)
+                            .ok_or_else(|| anyhow!("not enough distinct RR intervals to bin"))?,
+                        PlotKind::Psd => figure_from_psd(&hrv_psd(&rr_series()?, interp_fs)),
+                    })
+                }
+                (None, None) => unreachable!("clap requires a figure kind or --figure"),
+            };
+            let mut backend = PlottersBackend::new(&out)?.size(width, height);
+            match spec {
+                PlotSpec::Figure(figure) => backend.draw(&figure)?,
+                PlotSpec::Subplots(plots) => backend.draw_subplots(&plots)?,
+            }
+            output.value(&ImageSummary { out, width, height })?
+        }
+        Commands::HrvReport {
+            out: html,
+            title,
+            fs,
+            input,
+            wfdb_header,
+            wfdb_lead,
+            eeg_edf,
+            eeg_channel,
+            annotations,
+            bids_events,
+            pipeline,
+            excerpts,
+            excerpt_s,
+            interp_fs,
+        } => {
+            let ts = load_time_series(
+                fs,
+                input.as_deref(),
+                wfdb_header.as_deref(),
+                wfdb_lead,
+                eeg_edf.as_deref(),
+                eeg_channel,
+            )?;
+            let (events, beat_source) = match load_annotation_events(
+                annotations.as_deref(),
+                bids_events.as_deref(),
+                ts.fs,
+            )? {
+                Some(events) => {
+                    let path = bids_events.as_deref().or(annotations.as_deref());
+                    let path = path.map(|p| p.display().to_string()).unwrap_or_default();
+                    (events, report::BeatSource::Annotations { path })
+                }
+                None => {
+                    let cfg = EcgPipelineConfig::from(pipeline);
+                    let events = detect_r_peaks_with_config(&ts, &cfg);
+                    (events, report::BeatSource::Detector(cfg))
+                }
+            };
+            let source = [&wfdb_header, &eeg_edf, &input]
+                .into_iter()
+                .flatten()
+                .next()
+                .map_or_else(|| "stdin".to_string(), |p| p.display().to_string());
+            let options = report::ReportOptions {
+                title,
+                excerpts: excerpts.max(1),
+                excerpt_s,
+                interp_fs,
+            };
+            let report = report::HrvReport::build(&ts, &events, source, beat_source, options);
+            report::write_report(&report, &html)?;
+            output.value(&report.summary(html))?;
+        }
+        Commands::Eda {
+            input,
+            signal,
+            fs,
+            bundle,
+            event_type,
+            offset_s,
+            tonic_window_s,
+            min_amplitude,
+            latency_min_s,
+            latency_max_s,
+        } => {
+            let cfg = EdaConfig {
+                tonic_window_s,
+                min_amplitude,
+                latency_min_s,
+                latency_max_s,
+                ..EdaConfig::default()
+            };
+            cmd_eda(
+                &output,
+                &input,
+                signal.as_deref(),
+                fs,
+                bundle.as_deref(),
+                &event_type,
+                offset_s,
+                &cfg,
+            )?
+        }
+        Commands::Resp {
+            input,
+            signal,
+            fs,
+            ecg_signal,
+            rr,
+            interp_fs,
+            rsa_half_width_hz,
+            min_breath_s,
+        } => {
+            let cfg = RespConfig {
+                min_breath_s,
+                ..RespConfig::default()
+            };
+            cmd_resp(
+                &output,
+                &input,
+                signal.as_deref(),
+                fs,
+                ecg_signal.as_deref(),
+                rr.as_deref(),
+                interp_fs,
+                rsa_half_width_hz,
+                &cfg,
+            )?
+        }
+        Commands::BidsAnalyze {
+            root,
+            subject,
+            session,
+            task,
+            column,
+            eeg_channel_label,
+            interp_fs,
+        } => {
+            let query = bids_io::BidsQuery {
+                subject,
+                session,
+                task,
+                ..bids_io::BidsQuery::default()
+            };
+            cmd_bids_analyze(
+                &output,
+                &root,
+                &query,
+                &column,
+                &eeg_channel_label,
+                interp_fs,
+            )?
+        }
+        Commands::EdfInfo {
+            input,
+            no_annotations,
+        } => cmd_edf_info(&output, &input, !no_annotations)?,
+        Commands::XdfInfo { input } => cmd_xdf_info(&output, &input)?,
+        Commands::WfdbExport {
+            fs,
+            input,
+            wfdb_header,
+            wfdb_lead,
+            eeg_edf,
+            eeg_channel,
+            out_dir,
+            record,
+            wfdb_format,
+            description,
+            units,
+            annotator,
+            no_beats,
+        } => {
+            let ts = load_time_series(
+                fs,
+                input.as_deref(),
+                wfdb_header.as_deref(),
+                wfdb_lead,
+                eeg_edf.as_deref(),
+                eeg_channel,
+            )?;
+            let signal = wfdb_io::WfdbWriteSignal {
+                description: &description,
+                units: &units,
+                data: &ts.data,
+            };
+            let annotator = (!no_beats).then_some(annotator.as_str());
+            cmd_wfdb_export(
+                &output,
+                &ts,
+                signal,
+                &out_dir,
+                &record,
+                wfdb_format,
+                annotator,
+            )?
+        }
+        Commands::Bitalino { input, signal, fs } => {
+            cmd_bitalino_hrv(&output, &input, &signal, fs.unwrap_or(0.0))?
+        }
+        Commands::OpenBci { input, channel, fs } => {
+            cmd_openbci_hrv(&output, &input, &channel, fs.unwrap_or(0.0))?
+        }
+        Commands::DatasetValidate {
+            spec,
+            json,
+            junit,
+            update_spec,
+        } => dataset::cmd_dataset_validate(
+            &output,
+            &spec,
+            json.as_deref(),
+            junit.as_deref(),
+            update_spec,
+        )?,
+        Commands::Sqi { input, fs } => cmd_sqi(&output, input.as_deref(), fs)?,
+        Commands::Batch {
+            glob,
+            manifest,
+            out_dir,
+            jobs,
+            summary,
+            signal,
+            fs,
+            pipeline,
+            retry_failed,
+            restart,
+        } => {
+            let inputs = match (&glob, &manifest) {
+                (_, Some(manifest)) => batch::inputs_from_manifest(manifest)?,
+                (Some(pattern), None) => batch::inputs_from_glob(pattern)?,
+                (None, None) => unreachable!("clap requires --glob or --manifest"),
+            };
+            let options = batch::BatchOptions {
+                out_dir,
+                jobs: jobs
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+                settings: batch::BatchSettings {
+                    signal,
+                    fs,
+                    pipeline: pipeline.into(),
+                },
+                summary,
+                retry_failed,
+                restart,
+            };
+            output.value(&batch::run_batch(&inputs, &options)?)?
+        }
+        Commands::Pipeline {
+            command:
+                PipelineCommand::Run {
+                    file,
+                    out_dir,
+                    provenance,
+                },
+        } => output.value(&pipeline::run_pipeline(
+            &file,
+            out_dir.as_deref(),
+            provenance.as_deref(),
+        )?)?,
+        Commands::BenchDetectors {
+            dir,
+            annotator,
+            lead,
+            detectors,
+            snr_db,
+            noise,
+            powerline_hz,
+            seed,
+            tolerance_ms,
+            pipeline,
+        } => {
+            let detectors = if detectors.is_empty() {
+                QrsDetector::ALL.to_vec()
+            } else {
+                detectors.into_iter().map(QrsDetector::from).collect()
+            };
+            let options = bench::BenchOptions {
+                dir,
+                annotator,
+                lead,
+                detectors,
+                pipeline: pipeline.into(),
+                snr_db,
+                noise: noise.into_iter().map(NoiseKind::from).collect(),
+                powerline_hz,
+                seed,
+                tolerance_s: tolerance_ms / 1e3,
+            };
+            output.value(&bench::run_bench(&options)?)?
+        }
+        Commands::Synth {
+            out,
+            signal,
+            truth,
+            beats,
+            pupil_csv,
+            fs,
+            duration_s,
+            seed,
+            heart_rate_bpm,
+            heart_rate_std_bpm,
+            lf_hz,
+            hf_hz,
+            lf_hf_ratio,
+            ectopic_rate,
+            noise,
+            snr_db,
+            powerline_hz,
+            resp_rate_bpm,
+            scr_per_min,
+            blink_per_min,
+            stimulus_interval_s,
+        } => {
+            let config = SynthConfig {
+                fs,
+                duration_s,
+                seed,
+                heart_rate_bpm,
+                heart_rate_std_bpm,
+                lf_hz,
+                hf_hz,
+                lf_hf_ratio,
+                ectopic_rate,
+                noise: NoiseConfig {
+                    kinds: noise.into_iter().map(NoiseKind::from).collect(),
+                    snr_db,
+                    powerline_hz,
+                },
+                resp_rate_bpm,
+                scr_per_min,
+                blink_per_min,
+                stimulus_interval_s,
+                ..SynthConfig::default()
+            };
+            let rec = generate(&config)?;
+            let snr = (!config.noise.kinds.is_empty()).then_some(snr_db);
+            let outputs = synth::SynthOutputs {
+                out,
+                signal,
+                truth,
+                beats,
+                pupil_csv,
+            };
+            output.value(&synth::write_synth(&rec, &outputs, snr)?)?
+        }
+        Commands::RunSimulate {
+            design,
+            trials,
+            sub,
+            ses,
+            run,
+            out,
+        } => cmd_run_simulate(&output, &design, &trials, &sub, &ses, &run, &out)?,
+    }
+    Ok(())
+}
+
+/// Plain values from a text file, stdin, or a Parquet/Arrow table (RR tables yield `rr_s`,
+/// signal tables their first channel).
 fn read_samples(input: Option<&Path>) -> Result<Vec<f64>> {
     match input {
+        Some(path) if parquet_io::is_table_path(path) => parquet_io::read_values(path),
         Some(path) => text_io::read_f64_series(path),
         None => {
             let mut buf = String::new();
@@ -323,626 +1417,88 @@ fn read_samples(input: Option<&Path>) -> Result<Vec<f64>> {
     }
 }
 
+/// RR intervals in seconds from a table, a device/app export (Polar HRM, Garmin FIT, Kubios,
+/// Elite HRV) or plain text on stdin; millisecond exports are converted.
 fn rr_series_from_input(input: Option<&Path>) -> Result<RRSeries> {
-    let rr = read_samples(input)?;
-    Ok(RRSeries { rr })
+    match input {
+        Some(path) if parquet_io::is_table_path(path) => Ok(RRSeries {
+            rr: parquet_io::read_values(path)?,
+        }),
+        Some(path) => Ok(rr_io::read_rr_file(path)?.rr),
+        None => {
+            let mut buf = String::new();
+            io::stdin().read_to_string(&mut buf)?;
+            Ok(rr_io::parse_rr_text(&buf)?.rr)
+        }
+    }
 }
 
-fn cmd_hrv_psd(input: Option<&Path>, interp_fs: f64) -> Result<()> {
+fn cmd_hrv_psd(out: &Output, input: Option<&Path>, interp_fs: f64) -> Result<()> {
     let rr = rr_series_from_input(input)?;
     let psd = hrv_psd(&rr, interp_fs);
-    println!("{}", serde_json::to_string(&psd)?);
-    Ok(())
+    out.value(&psd)
 }
 
-fn cmd_hrv_nonlinear(input: Option<&Path>) -> Result<()> {
+fn cmd_hrv_nonlinear(out: &Output, input: Option<&Path>) -> Result<()> {
     let rr = rr_series_from_input(input)?;
     let nonlinear = hrv_nonlinear(&rr);
-    println!("{}", serde_json::to_string(&nonlinear)?);
-    Ok(())
+    out.value(&nonlinear)
 }
 
-fn cmd_hrv_plot(input: Option<&Path>, out: &Path) -> Result<()> {
+/// Printed by the figure commands once the image is written.
+#[derive(Debug, Serialize)]
+struct ImageSummary {
+    out: PathBuf,
+    width: u32,
+    height: u32,
+}
+
+impl Tabular for ImageSummary {}
+
+fn cmd_hrv_plot(output: &Output, input: Option<&Path>, out: PathBuf) -> Result<()> {
     let rr = rr_series_from_input(input)?;
-    let fig = figure_from_rr(&rr);
-    draw_plotters_figure(out, &fig)?;
-    Ok(())
+    PlottersBackend::new(&out)?.draw(&figure_from_rr(&rr))?;
+    let (width, height) = PlottersBackend::DEFAULT_SIZE;
+    output.value(&ImageSummary { out, width, height })
 }
 
-fn cmd_bitalino_hrv(path: &Path, signal: &str, fs_override: f64) -> Result<()> {
+fn cmd_bitalino_hrv(out: &Output, path: &Path, signal: &str, fs_override: f64) -> Result<()> {
     let mut ts = bitalino_io::read_bitalino_csv(path, signal)?;
     if fs_override > 0.0 {
         ts.fs = fs_override;
     }
-    let result = run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default());
-    println!("{}", serde_json::to_string(&result)?);
-    Ok(())
+    out.value(&run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default()))
 }
 
-fn cmd_openbci_hrv(path: &Path, channel: &str, fs_override: f64) -> Result<()> {
+fn cmd_openbci_hrv(out: &Output, path: &Path, channel: &str, fs_override: f64) -> Result<()> {
     let mut ts = openbci_io::read_openbci_csv(path, channel)?;
     if fs_override > 0.0 {
         ts.fs = fs_override;
     }
-    let result = run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default());
-    println!("{}", serde_json::to_string(&result)?);
-    Ok(())
-}
-
-fn cmd_dataset_validate(spec_path: &Path, json: Option<&Path>, update_spec: bool) -> Result<()> {
-    let (mut spec, mut spec_value) = load_dataset_spec(spec_path)?;
-    let repo_root = workspace_root();
-    let mode = if update_spec {
-        DatasetValidatorMode::Update
-    } else {
-        DatasetValidatorMode::Validate
-    };
-
-    let report = run_dataset_spec(&mut spec, &mut spec_value, &repo_root, mode)?;
-
-    if update_spec {
-        let file = File::create(spec_path)
-            .with_context(|| format!("failed to write spec {}", spec_path.display()))?;
-        serde_json::to_writer_pretty(file, &spec_value)?;
-        println!("dataset spec updated at {}", spec_path.display());
-    }
-
-    for case in &report.cases {
-        println!("{}", case.message);
-    }
-    if let Some(suite_summary) = &report.suite {
-        println!(
-            "suite {} {} ({} cases)",
-            suite_summary.name, suite_summary.status, suite_summary.cases
-        );
-    }
-    if let Some(path) = json {
-        let file = File::create(path)
-            .with_context(|| format!("failed to write report {}", path.display()))?;
-        serde_json::to_writer_pretty(
-            file,
-            &report
-                .cases
-                .iter()
-                .map(|entry| &entry.result)
-                .collect::<Vec<_>>(),
-        )?;
-        println!("dataset report written to {}", path.display());
-    } else {
-        println!(
-            "{}",
-            serde_json::to_string(
-                &report
-                    .cases
-                    .iter()
-                    .map(|entry| &entry.result)
-                    .collect::<Vec<_>>()
-            )?
-        );
-    }
-    Ok(())
-}
-
-fn load_dataset_spec(spec_path: &Path) -> Result<(DatasetSpec, Value)> {
-    let spec_src = fs::read_to_string(spec_path)
-        .with_context(|| format!("failed to open spec {}", spec_path.display()))?;
-    let spec_value: Value =
-        serde_json::from_str(&spec_src).context("failed to parse dataset spec")?;
-    let spec: DatasetSpec =
-        serde_json::from_value(spec_value.clone()).context("failed to deserialize dataset spec")?;
-    Ok((spec, spec_value))
-}
-
-fn run_dataset_spec(
-    spec: &mut DatasetSpec,
-    spec_value: &mut Value,
-    repo_root: &Path,
-    mode: DatasetValidatorMode,
-) -> Result<DatasetReport> {
-    let mut report = DatasetReport::default();
-    match (spec, spec_value) {
-        (DatasetSpec::Case(case), value) => {
-            let result = validate_case(case, repo_root, &CaseDefaults::default(), mode)?;
-            if matches!(mode, DatasetValidatorMode::Update) {
-                update_case_value(value, result.time.as_ref(), result.psd.as_ref())?;
-            }
-            let message = format!(
-                "dataset {} {}",
-                case.name,
-                match mode {
-                    DatasetValidatorMode::Validate => "validated",
-                    DatasetValidatorMode::Update => "updated",
-                }
-            );
-            report.cases.push(DatasetCaseReport { result, message });
-        }
-        (DatasetSpec::Suite(suite), Value::Object(map)) => {
-            let defaults = CaseDefaults {
-                fs: suite.fs,
-                interp_fs: suite.interp_fs,
-                tolerance: suite.tolerance,
-            };
-            let cases_value = map
-                .get_mut("cases")
-                .and_then(|v| v.as_array_mut())
-                .ok_or_else(|| anyhow!("dataset suite {} missing cases array", suite.name))?;
-            if cases_value.len() != suite.cases.len() {
-                anyhow::bail!(
-                    "dataset suite {} cases count mismatch between spec and JSON",
-                    suite.name
-                );
-            }
-            for (case, case_value) in suite.cases.iter_mut().zip(cases_value.iter_mut()) {
-                let result = validate_case(case, repo_root, &defaults, mode)?;
-                if matches!(mode, DatasetValidatorMode::Update) {
-                    update_case_value(case_value, result.time.as_ref(), result.psd.as_ref())?;
-                }
-                let message = format!(
-                    "dataset {} {}",
-                    case.name,
-                    match mode {
-                        DatasetValidatorMode::Validate => "validated",
-                        DatasetValidatorMode::Update => "updated",
-                    }
-                );
-                report.cases.push(DatasetCaseReport { result, message });
-            }
-            report.suite = Some(DatasetSuiteSummary {
-                name: suite.name.clone(),
-                cases: suite.cases.len(),
-                status: match mode {
-                    DatasetValidatorMode::Validate => "validated".into(),
-                    DatasetValidatorMode::Update => "processed".into(),
-                },
-            });
-        }
-        _ => anyhow::bail!("dataset spec had unexpected shape"),
-    }
-    Ok(report)
-}
-
-#[derive(Default)]
-struct DatasetReport {
-    cases: Vec<DatasetCaseReport>,
-    suite: Option<DatasetSuiteSummary>,
-}
-
-struct DatasetCaseReport {
-    result: DatasetResult,
-    message: String,
-}
-
-struct DatasetSuiteSummary {
-    name: String,
-    cases: usize,
-    status: String,
-}
-
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum DatasetSpec {
-    Suite(DatasetSuite),
-    Case(DatasetCase),
-}
-
-#[derive(Deserialize)]
-struct DatasetSuite {
-    name: String,
-    #[serde(default)]
-    fs: Option<f64>,
-    #[serde(default)]
-    interp_fs: Option<f64>,
-    #[serde(default)]
-    tolerance: Option<f64>,
-    cases: Vec<DatasetCase>,
-}
-
-#[derive(Default, Clone, Copy)]
-struct CaseDefaults {
-    fs: Option<f64>,
-    interp_fs: Option<f64>,
-    tolerance: Option<f64>,
-}
-
-#[derive(Clone, Copy)]
-enum DatasetValidatorMode {
-    Validate,
-    Update,
-}
-
-#[derive(Serialize)]
-struct TimeMetricsRecord {
-    avnn: f64,
-    sdnn: f64,
-    rmssd: f64,
-    pnn50: f64,
-    tolerance: f64,
-}
-
-#[derive(Serialize)]
-struct PsdMetricsRecord {
-    lf: f64,
-    hf: f64,
-    vlf: f64,
-    lf_hf: f64,
-    total_power: f64,
-    tolerance: f64,
-}
-
-#[derive(Serialize)]
-struct DatasetResult {
-    name: String,
-    status: String,
-    time: Option<TimeMetricsRecord>,
-    psd: Option<PsdMetricsRecord>,
-}
-
-#[derive(Deserialize, Clone)]
-struct DatasetCase {
-    name: String,
-    #[serde(default)]
-    input: Option<String>,
-    #[serde(default)]
-    format: Option<String>,
-    #[serde(default)]
-    fs: Option<f64>,
-    #[serde(default)]
-    interp_fs: Option<f64>,
-    #[serde(default)]
-    tolerance: Option<f64>,
-    #[serde(default)]
-    wfdb_header: Option<String>,
-    #[serde(default)]
-    wfdb_lead: Option<usize>,
-    #[serde(default)]
-    annotations: Option<String>,
-    #[serde(default)]
-    bids_events: Option<String>,
-    #[serde(default)]
-    bitalino_input: Option<String>,
-    #[serde(default)]
-    bitalino_signal: Option<String>,
-    #[serde(default)]
-    openbci_input: Option<String>,
-    #[serde(default)]
-    openbci_channel: Option<String>,
-    #[serde(default)]
-    hrv_time: Option<HrvTimeSpec>,
-    #[serde(default)]
-    hrv_psd: Option<HrvPsdSpec>,
-}
-
-#[derive(Default, Deserialize, Clone)]
-struct HrvTimeSpec {
-    #[serde(default)]
-    tolerance: Option<f64>,
-    #[serde(default)]
-    avnn: Option<f64>,
-    #[serde(default)]
-    sdnn: Option<f64>,
-    #[serde(default)]
-    rmssd: Option<f64>,
-    #[serde(default)]
-    pnn50: Option<f64>,
-}
-
-#[derive(Default, Deserialize, Clone)]
-struct HrvPsdSpec {
-    #[serde(default)]
-    tolerance: Option<f64>,
-    #[serde(default)]
-    lf: Option<f64>,
-    #[serde(default)]
-    hf: Option<f64>,
-    #[serde(default)]
-    vlf: Option<f64>,
-    #[serde(default)]
-    lf_hf: Option<f64>,
-    #[serde(default)]
-    total_power: Option<f64>,
-}
-
-fn validate_case(
-    case: &mut DatasetCase,
-    repo_root: &Path,
-    defaults: &CaseDefaults,
-    mode: DatasetValidatorMode,
-) -> Result<DatasetResult> {
-    let tolerance = case.tolerance.or(defaults.tolerance).unwrap_or(0.5).abs();
-    let interp_fs = case.interp_fs.or(defaults.interp_fs).unwrap_or(4.0);
-    let rr = rr_series_from_case(case, repo_root, defaults)?;
-    let time_metrics = hrv_time(&rr);
-    let time_tolerance = case
-        .hrv_time
-        .as_ref()
-        .and_then(|spec| spec.tolerance)
-        .unwrap_or(tolerance)
-        .abs();
-    let had_time_spec = case.hrv_time.is_some();
-    let time_record = if had_time_spec || matches!(mode, DatasetValidatorMode::Update) {
-        let time_spec = case.hrv_time.get_or_insert_with(HrvTimeSpec::default);
-        if matches!(mode, DatasetValidatorMode::Validate) && had_time_spec {
-            verify_time_metrics(&case.name, time_spec, &time_metrics, tolerance)?;
-        }
-        if matches!(mode, DatasetValidatorMode::Update) {
-            time_spec.tolerance = Some(time_tolerance);
-            time_spec.avnn = Some(time_metrics.avnn);
-            time_spec.sdnn = Some(time_metrics.sdnn);
-            time_spec.rmssd = Some(time_metrics.rmssd);
-            time_spec.pnn50 = Some(time_metrics.pnn50);
-        }
-        Some(TimeMetricsRecord {
-            avnn: time_metrics.avnn,
-            sdnn: time_metrics.sdnn,
-            rmssd: time_metrics.rmssd,
-            pnn50: time_metrics.pnn50,
-            tolerance: time_tolerance,
-        })
-    } else {
-        None
-    };
-    let psd_metrics = hrv_psd(&rr, interp_fs);
-    let psd_tolerance = case
-        .hrv_psd
-        .as_ref()
-        .and_then(|spec| spec.tolerance)
-        .unwrap_or(tolerance)
-        .abs();
-    let had_psd_spec = case.hrv_psd.is_some();
-    let psd_record = if had_psd_spec || matches!(mode, DatasetValidatorMode::Update) {
-        let psd_spec = case.hrv_psd.get_or_insert_with(HrvPsdSpec::default);
-        if matches!(mode, DatasetValidatorMode::Validate) && had_psd_spec {
-            verify_psd_metrics(&case.name, psd_spec, &psd_metrics, tolerance)?;
-        }
-        if matches!(mode, DatasetValidatorMode::Update) {
-            psd_spec.tolerance = Some(psd_tolerance);
-            psd_spec.lf = Some(psd_metrics.lf);
-            psd_spec.hf = Some(psd_metrics.hf);
-            psd_spec.vlf = Some(psd_metrics.vlf);
-            psd_spec.lf_hf = Some(psd_metrics.lf_hf);
-            psd_spec.total_power = Some(psd_metrics.total_power);
-        }
-        Some(PsdMetricsRecord {
-            lf: psd_metrics.lf,
-            hf: psd_metrics.hf,
-            vlf: psd_metrics.vlf,
-            lf_hf: psd_metrics.lf_hf,
-            total_power: psd_metrics.total_power,
-            tolerance: psd_tolerance,
-        })
-    } else {
-        None
-    };
-    println!(
-        "dataset {} {}",
-        case.name,
-        match mode {
-            DatasetValidatorMode::Validate => "validated",
-            DatasetValidatorMode::Update => "updated",
-        }
-    );
-    Ok(DatasetResult {
-        name: case.name.clone(),
-        status: "ok".into(),
-        time: time_record,
-        psd: psd_record,
-    })
-}
-
-fn update_case_value(
-    case_value: &mut Value,
-    time_record: Option<&TimeMetricsRecord>,
-    psd_record: Option<&PsdMetricsRecord>,
-) -> Result<()> {
-    let map = case_value
-        .as_object_mut()
-        .ok_or_else(|| anyhow!("dataset spec case is not an object"))?;
-    if let Some(record) = time_record {
-        map.insert("hrv_time".into(), build_hrv_time_value(record));
-    }
-    if let Some(record) = psd_record {
-        map.insert("hrv_psd".into(), build_hrv_psd_value(record));
-    }
-    Ok(())
+    out.value(&run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default()))
 }
 
-fn build_hrv_time_value(record: &TimeMetricsRecord) -> Value {
-    let mut map = Map::new();
-    map.insert("tolerance".into(), json!(record.tolerance));
-    map.insert("avnn".into(), json!(record.avnn));
-    map.insert("sdnn".into(), json!(record.sdnn));
-    map.insert("rmssd".into(), json!(record.rmssd));
-    map.insert("pnn50".into(), json!(record.pnn50));
-    Value::Object(map)
-}
-
-fn build_hrv_psd_value(record: &PsdMetricsRecord) -> Value {
-    let mut map = Map::new();
-    map.insert("tolerance".into(), json!(record.tolerance));
-    map.insert("lf".into(), json!(record.lf));
-    map.insert("hf".into(), json!(record.hf));
-    map.insert("vlf".into(), json!(record.vlf));
-    map.insert("lf_hf".into(), json!(record.lf_hf));
-    map.insert("total_power".into(), json!(record.total_power));
-    Value::Object(map)
-}
-
-fn rr_series_from_case(
-    case: &DatasetCase,
-    repo_root: &Path,
-    defaults: &CaseDefaults,
-) -> Result<RRSeries> {
-    if case.format.as_deref() == Some("rr") {
-        let input = case
-            .input
-            .as_ref()
-            .ok_or_else(|| anyhow!("dataset {} missing RR input path", case.name))?;
-        let path = resolve_path(repo_root, input);
-        let rr = text_io::read_f64_series(&path)?;
-        return Ok(RRSeries { rr });
-    }
-
-    let events_fs = case.fs.or(defaults.fs);
-    let annotation_path = case
-        .annotations
-        .as_ref()
-        .map(|value| resolve_path(repo_root, value));
-    let bids_events_path = case
-        .bids_events
-        .as_ref()
-        .map(|value| resolve_path(repo_root, value));
-    if annotation_path.is_some() || bids_events_path.is_some() {
-        let fs = events_fs.ok_or_else(|| {
-            anyhow!(
-                "dataset {} requires fs when providing event annotations",
-                case.name
-            )
-        })?;
-        if annotation_path
-            .as_deref()
-            .map(|p| p.components().any(|comp| comp.as_os_str() == "run_bundle"))
-            .unwrap_or(false)
-        {
-            ensure_run_bundle(repo_root, fs)?;
-        }
-        if let Some(events) =
-            load_annotation_events(annotation_path.as_deref(), bids_events_path.as_deref(), fs)?
-        {
-            return Ok(RRSeries::from_events(&events, fs));
-        }
-        anyhow::bail!("dataset {} produced no events from annotations", case.name);
-    }
-
-    if let Some(bitalino_input) = &case.bitalino_input {
-        let signal = case.bitalino_signal.as_deref().unwrap_or("analog0");
-        let path = resolve_path(repo_root, bitalino_input);
-        let ts = bitalino_io::read_bitalino_csv(&path, signal)?;
-        let result = run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default());
-        return Ok(result.rr);
-    }
-
-    if let Some(openbci_input) = &case.openbci_input {
-        let channel = case.openbci_channel.as_deref().unwrap_or("Ch1");
-        let path = resolve_path(repo_root, openbci_input);
-        let ts = openbci_io::read_openbci_csv(&path, channel)?;
-        let result = run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default());
-        return Ok(result.rr);
-    }
-
-    let ts = if let Some(header) = &case.wfdb_header {
-        let lead = case.wfdb_lead.unwrap_or(0);
-        let path = resolve_path(repo_root, header);
-        wfdb_io::load_wfdb_lead(&path, lead)?
-    } else {
-        let input = case
-            .input
-            .as_ref()
-            .ok_or_else(|| anyhow!("dataset {} missing time series input path", case.name))?;
-        let path = resolve_path(repo_root, input);
-        let data = text_io::read_f64_series(&path)?;
-        let fs = events_fs.ok_or_else(|| {
-            anyhow!(
-                "dataset {} requires fs when providing raw samples",
-                case.name
-            )
-        })?;
-        TimeSeries { fs, data }
-    };
-    let result = run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default());
-    Ok(result.rr)
-}
-
-fn verify_time_metrics(
-    dataset: &str,
-    spec: &HrvTimeSpec,
-    computed: &HRVTime,
-    tolerance: f64,
-) -> Result<()> {
-    let tol = spec.tolerance.unwrap_or(tolerance).abs();
-    if let Some(expected) = spec.avnn {
-        assert_within(dataset, "AVNN", expected, computed.avnn, tol)?;
-    }
-    if let Some(expected) = spec.sdnn {
-        assert_within(dataset, "SDNN", expected, computed.sdnn, tol)?;
-    }
-    if let Some(expected) = spec.rmssd {
-        assert_within(dataset, "RMSSD", expected, computed.rmssd, tol)?;
-    }
-    if let Some(expected) = spec.pnn50 {
-        assert_within(dataset, "pNN50", expected, computed.pnn50, tol)?;
-    }
-    Ok(())
-}
-
-fn verify_psd_metrics(
-    dataset: &str,
-    spec: &HrvPsdSpec,
-    computed: &HRVPsd,
-    tolerance: f64,
-) -> Result<()> {
-    let tol = spec.tolerance.unwrap_or(tolerance).abs();
-    if let Some(expected) = spec.lf {
-        assert_within(dataset, "LF", expected, computed.lf, tol)?;
-    }
-    if let Some(expected) = spec.hf {
-        assert_within(dataset, "HF", expected, computed.hf, tol)?;
-    }
-    if let Some(expected) = spec.vlf {
-        assert_within(dataset, "VLF", expected, computed.vlf, tol)?;
-    }
-    if let Some(expected) = spec.lf_hf {
-        assert_within(dataset, "LF/HF", expected, computed.lf_hf, tol)?;
-    }
-    if let Some(expected) = spec.total_power {
-        assert_within(dataset, "Total Power", expected, computed.total_power, tol)?;
-    }
-    Ok(())
-}
-
-fn assert_within(dataset: &str, label: &str, expected: f64, actual: f64, tol: f64) -> Result<()> {
-    if (actual - expected).abs() > tol {
-        anyhow::bail!(
-            "{} mismatch for {}: expected {}, got {}",
-            label,
-            dataset,
-            expected,
-            actual
-        );
-    }
-    Ok(())
-}
-
-fn resolve_path(repo_root: &Path, input: &str) -> PathBuf {
-    let path = Path::new(input);
-    if path.is_absolute() {
-        path.to_path_buf()
-    } else {
-        repo_root.join(path)
-    }
-}
-
-fn workspace_root() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .and_then(|p| p.parent())
-        .expect("workspace root")
-        .to_path_buf()
-}
-
-fn cmd_sqi(input: Option<&Path>, fs: f64) -> Result<()> {
-    let data = read_samples(input)?;
-    let ts = TimeSeries {
-        fs: fs.max(1.0),
-        data,
-    };
+fn cmd_sqi(out: &Output, input: Option<&Path>, fs: f64) -> Result<()> {
+    let mut ts = load_time_series(fs, input, None, 0, None, 0)?;
+    ts.fs = ts.fs.max(1.0);
     let events = detect_r_peaks(&ts, 0.3);
     let rr = RRSeries::from_events(&events, ts.fs);
     let sqi = evaluate_sqi(&ts, &rr);
-    println!("{}", serde_json::to_string(&sqi)?);
-    Ok(())
+    out.value(&sqi)
+}
+
+/// Printed by `run-simulate` once the bundle is written.
+#[derive(Debug, Serialize)]
+struct RunSummary {
+    files: Vec<PathBuf>,
+    trials: usize,
+    events: usize,
 }
 
+impl Tabular for RunSummary {}
+
 fn cmd_run_simulate(
+    output: &Output,
     design: &Path,
     trials: &Path,
     sub: &str,
@@ -954,73 +1510,19 @@ fn cmd_run_simulate(
     let trial_specs = read_trials(trials)?;
     let bundle = simulate_run(&design_spec, &trial_specs, sub, ses, run_id);
     fs::create_dir_all(out)?;
-    write_events_tsv(&out.join("events.tsv"), &bundle.events)?;
-    write_events_json(&out.join("events.json"))?;
-    write_manifest(&out.join("run.json"), &bundle.manifest)?;
-    println!("run bundle written to {}", out.display());
-    Ok(())
-}
-
-fn draw_plotters_figure(path: &Path, fig: &Figure) -> Result<()> {
-    let backend = BitMapBackend::new(path, (800, 480));
-    let root = backend.into_drawing_area();
-    root.fill(&WHITE)?;
-    let x_values: Vec<f64> = fig
-        .series
-        .iter()
-        .flat_map(|series| match series {
-            Series::Line(line) => line.points.iter().map(|p| p[0]).collect::<Vec<_>>(),
-        })
-        .collect();
-    let y_values: Vec<f64> = fig
-        .series
-        .iter()
-        .flat_map(|series| match series {
-            Series::Line(line) => line.points.iter().map(|p| p[1]).collect::<Vec<_>>(),
-        })
-        .collect();
-    let x_min = *x_values
-        .iter()
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or(&0.0);
-    let x_max = *x_values
-        .iter()
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or(&1.0);
-    let y_min = *y_values
-        .iter()
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or(&0.0);
-    let y_max = *y_values
-        .iter()
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or(&1.0);
-    let mut chart = ChartBuilder::on(&root)
-        .margin(10)
-        .caption(
-            fig.title.clone().unwrap_or_else(|| "Plot".into()),
-            ("sans-serif", 24),
-        )
-        .x_label_area_size(30)
-        .y_label_area_size(40)
-        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
-    chart.configure_mesh().draw()?;
-    for series in &fig.series {
-        match series {
-            Series::Line(line) => {
-                chart.draw_series(LineSeries::new(
-                    line.points.iter().map(|p| (p[0], p[1])),
-                    &RGBColor(
-                        ((line.style.color.0 >> 16) & 0xFF) as u8,
-                        ((line.style.color.0 >> 8) & 0xFF) as u8,
-                        (line.style.color.0 & 0xFF) as u8,
-                    ),
-                ))?;
-            }
-        }
-    }
-    root.present()?;
-    Ok(())
+    let files = vec![
+        out.join("events.tsv"),
+        out.join("events.json"),
+        out.join("run.json"),
+    ];
+    write_events_tsv(&files[0], &bundle.events)?;
+    write_events_json(&files[1])?;
+    write_manifest(&files[2], &bundle.manifest)?;
+    output.value(&RunSummary {
+        files,
+        trials: bundle.manifest.total_trials,
+        events: bundle.events.len(),
+    })
 }
 
 fn load_time_series(
@@ -1035,12 +1537,37 @@ fn load_time_series(
         wfdb_io::load_wfdb_lead(header, wfdb_lead)
     } else if let Some(edf) = eeg_edf {
         eeg_io::load_edf_channel(edf, eeg_channel)
+    } else if let Some(path) = input {
+        load_recording(path, Some(fs))?.time_series(None)
     } else {
-        let data = read_samples(input)?;
+        let data = read_samples(None)?;
         Ok(TimeSeries { fs, data })
     }
 }
 
+/// Block reader over the same sources as [`load_time_series`].
+fn open_chunked_input(
+    fs: f64,
+    input: Option<&Path>,
+    wfdb_header: Option<&Path>,
+    wfdb_lead: usize,
+    eeg_edf: Option<&Path>,
+    eeg_channel: usize,
+) -> Result<Box<dyn chunked_io::ChunkedReader>> {
+    Ok(if let Some(header) = wfdb_header {
+        Box::new(chunked_io::WfdbChunkReader::open(header, wfdb_lead)?)
+    } else if let Some(edf) = eeg_edf {
+        Box::new(chunked_io::EdfChunkReader::open(edf, eeg_channel)?)
+    } else if let Some(path) = input {
+        chunked_io::open_chunked(path, None, Some(fs))?
+    } else {
+        Box::new(chunked_io::TextReader::from_reader(
+            Box::new(io::stdin()),
+            fs,
+        ))
+    })
+}
+
 fn load_annotation_events(
     annotations: Option<&Path>,
     bids_events: Option<&Path>,
@@ -1049,6 +1576,9 @@ fn load_annotation_events(
     if let Some(bids_path) = bids_events {
         return Ok(Some(eeg_io::load_bids_events_indices(bids_path, fs)?));
     }
+    if let Some(path) = annotations.filter(|p| parquet_io::is_table_path(p)) {
+        return Ok(Some(parquet_io::read_events(path)?.0));
+    }
     if let Some(path) = annotations {
         let events = if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
             if ext.eq_ignore_ascii_case("atr") {
@@ -1066,25 +1596,231 @@ fn load_annotation_events(
     Ok(None)
 }
 
-fn cmd_pupil_normalize(path: &Path, format: EyeFormat, min_confidence: f32) -> Result<()> {
-    let (timestamp_col, pupil_col, confidence_col, eye_col, delimiter) = format.columns();
-    let samples = eye_io::read_eye_csv(
-        path,
-        timestamp_col,
-        pupil_col,
-        confidence_col,
-        eye_col,
-        delimiter,
-    )
-    .map_err(|e| anyhow!("{}", e))?;
-    let filtered = eye_io::confidence_filter(&samples, min_confidence);
-    for sample in filtered {
-        println!("{}", serde_json::to_string(&sample)?);
+fn cmd_pupil_normalize(
+    out: &Output,
+    path: &Path,
+    format: EyeFormat,
+    min_confidence: f32,
+) -> Result<()> {
+    let samples = read_eye_samples(path, format)?;
+    out.records(&eye_io::confidence_filter(&samples, min_confidence))
+}
+
+/// The explicit `--eye-format`, otherwise whichever eye-tracker layout the loader registry detects.
+fn eye_format(path: &Path, format: Option<EyeFormat>) -> Result<EyeFormat> {
+    if let Some(format) = format {
+        return Ok(format);
     }
-    Ok(())
+    Ok(match loader::detect_format(path)?.as_str() {
+        "tobii" => EyeFormat::Tobii,
+        _ => EyeFormat::PupilLabs,
+    })
+}
+
+fn read_eye_samples(path: &Path, format: EyeFormat) -> Result<Vec<eye_io::PupilSample>> {
+    eye_io::read_eye_csv_layout(path, &format.layout()).map_err(|e| anyhow!("{}", e))
+}
+
+fn read_gaze_samples(path: &Path, format: EyeFormat) -> Result<Vec<eye_io::PupilSample>> {
+    eye_io::read_gaze_csv_layout(path, &format.layout()).map_err(|e| anyhow!("{}", e))
+}
+
+fn cmd_pupil_preprocess(
+    out: &Output,
+    path: &Path,
+    format: EyeFormat,
+    cfg: &PupilPipelineConfig,
+) -> Result<()> {
+    let samples = read_eye_samples(path, format)?;
+    let traces = preprocess_pupil(&samples, cfg);
+    if traces.is_empty() {
+        anyhow::bail!("no eye had enough valid pupil samples to preprocess");
+    }
+    out.records(&traces)
+}
+
+fn cmd_gaze_events(
+    out: &Output,
+    path: &Path,
+    format: EyeFormat,
+    min_confidence: f32,
+    screen: &ScreenGeometry,
+    cfg: &GazeEventConfig,
+) -> Result<()> {
+    let samples = read_gaze_samples(path, format)?;
+    let mut results = Vec::new();
+    for eye in [
+        eye_io::Eye::Left,
+        eye_io::Eye::Right,
+        eye_io::Eye::Binocular,
+    ] {
+        let points = gaze_points(&samples, eye, format.gaze_units(), screen, min_confidence);
+        if !points.iter().any(|p| p.x.is_finite() && p.y.is_finite()) {
+            continue;
+        }
+        results.push(detect_gaze_events(eye, &points, screen, cfg));
+    }
+    if results.is_empty() {
+        anyhow::bail!("no gaze samples found in {}", path.display());
+    }
+    out.records(&results)
+}
+
+/// Run-bundle events of `event_type` (`any` for all) as `(onset, condition)` pairs; the
+/// event type stands in for rows without a condition.
+fn read_bundle_events(bundle: &Path, event_type: &str) -> Result<Vec<(f64, String)>> {
+    let rows = read_events_tsv(&bundle.join("events.tsv"))?;
+    let events: Vec<(f64, String)> = rows
+        .into_iter()
+        .filter(|row| event_type == "any" || row.event_type == event_type)
+        .map(|row| {
+            let condition = if row.condition.is_empty() {
+                row.event_type
+            } else {
+                row.condition
+            };
+            (row.onset, condition)
+        })
+        .collect();
+    if events.is_empty() {
+        anyhow::bail!("no matching events in {}", bundle.display());
+    }
+    Ok(events)
+}
+
+/// Any registry-supported file (`elf_lib::io::loader`), with `fs` as the rate of plain sample
+/// files.
+fn load_recording(path: &Path, fs: Option<f64>) -> Result<loader::Recording> {
+    loader::load_recording(path, &loader::LoadOptions { fallback_fs: fs })
+}
+
+/// Loads `signal` (a column name or index) from any supported recording, or its first channel.
+/// An explicit `fs` overrides the file's own rate and is required for plain sample files.
+fn load_signal(input: &Path, signal: Option<&str>, fs: Option<f64>) -> Result<TimeSeries> {
+    let mut ts = load_recording(input, fs)?.time_series(signal)?;
+    if let Some(fs) = fs {
+        ts.fs = fs;
+    }
+    Ok(ts)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_resp(
+    out: &Output,
+    input: &Path,
+    signal: Option<&str>,
+    fs: Option<f64>,
+    ecg_signal: Option<&str>,
+    rr_path: Option<&Path>,
+    interp_fs: f64,
+    rsa_half_width_hz: f64,
+    cfg: &RespConfig,
+) -> Result<()> {
+    let ts = load_signal(input, signal, fs)?;
+    let result = analyze_resp(&ts, cfg);
+
+    let mut beat_times = None;
+    let rr = match (ecg_signal, rr_path) {
+        (Some(column), _) => {
+            let ecg = load_signal(input, Some(column), fs)?;
+            let beats = run_beat_hrv_pipeline(&ecg, &EcgPipelineConfig::default());
+            beat_times = Some(
+                beats
+                    .events
+                    .indices
+                    .iter()
+                    .map(|&i| i as f64 / ecg.fs)
+                    .collect::<Vec<f64>>(),
+            );
+            Some(beats.rr)
+        }
+        (None, Some(path)) => Some(rr_series_from_input(Some(path))?),
+        (None, None) => None,
+    };
+    let rsa = rr.filter(|_| result.metrics.resp_hz > 0.0).map(|rr| {
+        let spectral = hrv_rsa(&rr, interp_fs, result.metrics.resp_hz, rsa_half_width_hz);
+        let peak_valley_s = beat_times
+            .as_deref()
+            .and_then(|times| rsa_peak_valley(times, &result.breaths));
+        json!({ "spectral": spectral, "peak_valley_s": peak_valley_s })
+    });
+
+    out.value(&json!({
+        "metrics": result.metrics,
+        "breaths": result.breaths,
+        "rsa": rsa,
+    }))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_eda(
+    out: &Output,
+    input: &Path,
+    signal: Option<&str>,
+    fs: Option<f64>,
+    bundle: Option<&Path>,
+    event_type: &str,
+    offset_s: f64,
+    cfg: &EdaConfig,
+) -> Result<()> {
+    let ts = load_signal(input, signal, fs)?;
+    let events: Vec<ScrEvent> = match bundle {
+        Some(bundle) => read_bundle_events(bundle, event_type)?
+            .into_iter()
+            .map(|(onset, condition)| ScrEvent {
+                onset: onset + offset_s,
+                condition,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    out.value(&analyze_eda(&ts, &events, cfg))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_pupil_erp(
+    out: &Output,
+    bundle: &Path,
+    eye_path: &Path,
+    format: EyeFormat,
+    event_type: &str,
+    offset_s: Option<f64>,
+    manifest_clock: bool,
+    preprocess: &PupilPipelineConfig,
+    cfg: &PupilErpConfig,
+) -> Result<()> {
+    let events: Vec<PupilEpochEvent> = read_bundle_events(bundle, event_type)?
+        .into_iter()
+        .map(|(onset, condition)| PupilEpochEvent { onset, condition })
+        .collect();
+
+    let samples = read_eye_samples(eye_path, format)?;
+    let clock_offset = if manifest_clock {
+        read_manifest(&bundle.join("run.json"))?.start_time_unix
+    } else if let Some(offset) = offset_s {
+        offset
+    } else {
+        samples
+            .iter()
+            .map(|s| s.timestamp)
+            .min_by(f64::total_cmp)
+            .ok_or_else(|| anyhow!("no eye samples in {}", eye_path.display()))?
+    };
+
+    let traces = preprocess_pupil(&samples, preprocess);
+    if traces.is_empty() {
+        anyhow::bail!("no eye had enough valid pupil samples to preprocess");
+    }
+    let results: Vec<_> = traces
+        .iter()
+        .map(|trace| pupil_erp(trace, &events, clock_offset, cfg))
+        .collect();
+    out.records(&results)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn cmd_ecg_find_rpeaks(
+    out: &Output,
     fs: f64,
     min_rr_s: f64,
     input: Option<&Path>,
@@ -1094,30 +1830,283 @@ fn cmd_ecg_find_rpeaks(
     eeg_channel: usize,
 ) -> Result<()> {
     let ts = load_time_series(fs, input, wfdb_header, wfdb_lead, eeg_edf, eeg_channel)?;
-    let events = detect_r_peaks(&ts, min_rr_s);
-    let js = serde_json::to_string(&events)?;
-    println!("{}", js);
-    Ok(())
+    out.value(&detect_r_peaks(&ts, min_rr_s))
 }
 
-fn cmd_hrv_time(input: Option<&Path>) -> Result<()> {
-    let rr = read_samples(input)?;
-    let rr = RRSeries { rr };
-    let m = hrv_time(&rr);
-    let js = serde_json::to_string(&m)?;
-    println!("{}", js);
-    Ok(())
+/// ECG source of one BIDS recording: the physio column or the matching EDF channel.
+fn load_bids_ecg(
+    layout: &bids_io::BidsLayout,
+    file: &bids_io::BidsFile,
+    column: &str,
+    eeg_channel_label: &str,
+) -> Result<(TimeSeries, f64)> {
+    if file.kind() == bids_io::BidsKind::Eeg {
+        let mut reader = edf_io::EdfReader::open(&file.path)?;
+        let needle = eeg_channel_label.to_ascii_lowercase();
+        let signal = reader
+            .data_signals()
+            .into_iter()
+            .find(|&idx| {
+                reader.header().signals[idx]
+                    .label
+                    .to_ascii_lowercase()
+                    .contains(&needle)
+            })
+            .ok_or_else(|| anyhow!("no channel labelled {eeg_channel_label}"))?;
+        return Ok((reader.read_signal(signal)?, 0.0));
+    }
+    let (sidecar, ts) = layout.read_physio(file, column)?;
+    Ok((ts, sidecar.start_time))
+}
+
+fn cmd_bids_analyze(
+    out: &Output,
+    root: &Path,
+    query: &bids_io::BidsQuery,
+    column: &str,
+    eeg_channel_label: &str,
+    interp_fs: f64,
+) -> Result<()> {
+    let layout = bids_io::BidsLayout::index(root)?;
+    let writer = bids_io::DerivativesWriter::create(root, env!("CARGO_PKG_VERSION"))?;
+    let mut sources = Vec::new();
+    for kind in [bids_io::BidsKind::Physio, bids_io::BidsKind::Eeg] {
+        let query = bids_io::BidsQuery {
+            kind: Some(kind),
+            ..query.clone()
+        };
+        sources.extend(layout.query(&query));
+    }
+
+    let mut processed = Vec::new();
+    let mut skipped = Vec::new();
+    let mut group_rows = Vec::new();
+    for file in sources {
+        let (ts, start_time) = match load_bids_ecg(&layout, file, column, eeg_channel_label) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                skipped.push(json!({
+                    "source": file.path.display().to_string(),
+                    "reason": err.to_string(),
+                }));
+                continue;
+            }
+        };
+        let result = run_beat_hrv_pipeline(&ts, &EcgPipelineConfig::default());
+        let psd = hrv_psd(&result.rr, interp_fs);
+        let nonlinear = hrv_nonlinear(&result.rr);
+        let sqi = evaluate_sqi(&ts, &result.rr);
+        let derivative = writer.write_json(
+            file,
+            "hrv",
+            &file.suffix,
+            &json!({
+                "source": file.path.strip_prefix(root).unwrap_or(&file.path),
+                "fs": ts.fs,
+                "start_time": start_time,
+                "beats": result.events.indices.len(),
+                "hrv_time": result.hrv,
+                "hrv_psd": { "lf": psd.lf, "hf": psd.hf, "vlf": psd.vlf, "lf_hf": psd.lf_hf, "total_power": psd.total_power },
+                "hrv_nonlinear": nonlinear,
+                "sqi": sqi,
+                "sqi_acceptable": sqi.is_acceptable(),
+            }),
+        )?;
+        let beat_rows: Vec<Vec<String>> = result
+            .events
+            .indices
+            .iter()
+            .enumerate()
+            .map(|(i, &idx)| {
+                let rr = i
+                    .checked_sub(1)
+                    .and_then(|j| result.rr.rr.get(j))
+                    .map(|rr| format!("{rr:.4}"))
+                    .unwrap_or_else(|| "n/a".into());
+                vec![
+                    format!("{:.4}", idx as f64 / ts.fs + start_time),
+                    "0".into(),
+                    "R".into(),
+                    rr,
+                ]
+            })
+            .collect();
+        writer.write_tsv(
+            file,
+            "beats",
+            "events",
+            &["onset", "duration", "trial_type", "rr_s"],
+            &beat_rows,
+        )?;
+
+        let entity = |value: &Option<String>| value.clone().unwrap_or_else(|| "n/a".into());
+        group_rows.push(vec![
+            entity(&file.entities.sub),
+            entity(&file.entities.ses),
+            entity(&file.entities.task),
+            entity(&file.entities.run),
+            file.suffix.clone(),
+            result.events.indices.len().to_string(),
+            format!("{:.2}", 60.0 / result.hrv.avnn.max(f64::EPSILON)),
+            format!("{:.4}", result.hrv.sdnn),
+            format!("{:.4}", result.hrv.rmssd),
+            format!("{:.4}", psd.lf_hf),
+            sqi.is_acceptable().to_string(),
+        ]);
+        processed.push(json!({
+            "source": file.path.display().to_string(),
+            "derivative": derivative.display().to_string(),
+            "beats": result.events.indices.len(),
+            "sdnn": result.hrv.sdnn,
+            "rmssd": result.hrv.rmssd,
+        }));
+    }
+
+    let group = writer.write_summary_tsv(
+        "group_hrv.tsv",
+        &[
+            "sub", "ses", "task", "run", "suffix", "beats", "mean_hr", "sdnn", "rmssd", "lf_hf",
+            "sqi_ok",
+        ],
+        &group_rows,
+    )?;
+    out.value(&json!({
+        "dataset": root.display().to_string(),
+        "derivatives": writer.root().display().to_string(),
+        "group_summary": group.display().to_string(),
+        "processed": processed,
+        "skipped": skipped,
+    }))
+}
+
+fn cmd_edf_info(out: &Output, input: &Path, annotations: bool) -> Result<()> {
+    let mut reader = edf_io::EdfReader::open(input)?;
+    let header = reader.header().clone();
+    let channels: Vec<Value> = reader
+        .data_signals()
+        .into_iter()
+        .map(|idx| {
+            let signal = &header.signals[idx];
+            json!({
+                "label": signal.label,
+                "fs": header.fs(idx),
+                "physical_dimension": signal.physical_dimension,
+                "physical_min": signal.physical_min,
+                "physical_max": signal.physical_max,
+                "prefiltering": signal.prefiltering,
+                "transducer": signal.transducer,
+            })
+        })
+        .collect();
+    let annotations = if annotations {
+        Some(reader.annotations()?)
+    } else {
+        None
+    };
+    out.value(&json!({
+        "format": header.format,
+        "variant": header.reserved,
+        "start": header.start.to_string(),
+        "patient": header.patient,
+        "recording": header.recording,
+        "records": header.n_records,
+        "record_duration_s": header.record_duration_s,
+        "duration_s": reader.duration_s(),
+        "discontinuous": header.is_discontinuous(),
+        "channels": channels,
+        "annotations": annotations,
+    }))
+}
+
+fn cmd_xdf_info(out: &Output, input: &Path) -> Result<()> {
+    let file = xdf_io::read_xdf(input)?;
+    let streams: Vec<Value> = file
+        .streams
+        .iter()
+        .map(|stream| {
+            let channels: Vec<Value> = stream
+                .channel_labels()
+                .into_iter()
+                .enumerate()
+                .map(|(idx, label)| {
+                    let desc = stream.channels.get(idx);
+                    json!({
+                        "label": label,
+                        "unit": desc.and_then(|c| c.unit.clone()),
+                        "type": desc.and_then(|c| c.kind.clone()),
+                    })
+                })
+                .collect();
+            let markers: Option<Vec<Value>> = stream.is_markers().then(|| {
+                stream
+                    .markers()
+                    .into_iter()
+                    .map(|(time, label)| json!({ "time": time, "label": label }))
+                    .collect()
+            });
+            json!({
+                "id": stream.id,
+                "name": stream.name,
+                "type": stream.stream_type,
+                "source_id": stream.source_id,
+                "channel_format": stream.channel_format,
+                "nominal_srate": stream.nominal_srate,
+                "effective_srate": stream.effective_srate(),
+                "samples": stream.sample_count(),
+                "first_timestamp": stream.timestamps.first(),
+                "last_timestamp": stream.timestamps.last(),
+                "clock_offsets": stream.clock_offsets.len(),
+                "channels": channels,
+                "markers": markers,
+            })
+        })
+        .collect();
+    out.value(&json!({
+        "version": file.version,
+        "datetime": file.datetime,
+        "skipped_chunks": file.skipped_chunks,
+        "streams": streams,
+    }))
+}
+
+fn cmd_wfdb_export(
+    out: &Output,
+    ts: &TimeSeries,
+    signal: wfdb_io::WfdbWriteSignal<'_>,
+    out_dir: &Path,
+    record: &str,
+    format: u32,
+    annotator: Option<&str>,
+) -> Result<()> {
+    let format = wfdb_io::WfdbFormat::from_code(format)?;
+    let header = wfdb_io::write_wfdb_record(out_dir, record, ts.fs, &[signal], format)?;
+    let annotations = match annotator {
+        Some(annotator) => {
+            let events = run_beat_hrv_pipeline(ts, &EcgPipelineConfig::default()).events;
+            let path = out_dir.join(format!("{record}.{annotator}"));
+            wfdb_io::write_wfdb_annotations(&path, &wfdb_io::events_to_annotations(&events))?;
+            Some((path, events.indices.len()))
+        }
+        None => None,
+    };
+    out.value(&json!({
+        "header": header.display().to_string(),
+        "dat": header.with_extension("dat").display().to_string(),
+        "annotations": annotations.as_ref().map(|(path, _)| path.display().to_string()),
+        "beats": annotations.map(|(_, beats)| beats),
+    }))
+}
+
+fn cmd_hrv_time(out: &Output, input: Option<&Path>) -> Result<()> {
+    let rr = rr_series_from_input(input)?;
+    out.value(&hrv_time(&rr))
 }
 
 #[allow(clippy::too_many_arguments)]
 fn cmd_beat_hrv_pipeline(
+    out: &Output,
     fs: f64,
-    lowcut_hz: f64,
-    highcut_hz: f64,
-    integration_window_s: f64,
-    min_rr_s: f64,
-    threshold_scale: f64,
-    search_back_s: f64,
+    cfg: EcgPipelineConfig,
     input: Option<&Path>,
     wfdb_header: Option<&Path>,
     wfdb_lead: usize,
@@ -1125,22 +2114,19 @@ fn cmd_beat_hrv_pipeline(
     eeg_edf: Option<&Path>,
     eeg_channel: usize,
     bids_events: Option<&Path>,
+    stream_block_size: Option<usize>,
 ) -> Result<()> {
+    if let Some(block_size) = stream_block_size {
+        let mut reader =
+            open_chunked_input(fs, input, wfdb_header, wfdb_lead, eeg_edf, eeg_channel)?;
+        let summary = run_beat_hrv_pipeline_chunked(reader.as_mut(), &cfg, block_size)?;
+        return out.value(&summary);
+    }
     let ts = load_time_series(fs, input, wfdb_header, wfdb_lead, eeg_edf, eeg_channel)?;
-    let cfg = EcgPipelineConfig {
-        lowcut_hz,
-        highcut_hz,
-        integration_window_s,
-        min_rr_s,
-        threshold_scale,
-        search_back_s,
-    };
     let summary = if let Some(events) = load_annotation_events(annotations, bids_events, ts.fs)? {
         BeatHrvPipelineResult::from_events(&ts, events)
     } else {
         run_beat_hrv_pipeline(&ts, &cfg)
     };
-    let js = serde_json::to_string(&summary)?;
-    println!("{}", js);
-    Ok(())
+    out.value(&summary)
 }
@@ -0,0 +1,408 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use elf_lib::{
+    detectors::ecg::BeatHrvPipelineResult,
+    io::{eye::PupilSample, parquet as parquet_io},
+    metrics::{
+        eda::EdaResult,
+        gaze::GazeEvents,
+        hrv::{HRVNonlinear, HRVPsd, HRVTime},
+        pupil::{PupilErpResult, PupilTrace},
+        sqi::SQIResult,
+    },
+    signal::Events,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Json,
+    Ndjson,
+    Csv,
+    Parquet,
+    Table,
+}
+
+impl OutputFormat {
+    /// Format implied by a file extension (`.json`, `.ndjson`/`.jsonl`, `.csv`, `.parquet`/
+    /// `.arrow`, `.txt`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "json" => Self::Json,
+            "ndjson" | "jsonl" => Self::Ndjson,
+            "csv" => Self::Csv,
+            "parquet" | "arrow" | "ipc" | "feather" => Self::Parquet,
+            "txt" => Self::Table,
+            _ => return None,
+        })
+    }
+}
+
+/// Rows and columns of a result.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// Flattens a JSON value: an array gives one row per element, anything else one row.
+    pub fn from_value(value: &Value) -> Self {
+        let mut table = Table::default();
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| table.push_value(item)),
+            other => table.push_value(other),
+        }
+        table
+    }
+
+    /// Appends a row; columns not seen before are added in the order they appear.
+    pub fn push_row(&mut self, row: impl IntoIterator<Item = (String, Value)>) {
+        let mut cells = vec![Value::Null; self.columns.len()];
+        for (key, value) in row {
+            match self.columns.iter().position(|c| *c == key) {
+                Some(idx) => cells[idx] = value,
+                None => {
+                    self.columns.push(key);
+                    for existing in &mut self.rows {
+                        existing.push(Value::Null);
+                    }
+                    cells.push(value);
+                }
+            }
+        }
+        self.rows.push(cells);
+    }
+
+    fn push_value(&mut self, value: &Value) {
+        let mut row = Map::new();
+        flatten("", value, &mut row);
+        self.push_row(row);
+    }
+
+    fn extend(&mut self, other: Table) {
+        for cells in other.rows {
+            self.push_row(other.columns.iter().cloned().zip(cells));
+        }
+    }
+
+    fn row_objects(&self) -> impl Iterator<Item = Map<String, Value>> + '_ {
+        self.rows.iter().map(|cells| {
+            self.columns
+                .iter()
+                .cloned()
+                .zip(cells.iter().cloned())
+                .collect()
+        })
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, row: &mut Map<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, inner) in map {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&name, inner, row);
+            }
+        }
+        Value::Array(_) => {
+            row.insert(column_name(prefix), Value::String(value.to_string()));
+        }
+        scalar => {
+            row.insert(column_name(prefix), scalar.clone());
+        }
+    }
+}
+
+fn column_name(prefix: &str) -> String {
+    if prefix.is_empty() {
+        "value".to_string()
+    } else {
+        prefix.to_string()
+    }
+}
+
+/// Results that can be written in the row formats. The default flattens the JSON form.
+pub trait Tabular: Serialize {
+    fn table(&self) -> Result<Table> {
+        Ok(Table::from_value(&serde_json::to_value(self)?))
+    }
+}
+
+impl Tabular for Value {}
+impl Tabular for HRVTime {}
+impl Tabular for HRVNonlinear {}
+impl Tabular for SQIResult {}
+impl Tabular for PupilSample {}
+impl Tabular for PupilTrace {}
+impl Tabular for PupilErpResult {}
+impl Tabular for GazeEvents {}
+impl Tabular for EdaResult {}
+
+impl Tabular for HRVPsd {
+    /// Band powers only; the spectrum itself stays in the JSON output.
+    fn table(&self) -> Result<Table> {
+        Ok(Table::from_value(&json!({
+            "vlf": self.vlf,
+            "lf": self.lf,
+            "hf": self.hf,
+            "lf_hf": self.lf_hf,
+            "total_power": self.total_power,
+        })))
+    }
+}
+
+impl Tabular for BeatHrvPipelineResult {
+    /// One summary row; per-beat indices are available from `ecg-find-rpeaks`.
+    fn table(&self) -> Result<Table> {
+        Ok(Table::from_value(&json!({
+            "fs": self.fs,
+            "sample_count": self.sample_count,
+            "beats": self.events.indices.len(),
+            "n": self.hrv.n,
+            "avnn": self.hrv.avnn,
+            "sdnn": self.hrv.sdnn,
+            "rmssd": self.hrv.rmssd,
+            "pnn50": self.hrv.pnn50,
+        })))
+    }
+}
+
+impl Tabular for Events {
+    fn table(&self) -> Result<Table> {
+        let mut table = Table::default();
+        for &idx in &self.indices {
+            let mut row = Map::new();
+            row.insert("sample_index".into(), json!(idx));
+            table.push_row(row);
+        }
+        if table.columns.is_empty() {
+            table.columns.push("sample_index".into());
+        }
+        Ok(table)
+    }
+}
+
+/// Where and how command results are written.
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    format: Option<OutputFormat>,
+    path: Option<PathBuf>,
+}
+
+impl Output {
+    pub fn new(format: Option<OutputFormat>, path: Option<PathBuf>) -> Result<Self> {
+        if format == Some(OutputFormat::Parquet) && path.is_none() {
+            bail!("--format parquet writes a binary file; pass --output <file.parquet>");
+        }
+        Ok(Self { format, path })
+    }
+
+    /// A single result document.
+    pub fn value<T: Tabular>(&self, value: &T) -> Result<()> {
+        match self.format.unwrap_or(OutputFormat::Json) {
+            OutputFormat::Json => self.write_text(&format!("{}\n", serde_json::to_string(value)?)),
+            format => self.write_table(format, value.table()?),
+        }
+    }
+
+    /// A list of results. Without `--format` each one is printed as a JSON line, as before;
+    /// `--format json` prints a single JSON array instead.
+    pub fn records<T: Tabular>(&self, records: &[T]) -> Result<()> {
+        match self.format {
+            None => {
+                let mut text = String::new();
+                for record in records {
+                    text.push_str(&serde_json::to_string(record)?);
+                    text.push('\n');
+                }
+                self.write_text(&text)
+            }
+            Some(OutputFormat::Json) => {
+                self.write_text(&format!("{}\n", serde_json::to_string(records)?))
+            }
+            Some(format) => {
+                let mut table = Table::default();
+                for record in records {
+                    table.extend(record.table()?);
+                }
+                self.write_table(format, table)
+            }
+        }
+    }
+
+    fn write_table(&self, format: OutputFormat, table: Table) -> Result<()> {
+        match format {
+            OutputFormat::Json => unreachable!("JSON is written without a table"),
+            OutputFormat::Ndjson => {
+                let mut text = String::new();
+                for row in table.row_objects() {
+                    text.push_str(&serde_json::to_string(&row)?);
+                    text.push('\n');
+                }
+                self.write_text(&text)
+            }
+            OutputFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(&table.columns)?;
+                for cells in &table.rows {
+                    writer.write_record(cells.iter().map(cell_text))?;
+                }
+                let bytes = writer.into_inner().map_err(|e| anyhow!("{e}"))?;
+                self.write_text(&String::from_utf8(bytes)?)
+            }
+            OutputFormat::Table => self.write_text(&render_table(&table)),
+            OutputFormat::Parquet => {
+                let path = self.path.as_deref().expect("checked in Output::new");
+                let format = parquet_io::TableFormat::from_path(path)
+                    .unwrap_or(parquet_io::TableFormat::Parquet);
+                parquet_io::write_records(path, format, typed_columns(&table))
+                    .with_context(|| format!("writing {}", path.display()))
+            }
+        }
+    }
+
+    fn write_text(&self, text: &str) -> Result<()> {
+        match &self.path {
+            Some(path) => write_file(path, text),
+            None => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(text.as_bytes())?;
+                stdout.flush()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_file(path: &Path, text: &str) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Integer columns stay Int64 unless a value is missing; mixed columns become text.
+fn typed_columns(table: &Table) -> Vec<(String, parquet_io::Column)> {
+    table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let cells: Vec<&Value> = table.rows.iter().map(|row| &row[idx]).collect();
+            let column = if cells.iter().all(|v| v.is_i64()) {
+                parquet_io::Column::Int(cells.iter().filter_map(|v| v.as_i64()).collect())
+            } else if cells.iter().all(|v| v.is_number() || v.is_null()) {
+                parquet_io::Column::Float(
+                    cells
+                        .iter()
+                        .map(|v| v.as_f64().unwrap_or(f64::NAN))
+                        .collect(),
+                )
+            } else {
+                parquet_io::Column::Text(cells.iter().map(|v| cell_text(v)).collect())
+            };
+            (name.clone(), column)
+        })
+        .collect()
+}
+
+/// Aligned text; a single row is shown as `column  value` lines.
+fn render_table(table: &Table) -> String {
+    let text: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(display_cell).collect())
+        .collect();
+    let mut out = String::new();
+    if text.len() == 1 {
+        let width = table.columns.iter().map(|c| c.len()).max().unwrap_or(0);
+        for (name, value) in table.columns.iter().zip(&text[0]) {
+            out.push_str(&format!("{name:<width$}  {value}\n"));
+        }
+        return out;
+    }
+    let widths: Vec<usize> = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            text.iter()
+                .map(|row| row[idx].chars().count())
+                .chain(std::iter::once(name.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |cells: &mut dyn Iterator<Item = &String>| {
+        let parts: Vec<String> = cells
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        format!("{}\n", parts.join("  ").trim_end())
+    };
+    out.push_str(&line(&mut table.columns.iter()));
+    out.push_str(&line(
+        &mut widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .iter(),
+    ));
+    for row in &text {
+        out.push_str(&line(&mut row.iter()));
+    }
+    out
+}
+
+fn display_cell(value: &Value) -> String {
+    match value {
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if !n.is_i64() && !n.is_u64() => format!("{f:.6}")
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string(),
+            _ => n.to_string(),
+        },
+        other => cell_text(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattens_nested_objects_and_unions_columns() {
+        let mut table = Table::from_value(&json!({"a": 1, "b": {"c": 2.5, "d": [1, 2]}}));
+        table.extend(Table::from_value(&json!({"a": 2, "e": "x"})));
+        assert_eq!(table.columns, vec!["a", "b.c", "b.d", "e"]);
+        assert_eq!(table.rows[0][2], json!("[1,2]"));
+        assert_eq!(
+            table.rows[1],
+            vec![json!(2), Value::Null, Value::Null, json!("x")]
+        );
+        let rendered = render_table(&table);
+        assert!(rendered.starts_with("a  b.c  b.d    e\n"));
+    }
+}
@@ -0,0 +1,504 @@
+use crate::output::{Output, OutputFormat, Tabular};
+use anyhow::{anyhow, bail, Context, Result};
+use elf_lib::{
+    detectors::ecg::EcgPipelineConfig,
+    detectors::ecg::{bandpass_filter, run_beat_hrv_pipeline, BeatHrvPipelineResult},
+    io::{loader, parquet as parquet_io, rr as rr_io, wfdb as wfdb_io},
+    metrics::{
+        hrv::{
+            clean_rr, hrv_nonlinear, hrv_psd, hrv_time, HRVNonlinear, HRVPsd, HRVTime,
+            RrCleanConfig, RrCleanResult,
+        },
+        sqi::{evaluate_sqi, SQIResult},
+    },
+    signal::{RRSeries, TimeSeries},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSpec {
+    #[serde(default)]
+    pub pipeline: PipelineMeta,
+    #[serde(default, rename = "source")]
+    pub sources: Vec<SourceSpec>,
+    #[serde(default, rename = "stage")]
+    pub stages: Vec<StageSpec>,
+    #[serde(default, rename = "sink")]
+    pub sinks: Vec<SinkSpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineMeta {
+    pub name: Option<String>,
+    /// Provenance record path (default `<pipeline stem>.provenance.json`).
+    pub provenance: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// A channel of any recording the loader registry reads.
+    #[default]
+    Signal,
+    /// An RR interval export (text, Polar HRM, Garmin FIT, Kubios, Elite HRV).
+    Rr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SourceSpec {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub kind: SourceKind,
+    /// Channel label or index; the first channel when omitted.
+    pub signal: Option<String>,
+    /// Sampling rate for plain sample files.
+    pub fs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageSpec {
+    pub name: String,
+    pub input: String,
+    #[serde(flatten)]
+    pub op: StageOp,
+}
+
+/// Each kind rejects keys it does not know, so a misspelled setting is an error rather than a
+/// silent default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum StageOp {
+    /// Single-pole band-pass of a signal.
+    Filter {
+        #[serde(default = "default_lowcut")]
+        lowcut_hz: f64,
+        #[serde(default = "default_highcut")]
+        highcut_hz: f64,
+    },
+    /// R-peak detection, RR and time-domain HRV on a signal.
+    Detect(EcgPipelineConfig),
+    /// Artifact rejection on RR intervals.
+    RrClean(RrCleanConfig),
+    HrvTime {},
+    HrvPsd {
+        #[serde(default = "default_interp_fs")]
+        interp_fs: f64,
+    },
+    HrvNonlinear {},
+    /// Signal quality of a signal; beats come from the `rr` stage or are detected.
+    Sqi {
+        rr: Option<String>,
+    },
+}
+
+fn default_lowcut() -> f64 {
+    0.5
+}
+
+fn default_highcut() -> f64 {
+    40.0
+}
+
+fn default_interp_fs() -> f64 {
+    4.0
+}
+
+impl StageOp {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StageOp::Filter { .. } => "filter",
+            StageOp::Detect(_) => "detect",
+            StageOp::RrClean(_) => "rr-clean",
+            StageOp::HrvTime {} => "hrv-time",
+            StageOp::HrvPsd { .. } => "hrv-psd",
+            StageOp::HrvNonlinear {} => "hrv-nonlinear",
+            StageOp::Sqi { .. } => "sqi",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SinkSpec {
+    pub path: PathBuf,
+    pub stages: Vec<String>,
+    /// Defaults to the format implied by the extension.
+    pub format: Option<OutputFormat>,
+}
+
+/// Result of a source or stage.
+#[derive(Debug, Clone)]
+pub enum StageValue {
+    Signal(TimeSeries),
+    Rr(RRSeries),
+    Beats(BeatHrvPipelineResult),
+    RrClean(RrCleanResult),
+    HrvTime(HRVTime),
+    HrvPsd(HRVPsd),
+    HrvNonlinear(HRVNonlinear),
+    Sqi(SQIResult),
+}
+
+impl StageValue {
+    fn signal(&self, name: &str) -> Result<&TimeSeries> {
+        match self {
+            StageValue::Signal(ts) => Ok(ts),
+            _ => bail!("'{name}' is not a signal"),
+        }
+    }
+
+    fn rr(&self, name: &str) -> Result<&RRSeries> {
+        match self {
+            StageValue::Rr(rr) => Ok(rr),
+            StageValue::Beats(beats) => Ok(&beats.rr),
+            StageValue::RrClean(cleaned) => Ok(&cleaned.rr),
+            _ => bail!("'{name}' does not hold RR intervals"),
+        }
+    }
+
+    fn to_json(&self) -> Result<Value> {
+        Ok(match self {
+            StageValue::Signal(v) => serde_json::to_value(v)?,
+            StageValue::Rr(v) => serde_json::to_value(v)?,
+            StageValue::Beats(v) => serde_json::to_value(v)?,
+            StageValue::RrClean(v) => serde_json::to_value(v)?,
+            StageValue::HrvTime(v) => serde_json::to_value(v)?,
+            StageValue::HrvPsd(v) => serde_json::to_value(v)?,
+            StageValue::HrvNonlinear(v) => serde_json::to_value(v)?,
+            StageValue::Sqi(v) => serde_json::to_value(v)?,
+        })
+    }
+
+    fn write(&self, out: &Output) -> Result<()> {
+        match self {
+            StageValue::Beats(v) => out.value(v),
+            StageValue::HrvTime(v) => out.value(v),
+            StageValue::HrvPsd(v) => out.value(v),
+            StageValue::HrvNonlinear(v) => out.value(v),
+            StageValue::Sqi(v) => out.value(v),
+            other => out.value(&other.to_json()?),
+        }
+    }
+}
+
+impl PipelineSpec {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read pipeline {}", path.display()))?;
+        let spec: PipelineSpec =
+            toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    /// Names are unique, stages only read earlier results and sinks name known stages.
+    fn validate(&self) -> Result<()> {
+        let mut known: Vec<&str> = Vec::new();
+        for name in self.sources.iter().map(|s| s.name.as_str()) {
+            if known.contains(&name) {
+                bail!("duplicate source or stage name '{name}'");
+            }
+            known.push(name);
+        }
+        for stage in &self.stages {
+            let mut inputs = vec![stage.input.as_str()];
+            if let StageOp::Sqi { rr: Some(rr) } = &stage.op {
+                inputs.push(rr);
+            }
+            for input in inputs {
+                if !known.contains(&input) {
+                    bail!(
+                        "stage '{}' reads '{input}', which is not an earlier source or stage",
+                        stage.name
+                    );
+                }
+            }
+            if known.contains(&stage.name.as_str()) {
+                bail!("duplicate source or stage name '{}'", stage.name);
+            }
+            known.push(&stage.name);
+        }
+        for sink in &self.sinks {
+            if sink.stages.is_empty() {
+                bail!("sink {} lists no stages", sink.path.display());
+            }
+            if let Some(name) = sink.stages.iter().find(|s| !known.contains(&s.as_str())) {
+                bail!("sink {} names unknown stage '{name}'", sink.path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Provenance of one file the run read or wrote.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRecord {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl FileRecord {
+    pub fn of(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut ctx = Sha256::new();
+        let mut buf = vec![0u8; 1 << 16];
+        let mut bytes = 0u64;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            ctx.update(&buf[..n]);
+            bytes += n as u64;
+        }
+        let sha256 = ctx.finalize().iter().map(|b| format!("{b:02x}")).collect();
+        Ok(Self {
+            path: path.to_path_buf(),
+            bytes,
+            sha256,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageRecord {
+    pub name: String,
+    pub kind: &'static str,
+    pub input: String,
+    pub duration_s: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub name: Option<String>,
+    pub pipeline: FileRecord,
+    pub config: PipelineSpec,
+    pub started_unix: f64,
+    pub duration_s: f64,
+    pub inputs: Vec<FileRecord>,
+    pub stages: Vec<StageRecord>,
+    pub outputs: Vec<FileRecord>,
+}
+
+/// What `elf pipeline run` prints.
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineRun {
+    pub name: Option<String>,
+    pub stages: usize,
+    pub outputs: Vec<PathBuf>,
+    pub provenance: PathBuf,
+}
+
+impl Tabular for PipelineRun {}
+
+/// Runs every stage of the pipeline at `path`, writes its sinks and the provenance record.
+pub fn run_pipeline(
+    path: &Path,
+    out_dir: Option<&Path>,
+    provenance_path: Option<&Path>,
+) -> Result<PipelineRun> {
+    let started = Instant::now();
+    let started_unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    let spec = PipelineSpec::read(path)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    let out_dir = out_dir.unwrap_or(base);
+
+    let mut values: HashMap<String, StageValue> = HashMap::new();
+    let mut inputs = Vec::new();
+    for source in &spec.sources {
+        let source_path = base.join(&source.path);
+        let value = load_source(source, &source_path)
+            .with_context(|| format!("loading source '{}'", source.name))?;
+        for file in source_files(&source_path)? {
+            inputs.push(FileRecord::of(&file)?);
+        }
+        values.insert(source.name.clone(), value);
+    }
+
+    let mut stages = Vec::new();
+    for stage in &spec.stages {
+        let stage_started = Instant::now();
+        let value =
+            run_stage(stage, &values).with_context(|| format!("running stage '{}'", stage.name))?;
+        stages.push(StageRecord {
+            name: stage.name.clone(),
+            kind: stage.op.kind(),
+            input: stage.input.clone(),
+            duration_s: stage_started.elapsed().as_secs_f64(),
+        });
+        values.insert(stage.name.clone(), value);
+    }
+
+    let mut outputs = Vec::new();
+    for sink in &spec.sinks {
+        let sink_path = out_dir.join(&sink.path);
+        write_sink(sink, &sink_path, &values)
+            .with_context(|| format!("writing sink {}", sink_path.display()))?;
+        outputs.push(FileRecord::of(&sink_path)?);
+    }
+
+    let provenance_path = match provenance_path.or(spec.pipeline.provenance.as_deref()) {
+        Some(p) => out_dir.join(p),
+        None => {
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("pipeline");
+            out_dir.join(format!("{stem}.provenance.json"))
+        }
+    };
+    let record = Provenance {
+        tool: "elf",
+        version: env!("CARGO_PKG_VERSION"),
+        name: spec.pipeline.name.clone(),
+        pipeline: FileRecord::of(path)?,
+        config: spec.clone(),
+        started_unix,
+        duration_s: started.elapsed().as_secs_f64(),
+        inputs,
+        stages,
+        outputs: outputs.clone(),
+    };
+    create_parent(&provenance_path)?;
+    fs::write(&provenance_path, serde_json::to_string_pretty(&record)?)
+        .with_context(|| format!("failed to write {}", provenance_path.display()))?;
+
+    Ok(PipelineRun {
+        name: spec.pipeline.name,
+        stages: spec.stages.len(),
+        outputs: outputs.into_iter().map(|o| o.path).collect(),
+        provenance: provenance_path,
+    })
+}
+
+fn load_source(source: &SourceSpec, path: &Path) -> Result<StageValue> {
+    match source.kind {
+        SourceKind::Rr => Ok(StageValue::Rr(rr_io::read_rr_file(path)?.rr)),
+        SourceKind::Signal => {
+            let options = loader::LoadOptions {
+                fallback_fs: source.fs,
+            };
+            let mut ts =
+                loader::load_recording(path, &options)?.time_series(source.signal.as_deref())?;
+            if let Some(fs) = source.fs {
+                ts.fs = fs;
+            }
+            Ok(StageValue::Signal(ts))
+        }
+    }
+}
+
+/// The file itself, plus the signal files (and segment headers) a WFDB header refers to.
+fn source_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![path.to_path_buf()];
+    let is_header = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("hea"));
+    if !is_header {
+        return Ok(files);
+    }
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let header = wfdb_io::read_wfdb_header(path)?;
+    let mut headers = vec![header];
+    for segment in headers[0].segments.clone() {
+        if segment.name != "~" {
+            let segment_path = dir.join(format!("{}.hea", segment.name));
+            headers.push(wfdb_io::read_wfdb_header(&segment_path)?);
+            files.push(segment_path);
+        }
+    }
+    for spec in headers.iter().flat_map(|h| &h.signals) {
+        let file = dir.join(&spec.file_name);
+        if spec.file_name != "~" && !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+fn run_stage(stage: &StageSpec, values: &HashMap<String, StageValue>) -> Result<StageValue> {
+    let lookup = |name: &str| {
+        values
+            .get(name)
+            .ok_or_else(|| anyhow!("no result named '{name}'"))
+    };
+    let input = lookup(&stage.input)?;
+    let name = stage.input.as_str();
+    Ok(match &stage.op {
+        StageOp::Filter {
+            lowcut_hz,
+            highcut_hz,
+        } => StageValue::Signal(bandpass_filter(
+            input.signal(name)?,
+            *lowcut_hz,
+            *highcut_hz,
+        )),
+        StageOp::Detect(cfg) => StageValue::Beats(run_beat_hrv_pipeline(input.signal(name)?, cfg)),
+        StageOp::RrClean(cfg) => StageValue::RrClean(clean_rr(input.rr(name)?, cfg)),
+        StageOp::HrvTime {} => StageValue::HrvTime(hrv_time(input.rr(name)?)),
+        StageOp::HrvPsd { interp_fs } => StageValue::HrvPsd(hrv_psd(input.rr(name)?, *interp_fs)),
+        StageOp::HrvNonlinear {} => StageValue::HrvNonlinear(hrv_nonlinear(input.rr(name)?)),
+        StageOp::Sqi { rr } => {
+            let ts = input.signal(name)?;
+            let detected;
+            let rr = match rr {
+                Some(rr_name) => lookup(rr_name)?.rr(rr_name)?,
+                None => {
+                    detected = run_beat_hrv_pipeline(ts, &EcgPipelineConfig::default()).rr;
+                    &detected
+                }
+            };
+            StageValue::Sqi(evaluate_sqi(ts, rr))
+        }
+    })
+}
+
+/// One stage is written as itself (a filtered signal as a signal table when the sink is
+/// Parquet/Arrow); several stages as one document keyed by stage name.
+fn write_sink(sink: &SinkSpec, path: &Path, values: &HashMap<String, StageValue>) -> Result<()> {
+    let format = match sink.format.or_else(|| OutputFormat::from_path(path)) {
+        Some(format) => format,
+        None => bail!("cannot tell the format of {}; set `format`", path.display()),
+    };
+    create_parent(path)?;
+    let out = Output::new(Some(format), Some(path.to_path_buf()))?;
+    if let [name] = sink.stages.as_slice() {
+        let value = &values[name];
+        if let (StageValue::Signal(ts), OutputFormat::Parquet) = (value, format) {
+            return parquet_io::write_timeseries(path, ts, name, None);
+        }
+        return value.write(&out);
+    }
+    let mut doc = Map::new();
+    for name in &sink.stages {
+        doc.insert(name.clone(), values[name].to_json()?);
+    }
+    out.value(&json!(doc))
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    Ok(())
+}
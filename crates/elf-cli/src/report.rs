@@ -0,0 +1,425 @@
+use crate::output::Tabular;
+use anyhow::{Context, Result};
+use elf_lib::{
+    detectors::ecg::EcgPipelineConfig,
+    metrics::{
+        hrv::{
+            hrv_nonlinear, hrv_psd, hrv_time, HRVNonlinear, HRVPsd, HRVTime, HF_BAND, LF_BAND,
+            VLF_BAND,
+        },
+        sqi::{evaluate_sqi, SQIResult},
+    },
+    plot::{
+        figure_from_ecg_window, figure_from_psd, figure_from_rr, figure_to_svg,
+        rr_histogram_figure, Color, Figure, LineSeries, MarkerShape, ScatterSeries, Series,
+        SignalPyramid, Style,
+    },
+    signal::{Events, RRSeries, TimeSeries},
+};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Histogram bin width; 1/128 s is the bin the HRV triangular index is defined on.
+const HIST_BIN_S: f64 = 1.0 / 128.0;
+/// Upper bound of points drawn per excerpt.
+const EXCERPT_POINTS: usize = 2000;
+
+const RR_COLOR: u32 = 0xFF0077;
+/// Width of the figures in SVG user units.
+const PAGE_WIDTH: f64 = 760.0;
+
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub title: Option<String>,
+    /// Number of ECG excerpts spread over the recording.
+    pub excerpts: usize,
+    /// Length of each excerpt in seconds.
+    pub excerpt_s: f64,
+    /// Resampling rate of the RR series for the PSD.
+    pub interp_fs: f64,
+}
+
+/// How the beats were obtained, for the parameters table.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum BeatSource {
+    Detector(EcgPipelineConfig),
+    Annotations { path: String },
+}
+
+/// The figures and tables of one report.
+pub struct HrvReport {
+    pub title: String,
+    pub source: String,
+    pub beat_source: BeatSource,
+    pub options: ReportOptions,
+    pub fs: f64,
+    pub sample_count: usize,
+    pub beats: usize,
+    pub hrv: HRVTime,
+    pub psd: HRVPsd,
+    pub nonlinear: HRVNonlinear,
+    pub sqi: SQIResult,
+    panels: Vec<Panel>,
+}
+
+/// A figure and the height it is drawn at.
+struct Panel {
+    figure: Figure,
+    height: f64,
+}
+
+impl Panel {
+    fn new(figure: Figure, height: f64) -> Self {
+        Self { figure, height }
+    }
+}
+
+/// Printed by the command once the files are written.
+#[derive(Debug, Serialize)]
+pub struct ReportSummary {
+    pub html: PathBuf,
+    pub fs: f64,
+    pub duration_s: f64,
+    pub beats: usize,
+    pub hrv: HRVTime,
+    pub psd: PsdBands,
+    pub nonlinear: HRVNonlinear,
+    pub sqi: SQIResult,
+    pub sqi_acceptable: bool,
+}
+
+impl Tabular for ReportSummary {}
+
+/// `HRVPsd` without the spectrum itself.
+#[derive(Debug, Serialize)]
+pub struct PsdBands {
+    pub vlf: f64,
+    pub lf: f64,
+    pub hf: f64,
+    pub lf_hf: f64,
+    pub total_power: f64,
+}
+
+impl HrvReport {
+    pub fn build(
+        ts: &TimeSeries,
+        events: &Events,
+        source: String,
+        beat_source: BeatSource,
+        options: ReportOptions,
+    ) -> Self {
+        let rr = RRSeries::from_events(events, ts.fs);
+        let psd = hrv_psd(&rr, options.interp_fs);
+        let mut panels = excerpt_panels(ts, events, &options);
+        panels.push(tachogram_panel(&rr));
+        panels.push(histogram_panel(&rr));
+        panels.push(poincare_panel(&rr));
+        panels.push(psd_panel(&psd));
+        Self {
+            title: options
+                .title
+                .clone()
+                .unwrap_or_else(|| format!("HRV report: {source}")),
+            source,
+            beat_source,
+            fs: ts.fs,
+            sample_count: ts.len(),
+            beats: events.indices.len(),
+            hrv: hrv_time(&rr),
+            psd,
+            nonlinear: hrv_nonlinear(&rr),
+            sqi: evaluate_sqi(ts, &rr),
+            options,
+            panels,
+        }
+    }
+
+    pub fn duration_s(&self) -> f64 {
+        self.sample_count as f64 / self.fs.max(f64::MIN_POSITIVE)
+    }
+
+    pub fn summary(&self, html: PathBuf) -> ReportSummary {
+        ReportSummary {
+            html,
+            fs: self.fs,
+            duration_s: self.duration_s(),
+            beats: self.beats,
+            hrv: self.hrv,
+            psd: PsdBands {
+                vlf: self.psd.vlf,
+                lf: self.psd.lf,
+                hf: self.psd.hf,
+                lf_hf: self.psd.lf_hf,
+                total_power: self.psd.total_power,
+            },
+            nonlinear: self.nonlinear.clone(),
+            sqi: self.sqi,
+            sqi_acceptable: self.sqi.is_acceptable(),
+        }
+    }
+
+    /// Metric and parameter tables as `(heading, [(label, value)])`, shared by both outputs.
+    fn tables(&self) -> Vec<(&'static str, Vec<(String, String)>)> {
+        let row = |label: &str, value: String| (label.to_string(), value);
+        let mean_hr = if self.hrv.avnn > 0.0 {
+            60.0 / self.hrv.avnn
+        } else {
+            f64::NAN
+        };
+        let recording = vec![
+            row("Source", self.source.clone()),
+            row("Sampling rate (Hz)", num(self.fs, 2)),
+            row("Samples", self.sample_count.to_string()),
+            row("Duration (s)", num(self.duration_s(), 1)),
+            row("Beats", self.beats.to_string()),
+            row("Mean heart rate (bpm)", num(mean_hr, 1)),
+        ];
+        let time = vec![
+            row("RR intervals", self.hrv.n.to_string()),
+            row("AVNN (ms)", num(self.hrv.avnn * 1e3, 1)),
+            row("SDNN (ms)", num(self.hrv.sdnn * 1e3, 1)),
+            row("RMSSD (ms)", num(self.hrv.rmssd * 1e3, 1)),
+            row("pNN50 (%)", num(self.hrv.pnn50 * 100.0, 1)),
+        ];
+        let frequency = vec![
+            row("VLF power", num(self.psd.vlf, 1)),
+            row("LF power", num(self.psd.lf, 1)),
+            row("HF power", num(self.psd.hf, 1)),
+            row("LF/HF", num(self.psd.lf_hf, 3)),
+            row("Total power", num(self.psd.total_power, 1)),
+        ];
+        let nonlinear = vec![
+            row("SD1 (ms)", num(self.nonlinear.sd1 * 1e3, 1)),
+            row("SD2 (ms)", num(self.nonlinear.sd2 * 1e3, 1)),
+            row("Sample entropy", num(self.nonlinear.samp_entropy, 3)),
+            row("DFA α1", num(self.nonlinear.dfa_alpha1, 3)),
+        ];
+        let sqi = vec![
+            row("Kurtosis", num(self.sqi.kurtosis, 2)),
+            row("SNR", num(self.sqi.snr, 2)),
+            row("RR CV", num(self.sqi.rr_cv, 3)),
+            row("Spectral entropy", num(self.sqi.spectral_entropy, 3)),
+            row("PPG spike ratio", num(self.sqi.ppg_spike_ratio, 3)),
+            row(
+                "Acceptable",
+                if self.sqi.is_acceptable() {
+                    "yes"
+                } else {
+                    "no"
+                }
+                .into(),
+            ),
+        ];
+        let mut params = match &self.beat_source {
+            BeatSource::Detector(cfg) => vec![
+                row("Beats from", "R-peak detector".into()),
+                row(
+                    "Band-pass (Hz)",
+                    format!("{}–{}", cfg.lowcut_hz, cfg.highcut_hz),
+                ),
+                row(
+                    "Integration window (s)",
+                    cfg.integration_window_s.to_string(),
+                ),
+                row("Minimum RR (s)", cfg.min_rr_s.to_string()),
+                row("Threshold scale", cfg.threshold_scale.to_string()),
+                row("Search-back (s)", cfg.search_back_s.to_string()),
+            ],
+            BeatSource::Annotations { path } => vec![row("Beats from", path.clone())],
+        };
+        params.push(row(
+            "PSD resampling (Hz)",
+            self.options.interp_fs.to_string(),
+        ));
+        params.push(row(
+            "PSD bands (Hz)",
+            [("VLF", VLF_BAND), ("LF", LF_BAND), ("HF", HF_BAND)]
+                .iter()
+                .map(|(name, (lo, hi))| format!("{name} {lo}–{hi}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ));
+        params.push(row("Tool", format!("elf {}", env!("CARGO_PKG_VERSION"))));
+        vec![
+            ("Recording", recording),
+            ("Time domain", time),
+            ("Frequency domain", frequency),
+            ("Nonlinear", nonlinear),
+            ("Signal quality", sqi),
+            ("Processing parameters", params),
+        ]
+    }
+
+    /// One HTML page with inline CSS and SVG; `@media print` rules make it print cleanly to PDF.
+    pub fn to_html(&self) -> Result<String> {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title)
+        );
+        html.push_str("<div class=\"tables\">\n");
+        for (heading, rows) in self.tables() {
+            let _ = write!(html, "<table>\n<caption>{}</caption>\n", escape(heading));
+            for (label, value) in rows {
+                let _ = writeln!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape(&label),
+                    escape(&value)
+                );
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</div>\n");
+        for panel in &self.panels {
+            let size = (PAGE_WIDTH as u32, panel.height as u32);
+            let svg = figure_to_svg(&panel.figure, size)?;
+            let _ = write!(html, "<figure>\n{}\n</figure>\n", svg.trim_end());
+        }
+        html.push_str("</body>\n</html>\n");
+        Ok(html)
+    }
+}
+
+/// Writes the HTML report, creating its directory.
+pub fn write_report(report: &HrvReport, html: &Path) -> Result<()> {
+    if let Some(parent) = html.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+    }
+    fs::write(html, report.to_html()?).with_context(|| format!("writing {}", html.display()))
+}
+
+const STYLE: &str = "body{font-family:Helvetica,Arial,sans-serif;color:#222;max-width:820px;\
+margin:24px auto;padding:0 16px}h1{font-size:20px}\
+.tables{display:grid;grid-template-columns:repeat(auto-fill,minmax(250px,1fr));gap:12px 24px}\
+table{border-collapse:collapse;font-size:13px;width:100%}\
+caption{text-align:left;font-weight:bold;padding:4px 0}\
+th{text-align:left;font-weight:normal;color:#555;padding:2px 8px 2px 0}\
+td{text-align:right;padding:2px 0;font-variant-numeric:tabular-nums}\
+tr{border-bottom:1px solid #eee}figure{margin:16px 0}svg{width:100%;height:auto}\
+@page{size:A4;margin:14mm}\
+@media print{body{margin:0;max-width:none}figure,table{break-inside:avoid}}";
+
+fn marker_series(name: &str, points: Vec<[f64; 2]>, color: u32) -> Series {
+    Series::Scatter(ScatterSeries {
+        name: name.into(),
+        points,
+        shape: MarkerShape::Circle,
+        size: 2.5,
+        color: Color(color),
+    })
+}
+
+/// ECG excerpts spread evenly over the recording with the detected beats marked.
+fn excerpt_panels(ts: &TimeSeries, events: &Events, options: &ReportOptions) -> Vec<Panel> {
+    if ts.data.is_empty() {
+        return Vec::new();
+    }
+    let fs = ts.fs.max(f64::MIN_POSITIVE);
+    let len = ((options.excerpt_s * fs).round() as usize).clamp(1, ts.len());
+    let count = if ts.len() <= len { 1 } else { options.excerpts };
+    let pyramid = SignalPyramid::new(ts);
+    (0..count)
+        .map(|k| {
+            let start = if count > 1 {
+                k * (ts.len() - len) / (count - 1)
+            } else {
+                0
+            };
+            let end = start + len;
+            let window = [start as f64 / fs, (end - 1) as f64 / fs];
+            let mut figure =
+                figure_from_ecg_window(ts, &pyramid, Some(events), window, EXCERPT_POINTS);
+            figure.title = Some(format!(
+                "ECG {:.1}–{:.1} s",
+                start as f64 / fs,
+                end as f64 / fs
+            ));
+            Panel::new(figure, 220.0)
+        })
+        .collect()
+}
+
+fn tachogram_panel(rr: &RRSeries) -> Panel {
+    let mut figure = figure_from_rr(rr).with_axis_labels("Beat", "RR (s)");
+    figure.title = Some("RR tachogram".into());
+    Panel::new(figure, 260.0)
+}
+
+/// RR histogram over bins of about [`HIST_BIN_S`].
+fn histogram_panel(rr: &RRSeries) -> Panel {
+    let (lo, hi) = rr
+        .rr
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let bins = ((hi - lo) / HIST_BIN_S).ceil().max(1.0) as usize;
+    let figure = rr_histogram_figure(rr, bins).unwrap_or_else(|| {
+        Figure::new(Some("RR histogram".into())).with_axis_labels("RR (s)", "Share")
+    });
+    Panel::new(figure, 260.0)
+}
+
+/// RR(n+1) against RR(n) with the line of identity.
+fn poincare_panel(rr: &RRSeries) -> Panel {
+    let points: Vec<[f64; 2]> = rr.rr.windows(2).map(|w| [w[0], w[1]]).collect();
+    let mut figure =
+        Figure::new(Some("Poincaré plot".into())).with_axis_labels("RR(n) (s)", "RR(n+1) (s)");
+    let lo = points
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::INFINITY, f64::min);
+    let hi = points
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    figure.add_series(marker_series("RR pairs", points, RR_COLOR));
+    if lo < hi {
+        figure.add_series(Series::Line(LineSeries {
+            name: "Identity".into(),
+            points: vec![[lo, lo], [hi, hi]],
+            style: Style {
+                width: 1.0,
+                dash: Some([4.0, 3.0]),
+                color: Color(0x888888),
+            },
+        }));
+    }
+    Panel::new(figure, 300.0)
+}
+
+/// Welch spectrum up to 0.5 Hz with the VLF/LF/HF bands shaded.
+fn psd_panel(psd: &HRVPsd) -> Panel {
+    let shown = HRVPsd {
+        points: psd.points.iter().filter(|p| p[0] <= 0.5).copied().collect(),
+        ..psd.clone()
+    };
+    let mut figure = figure_from_psd(&shown);
+    figure.title = Some("Power spectral density".into());
+    Panel::new(figure, 260.0)
+}
+
+fn num(value: f64, decimals: usize) -> String {
+    if value.is_finite() {
+        format!("{value:.decimals$}")
+    } else {
+        "–".into()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
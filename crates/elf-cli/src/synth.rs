@@ -0,0 +1,220 @@
+use crate::output::Tabular;
+use anyhow::{bail, Context, Result};
+use elf_lib::{
+    io::{edf as edf_io, parquet as parquet_io, wfdb as wfdb_io},
+    metrics::hrv::{hrv_time, HRVTime},
+    synth::SynthRecording,
+};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files `elf synth` writes besides the recording.
+pub struct SynthOutputs {
+    pub out: PathBuf,
+    /// Channel written to a `.txt` recording, by name (`ecg`, `ppg`, ...).
+    pub signal: String,
+    /// BIDS-style `events.tsv` with every ground-truth event.
+    pub truth: Option<PathBuf>,
+    /// True R-peak sample indices, one per line.
+    pub beats: Option<PathBuf>,
+    /// Pupil trace in the Pupil Labs `pupil_positions.csv` layout.
+    pub pupil_csv: Option<PathBuf>,
+}
+
+/// Printed by the command once the files are written.
+#[derive(Debug, Serialize)]
+pub struct SynthSummary {
+    pub files: Vec<PathBuf>,
+    pub fs: f64,
+    pub duration_s: f64,
+    pub samples: usize,
+    pub beats: usize,
+    pub ectopic_beats: usize,
+    pub mean_hr_bpm: f64,
+    pub snr_db: Option<f64>,
+    /// Time-domain HRV of the true RR intervals.
+    pub hrv: HRVTime,
+}
+
+impl Tabular for SynthSummary {}
+
+pub fn write_synth(
+    rec: &SynthRecording,
+    outputs: &SynthOutputs,
+    snr_db: Option<f64>,
+) -> Result<SynthSummary> {
+    let mut files = write_recording(rec, &outputs.out, &outputs.signal)?;
+    if let Some(path) = &outputs.truth {
+        fs::write(path, truth_tsv(rec))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        files.push(path.clone());
+    }
+    if let Some(path) = &outputs.beats {
+        let text: String = rec
+            .truth
+            .beats
+            .iter()
+            .map(|b| format!("{}\n", b.sample))
+            .collect();
+        fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))?;
+        files.push(path.clone());
+    }
+    if let Some(path) = &outputs.pupil_csv {
+        fs::write(path, pupil_csv(rec))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        files.push(path.clone());
+    }
+    let hrv = hrv_time(&rec.rr());
+    Ok(SynthSummary {
+        files,
+        fs: rec.fs,
+        duration_s: rec.len() as f64 / rec.fs,
+        samples: rec.len(),
+        beats: rec.truth.beats.len(),
+        ectopic_beats: rec.truth.beats.iter().filter(|b| b.ectopic).count(),
+        mean_hr_bpm: if hrv.avnn > 0.0 { 60.0 / hrv.avnn } else { 0.0 },
+        snr_db,
+        hrv,
+    })
+}
+
+fn write_recording(rec: &SynthRecording, out: &Path, signal: &str) -> Result<Vec<PathBuf>> {
+    let ext = out
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let channels = rec.channels();
+    match ext.as_str() {
+        "hea" => {
+            let dir = out.parent().unwrap_or(Path::new("."));
+            let record = out
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .context("WFDB output needs a record name")?;
+            let signals: Vec<_> = channels
+                .iter()
+                .map(|&(description, units, data)| wfdb_io::WfdbWriteSignal {
+                    description,
+                    units,
+                    data,
+                })
+                .collect();
+            let header = wfdb_io::write_wfdb_record(
+                dir,
+                record,
+                rec.fs,
+                &signals,
+                wfdb_io::WfdbFormat::F16,
+            )?;
+            let annotations: Vec<_> = rec
+                .truth
+                .beats
+                .iter()
+                .map(|b| {
+                    let code = if b.ectopic {
+                        wfdb_io::PVC
+                    } else {
+                        wfdb_io::NORMAL
+                    };
+                    wfdb_io::WfdbAnnotation::new(b.sample, code)
+                })
+                .collect();
+            let atr = dir.join(format!("{record}.atr"));
+            wfdb_io::write_wfdb_annotations(&atr, &annotations)?;
+            Ok(vec![header.clone(), header.with_extension("dat"), atr])
+        }
+        "edf" => {
+            let signals: Vec<_> = channels
+                .iter()
+                .map(
+                    |&(label, physical_dimension, data)| edf_io::EdfWriteSignal {
+                        label,
+                        physical_dimension,
+                        prefiltering: "",
+                        fs: rec.fs,
+                        data,
+                    },
+                )
+                .collect();
+            let annotations: Vec<_> = rec
+                .events()
+                .into_iter()
+                .map(|e| edf_io::EdfAnnotation {
+                    onset_s: e.onset_s,
+                    duration_s: (e.duration_s > 0.0).then_some(e.duration_s),
+                    text: e.kind.to_string(),
+                })
+                .collect();
+            edf_io::write_edf_plus(
+                out,
+                &signals,
+                &annotations,
+                &edf_io::EdfWriteOptions::default(),
+            )?;
+            Ok(vec![out.to_path_buf()])
+        }
+        _ if parquet_io::is_table_path(out) => {
+            let table = parquet_io::SignalTable {
+                fs: rec.fs,
+                start_time: 0.0,
+                channels: channels
+                    .iter()
+                    .map(|&(name, units, data)| parquet_io::SignalChannel {
+                        name: name.to_ascii_lowercase(),
+                        units: Some(units.to_string()),
+                        data: data.to_vec(),
+                    })
+                    .collect(),
+            };
+            parquet_io::write_signals(out, &table)?;
+            Ok(vec![out.to_path_buf()])
+        }
+        "txt" => {
+            let Some(&(_, _, data)) = channels
+                .iter()
+                .find(|(name, _, _)| name.eq_ignore_ascii_case(signal))
+            else {
+                bail!("unknown channel {signal:?}; expected ecg, ppg, resp, eda or pupil");
+            };
+            let text: String = data.iter().map(|v| format!("{v}\n")).collect();
+            fs::write(out, text).with_context(|| format!("failed to write {}", out.display()))?;
+            Ok(vec![out.to_path_buf()])
+        }
+        _ => bail!(
+            "cannot tell the format of {}; use .hea, .edf, .parquet, .arrow or .txt",
+            out.display()
+        ),
+    }
+}
+
+/// Every ground-truth event as a BIDS `events.tsv` (`onset`, `duration`, `trial_type`,
+/// `sample`).
+fn truth_tsv(rec: &SynthRecording) -> String {
+    let mut tsv = String::from("onset\tduration\ttrial_type\tsample\n");
+    for e in rec.events() {
+        let _ = writeln!(
+            tsv,
+            "{:.6}\t{:.6}\t{}\t{}",
+            e.onset_s, e.duration_s, e.kind, e.sample
+        );
+    }
+    tsv
+}
+
+/// The columns `EyeCsvLayout::PUPIL_LABS` reads; blinks have no diameter and zero
+/// confidence.
+fn pupil_csv(rec: &SynthRecording) -> String {
+    let mut csv = String::from("timestamp,diameter,confidence,eye\n");
+    for (i, v) in rec.pupil.iter().enumerate() {
+        let t = i as f64 / rec.fs;
+        if v.is_finite() {
+            let _ = writeln!(csv, "{t:.6},{v:.4},0.99,left");
+        } else {
+            let _ = writeln!(csv, "{t:.6},,0.00,left");
+        }
+    }
+    csv
+}
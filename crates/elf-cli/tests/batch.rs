@@ -0,0 +1,124 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+/// Two copies of the synthetic recording with the same file name, plus one unreadable file.
+fn recordings(root: &Path) -> Result<(), Box<dyn Error>> {
+    for dir in ["a", "b"] {
+        fs::create_dir_all(root.join(dir))?;
+        fs::copy(
+            sample_path("test_data/synthetic_recording_a.txt"),
+            root.join(dir).join("rec.txt"),
+        )?;
+    }
+    fs::write(root.join("a").join("broken.txt"), "not,a\nsignal,at all\n")?;
+    Ok(())
+}
+
+#[test]
+fn batch_runs_in_parallel_and_resumes() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    recordings(temp.path())?;
+    let pattern = format!("{}/*/*.txt", temp.path().display());
+    let out = temp.path().join("out");
+    let args = [
+        "batch",
+        "--glob",
+        &pattern,
+        "--out-dir",
+        out.to_str().unwrap(),
+        "--fs",
+        "250",
+        "--jobs",
+        "2",
+    ];
+    let report = run_json(&args)?;
+    assert_eq!(report["total"], 3);
+    assert_eq!(report["done"], 2);
+    assert_eq!(report["failed"], 1);
+    assert!(report["failures"][0]["input"]
+        .as_str()
+        .unwrap()
+        .ends_with("broken.txt"));
+    assert!(!report["failures"][0]["error"].as_str().unwrap().is_empty());
+
+    // Same stem twice: the second output gets a suffix.
+    let single = run_json(&[
+        "beat-hrv-pipeline",
+        "--fs",
+        "250",
+        "--input",
+        &sample_path("test_data/synthetic_recording_a.txt"),
+    ])?;
+    for name in ["rec.json", "rec-2.json"] {
+        let result: Value = serde_json::from_str(&fs::read_to_string(out.join(name))?)?;
+        assert_eq!(result["events"], single["events"], "{name}");
+    }
+    let summary = fs::read_to_string(out.join("summary.csv"))?;
+    assert!(summary.starts_with("input,status,error,"));
+    assert_eq!(summary.lines().count(), 4);
+
+    // Simulate an interrupted run: one finished job is back to pending without its output.
+    let state_path = out.join("batch_state.json");
+    let mut state: Value = serde_json::from_str(&fs::read_to_string(&state_path)?)?;
+    let job = state["jobs"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|j| j["output"] == "rec-2.json")
+        .unwrap();
+    job["status"] = "pending".into();
+    fs::write(&state_path, serde_json::to_string(&state)?)?;
+    fs::remove_file(out.join("rec-2.json"))?;
+
+    let resumed = run_json(&args)?;
+    assert_eq!(resumed["processed"], 1);
+    assert_eq!(resumed["resumed"], 2);
+    assert_eq!(resumed["done"], 2);
+    assert!(out.join("rec-2.json").exists());
+
+    let retried = run_json(&[&args[..], &["--retry-failed"]].concat())?;
+    assert_eq!(retried["processed"], 1);
+    assert_eq!(retried["failed"], 1);
+
+    // Different settings must not silently mix with the earlier results.
+    cargo_bin_cmd!("elf")
+        .args([&args[..], &["--min-rr-s", "0.3"]].concat())
+        .assert()
+        .failure();
+    let restarted = run_json(&[&args[..], &["--min-rr-s", "0.3", "--restart"]].concat())?;
+    assert_eq!(restarted["processed"], 3);
+    Ok(())
+}
+
+#[test]
+fn manifest_lists_recordings_relative_to_itself() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    recordings(temp.path())?;
+    let manifest = temp.path().join("manifest.tsv");
+    fs::write(&manifest, "subject\tpath\ns1\ta/rec.txt\ns2\tb/rec.txt\n")?;
+    let out = temp.path().join("out");
+    let report = run_json(&[
+        "batch",
+        "--manifest",
+        manifest.to_str().unwrap(),
+        "--out-dir",
+        out.to_str().unwrap(),
+        "--fs",
+        "250",
+        "--summary",
+        "summary.json",
+    ])?;
+    assert_eq!(report["done"], 2);
+    let summary: Value = serde_json::from_str(&fs::read_to_string(out.join("summary.json"))?)?;
+    assert_eq!(summary.as_array().unwrap().len(), 2);
+    assert_eq!(summary[0]["status"], "done");
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::run_json;
+
+#[test]
+fn bench_detectors_ranks_detectors_on_synthetic_records() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let dir = temp.path().join("records");
+    fs::create_dir_all(dir.join("nested"))?;
+    for (name, seed) in [("a", "1"), ("nested/b", "2")] {
+        let hea = dir.join(format!("{name}.hea"));
+        run_json(&[
+            "synth",
+            "--out",
+            hea.to_str().unwrap(),
+            "--duration-s",
+            "60",
+            "--ectopic-rate",
+            "0.05",
+            "--seed",
+            seed,
+        ])?;
+    }
+    // A header without reference annotations is reported, not scored.
+    fs::copy(dir.join("a.hea"), dir.join("unannotated.hea"))?;
+
+    let dir_arg = dir.to_str().unwrap();
+    let report = run_json(&["bench-detectors", "--dir", dir_arg, "--snr-db", "12,0"])?;
+    assert_eq!(report["records"], serde_json::json!(["a", "b"]));
+    assert_eq!(report["skipped"].as_array().unwrap().len(), 1);
+    assert_eq!(report["tolerance_ms"], 150.0);
+
+    let results = report["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2 * 3 * 3);
+    let board = report["leaderboard"].as_array().unwrap();
+    assert_eq!(board.len(), 3 * 3);
+    // Clean first, then decreasing SNR; ranks restart at every level.
+    let levels: Vec<Value> = board.iter().map(|r| r["snr_db"].clone()).collect();
+    assert_eq!(levels[0], Value::Null);
+    assert_eq!(levels[3], 12.0);
+    assert_eq!(levels[6], 0.0);
+    let ranks: Vec<u64> = board.iter().filter_map(|r| r["rank"].as_u64()).collect();
+    assert_eq!(ranks, [1, 2, 3, 1, 2, 3, 1, 2, 3]);
+    for level in board.chunks(3) {
+        assert!(level[0]["f1"].as_f64() >= level[1]["f1"].as_f64());
+        assert!(level[1]["f1"].as_f64() >= level[2]["f1"].as_f64());
+    }
+
+    // Gross statistics add up the records.
+    let clean_pt = |r: &&Value| r["detector"] == "pan-tompkins" && r["snr_db"].is_null();
+    let total = board.iter().find(clean_pt).unwrap();
+    let per_record: u64 = results
+        .iter()
+        .filter(clean_pt)
+        .map(|r| r["tp"].as_u64().unwrap())
+        .sum();
+    assert_eq!(total["tp"].as_u64(), Some(per_record));
+    assert_eq!(total["record"], "all");
+    assert!(total["se"].as_f64().unwrap() > 0.99);
+    assert!(total["timing_error_ms"].as_f64().unwrap() < 20.0);
+
+    // The noise is seeded, so the scores repeat.
+    let again = run_json(&["bench-detectors", "--dir", dir_arg, "--snr-db", "12,0"])?;
+    let scores = |r: &Value| {
+        r["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| (row["tp"].clone(), row["fp"].clone()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(scores(&report), scores(&again));
+
+    let csv = cargo_bin_cmd!("elf")
+        .args([
+            "bench-detectors",
+            "--dir",
+            dir_arg,
+            "--detectors",
+            "streaming",
+        ])
+        .args(["--format", "csv"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let csv = String::from_utf8(csv)?;
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("rank,record,detector,snr_db,"));
+    assert_eq!(lines.count(), 1 + 2);
+
+    cargo_bin_cmd!("elf")
+        .args([
+            "bench-detectors",
+            "--dir",
+            temp.path().join("missing").to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+#[test]
+fn bids_analyze_writes_derivatives_for_every_recording() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let root = temp.path().join("study");
+    copy_dir(&sample_path("test_data/bids_study"), &root)?;
+
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args(["bids-analyze", "--root", root.to_str().unwrap()]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: Value = serde_json::from_slice(&output)?;
+    assert_eq!(summary["processed"].as_array().unwrap().len(), 2);
+    assert!(summary["skipped"].as_array().unwrap().is_empty());
+
+    let derivatives = root.join("derivatives/elf");
+    let description: Value = serde_json::from_str(&fs::read_to_string(
+        derivatives.join("dataset_description.json"),
+    )?)?;
+    assert_eq!(description["DatasetType"], "derivative");
+    let hrv: Value = serde_json::from_str(&fs::read_to_string(
+        derivatives.join("sub-02/beh/sub-02_task-rest_recording-ecg_desc-hrv_physio.json"),
+    )?)?;
+    assert_eq!(hrv["start_time"], -0.5);
+    assert!(hrv["beats"].as_u64().unwrap() > 30);
+    let beats = fs::read_to_string(
+        derivatives
+            .join("sub-01/ses-1/beh/sub-01_ses-1_task-rest_recording-ecg_desc-beats_events.tsv"),
+    )?;
+    assert!(beats.starts_with("onset\tduration\ttrial_type\trr_s\n"));
+    let group = fs::read_to_string(derivatives.join("group_hrv.tsv"))?;
+    assert_eq!(group.lines().count(), 3);
+
+    // Subject filter narrows the batch to one recording.
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "bids-analyze",
+        "--root",
+        root.to_str().unwrap(),
+        "--subject",
+        "01",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: Value = serde_json::from_slice(&output)?;
+    assert_eq!(summary["processed"].as_array().unwrap().len(), 1);
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+fn sample_path(relative: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("workspace root")
+        .join(relative)
+}
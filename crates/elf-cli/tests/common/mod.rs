@@ -0,0 +1,29 @@
+#![allow(dead_code)]
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Absolute path of a file relative to the workspace root, e.g. `test_data/rr_sample.txt`.
+pub fn sample_path(rel: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("workspace root")
+        .join(rel)
+        .display()
+        .to_string()
+}
+
+/// Runs `elf` with `args`, expects success and parses stdout as JSON.
+pub fn run_json(args: &[&str]) -> Result<Value, Box<dyn Error>> {
+    let out = cargo_bin_cmd!("elf")
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    Ok(serde_json::from_slice(&out)?)
+}
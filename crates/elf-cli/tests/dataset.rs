@@ -0,0 +1,262 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn dataset_validate(spec: &Path, extra: &[&str]) -> assert_cmd::assert::Assert {
+    cargo_bin_cmd!("elf")
+        .args(["dataset-validate", "--spec", spec.to_str().unwrap()])
+        .args(extra)
+        .assert()
+}
+
+#[test]
+fn dataset_validate_scores_beats_sqi_and_reports_metric_diffs() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let hea = temp.path().join("syn.hea");
+    cargo_bin_cmd!("elf")
+        .args([
+            "synth",
+            "--out",
+            hea.to_str().unwrap(),
+            "--duration-s",
+            "60",
+        ])
+        .assert()
+        .success();
+    let spec = temp.path().join("spec.json");
+    fs::write(
+        &spec,
+        serde_json::to_string(&json!({
+            "name": "synthetic",
+            "cases": [{
+                "name": "syn_lead0",
+                "wfdb_header": hea,
+                "annotations": temp.path().join("syn.atr"),
+                "fs": 250.0,
+                "hrv_nonlinear": { "tolerance": 0.0, "relative_tolerance": 0.01 },
+                "sqi": {},
+                "beats": { "window_ms": 100.0, "tolerance": 0.001 }
+            }]
+        }))?,
+    )?;
+
+    // An update fills in every section and keeps the settings it does not compute.
+    dataset_validate(&spec, &["--update-spec"]).success();
+    let mut updated: Value = serde_json::from_str(&fs::read_to_string(&spec)?)?;
+    let case = &updated["cases"][0];
+    for section in ["hrv_time", "hrv_psd", "hrv_nonlinear", "sqi", "beats"] {
+        assert!(case[section]["tolerance"].is_number(), "{section}");
+    }
+    assert_eq!(case["hrv_nonlinear"]["relative_tolerance"], 0.01);
+    assert_eq!(case["beats"]["window_ms"], 100.0);
+    assert!(case["beats"]["se"].as_f64().unwrap() > 0.99);
+    assert!(case["beats"]["count"].as_f64() >= case["beats"]["reference"].as_f64());
+    assert!(case["sqi"]["kurtosis"].is_number());
+
+    let report = temp.path().join("report.json");
+    let report_arg = report.to_str().unwrap();
+    dataset_validate(&spec, &["--json", report_arg]).success();
+    let results: Value = serde_json::from_str(&fs::read_to_string(&report)?)?;
+    assert_eq!(results[0]["status"], "passed");
+    let checks = results[0]["checks"].as_array().unwrap();
+    assert!(checks.iter().all(|c| c["passed"] == true));
+    assert!(checks.iter().any(|c| c["metric"] == "beats.ppv"));
+
+    // Two regressions: both are reported, each with its delta.
+    let case = &mut updated["cases"][0];
+    let count = case["beats"]["count"].as_f64().unwrap();
+    case["beats"]["count"] = json!(count + 3.0);
+    let sd1 = case["hrv_nonlinear"]["sd1"].as_f64().unwrap();
+    case["hrv_nonlinear"]["sd1"] = json!(sd1 * 1.5);
+    fs::write(&spec, serde_json::to_string(&updated)?)?;
+    let junit = temp.path().join("junit.xml");
+    dataset_validate(
+        &spec,
+        &["--json", report_arg, "--junit", junit.to_str().unwrap()],
+    )
+    .failure();
+    let results: Value = serde_json::from_str(&fs::read_to_string(&report)?)?;
+    assert_eq!(results[0]["status"], "failed");
+    let failed: Vec<&Value> = results[0]["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|c| c["passed"] == false)
+        .collect();
+    assert_eq!(failed.len(), 2);
+    assert_eq!(failed[0]["metric"], "hrv_nonlinear.sd1");
+    assert_eq!(failed[1]["metric"], "beats.count");
+    assert_eq!(failed[1]["delta"], -3.0);
+
+    let xml = fs::read_to_string(&junit)?;
+    assert!(xml.contains("<testsuite name=\"synthetic\" tests=\"1\" failures=\"1\" errors=\"0\""));
+    assert!(xml.contains("<failure message=\"2 of "));
+    assert!(xml.contains("beats.count: expected"));
+    Ok(())
+}
+
+#[test]
+fn dataset_validate_applies_relative_tolerances_and_reports_errors() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let rr = temp.path().join("rr.txt");
+    fs::write(&rr, "0.8\n0.9\n0.7\n0.85\n0.75\n0.8\n")?;
+    let case = |sdnn: f64, relative: f64| {
+        json!({
+            "name": "rr_case",
+            "input": rr,
+            "format": "rr",
+            "hrv_time": {
+                "tolerance": 0.0,
+                "relative_tolerance": relative,
+                "avnn": 0.8,
+                "sdnn": sdnn
+            }
+        })
+    };
+    let spec = temp.path().join("spec.json");
+    let sdnn = (0.025f64 / 5.0).sqrt();
+    fs::write(&spec, case(sdnn * 1.04, 0.05).to_string())?;
+    dataset_validate(&spec, &[]).success();
+    fs::write(&spec, case(sdnn * 1.04, 0.01).to_string())?;
+    dataset_validate(&spec, &[]).failure();
+
+    // A case that cannot be computed is an error in the report, not an abort.
+    let missing = json!({
+        "name": "missing_suite",
+        "cases": [case(sdnn, 0.05), { "name": "gone", "input": "nope.txt", "format": "rr" }]
+    });
+    fs::write(&spec, missing.to_string())?;
+    let junit = temp.path().join("junit.xml");
+    dataset_validate(&spec, &["--junit", junit.to_str().unwrap()]).failure();
+    let xml = fs::read_to_string(&junit)?;
+    assert!(xml.contains("tests=\"2\" failures=\"0\" errors=\"1\""));
+    assert!(xml.contains("<testcase classname=\"missing_suite\" name=\"rr_case\""));
+    assert!(xml.contains("<error message="));
+    Ok(())
+}
+
+#[test]
+fn dataset_validate_reads_any_format_with_per_case_pipelines() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let edf = temp.path().join("syn.edf");
+    let beats = temp.path().join("beats.txt");
+    let pupil = temp.path().join("pupil.csv");
+    cargo_bin_cmd!("elf")
+        .args(["synth", "--out", edf.to_str().unwrap()])
+        .args(["--beats", beats.to_str().unwrap()])
+        .args(["--pupil-csv", pupil.to_str().unwrap()])
+        .args(["--duration-s", "40", "--seed", "5"])
+        .assert()
+        .success();
+    let ecg_case = |name: &str, pipeline: Value| {
+        json!({
+            "name": name,
+            "input": edf,
+            "format": "edf",
+            "signal": "ECG",
+            "pipeline": pipeline,
+            "preprocess": [
+                { "kind": "filter", "lowcut_hz": 0.5, "highcut_hz": 40.0 },
+                { "kind": "rr-clean", "max_rr_s": 1.5 }
+            ],
+            "hrv_time": {},
+            "beats": { "annotations": beats }
+        })
+    };
+    let spec = temp.path().join("spec.json");
+    fs::write(
+        &spec,
+        json!({
+            "name": "formats",
+            "cases": [
+                ecg_case("edf_default", json!({})),
+                // A refractory period longer than the RR interval skips every other beat.
+                ecg_case("edf_slow", json!({ "min_rr_s": 1.2 })),
+                {
+                    "name": "pupil",
+                    "input": pupil,
+                    "pupil_pipeline": { "resample_hz": 30.0, "interpolation": "cubic" },
+                    "pupil": {}
+                }
+            ]
+        })
+        .to_string(),
+    )?;
+    dataset_validate(&spec, &["--update-spec"]).success();
+    let updated: Value = serde_json::from_str(&fs::read_to_string(&spec)?)?;
+    let cases = updated["cases"].as_array().unwrap();
+    let default_beats = &cases[0]["beats"];
+    assert!(default_beats["se"].as_f64().unwrap() > 0.99);
+    let slow_count = cases[1]["beats"]["count"].as_f64().unwrap();
+    assert!(slow_count < 0.7 * default_beats["reference"].as_f64().unwrap());
+    assert!(cases[0]["hrv_time"]["avnn"].as_f64().unwrap() < 1.5);
+    // The eye export has no RR intervals, so only the pupil section is filled in.
+    let pupil = &cases[2]["pupil"];
+    assert!(pupil["left_blinks"].as_f64().unwrap() > 0.0);
+    assert!((pupil["left_mean_mm"].as_f64().unwrap() - 4.0).abs() < 1.0);
+    assert!(cases[2].get("hrv_time").is_none());
+    dataset_validate(&spec, &[]).success();
+
+    // Stages that do not preprocess a signal or RR intervals are rejected, and the pupil
+    // settings are part of what the stored metrics pin down.
+    let mut bad = updated.clone();
+    bad["cases"][0]["preprocess"] = json!([{ "kind": "hrv-time" }]);
+    bad["cases"][2]["pupil_pipeline"] = json!({ "min_pupil_mm": 4.5 });
+    fs::write(&spec, bad.to_string())?;
+    let report = temp.path().join("report.json");
+    dataset_validate(&spec, &["--json", report.to_str().unwrap()]).failure();
+    let results: Value = serde_json::from_str(&fs::read_to_string(&report)?)?;
+    assert_eq!(results[0]["status"], "error");
+    assert!(results[0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("hrv-time cannot preprocess"));
+    assert_eq!(results[1]["status"], "passed");
+    assert_eq!(results[2]["status"], "failed");
+
+    // Preprocess stages reject settings they do not know.
+    let mut typo = updated.clone();
+    typo["cases"][0]["preprocess"] = json!([{ "kind": "rr-clean", "max_rr": 1.5 }]);
+    fs::write(&spec, typo.to_string())?;
+    let assert = dataset_validate(&spec, &[]).failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("unknown field `max_rr`"), "{stderr}");
+    Ok(())
+}
+
+#[test]
+fn dataset_validate_rejects_unknown_keys_and_cases_without_checks() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let rr = temp.path().join("rr.txt");
+    fs::write(&rr, "0.8\n0.9\n0.7\n0.85\n")?;
+    let spec = temp.path().join("spec.json");
+
+    // A misspelled section is an error, not a case with nothing to check.
+    let typo = json!({
+        "name": "typo",
+        "cases": [{ "name": "rr", "input": rr, "format": "rr", "hrv_nonlinar": { "sd1": 0.1 } }]
+    });
+    fs::write(&spec, typo.to_string())?;
+    let assert = dataset_validate(&spec, &[]).failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("unknown field `hrv_nonlinar`"), "{stderr}");
+
+    let report = temp.path().join("report.json");
+    let empty = json!({ "name": "rr", "input": rr, "format": "rr" });
+    fs::write(&spec, empty.to_string())?;
+    dataset_validate(&spec, &["--json", report.to_str().unwrap()]).failure();
+    let results: Value = serde_json::from_str(&fs::read_to_string(&report)?)?;
+    assert_eq!(results[0]["status"], "error");
+    assert!(results[0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("no stored metrics"));
+
+    // Filling the case in gives it something to check.
+    dataset_validate(&spec, &["--update-spec"]).success();
+    dataset_validate(&spec, &[]).success();
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::sample_path;
+
+#[test]
+fn eda_scores_bundle_events() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let bundle = temp.path().join("bundle");
+    fs::create_dir_all(&bundle)?;
+    fs::write(
+        bundle.join("events.tsv"),
+        "onset\tduration\ttrial\tblock\tevent_type\tstim_id\tcondition\tresp_key\tresp_rt\tvalue\n\
+         10\t1\t1\t1\tstim\tA\tthreat\t\t\t\n\
+         25\t1\t2\t1\tstim\tB\tsafe\t\t\t\n",
+    )?;
+
+    // 50 Hz skin conductance: 2 µS level plus one SCR starting 1.5 s after the threat cue.
+    let samples: Vec<String> = (0..2000)
+        .map(|i| {
+            let t = i as f64 / 50.0;
+            let x = t - 11.5;
+            let scr = if x > 0.0 {
+                0.4 * ((-x / 2.0).exp() - (-x / 0.75).exp()) / 0.3
+            } else {
+                0.0
+            };
+            format!("{:.5}", 2.0 + scr)
+        })
+        .collect();
+    let input = temp.path().join("eda.txt");
+    fs::write(&input, samples.join("\n"))?;
+
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "eda",
+        "--input",
+        input.to_str().unwrap(),
+        "--fs",
+        "50",
+        "--bundle",
+        bundle.to_str().unwrap(),
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let result: Value = serde_json::from_slice(output.trim_ascii())?;
+    assert_eq!(result["summary"]["scr_count"].as_u64(), Some(1));
+    let events = result["events"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["responded"].as_bool(), Some(true));
+    assert_eq!(events[1]["responded"].as_bool(), Some(false));
+    assert_eq!(
+        result["conditions"][0]["condition"].as_str(),
+        Some("threat")
+    );
+    Ok(())
+}
+
+#[test]
+fn eda_reads_bitalino_column() {
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "eda",
+        "--input",
+        &sample_path("test_data/bitalino_sample.csv"),
+        "--signal",
+        "analog1",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let result: Value = serde_json::from_slice(output.trim_ascii()).unwrap();
+    assert!(result["summary"]["duration_s"].as_f64().unwrap() > 0.0);
+    assert!(result["scrs"].is_array());
+}
@@ -0,0 +1,37 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use elf_lib::io::edf::{write_edf_plus, EdfAnnotation, EdfWriteOptions, EdfWriteSignal};
+use serde_json::Value;
+use std::error::Error;
+use tempfile::tempdir;
+
+#[test]
+fn edf_info_lists_channels_and_annotations() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let path = temp.path().join("rec.edf");
+    let data: Vec<f64> = (0..1000).map(|i| (i as f64 * 0.1).sin()).collect();
+    let signals = [EdfWriteSignal {
+        label: "EEG Cz",
+        physical_dimension: "uV",
+        prefiltering: "HP:0.5Hz",
+        fs: 100.0,
+        data: &data,
+    }];
+    let annotations = [EdfAnnotation {
+        onset_s: 2.0,
+        duration_s: Some(0.5),
+        text: "stim".into(),
+    }];
+    write_edf_plus(&path, &signals, &annotations, &EdfWriteOptions::default())?;
+
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args(["edf-info", "--input", path.to_str().unwrap()]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let info: Value = serde_json::from_slice(&output)?;
+    assert_eq!(info["variant"], "EDF+C");
+    assert_eq!(info["duration_s"], 10.0);
+    assert_eq!(info["channels"][0]["label"], "EEG Cz");
+    assert_eq!(info["channels"][0]["prefiltering"], "HP:0.5Hz");
+    assert_eq!(info["annotations"][0]["text"], "stim");
+    assert_eq!(info["annotations"][0]["duration_s"], 0.5);
+    Ok(())
+}
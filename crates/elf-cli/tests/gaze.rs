@@ -0,0 +1,47 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn gaze_events_reports_fixations_and_saccades() -> Result<(), Box<dyn Error>> {
+    // 250 Hz Pupil Labs export: fixate at (0.25, 0.5), jump to (0.75, 0.5) over 40 ms.
+    let mut csv = String::from("timestamp,diameter,confidence,eye,norm_pos_x,norm_pos_y\n");
+    for i in 0..250 {
+        let t = i as f64 / 250.0;
+        let x = if t < 0.4 {
+            0.25
+        } else if t < 0.44 {
+            0.25 + (t - 0.4) / 0.04 * 0.5
+        } else {
+            0.75
+        };
+        csv.push_str(&format!("{t:.4},4.0,0.95,left,{x:.4},0.5\n"));
+    }
+    let temp = tempdir()?;
+    let input = temp.path().join("gaze.csv");
+    fs::write(&input, csv)?;
+
+    for method in ["ivt", "idt"] {
+        let mut cmd = cargo_bin_cmd!("elf");
+        cmd.args([
+            "gaze-events",
+            "--input",
+            input.to_str().unwrap(),
+            "--method",
+            method,
+        ]);
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let events: Value = serde_json::from_slice(output.trim_ascii())?;
+        assert_eq!(events["eye"].as_str(), Some("Left"));
+        assert_eq!(events["summary"]["fixations"].as_u64(), Some(2), "{method}");
+        assert_eq!(events["summary"]["saccades"].as_u64(), Some(1), "{method}");
+        let amplitude = events["saccades"][0]["amplitude_deg"].as_f64().unwrap();
+        assert!(
+            amplitude > 15.0 && amplitude < 25.0,
+            "{method}: {amplitude}"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn report_is_self_contained_html() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let html = temp.path().join("report/118.html");
+    let record = [
+        "--wfdb-header",
+        &sample_path("test_data/mitdb/118.hea"),
+        "--annotations",
+        &sample_path("test_data/mitdb/118.atr"),
+    ];
+    let pipeline = run_json(&[&["beat-hrv-pipeline"], &record[..]].concat())?;
+    let summary = run_json(
+        &[
+            &["hrv-report", "--out", html.to_str().unwrap()][..],
+            &record,
+        ]
+        .concat(),
+    )?;
+    assert_eq!(summary["hrv"], pipeline["hrv"]);
+    assert_eq!(
+        summary["beats"].as_u64(),
+        pipeline["events"]["indices"]
+            .as_array()
+            .map(|a| a.len() as u64)
+    );
+
+    let page = fs::read_to_string(&html)?;
+    // Three excerpts, tachogram, histogram, Poincaré plot and PSD.
+    assert_eq!(page.matches("<svg").count(), 7);
+    for needle in [
+        "Poincaré plot",
+        "Power spectral density",
+        "Signal quality",
+        "\nHF\n</text>",
+    ] {
+        assert!(page.contains(needle), "{needle}");
+    }
+    let rmssd = format!("{:.1}", pipeline["hrv"]["rmssd"].as_f64().unwrap() * 1e3);
+    assert!(page.contains(&format!("<th>RMSSD (ms)</th><td>{rmssd}</td>")));
+    for external in ["<script", "<link", "src=", "href=", "url("] {
+        assert!(!page.contains(external), "{external}");
+    }
+    assert!(page.contains("@page{size:A4"));
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use elf_lib::io::edf::{write_edf_plus, EdfWriteOptions, EdfWriteSignal};
+use elf_lib::io::wfdb::load_wfdb_lead;
+use serde_json::Value;
+use std::error::Error;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn input_accepts_edf_and_wfdb_without_format_flags() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let mut ts = load_wfdb_lead(Path::new(&sample_path("test_data/mitdb/100.hea")), 0)?;
+    ts.data.truncate(60 * 360);
+    let edf = temp.path().join("ecg.edf");
+    write_edf_plus(
+        &edf,
+        &[EdfWriteSignal {
+            label: "ECG MLII",
+            physical_dimension: "mV",
+            prefiltering: "",
+            fs: ts.fs,
+            data: &ts.data,
+        }],
+        &[],
+        &EdfWriteOptions::default(),
+    )?;
+
+    let sniffed = run_json(&["beat-hrv-pipeline", "--input", edf.to_str().unwrap()])?;
+    let explicit = run_json(&["beat-hrv-pipeline", "--eeg-edf", edf.to_str().unwrap()])?;
+    assert_eq!(sniffed["fs"], 360.0);
+    assert_eq!(sniffed["events"], explicit["events"]);
+
+    let sqi = run_json(&["sqi", "--input", &sample_path("test_data/mitdb/100.hea")])?;
+    assert!(sqi.is_object());
+    Ok(())
+}
+
+#[test]
+fn eye_format_is_detected_from_the_header() -> Result<(), Box<dyn Error>> {
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "pupil-normalize",
+        "--input",
+        &sample_path("test_data/tobii_sample.tsv"),
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let first = output.split(|b| *b == b'\n').next().unwrap();
+    let sample: Value = serde_json::from_slice(first)?;
+    assert_eq!(sample["eye"], "Left");
+    assert!((sample["pupil_mm"].as_f64().unwrap() - 4.12).abs() < 1e-4);
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use elf_lib::io::parquet::{read_records, Column};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::sample_path;
+
+fn run_text(args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let out = cargo_bin_cmd!("elf")
+        .args(args)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    Ok(String::from_utf8(out)?)
+}
+
+#[test]
+fn hrv_time_formats_agree() -> Result<(), Box<dyn Error>> {
+    let rr = sample_path("test_data/tiny_rr.txt");
+    let json: Value = serde_json::from_str(&run_text(&["hrv-time", "--input", &rr])?)?;
+
+    let csv = run_text(&["hrv-time", "--input", &rr, "--format", "csv"])?;
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    assert!(lines.next().is_none());
+    for (name, cell) in header.iter().zip(&row) {
+        let expected = json[*name].as_f64().unwrap();
+        assert!((expected - cell.parse::<f64>()?).abs() < 1e-12, "{name}");
+    }
+
+    let ndjson = run_text(&["--format", "ndjson", "hrv-time", "--input", &rr])?;
+    let line: Value = serde_json::from_str(ndjson.trim())?;
+    assert_eq!(line, json);
+
+    let table = run_text(&["hrv-time", "--input", &rr, "--format", "table"])?;
+    assert!(table.lines().any(|l| l.starts_with("rmssd ")));
+    Ok(())
+}
+
+#[test]
+fn beat_pipeline_writes_records_and_files() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let header = sample_path("test_data/mitdb/100.hea");
+    let json: Value =
+        serde_json::from_str(&run_text(&["beat-hrv-pipeline", "--wfdb-header", &header])?)?;
+
+    let table = temp.path().join("summary.parquet");
+    run_text(&[
+        "beat-hrv-pipeline",
+        "--wfdb-header",
+        &header,
+        "--format",
+        "parquet",
+        "--output",
+        table.to_str().unwrap(),
+    ])?;
+    let columns = read_records(&table)?;
+    let column = |name: &str| columns.iter().find(|(n, _)| n == name).map(|(_, c)| c);
+    let beats = json["events"]["indices"].as_array().unwrap().len() as i64;
+    assert_eq!(column("beats"), Some(&Column::Int(vec![beats])));
+    assert_eq!(
+        column("rmssd"),
+        Some(&Column::Float(vec![json["hrv"]["rmssd"].as_f64().unwrap()]))
+    );
+
+    // --output without --format keeps the JSON document.
+    let doc = temp.path().join("summary.json");
+    let stdout = run_text(&[
+        "beat-hrv-pipeline",
+        "--wfdb-header",
+        &header,
+        "--output",
+        doc.to_str().unwrap(),
+    ])?;
+    assert!(stdout.is_empty());
+    assert_eq!(
+        serde_json::from_str::<Value>(&fs::read_to_string(&doc)?)?,
+        json
+    );
+
+    cargo_bin_cmd!("elf")
+        .args([
+            "hrv-time",
+            "--format",
+            "parquet",
+            "--input",
+            &sample_path("test_data/tiny_rr.txt"),
+        ])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn record_streams_become_rows() -> Result<(), Box<dyn Error>> {
+    let args = [
+        "ecg-find-rpeaks",
+        "--wfdb-header",
+        &sample_path("test_data/mitdb/100.hea"),
+    ];
+    let json: Value = serde_json::from_str(&run_text(&args)?)?;
+    let csv = run_text(&[&args[..], &["--format", "csv"]].concat())?;
+    let indices: Vec<u64> = csv.lines().skip(1).map(|l| l.parse().unwrap()).collect();
+    assert_eq!(csv.lines().next(), Some("sample_index"));
+    assert_eq!(
+        indices,
+        json["indices"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_u64().unwrap())
+            .collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn file_writing_commands_summarise_in_the_chosen_format() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let rr = sample_path("test_data/tiny_rr.txt");
+    let svg = temp.path().join("rr.svg");
+    let summary: Value = serde_json::from_str(&run_text(&[
+        "hrv-plot",
+        "--input",
+        &rr,
+        "--out",
+        svg.to_str().unwrap(),
+    ])?)?;
+    assert_eq!(summary["out"].as_str(), svg.to_str());
+    assert_eq!(summary["width"].as_u64(), Some(800));
+
+    let bundle = temp.path().join("run");
+    let csv = run_text(&[
+        "run-simulate",
+        "--design",
+        &sample_path("test_data/run_design.toml"),
+        "--trials",
+        &sample_path("test_data/run_trials.csv"),
+        "--out",
+        bundle.to_str().unwrap(),
+        "--format",
+        "csv",
+    ])?;
+    assert_eq!(csv.lines().next(), Some("events,files,trials"));
+    assert_eq!(csv.lines().count(), 2);
+    Ok(())
+}
+
+#[test]
+fn eye_layouts_are_still_accepted_as_format() -> Result<(), Box<dyn Error>> {
+    let pupil = sample_path("test_data/pupil_labs_sample.csv");
+    let layout = run_text(&[
+        "pupil-normalize",
+        "--input",
+        &pupil,
+        "--format",
+        "pupil-labs",
+    ])?;
+    let explicit = run_text(&[
+        "pupil-normalize",
+        "--input",
+        &pupil,
+        "--eye-format",
+        "pupil-labs",
+    ])?;
+    assert_eq!(layout, explicit);
+
+    let assert = cargo_bin_cmd!("elf")
+        .args(["hrv-time", "--input", &sample_path("test_data/tiny_rr.txt")])
+        .args(["--format", "pupil-labs"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(
+        stderr.contains("only applies to pupil-normalize"),
+        "{stderr}"
+    );
+    Ok(())
+}
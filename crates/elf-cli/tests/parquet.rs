@@ -0,0 +1,51 @@
+use elf_lib::io::parquet::{write_rr, write_timeseries};
+use elf_lib::io::wfdb::load_wfdb_lead;
+use elf_lib::signal::RRSeries;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn beat_pipeline_reads_parquet_with_embedded_fs() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let mut ts = load_wfdb_lead(Path::new(&sample_path("test_data/mitdb/100.hea")), 0)?;
+    ts.data.truncate(60 * 360);
+    let table = temp.path().join("ecg.parquet");
+    write_timeseries(&table, &ts, "MLII", Some("mV"))?;
+    let text = temp.path().join("ecg.txt");
+    let lines: Vec<String> = ts.data.iter().map(|v| v.to_string()).collect();
+    fs::write(&text, lines.join("\n"))?;
+
+    // No --fs: the 360 Hz rate comes from the table metadata.
+    let from_table = run_json(&["beat-hrv-pipeline", "--input", table.to_str().unwrap()])?;
+    let from_text = run_json(&[
+        "beat-hrv-pipeline",
+        "--fs",
+        "360",
+        "--input",
+        text.to_str().unwrap(),
+    ])?;
+    assert_eq!(from_table["fs"], 360.0);
+    assert_eq!(from_table["events"], from_text["events"]);
+    assert_eq!(from_table["hrv"], from_text["hrv"]);
+    Ok(())
+}
+
+#[test]
+fn hrv_time_reads_rr_tables() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let rr = RRSeries {
+        rr: vec![0.8, 0.82, 0.78, 0.81, 0.79, 0.83],
+    };
+    let table = temp.path().join("rr.arrow");
+    write_rr(&table, &rr)?;
+    let summary = run_json(&["hrv-time", "--input", table.to_str().unwrap()])?;
+    assert_eq!(summary["n"], 6);
+    assert!((summary["avnn"].as_f64().unwrap() - 0.805).abs() < 1e-9);
+    Ok(())
+}
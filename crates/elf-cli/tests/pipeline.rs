@@ -4,6 +4,10 @@ use elf_lib::signal::{Events, RRSeries};
 use serde::Deserialize;
 use std::{error::Error, fs, path::PathBuf};
 
+mod common;
+
+use common::sample_path;
+
 #[derive(Deserialize)]
 struct PipelineOutput {
     hrv: HRVTime,
@@ -128,10 +132,3 @@ fn workspace_root() -> PathBuf {
         .expect("workspace root")
         .to_path_buf()
 }
-
-fn sample_path(relative: &str) -> String {
-    workspace_root()
-        .join(relative)
-        .to_string_lossy()
-        .to_string()
-}
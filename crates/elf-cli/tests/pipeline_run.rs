@@ -0,0 +1,159 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use elf_lib::io::parquet::read_timeseries;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn example_pipeline_writes_sinks_and_provenance() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let out_dir = temp.path().to_str().unwrap();
+    let run = run_json(&[
+        "pipeline",
+        "run",
+        &sample_path("test_data/ecg_hrv_pipeline.toml"),
+        "--out-dir",
+        out_dir,
+    ])?;
+    assert_eq!(run["stages"], 7);
+    assert_eq!(run["outputs"].as_array().unwrap().len(), 3);
+
+    let dir = temp.path().join("pipeline_out");
+    let summary: Value = serde_json::from_str(&fs::read_to_string(dir.join("summary.json"))?)?;
+    for key in ["hrv", "psd", "nonlinear", "quality"] {
+        assert!(summary[key].is_object(), "{key}");
+    }
+    let csv = fs::read_to_string(dir.join("hrv.csv"))?;
+    let header: Vec<&str> = csv.lines().next().unwrap().split(',').collect();
+    let row: Vec<&str> = csv.lines().nth(1).unwrap().split(',').collect();
+    let n = header.iter().position(|c| *c == "n").unwrap();
+    assert_eq!(
+        row[n].parse::<u64>()?,
+        summary["hrv"]["n"].as_u64().unwrap()
+    );
+
+    let filtered = read_timeseries(&dir.join("filtered.parquet"), None)?;
+    assert_eq!(filtered.fs, 360.0);
+    assert_eq!(filtered.data.len(), 650_000);
+
+    let provenance: Value =
+        serde_json::from_str(&fs::read_to_string(dir.join("provenance.json"))?)?;
+    assert_eq!(provenance["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(provenance["config"]["stage"][1]["min_rr_s"], 0.25);
+    let inputs = provenance["inputs"].as_array().unwrap();
+    assert!(inputs
+        .iter()
+        .any(|i| i["path"].as_str().unwrap().ends_with("100.dat")));
+    assert!(inputs
+        .iter()
+        .all(|i| i["sha256"].as_str().unwrap().len() == 64));
+    assert_eq!(provenance["outputs"].as_array().unwrap().len(), 3);
+    Ok(())
+}
+
+#[test]
+fn rr_source_matches_hrv_time() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let rr = sample_path("test_data/elite_hrv_sample.txt");
+    let pipeline = temp.path().join("rr.toml");
+    fs::write(
+        &pipeline,
+        format!(
+            r#"
+[[source]]
+name = "rr"
+kind = "rr"
+path = "{}"
+
+[[stage]]
+name = "hrv"
+kind = "hrv-time"
+input = "rr"
+
+[[sink]]
+path = "hrv.json"
+stages = ["hrv"]
+"#,
+            rr.replace('\\', "/")
+        ),
+    )?;
+    run_json(&["pipeline", "run", pipeline.to_str().unwrap()])?;
+    let from_pipeline: Value =
+        serde_json::from_str(&fs::read_to_string(temp.path().join("hrv.json"))?)?;
+    assert_eq!(from_pipeline, run_json(&["hrv-time", "--input", &rr])?);
+    assert!(temp.path().join("rr.provenance.json").exists());
+    Ok(())
+}
+
+#[test]
+fn stages_must_read_earlier_results() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let pipeline = temp.path().join("bad.toml");
+    fs::write(
+        &pipeline,
+        r#"
+[[source]]
+name = "ecg"
+path = "ecg.txt"
+fs = 250
+
+[[stage]]
+name = "hrv"
+kind = "hrv-time"
+input = "beats"
+"#,
+    )?;
+    let assert = cargo_bin_cmd!("elf")
+        .args(["pipeline", "run", pipeline.to_str().unwrap()])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("'beats'"), "{stderr}");
+    Ok(())
+}
+
+#[test]
+fn stages_reject_unknown_settings() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let pipeline = temp.path().join("typo.toml");
+    for (kind, setting) in [
+        ("rr-clean", "max_deviaton = 0.3"),
+        ("detect", "lowcut = 1.0"),
+        ("filter", "highcut = 30.0"),
+        ("hrv-time", "interp_fs = 4.0"),
+    ] {
+        fs::write(
+            &pipeline,
+            format!(
+                r#"
+[[source]]
+name = "ecg"
+path = "ecg.txt"
+fs = 250
+
+[[stage]]
+name = "step"
+kind = "{kind}"
+input = "ecg"
+{setting}
+"#
+            ),
+        )?;
+        let assert = cargo_bin_cmd!("elf")
+            .args(["pipeline", "run", pipeline.to_str().unwrap()])
+            .assert()
+            .failure();
+        let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+        let key = setting.split(' ').next().unwrap();
+        assert!(
+            stderr.contains(&format!("unknown field `{key}`")),
+            "{kind}: {stderr}"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::json;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::sample_path;
+
+#[test]
+fn gui_figures_export_to_svg_and_png() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let ecg = temp.path().join("ecg.svg");
+    cargo_bin_cmd!("elf")
+        .args([
+            "plot",
+            "ecg",
+            "--wfdb-header",
+            &sample_path("test_data/mitdb/118.hea"),
+        ])
+        .args(["--out", ecg.to_str().unwrap()])
+        .assert()
+        .success();
+    let svg = fs::read_to_string(&ecg)?;
+    assert!(svg.contains("ECG waveform"));
+    assert!(svg.contains("#FF3333"));
+
+    let rr = sample_path("test_data/tiny_rr.txt");
+    for kind in ["rr", "rr-histogram", "psd"] {
+        let png = temp.path().join(format!("{kind}.png"));
+        cargo_bin_cmd!("elf")
+            .args(["plot", kind, "--rr", &rr, "--out", png.to_str().unwrap()])
+            .assert()
+            .success();
+        assert!(fs::read(&png)?.starts_with(b"\x89PNG"), "{kind}");
+    }
+
+    let hrv_plot = temp.path().join("rr.svg");
+    cargo_bin_cmd!("elf")
+        .args([
+            "hrv-plot",
+            "--input",
+            &rr,
+            "--out",
+            hrv_plot.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&hrv_plot)?.contains("RR intervals"));
+
+    cargo_bin_cmd!("elf")
+        .args(["plot", "rr", "--rr", &rr, "--out"])
+        .arg(temp.path().join("rr.pdf"))
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn figure_json_renders_every_series() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let series = |name: &str, color: u32, dash: Option<[f32; 2]>| {
+        json!({"Line": {
+            "name": name,
+            "points": [[0.0, 1.0], [1.0, 3.0], [2.0, 2.0]],
+            "style": {"width": 2.0, "dash": dash, "color": color},
+        }})
+    };
+    let figure = json!({
+        "title": "Custom figure",
+        "x": {"label": "Time (s)"},
+        "y": {"label": "Value"},
+        "series": [
+            series("measured", 0x0077FF, None),
+            series("reference", 0x33AA33, Some([6.0, 3.0])),
+        ],
+    });
+    let path = temp.path().join("figure.json");
+    fs::write(&path, serde_json::to_string(&figure)?)?;
+    let out = temp.path().join("figure.svg");
+    cargo_bin_cmd!("elf")
+        .args(["plot", "--figure", path.to_str().unwrap()])
+        .args([
+            "--out",
+            out.to_str().unwrap(),
+            "--width",
+            "640",
+            "--height",
+            "360",
+        ])
+        .assert()
+        .success();
+    let svg = fs::read_to_string(&out)?;
+    for text in [
+        "Custom figure",
+        "Time (s)",
+        "Value",
+        "measured",
+        "reference",
+        "#0077FF",
+        "#33AA33",
+    ] {
+        assert!(svg.contains(text), "{text}");
+    }
+    assert!(svg.contains("width=\"640\""));
+    Ok(())
+}
+
+#[test]
+fn subplots_json_renders_markers_bars_spans_and_events() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let panel = |title: &str, series: serde_json::Value| {
+        json!({
+            "title": title,
+            "x": {"label": "Time (s)"},
+            "y": {"label": null},
+            "series": [series],
+            "spans": [{"from": 0.5, "to": 1.0, "label": "artefact", "color": 0xB0B0B0, "opacity": 0.3}],
+            "events": [{"x": 1.5, "label": "stimulus", "style": {"width": 1.0, "dash": [4.0, 2.0], "color": 0x884400}}],
+        })
+    };
+    let plots = json!({
+        "title": "Stacked",
+        "share_x": true,
+        "panels": [
+            panel("Peaks", json!({"Scatter": {
+                "name": "peaks", "points": [[0.0, 1.0], [2.0, 2.0]],
+                "shape": "Triangle", "size": 4.0, "color": 0x1F4E9E,
+            }})),
+            panel("Counts", json!({"Bars": {
+                "name": "counts", "bars": [[0.5, 3.0], [1.5, 1.0]],
+                "width": 1.0, "color": 0x33AA33,
+            }})),
+        ],
+    });
+    let path = temp.path().join("plots.json");
+    fs::write(&path, serde_json::to_string(&plots)?)?;
+    let out = temp.path().join("plots.svg");
+    cargo_bin_cmd!("elf")
+        .args(["plot", "--figure", path.to_str().unwrap()])
+        .args(["--out", out.to_str().unwrap()])
+        .assert()
+        .success();
+    let svg = fs::read_to_string(&out)?;
+    for text in [
+        "Stacked", "Peaks", "Counts", "artefact", "stimulus", "#1F4E9E", "#33AA33",
+    ] {
+        assert!(svg.contains(text), "{text}");
+    }
+
+    let ecg = temp.path().join("beats.svg");
+    cargo_bin_cmd!("elf")
+        .args(["plot", "ecg", "--wfdb-header"])
+        .arg(sample_path("test_data/mitdb/118.hea"))
+        .args(["--annotations", &sample_path("test_data/mitdb/118.atr")])
+        .args(["--out", ecg.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&ecg)?.contains("R-peaks"));
+    Ok(())
+}
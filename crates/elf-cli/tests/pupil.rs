@@ -1,7 +1,12 @@
 use assert_cmd::cargo::cargo_bin_cmd;
 use serde_json::Value;
 use std::error::Error;
-use std::path::PathBuf;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::sample_path;
 
 #[test]
 fn pupil_normalize_outputs_json() -> Result<(), Box<dyn Error>> {
@@ -42,11 +47,78 @@ fn pupil_normalize_outputs_json() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn sample_path(relative: &str) -> String {
-    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .and_then(|p| p.parent())
-        .expect("workspace root")
-        .join(relative);
-    root.to_string_lossy().to_string()
+#[test]
+fn pupil_preprocess_outputs_trace_per_eye() -> Result<(), Box<dyn Error>> {
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "pupil-preprocess",
+        "--input",
+        &sample_path("test_data/pupil_labs_sample.csv"),
+        "--eye-format",
+        "pupil-labs",
+        "--resample-hz",
+        "50",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let lines: Vec<&[u8]> = output
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .collect();
+    // The right eye only has a single sample, so only the left trace is produced.
+    assert_eq!(lines.len(), 1);
+
+    let trace: Value = serde_json::from_slice(lines[0])?;
+    assert_eq!(trace.get("eye").and_then(Value::as_str), Some("Left"));
+    assert_eq!(trace["series"]["fs"].as_f64(), Some(50.0));
+    assert_eq!(trace["series"]["data"].as_array().map(Vec::len), Some(3));
+    assert_eq!(trace["report"]["raw_samples"].as_u64(), Some(2));
+    Ok(())
+}
+
+#[test]
+fn pupil_erp_averages_bundle_conditions() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let bundle = temp.path().join("bundle");
+    fs::create_dir_all(&bundle)?;
+    fs::write(
+        bundle.join("events.tsv"),
+        "onset\tduration\ttrial\tblock\tevent_type\tstim_id\tcondition\tresp_key\tresp_rt\tvalue\n\
+         2\t0.8\t1\t1\tstim\tA\thard\t\t\t\n\
+         2.8\t0\t1\t1\tresponse\tA\thard\tJ\t0.6\t\n\
+         8\t0.8\t2\t1\tstim\tB\teasy\t\t\t\n",
+    )?;
+
+    // Eye tracker clock starts at 50 s; hard trials dilate by 0.6 mm, easy ones by 0.2 mm.
+    let mut csv = String::from("timestamp,diameter,confidence,eye\n");
+    for i in 0..900 {
+        let t = i as f64 / 60.0;
+        let bump = |onset: f64, amp: f64| amp * (-((t - onset - 1.0) / 0.4).powi(2)).exp();
+        let diameter = 4.0 + bump(2.0, 0.6) + bump(8.0, 0.2);
+        csv.push_str(&format!("{:.4},{:.4},0.99,left\n", 50.0 + t, diameter));
+    }
+    let eye = temp.path().join("eye.csv");
+    fs::write(&eye, csv)?;
+
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "pupil-erp",
+        "--bundle",
+        bundle.to_str().unwrap(),
+        "--eye",
+        eye.to_str().unwrap(),
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let result: Value = serde_json::from_slice(output.trim_ascii())?;
+    assert_eq!(result["eye"].as_str(), Some("Left"));
+    assert_eq!(result["baseline"].as_str(), Some("Subtractive"));
+    let conditions = result["conditions"].as_array().unwrap();
+    assert_eq!(conditions.len(), 2);
+    assert_eq!(conditions[0]["condition"].as_str(), Some("hard"));
+    let hard_peak = conditions[0]["metrics"]["peak"].as_f64().unwrap();
+    let easy_peak = conditions[1]["metrics"]["peak"].as_f64().unwrap();
+    assert!((hard_peak - 0.6).abs() < 0.05, "hard peak {hard_peak}");
+    assert!((easy_peak - 0.2).abs() < 0.05, "easy peak {easy_peak}");
+    let latency = conditions[0]["metrics"]["peak_latency_s"].as_f64().unwrap();
+    assert!((latency - 1.0).abs() < 0.1);
+    Ok(())
 }
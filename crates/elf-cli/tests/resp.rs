@@ -0,0 +1,54 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn resp_reports_rate_and_rsa() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    // 90 s BITalino-style export breathing at 0.25 Hz (15 breaths/min).
+    let mut csv = String::from("timestamp,analog0,analog2\n");
+    for i in 0..(90 * 50) {
+        let t = i as f64 / 50.0;
+        let belt = 500.0 - 40.0 * (2.0 * PI * 0.25 * t).cos();
+        csv.push_str(&format!("{t:.3},0,{belt:.3}\n"));
+    }
+    let input = temp.path().join("resp.csv");
+    fs::write(&input, csv)?;
+
+    // Heart period modulated at the breathing frequency.
+    let mut t = 0.0;
+    let rr: Vec<String> = (0..110)
+        .map(|_| {
+            let interval = 0.8 + 0.04 * (2.0 * PI * 0.25 * t).sin();
+            t += interval;
+            format!("{interval:.5}")
+        })
+        .collect();
+    let rr_path = temp.path().join("rr.txt");
+    fs::write(&rr_path, rr.join("\n"))?;
+
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "resp",
+        "--input",
+        input.to_str().unwrap(),
+        "--signal",
+        "analog2",
+        "--rr",
+        rr_path.to_str().unwrap(),
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let result: Value = serde_json::from_slice(output.trim_ascii())?;
+    let rate = result["metrics"]["rate_bpm"].as_f64().unwrap();
+    assert!((rate - 15.0).abs() < 0.5, "rate {rate}");
+    assert!(result["breaths"].as_array().unwrap().len() >= 20);
+    let spectral = &result["rsa"]["spectral"];
+    assert!((spectral["resp_hz"].as_f64().unwrap() - 0.25).abs() < 0.01);
+    assert_eq!(spectral["within_hf"].as_bool(), Some(true));
+    assert!(spectral["fraction"].as_f64().unwrap() > 0.5);
+    assert!(result["rsa"]["peak_valley_s"].is_null());
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use std::error::Error;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn millisecond_exports_match_seconds_text() -> Result<(), Box<dyn Error>> {
+    let reference = run_json(&["hrv-time", "--input", &sample_path("test_data/tiny_rr.txt")])?;
+    for rel in [
+        "test_data/polar_rr_sample.hrm",
+        "test_data/elite_hrv_sample.txt",
+        "test_data/polar_sensor_logger_sample.csv",
+    ] {
+        let summary = run_json(&["hrv-time", "--input", &sample_path(rel)])?;
+        assert_eq!(summary["n"], reference["n"], "{rel}");
+        for key in ["avnn", "sdnn", "rmssd"] {
+            let a = summary[key].as_f64().unwrap();
+            let b = reference[key].as_f64().unwrap();
+            assert!((a - b).abs() < 1e-9, "{rel} {key}: {a} vs {b}");
+        }
+    }
+    let nonlinear = run_json(&[
+        "hrv-nonlinear",
+        "--input",
+        &sample_path("test_data/polar_rr_sample.hrm"),
+    ])?;
+    assert!(nonlinear["sd1"].as_f64().unwrap() > 0.0);
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::error::Error;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn streamed_pipeline_matches_in_memory_run() -> Result<(), Box<dyn Error>> {
+    let header = sample_path("test_data/mitdb/100.hea");
+    let batch = run_json(&["beat-hrv-pipeline", "--wfdb-header", &header])?;
+    let streamed = run_json(&[
+        "beat-hrv-pipeline",
+        "--wfdb-header",
+        &header,
+        "--stream",
+        "--block-size",
+        "2048",
+    ])?;
+    assert_eq!(streamed, batch);
+
+    let streamed_input = run_json(&["beat-hrv-pipeline", "--input", &header, "--stream"])?;
+    assert_eq!(streamed_input["events"], batch["events"]);
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use tempfile::tempdir;
+
+mod common;
+
+use common::run_json;
+
+#[test]
+fn synth_wfdb_record_round_trips_through_the_pipeline() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let hea = temp.path().join("syn.hea");
+    let truth = temp.path().join("events.tsv");
+    let beats = temp.path().join("beats.txt");
+    let hea_arg = hea.to_str().unwrap();
+    let synth_args = [
+        "synth",
+        "--out",
+        hea_arg,
+        "--truth",
+        truth.to_str().unwrap(),
+        "--beats",
+        beats.to_str().unwrap(),
+        "--duration-s",
+        "120",
+        "--heart-rate-bpm",
+        "72",
+        "--ectopic-rate",
+        "0.05",
+        "--seed",
+        "11",
+    ];
+    let summary = run_json(&synth_args)?;
+    assert_eq!(summary["samples"], 30_000);
+    let n_beats = summary["beats"].as_u64().unwrap();
+    assert!((140..=148).contains(&n_beats), "{n_beats}");
+    assert!(summary["ectopic_beats"].as_u64().unwrap() > 0);
+    assert!((summary["mean_hr_bpm"].as_f64().unwrap() - 72.0).abs() < 2.0);
+    for file in ["syn.hea", "syn.dat", "syn.atr", "events.tsv", "beats.txt"] {
+        assert!(temp.path().join(file).exists(), "{file}");
+    }
+
+    // Same seed, same recording.
+    let dat = fs::read(temp.path().join("syn.dat"))?;
+    run_json(&synth_args)?;
+    assert_eq!(fs::read(temp.path().join("syn.dat"))?, dat);
+
+    let tsv = fs::read_to_string(&truth)?;
+    assert!(tsv.starts_with("onset\tduration\ttrial_type\tsample\n"));
+    for kind in [
+        "\tbeat\t",
+        "\tectopic_beat\t",
+        "\tpulse_peak\t",
+        "\tbreath_peak\t",
+    ] {
+        assert!(tsv.contains(kind), "{kind}");
+    }
+
+    // The annotation file carries the true beats, PVCs included.
+    let annotated = run_json(&[
+        "beat-hrv-pipeline",
+        "--wfdb-header",
+        hea_arg,
+        "--annotations",
+        temp.path().join("syn.atr").to_str().unwrap(),
+    ])?;
+    assert_eq!(
+        annotated["events"]["indices"].as_array().unwrap().len() as u64,
+        n_beats
+    );
+
+    let detected = run_json(&["beat-hrv-pipeline", "--wfdb-header", hea_arg])?;
+    let detected: Vec<u64> = detected["events"]["indices"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(Value::as_u64)
+        .collect();
+    let truth: Vec<u64> = fs::read_to_string(&beats)?
+        .lines()
+        .map(|l| l.parse().unwrap())
+        .collect();
+    let found = truth
+        .iter()
+        .filter(|&&t| detected.iter().any(|&d| d.abs_diff(t) <= 12))
+        .count();
+    assert!(found * 100 >= truth.len() * 95, "{found}/{}", truth.len());
+    Ok(())
+}
+
+#[test]
+fn synth_writes_edf_tables_text_and_pupil_csv() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let edf = temp.path().join("syn.edf");
+    run_json(&[
+        "synth",
+        "--out",
+        edf.to_str().unwrap(),
+        "--noise",
+        "baseline-wander,emg,electrode-motion,powerline",
+        "--snr-db",
+        "6",
+    ])?;
+    let info = run_json(&["edf-info", "--input", edf.to_str().unwrap()])?;
+    let labels: Vec<&str> = info["channels"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|s| s["label"].as_str())
+        .collect();
+    assert_eq!(labels, ["ECG", "PPG", "RESP", "EDA", "PUPIL"]);
+    let annotations = info["annotations"].as_array().unwrap();
+    assert!(annotations.iter().any(|a| a["text"] == "blink"));
+
+    let table = temp.path().join("syn.parquet");
+    let pupil = temp.path().join("pupil.csv");
+    let summary = run_json(&[
+        "synth",
+        "--out",
+        table.to_str().unwrap(),
+        "--pupil-csv",
+        pupil.to_str().unwrap(),
+    ])?;
+    assert_eq!(summary["snr_db"], Value::Null);
+    let preprocessed = run_json(&["pupil-preprocess", "--input", pupil.to_str().unwrap()])?;
+    assert!(preprocessed.is_object());
+
+    let resp = temp.path().join("resp.txt");
+    run_json(&[
+        "synth",
+        "--out",
+        resp.to_str().unwrap(),
+        "--signal",
+        "resp",
+        "--duration-s",
+        "10",
+    ])?;
+    assert_eq!(fs::read_to_string(&resp)?.lines().count(), 2500);
+
+    cargo_bin_cmd!("elf")
+        .args([
+            "synth",
+            "--out",
+            temp.path().join("x.bin").to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use elf_lib::io::wfdb::{load_wfdb_annotations, read_wfdb_record};
+use serde_json::Value;
+use std::error::Error;
+use tempfile::tempdir;
+
+mod common;
+
+use common::sample_path;
+
+#[test]
+fn wfdb_export_round_trips_mitdb_lead() -> Result<(), Box<dyn Error>> {
+    let temp = tempdir()?;
+    let mut cmd = cargo_bin_cmd!("elf");
+    cmd.args([
+        "wfdb-export",
+        "--wfdb-header",
+        &sample_path("test_data/mitdb/100.hea"),
+        "--out-dir",
+        temp.path().to_str().unwrap(),
+        "--record",
+        "det100",
+        "--wfdb-format",
+        "212",
+        "--description",
+        "MLII",
+    ]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let summary: Value = serde_json::from_slice(&output)?;
+    let beats = summary["beats"].as_u64().unwrap() as usize;
+    assert!(beats > 2000, "MIT-BIH 100 has ~2270 beats, got {beats}");
+
+    let record = read_wfdb_record(&temp.path().join("det100.hea"))?;
+    assert_eq!(record.header.fs, 360.0);
+    assert_eq!(record.signals[0].description, "MLII");
+    assert_eq!(record.signals[0].data.len(), 650_000);
+
+    let annotations = load_wfdb_annotations(&temp.path().join("det100.atr"))?;
+    assert_eq!(annotations.len(), beats);
+    assert!(annotations.iter().all(|ann| ann.mnemonic() == "N"));
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use std::error::Error;
+
+mod common;
+
+use common::{run_json, sample_path};
+
+#[test]
+fn xdf_info_lists_streams_on_the_recorder_clock() -> Result<(), Box<dyn Error>> {
+    let xdf = sample_path("test_data/xdf_sample.xdf");
+    let info = run_json(&["xdf-info", "--input", &xdf])?;
+    let streams = info["streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 2);
+    assert_eq!(streams[0]["name"], "ECG");
+    assert_eq!(streams[0]["samples"], 200);
+    assert_eq!(streams[0]["channels"][1]["label"], "Resp");
+    assert_eq!(streams[0]["first_timestamp"], 9.5);
+    assert_eq!(streams[1]["markers"][1]["label"], "stim");
+    assert_eq!(streams[1]["markers"][1]["time"], 10.5);
+
+    // The loader registry exposes XDF channels to the signal commands.
+    let resp = run_json(&["resp", "--input", &xdf, "--signal", "resp"])?;
+    assert!(resp["metrics"].is_object());
+    Ok(())
+}
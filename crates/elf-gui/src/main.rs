@@ -1,15 +1,19 @@
 use crossbeam_channel::{bounded, Sender};
 use eframe::{egui, egui::ViewportBuilder};
 use egui::{Color32, Margin, ScrollArea};
-use egui_plot::{Line, Plot, VLine};
+use egui_plot::{Bar, BarChart, Line, LineStyle, Plot, PlotPoint, Points, Polygon, Text, VLine};
 use elf_keys::KeyEntry;
 use elf_lib::detectors::ecg::{run_beat_hrv_pipeline, EcgPipelineConfig};
-use elf_lib::io::{eeg as eeg_io, eye as eye_io, text as text_io, wfdb as wfdb_io};
-use elf_lib::plot::{Figure, Series, Style};
+use elf_lib::io::{
+    bitalino as bitalino_io, chunked, edf as edf_io, eeg as eeg_io, eye as eye_io, loader,
+    text as text_io, wfdb as wfdb_io,
+};
+use elf_lib::metrics::gaze::{FixationHeatmap, GazeUnits};
+use elf_lib::plot::{Figure, MarkerShape, Series, Style, Subplots};
 use elf_lib::signal::{Events, TimeSeries};
+use elf_lib::synth::{self, SynthConfig, SynthRecording};
 use rfd::FileDialog;
 use serde_json;
-use std::env;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -36,6 +40,7 @@ enum GuiTab {
     Hrv,
     Eeg,
     Eye,
+    Resp,
     Settings,
 }
 
@@ -46,16 +51,18 @@ impl GuiTab {
             GuiTab::Hrv => "ECG / HRV",
             GuiTab::Eeg => "EEG",
             GuiTab::Eye => "Eye",
+            GuiTab::Resp => "Respiration",
             GuiTab::Settings => "Security",
         }
     }
 
-    fn all() -> [GuiTab; 5] {
+    fn all() -> [GuiTab; 6] {
         [
             GuiTab::Landing,
             GuiTab::Hrv,
             GuiTab::Eeg,
             GuiTab::Eye,
+            GuiTab::Resp,
             GuiTab::Settings,
         ]
     }
@@ -75,6 +82,47 @@ use run_loader::{
 use std::collections::HashMap;
 use store::{RunBundleState, Store};
 
+/// Write one signal plus annotations to an EDF+ file chosen by the user.
+fn export_edf_dialog(
+    file_name: &str,
+    label: &str,
+    physical_dimension: &str,
+    ts: &TimeSeries,
+    annotations: &[edf_io::EdfAnnotation],
+    start_unix: f64,
+) -> Result<Option<PathBuf>, String> {
+    let Some(path) = FileDialog::new()
+        .add_filter("EDF+", &["edf"])
+        .set_file_name(file_name)
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    let signal = edf_io::EdfWriteSignal {
+        label,
+        physical_dimension,
+        prefiltering: "",
+        fs: ts.fs,
+        data: &ts.data,
+    };
+    // EDF needs whole samples per record; 1 s records cover integer rates, 0.1 s most others.
+    let record_duration_s = if ts.fs.fract() == 0.0 { 1.0 } else { 0.1 };
+    let opts = edf_io::EdfWriteOptions {
+        start: edf_io::EdfStart::from_unix(start_unix),
+        record_duration_s,
+        ..edf_io::EdfWriteOptions::default()
+    };
+    edf_io::write_edf_plus(&path, &[signal], annotations, &opts).map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+fn unix_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
 enum HrvExportOutcome {
     Exported(PathBuf),
     Cancelled,
@@ -95,30 +143,17 @@ impl EyeLayout {
         }
     }
 
-    fn parameters(
-        &self,
-    ) -> (
-        &'static str,
-        &'static str,
-        Option<&'static str>,
-        Option<&'static str>,
-        u8,
-    ) {
+    fn layout(&self) -> eye_io::EyeCsvLayout<'static> {
+        match self {
+            EyeLayout::PupilLabs => eye_io::EyeCsvLayout::PUPIL_LABS,
+            EyeLayout::Tobii => eye_io::EyeCsvLayout::TOBII,
+        }
+    }
+
+    fn gaze_units(&self) -> GazeUnits {
         match self {
-            EyeLayout::PupilLabs => (
-                "timestamp",
-                "diameter",
-                Some("confidence"),
-                Some("eye"),
-                b',',
-            ),
-            EyeLayout::Tobii => (
-                "system_time_stamp",
-                "pupil_diameter_2d",
-                Some("confidence"),
-                Some("eye"),
-                b'\t',
-            ),
+            EyeLayout::PupilLabs => GazeUnits::NormalizedBottomLeft,
+            EyeLayout::Tobii => GazeUnits::Normalized,
         }
     }
 }
@@ -182,21 +217,16 @@ struct BatchFeedback {
     success: bool,
 }
 
-const SYNTHETIC_RR: [f64; 12] = [
-    0.82, 0.78, 0.8, 0.79, 0.83, 0.77, 0.84, 0.88, 0.86, 0.81, 0.79, 0.82,
-];
 const STREAM_CHUNK_SIZE: usize = 4;
 const STREAM_INTERVAL_MS: u64 = 450;
 
-fn synthetic_event_indices(fs: f64) -> Vec<usize> {
-    let mut events = Vec::with_capacity(SYNTHETIC_RR.len() + 1);
-    let mut t = 0.0;
-    events.push(0);
-    for &rr in SYNTHETIC_RR.iter() {
-        t += rr;
-        events.push((t * fs).round() as usize);
-    }
-    events
+/// One minute of synthetic recording at `fs`, with the default heart rate and variability.
+fn synthetic_recording(fs: f64) -> Result<SynthRecording, String> {
+    synth::generate(&SynthConfig {
+        fs,
+        ..SynthConfig::default()
+    })
+    .map_err(|e| e.to_string())
 }
 
 struct StreamingSimulator {
@@ -208,7 +238,10 @@ impl StreamingSimulator {
     fn start(cmd_sender: Sender<StreamCommand>, fs: f64) -> Self {
         let (stop_tx, stop_rx) = bounded(1);
         let handle = std::thread::spawn(move || {
-            let indices = synthetic_event_indices(fs);
+            let indices = match synthetic_recording(fs) {
+                Ok(rec) => rec.beat_events().indices,
+                Err(_) => return,
+            };
             for chunk in indices.chunks(STREAM_CHUNK_SIZE) {
                 if chunk.is_empty() {
                     continue;
@@ -240,21 +273,16 @@ impl StreamingSimulator {
     }
 }
 
-fn workspace_root() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .and_then(|p| p.parent())
-        .expect("workspace root")
-        .to_path_buf()
-}
-
-fn synthetic_recording_path() -> PathBuf {
-    workspace_root().join("test_data/synthetic_recording_a.txt")
+/// Progress of a raw recording read on a background thread.
+enum RawLoadFeedback {
+    Samples(usize),
+    Done(PathBuf, Result<(String, TimeSeries), String>),
 }
 
 struct ElfApp {
     store: StreamingStateRouter,
     raw_path: Option<String>,
+    raw_receiver: Option<mpsc::Receiver<RawLoadFeedback>>,
     annotation_path: Option<String>,
     fs: f64,
     psd_interp_fs: f64,
@@ -274,6 +302,11 @@ struct ElfApp {
     eye_min_conf: f32,
     eye_status: String,
     eye_layout: EyeLayout,
+    // Respiration tab state
+    resp_path: Option<String>,
+    resp_signal: String,
+    resp_fs: f64,
+    resp_status: String,
     stream_simulator: Option<StreamingSimulator>,
     run_bundle_path: Option<String>,
     run_manifest: Option<RunManifest>,
@@ -304,6 +337,7 @@ impl Default for ElfApp {
         Self {
             store: StreamingStateRouter::new(Store::new()),
             raw_path: None,
+            raw_receiver: None,
             annotation_path: None,
             fs: 250.0,
             psd_interp_fs: 4.0,
@@ -321,6 +355,10 @@ impl Default for ElfApp {
             eye_min_conf: 0.5,
             eye_status: "No eye data".into(),
             eye_layout: EyeLayout::PupilLabs,
+            resp_path: None,
+            resp_signal: "analog2".into(),
+            resp_fs: 50.0,
+            resp_status: "No respiration loaded".into(),
             stream_simulator: None,
             run_bundle_path: None,
             run_manifest: None,
@@ -349,48 +387,52 @@ impl Default for ElfApp {
 }
 
 impl ElfApp {
-    fn load_raw(&mut self, path: &Path) -> Result<(), String> {
-        let (ts, status_label) = if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
-            let ext = ext.to_ascii_lowercase();
-            if ext == "hea" {
-                let ts = wfdb_io::load_wfdb_lead(path, 0).map_err(|e| e.to_string())?;
-                let label = format!("Loaded WFDB record {}", path.display());
-                (ts, label)
-            } else if ext == "dat" {
-                let header = path.with_extension("hea");
-                if !header.exists() {
-                    return Err(format!("WFDB header not found for {}", path.display()));
+    /// Reads the recording block by block on a worker thread so long files keep the UI live.
+    fn load_raw(&mut self, path: &Path) {
+        let (tx, rx) = mpsc::channel();
+        let path = path.to_path_buf();
+        let fallback_fs = self.fs;
+        self.status = format!("Loading {}...", path.display());
+        self.raw_receiver = Some(rx);
+        thread::spawn(move || {
+            let result = read_raw_blocks(&path, fallback_fs, &tx).map_err(|e| format!("{e:#}"));
+            let _ = tx.send(RawLoadFeedback::Done(path, result));
+        });
+    }
+
+    fn poll_raw_load(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.raw_receiver else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(RawLoadFeedback::Samples(count)) => {
+                    self.status = format!("Loading... {count} samples read");
+                }
+                Ok(RawLoadFeedback::Done(path, Ok((format, ts)))) => {
+                    self.fs = ts.fs.max(1.0);
+                    let len = ts.data.len();
+                    self.store.set_ecg(ts);
+                    self.raw_path = Some(path.display().to_string());
+                    self.status = format!(
+                        "Loaded {} recording {} ({} samples @ {:.1} Hz)",
+                        format,
+                        path.display(),
+                        len,
+                        self.fs
+                    );
+                }
+                Ok(RawLoadFeedback::Done(_, Err(err))) => self.status = err,
+                Err(TryRecvError::Empty) => {
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                    break;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.raw_receiver = None;
+                    break;
                 }
-                let ts = wfdb_io::load_wfdb_lead(&header, 0).map_err(|e| e.to_string())?;
-                let label = format!(
-                    "Loaded WFDB record {} (via {})",
-                    header.display(),
-                    path.display()
-                );
-                (ts, label)
-            } else {
-                let samples = text_io::read_f64_series(path).map_err(|e| e.to_string())?;
-                let ts = TimeSeries {
-                    fs: self.fs,
-                    data: samples,
-                };
-                (ts, format!("Loaded raw ECG from {}", path.display()))
             }
-        } else {
-            let samples = text_io::read_f64_series(path).map_err(|e| e.to_string())?;
-            let ts = TimeSeries {
-                fs: self.fs,
-                data: samples,
-            };
-            (ts, format!("Loaded raw ECG from {}", path.display()))
-        };
-
-        self.fs = ts.fs.max(1.0);
-        let len = ts.data.len();
-        self.store.set_ecg(ts);
-        self.raw_path = Some(path.display().to_string());
-        self.status = format!("{} ({} samples @ {:.1} Hz)", status_label, len, self.fs);
-        Ok(())
+        }
     }
 
     fn load_annotations(&mut self, path: &Path) -> Result<(), String> {
@@ -443,14 +485,14 @@ impl ElfApp {
     }
 
     fn process_synthetic_ecg(&mut self) -> Result<(), String> {
-        let path = synthetic_recording_path();
-        let samples = text_io::read_f64_series(&path).map_err(|e| e.to_string())?;
+        let rec = synthetic_recording(self.fs.max(1.0))?;
+        let beats = rec.truth.beats.len();
         let ts = TimeSeries {
-            fs: self.fs.max(1.0),
-            data: samples,
+            fs: rec.fs,
+            data: rec.ecg,
         };
         self.store.submit_ecg(ts);
-        self.status = format!("Queued synthetic ECG ({})", path.display());
+        self.status = format!("Queued synthetic ECG ({beats} beats)");
         Ok(())
     }
 
@@ -549,13 +591,33 @@ impl ElfApp {
     }
 
     fn load_eeg_trace(&mut self, path: &Path, channel: usize) -> Result<(), String> {
-        let ts = eeg_io::load_edf_channel(path, channel).map_err(|e| e.to_string())?;
+        let mut reader = edf_io::EdfReader::open(path).map_err(|e| e.to_string())?;
+        let signals = reader.data_signals();
+        let signal = *signals.get(channel).ok_or_else(|| {
+            format!(
+                "EDF file has {} channels; channel {} is out of range",
+                signals.len(),
+                channel
+            )
+        })?;
+        let ts = reader.read_signal(signal).map_err(|e| e.to_string())?;
+        let annotations = reader.annotations().map_err(|e| e.to_string())?;
+        let label = reader.header().signals[signal].label.clone();
+        let start = reader.header().start;
         let len = ts.data.len();
         let rate = ts.fs;
         self.store.set_eeg(ts);
         self.eeg_channel = channel;
         self.eeg_path = Some(path.display().to_string());
-        self.eeg_status = format!("Loaded {} samples at {:.1} Hz", len, rate);
+        self.eeg_status = format!(
+            "Loaded {label}: {} samples at {:.1} Hz, starting {start}",
+            len, rate
+        );
+        if !annotations.is_empty() {
+            self.store
+                .set_eeg_events(annotations.iter().map(|ann| ann.onset_s).collect());
+            self.eeg_event_source = Some(format!("{} EDF+ annotations", annotations.len()));
+        }
         Ok(())
     }
 
@@ -569,18 +631,10 @@ impl ElfApp {
     }
 
     fn load_eye_csv(&mut self, path: &Path) -> Result<(), String> {
-        let (timestamp_col, pupil_col, confidence_col, eye_col, delimiter) =
-            self.eye_layout.parameters();
-        let samples = eye_io::read_eye_csv(
-            path,
-            timestamp_col,
-            pupil_col,
-            confidence_col,
-            eye_col,
-            delimiter,
-        )
-        .map_err(|e| e.to_string())?;
-        self.store.set_eye_samples(samples);
+        let samples = eye_io::read_gaze_csv_layout(path, &self.eye_layout.layout())
+            .map_err(|e| e.to_string())?;
+        self.store
+            .set_eye_samples(samples, self.eye_layout.gaze_units());
         self.store.set_eye_threshold(self.eye_min_conf);
         self.eye_path = Some(path.display().to_string());
         self.eye_status = format!(
@@ -591,6 +645,26 @@ impl ElfApp {
         Ok(())
     }
 
+    fn load_resp(&mut self, path: &Path) -> Result<(), String> {
+        let is_csv = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("csv"))
+            .unwrap_or(false);
+        let ts = if is_csv {
+            bitalino_io::read_bitalino_csv(path, &self.resp_signal).map_err(|e| e.to_string())?
+        } else {
+            let data = text_io::read_f64_series(path).map_err(|e| e.to_string())?;
+            TimeSeries {
+                fs: self.resp_fs,
+                data,
+            }
+        };
+        self.resp_status = format!("Loaded {} samples at {:.1} Hz", ts.len(), ts.fs);
+        self.store.set_resp(ts);
+        self.resp_path = Some(path.display().to_string());
+        Ok(())
+    }
+
     fn try_load_run_bundle(&mut self, path: &Path) -> Result<(), String> {
         let events_path = path.join("events.tsv");
         let manifest_path = path.join("run.json");
@@ -640,6 +714,72 @@ impl ElfApp {
         Ok(HrvExportOutcome::Cancelled)
     }
 
+    fn export_ecg_edf(&mut self) {
+        let Some(ts) = self.store.ecg() else {
+            self.status = "No ECG loaded".into();
+            return;
+        };
+        let mut annotations: Vec<edf_io::EdfAnnotation> = self
+            .store
+            .events()
+            .map(|events| {
+                events
+                    .indices
+                    .iter()
+                    .map(|&idx| edf_io::EdfAnnotation {
+                        onset_s: idx as f64 / ts.fs,
+                        duration_s: None,
+                        text: "R".into(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        annotations.extend(self.run_bundle_event_records.iter().map(|record| {
+            edf_io::EdfAnnotation {
+                onset_s: record.onset,
+                duration_s: record.duration,
+                text: record
+                    .label
+                    .clone()
+                    .unwrap_or_else(|| record.event_type.clone()),
+            }
+        }));
+        let start = self
+            .run_manifest
+            .as_ref()
+            .map(|manifest| manifest.start_time_unix)
+            .unwrap_or_else(unix_now);
+        self.status = match export_edf_dialog("ecg.edf", "ECG", "mV", ts, &annotations, start) {
+            Ok(Some(path)) => format!("Exported EDF+ to {}", path.display()),
+            Ok(None) => "Export cancelled".into(),
+            Err(err) => format!("Export failed: {err}"),
+        };
+    }
+
+    fn export_eeg_edf(&mut self) {
+        let Some(ts) = self.store.eeg() else {
+            self.eeg_status = "No EEG loaded".into();
+            return;
+        };
+        let annotations: Vec<edf_io::EdfAnnotation> = self
+            .store
+            .eeg_events()
+            .iter()
+            .map(|&onset| edf_io::EdfAnnotation {
+                onset_s: onset,
+                duration_s: None,
+                text: "event".into(),
+            })
+            .collect();
+        let label = format!("EEG {}", self.eeg_channel);
+        self.eeg_status =
+            match export_edf_dialog("eeg.edf", &label, "uV", ts, &annotations, unix_now()) {
+                Ok(Some(path)) => format!("Exported EDF+ to {}", path.display()),
+                Ok(None) => "Export cancelled".into(),
+                Err(err) => format!("Export failed: {err}"),
+            };
+    }
+
     fn build_run_event_filter(&self) -> RunEventFilter {
         let mut filter = RunEventFilter::default();
         filter.onset_column = self.run_bundle_onset_column.clone();
@@ -738,12 +878,17 @@ impl ElfApp {
 
             if ui.button("Load raw ECG").clicked() {
                 if let Some(path) = FileDialog::new()
-                    .add_filter("ECG", &["txt", "csv", "ecg", "dat", "hea", "atr"])
+                    .add_filter(
+                        "ECG",
+                        &[
+                            "txt", "csv", "ecg", "dat", "hea", "edf", "bdf", "parquet", "arrow",
+                            "xdf",
+                        ],
+                    )
+                    .add_filter("All files", &["*"])
                     .pick_file()
                 {
-                    if let Err(err) = self.load_raw(&path) {
-                        self.status = err;
-                    }
+                    self.load_raw(&path);
                 }
             }
 
@@ -832,6 +977,13 @@ impl ElfApp {
                         }
                     }
                 }
+                if ui
+                    .add_enabled(self.store.ecg().is_some(), egui::Button::new("Export EDF+"))
+                    .on_hover_text("ECG trace with R-peaks and run events as EDF+ annotations")
+                    .clicked()
+                {
+                    self.export_ecg_edf();
+                }
             });
 
             ui.group(|ui| {
@@ -1095,14 +1247,15 @@ impl ElfApp {
                 }
 
                 if let Some(fig) = self.store.ecg_figure() {
-                    Plot::new("ecg_plot").height(360.0).show(ui, |plot_ui| {
+                    let shown = Plot::new("ecg_plot").height(360.0).show(ui, |plot_ui| {
                         plot_plot_figure(plot_ui, fig);
-                        for time in self.store.event_seconds() {
-                            plot_ui.vline(
-                                VLine::new(time).stroke(egui::Stroke::new(1.0, egui::Color32::RED)),
-                            );
-                        }
                     });
+                    // Zooming re-fetches the visible range from the store's pyramid.
+                    let bounds = shown.transform.bounds();
+                    let range = [bounds.min()[0], bounds.max()[0]];
+                    if self.store.set_ecg_view(range, shown.response.rect.width()) {
+                        ctx.request_repaint();
+                    }
                 } else {
                     ui.centered_and_justified(|ui| {
                         ui.label("Preparing ECG waveform...");
@@ -1194,6 +1347,13 @@ impl ElfApp {
                 }
             }
 
+            if ui
+                .add_enabled(self.store.eeg().is_some(), egui::Button::new("Export EDF+"))
+                .clicked()
+            {
+                self.export_eeg_edf();
+            }
+
             ui.separator();
             if let Some(raw) = &self.eeg_path {
                 ui.horizontal(|ui| {
@@ -1225,12 +1385,6 @@ impl ElfApp {
             if let Some(fig) = self.store.eeg_figure() {
                 Plot::new("eeg_plot").height(320.0).show(ui, |plot_ui| {
                     plot_plot_figure(plot_ui, fig);
-                    for &onset in self.store.eeg_events() {
-                        plot_ui.vline(
-                            VLine::new(onset)
-                                .stroke(egui::Stroke::new(1.0, egui::Color32::LIGHT_BLUE)),
-                        );
-                    }
                 });
             } else {
                 ui.centered_and_justified(|ui| {
@@ -1326,6 +1480,110 @@ impl ElfApp {
                 let sum: f64 = values.iter().copied().sum();
                 ui.label(format!("Mean pupil: {:.3} mm", sum / values.len() as f64));
             }
+
+            if let Some(events) = self.store.eye_gaze_events() {
+                ui.separator();
+                let summary = &events.summary;
+                ui.label(format!(
+                    "Fixations: {} (mean {:.0} ms) | Saccades: {} (mean {:.1}°, peak {:.0}°/s) | Blinks: {} ({:.1}/min)",
+                    summary.fixations,
+                    summary.mean_fixation_s * 1000.0,
+                    summary.saccades,
+                    summary.mean_saccade_amplitude_deg,
+                    summary.mean_peak_velocity_deg_s,
+                    summary.blinks,
+                    summary.blink_rate_per_min,
+                ));
+                ui.columns(2, |columns| {
+                    if let Some(fig) = self.store.eye_scanpath() {
+                        Plot::new("eye_scanpath")
+                            .height(260.0)
+                            .data_aspect(1.0)
+                            .show(&mut columns[0], |plot_ui| {
+                                plot_plot_figure(plot_ui, fig);
+                            });
+                    }
+                    if let Some(heatmap) = self.store.eye_heatmap() {
+                        paint_heatmap(&mut columns[1], heatmap, 260.0);
+                    }
+                });
+            }
+        });
+    }
+
+    fn show_resp_tab(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("resp_controls").show(ctx, |ui| {
+            ui.heading("Respiration");
+            ui.horizontal(|ui| {
+                ui.label("CSV column");
+                ui.text_edit_singleline(&mut self.resp_signal);
+            });
+            ui.add(
+                egui::DragValue::new(&mut self.resp_fs)
+                    .range(1.0..=10_000.0)
+                    .prefix("fs (text files): "),
+            );
+            if ui.button("Load respiration").clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("CSV / text", &["csv", "txt"])
+                    .pick_file()
+                {
+                    if let Err(err) = self.load_resp(&path) {
+                        self.resp_status = err;
+                    }
+                }
+            }
+
+            ui.separator();
+            if let Some(path) = &self.resp_path {
+                ui.horizontal(|ui| {
+                    ui.label("File: ");
+                    ui.monospace(path);
+                });
+            }
+            ui.label(format!("Status: {}", self.resp_status));
+
+            if let Some(result) = self.store.resp_result() {
+                let m = &result.metrics;
+                ui.separator();
+                ui.label(format!("Breaths: {}", m.breaths));
+                ui.label(format!(
+                    "Rate: {:.1} ± {:.1} /min",
+                    m.rate_bpm, m.rate_sd_bpm
+                ));
+                ui.label(format!(
+                    "Ti / Te: {:.2} s / {:.2} s (duty {:.2})",
+                    m.ti_mean_s, m.te_mean_s, m.duty_cycle
+                ));
+                ui.label(format!("Tidal amplitude CV: {:.2}", m.tidal_amplitude_cv));
+                ui.label(format!("Irregularity: {:.3}", m.irregularity));
+            }
+            if let Some(rsa) = self.store.resp_rsa() {
+                ui.separator();
+                ui.label(format!(
+                    "RSA ({:.2}–{:.2} Hz): {:.2} ln, {:.0}% of LF+HF",
+                    rsa.band[0],
+                    rsa.band[1],
+                    rsa.ln_power,
+                    rsa.fraction * 100.0
+                ));
+                if !rsa.within_hf {
+                    ui.label("Breathing is outside the HF band; HF power does not reflect RSA.");
+                }
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(plots) = self.store.resp_figures() else {
+                ui.centered_and_justified(|ui| {
+                    ui.label("Load a respiration belt recording to see breath-by-breath rate.");
+                });
+                return;
+            };
+            plot_subplots(ui, "resp", plots, 220.0);
+            if plots.panels.len() < 2 {
+                ui.label("No complete breaths detected.");
+            }
         });
     }
 
@@ -1697,6 +1955,27 @@ impl ElfApp {
     }
 }
 
+fn read_raw_blocks(
+    path: &Path,
+    fallback_fs: f64,
+    progress: &mpsc::Sender<RawLoadFeedback>,
+) -> anyhow::Result<(String, TimeSeries)> {
+    let format = loader::detect_format(path)?;
+    let mut reader = chunked::open_chunked(path, None, Some(fallback_fs))?;
+    let mut data = Vec::with_capacity(reader.len_hint().unwrap_or(0));
+    while let Some(block) = reader.next_block(chunked::DEFAULT_BLOCK_SIZE)? {
+        data.extend(block);
+        let _ = progress.send(RawLoadFeedback::Samples(data.len()));
+    }
+    Ok((
+        format,
+        TimeSeries {
+            fs: reader.fs(),
+            data,
+        },
+    ))
+}
+
 fn run_batch_task(config: LandingBatch, dest: PathBuf, sender: mpsc::Sender<BatchFeedback>) {
     if let Err(err) = std::fs::create_dir_all(&dest) {
         let _ = sender.send(BatchFeedback {
@@ -1819,6 +2098,7 @@ impl eframe::App for ElfApp {
             });
         });
 
+        self.poll_raw_load(ctx);
         self.store.prepare_active_tab(self.active_tab);
 
         match self.active_tab {
@@ -1826,6 +2106,7 @@ impl eframe::App for ElfApp {
             GuiTab::Hrv => self.show_hrv_tab(ctx),
             GuiTab::Eeg => self.show_eeg_tab(ctx),
             GuiTab::Eye => self.show_eye_tab(ctx),
+            GuiTab::Resp => self.show_resp_tab(ctx),
             GuiTab::Settings => self.show_settings_tab(ctx),
         }
 
@@ -1835,6 +2116,7 @@ impl eframe::App for ElfApp {
                 GuiTab::Hrv => ui.label("Ready to inspect ECGs and beat annotations."),
                 GuiTab::Eeg => ui.label("Ready to explore EEG traces and events."),
                 GuiTab::Eye => ui.label("Ready to explore eye-tracking data."),
+                GuiTab::Resp => ui.label("Ready to analyse respiration belt signals."),
                 GuiTab::Settings => ui.label("Manage TLS keys/certs for secure transports."),
             });
         });
@@ -1842,17 +2124,130 @@ impl eframe::App for ElfApp {
 }
 
 fn plot_plot_figure(plot_ui: &mut egui_plot::PlotUi, figure: &Figure) {
+    let y_range = figure.data_bounds().map(|(_, y)| y);
+    if let Some([y0, y1]) = y_range {
+        for span in &figure.spans {
+            let fill = color_from_u32(span.color.0).gamma_multiply(span.opacity.clamp(0.0, 1.0));
+            let corners = vec![
+                [span.from, y0],
+                [span.to, y0],
+                [span.to, y1],
+                [span.from, y1],
+            ];
+            plot_ui.polygon(
+                Polygon::new(corners)
+                    .fill_color(fill)
+                    .stroke(egui::Stroke::NONE),
+            );
+            if let Some(label) = &span.label {
+                plot_ui.text(
+                    Text::new(
+                        PlotPoint::new((span.from + span.to) / 2.0, y1),
+                        label.clone(),
+                    )
+                    .anchor(egui::Align2::CENTER_TOP),
+                );
+            }
+        }
+    }
     for series in &figure.series {
         match series {
             Series::Line(line) => {
-                plot_ui.line(
-                    Line::new(line.points.clone())
-                        .stroke(stroke_from_style(&line.style))
-                        .name(line.name.clone()),
+                let mut plotted = Line::new(line.points.clone())
+                    .stroke(stroke_from_style(&line.style))
+                    .name(line.name.clone());
+                if let Some([length, _]) = line.style.dash {
+                    plotted = plotted.style(LineStyle::Dashed { length });
+                }
+                plot_ui.line(plotted);
+            }
+            Series::Scatter(scatter) => {
+                let shape = match scatter.shape {
+                    MarkerShape::Circle => egui_plot::MarkerShape::Circle,
+                    MarkerShape::Square => egui_plot::MarkerShape::Square,
+                    MarkerShape::Cross => egui_plot::MarkerShape::Cross,
+                    MarkerShape::Triangle => egui_plot::MarkerShape::Up,
+                };
+                plot_ui.points(
+                    Points::new(scatter.points.clone())
+                        .shape(shape)
+                        .radius(scatter.size)
+                        .filled(true)
+                        .color(color_from_u32(scatter.color.0))
+                        .name(scatter.name.clone()),
+                );
+            }
+            Series::Bars(bars) => {
+                let plotted = bars
+                    .bars
+                    .iter()
+                    .map(|&[x, height]| Bar::new(x, height).width(bars.width))
+                    .collect();
+                plot_ui.bar_chart(
+                    BarChart::new(plotted)
+                        .color(color_from_u32(bars.color.0))
+                        .name(bars.name.clone()),
                 );
             }
         }
     }
+    for event in &figure.events {
+        let mut line = VLine::new(event.x).stroke(stroke_from_style(&event.style));
+        if let Some([length, _]) = event.style.dash {
+            line = line.style(LineStyle::Dashed { length });
+        }
+        plot_ui.vline(line);
+        if let (Some(label), Some([_, y1])) = (&event.label, y_range) {
+            plot_ui.text(
+                Text::new(PlotPoint::new(event.x, y1), label.clone())
+                    .anchor(egui::Align2::LEFT_TOP),
+            );
+        }
+    }
+}
+
+/// Draws stacked panels; with a shared x axis they pan and zoom together and
+/// only the bottom panel shows tick labels.
+fn plot_subplots(ui: &mut egui::Ui, id: &str, plots: &Subplots, panel_height: f32) {
+    let link = egui::Id::new(id).with("x");
+    let last = plots.panels.len().saturating_sub(1);
+    for (i, panel) in plots.panels.iter().enumerate() {
+        let mut plot = Plot::new((id, i))
+            .height(panel_height)
+            .show_axes([i == last || !plots.share_x, true]);
+        if plots.share_x {
+            plot = plot
+                .link_axis(link, [true, false])
+                .link_cursor(link, egui::Vec2b::new(true, false));
+        }
+        plot.show(ui, |plot_ui| plot_plot_figure(plot_ui, panel));
+    }
+}
+
+/// Draws a fixation heatmap as a grid of cells shaded from transparent to orange.
+fn paint_heatmap(ui: &mut egui::Ui, heatmap: &FixationHeatmap, height: f32) {
+    let width = ui.available_width();
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_gray(20));
+    let cell_w = rect.width() / heatmap.cols.max(1) as f32;
+    let cell_h = rect.height() / heatmap.rows.max(1) as f32;
+    for row in 0..heatmap.rows {
+        for col in 0..heatmap.cols {
+            let value = heatmap.values[row * heatmap.cols + col] as f32;
+            if value <= 0.01 {
+                continue;
+            }
+            let min = rect.min + egui::vec2(col as f32 * cell_w, row as f32 * cell_h);
+            let cell = egui::Rect::from_min_size(min, egui::vec2(cell_w, cell_h));
+            let alpha = (value.clamp(0.0, 1.0) * 255.0) as u8;
+            painter.rect_filled(
+                cell,
+                0.0,
+                Color32::from_rgba_unmultiplied(255, 140, 0, alpha),
+            );
+        }
+    }
 }
 
 fn stroke_from_style(style: &Style) -> egui::Stroke {
@@ -1,22 +1,13 @@
 use crate::{store::Store, GuiTab};
-use anyhow::{anyhow, Context, Result};
-use arrow::array::{Array, PrimitiveArray};
-use arrow::chunk::Chunk;
-use arrow::datatypes::{DataType, Field, Schema};
-use arrow::error::Result as ArrowResult;
-use arrow::io::parquet::write::{
-    CompressionOptions as ArrowCompressionOptions, Encoding, FileWriter as ArrowFileWriter,
-    RowGroupIterator as ArrowRowGroupIterator, Version, WriteOptions as ArrowWriteOptions,
-};
+use anyhow::{anyhow, Result};
 use crossbeam_channel::{bounded, Receiver, Sender};
 use elf_lib::detectors::ecg::{run_beat_hrv_pipeline, EcgPipelineConfig};
+use elf_lib::io::parquet::ParquetSignalWriter;
 use elf_lib::metrics::hrv::{hrv_nonlinear, hrv_psd, hrv_time, HRVNonlinear, HRVPsd, HRVTime};
 use elf_lib::signal::{Events, RRSeries, TimeSeries};
 use lsl::{self, ChannelFormat, ProcessingOption, Pullable};
-use std::fs::File;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::Duration;
 
@@ -707,94 +698,30 @@ fn run_lsl_loop(
 }
 
 struct ParquetRecorder {
-    writer: ArrowFileWriter<File>,
-    schema: Schema,
-    encodings: Vec<Vec<Encoding>>,
-    options: ArrowWriteOptions,
+    writer: ParquetSignalWriter,
     path: PathBuf,
-    next_index: i64,
-    fs: f64,
 }
 
 impl ParquetRecorder {
     fn new(path: PathBuf, fs: f64) -> Result<Self> {
-        if fs <= 0.0 {
-            return Err(anyhow!("Recording requires a positive sampling rate"));
-        }
-        let schema = Schema::from(vec![
-            Field::new("sample_index", DataType::Int64, false),
-            Field::new("timestamp", DataType::Float64, false),
-            Field::new("value", DataType::Float64, false),
-        ]);
-        let options = ArrowWriteOptions {
-            write_statistics: false,
-            version: Version::V2,
-            compression: ArrowCompressionOptions::Uncompressed,
-            data_pagesize_limit: None,
-        };
-        let encodings = vec![
-            vec![Encoding::Plain],
-            vec![Encoding::Plain],
-            vec![Encoding::Plain],
-        ];
-        let file = File::create(&path)
-            .with_context(|| format!("Failed to create Parquet recording at {}", path.display()))?;
-        let writer = ArrowFileWriter::try_new(file, schema.clone(), options)
-            .context("Failed to initialize Parquet writer")?;
-        Ok(Self {
-            writer,
-            schema,
-            encodings,
-            options,
-            path,
-            next_index: 0,
-            fs,
-        })
+        let writer = ParquetSignalWriter::create(&path, fs, "value", None)?;
+        Ok(Self { writer, path })
     }
 
     fn append(&mut self, samples: &[f64], fs: f64) -> Result<usize> {
-        if samples.is_empty() {
-            return Ok(self.next_index as usize);
-        }
-        if (fs - self.fs).abs() > f64::EPSILON {
+        if !samples.is_empty() && (fs - self.writer.fs()).abs() > f64::EPSILON {
             return Err(anyhow!(
                 "Recorder sample rate mismatch: expected {:.3} Hz, got {:.3} Hz",
-                self.fs,
+                self.writer.fs(),
                 fs
             ));
         }
-        let indices: Vec<i64> = (0..samples.len())
-            .map(|offset| self.next_index + offset as i64)
-            .collect();
-        self.next_index += samples.len() as i64;
-        let timestamps: Vec<f64> = indices.iter().map(|idx| *idx as f64 / self.fs).collect();
-
-        let chunk = Chunk::try_new(vec![
-            Arc::new(PrimitiveArray::<i64>::from_vec(indices)) as Arc<dyn Array>,
-            Arc::new(PrimitiveArray::<f64>::from_vec(timestamps)),
-            Arc::new(PrimitiveArray::<f64>::from_vec(samples.to_vec())),
-        ])?;
-
-        let mut row_groups = ArrowRowGroupIterator::try_new(
-            std::iter::once(ArrowResult::Ok(chunk)),
-            &self.schema,
-            self.options,
-            self.encodings.clone(),
-        )?;
-        if let Some(group) = row_groups.next() {
-            let row_group = group?;
-            self.writer
-                .write(row_group)
-                .context("Failed to write Parquet row group")?;
-        }
-        Ok(self.next_index as usize)
+        self.writer.append(samples)?;
+        Ok(self.writer.len())
     }
 
-    fn finish(mut self) -> Result<()> {
-        self.writer
-            .end(None)
-            .context("Failed to finalize Parquet file")?;
-        Ok(())
+    fn finish(self) -> Result<()> {
+        self.writer.finish()
     }
 
     fn path(&self) -> &Path {
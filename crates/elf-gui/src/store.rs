@@ -1,25 +1,36 @@
-use crate::hrv_helpers::rr_histogram_figure;
 use crate::run_loader::{RunEventFilter, RunManifest};
 use crate::GuiTab;
 use elf_lib::{
     io::eye as eye_io,
     metrics::{
-        hrv::{hrv_nonlinear, hrv_psd, hrv_time, HRVNonlinear, HRVPsd, HRVTime},
+        gaze::{
+            detect_gaze_events, fixation_heatmap, gaze_points, FixationHeatmap, GazeEventConfig,
+            GazeEvents, GazeUnits, ScreenGeometry,
+        },
+        hrv::{hrv_nonlinear, hrv_psd, hrv_rsa, hrv_time, HRVNonlinear, HRVPsd, HRVRsa, HRVTime},
+        resp::{analyze_resp, RespConfig, RespResult},
         sqi::{evaluate_sqi, SQIResult},
     },
-    plot::{decimate_points, figure_from_rr, Color, Figure, LineSeries, Series, Style},
+    plot::{
+        figure_from_ecg, figure_from_ecg_window, figure_from_points, figure_from_psd,
+        figure_from_rr, figure_from_timeseries, minmax_points, rr_histogram_figure, Color,
+        EventLine, Figure, SignalPyramid, Style, Subplots,
+    },
     signal::{Events, RRSeries, TimeSeries},
 };
 use serde::Serialize;
 
-const MAX_WAVEFORM_POINTS: usize = 2048;
 const MAX_EEG_POINTS: usize = 2048;
 const MAX_EYE_POINTS: usize = 1024;
+const MAX_RESP_POINTS: usize = 2048;
+const EYE_HEATMAP_COLS: usize = 32;
+const EYE_HEATMAP_ROWS: usize = 18;
 
 pub struct Store {
     stream: StreamStore,
     eeg: EegStore,
     eye: EyeStore,
+    resp: RespStore,
     run_bundle_state: Option<RunBundleState>,
 }
 
@@ -70,6 +81,7 @@ impl Default for Store {
             stream: StreamStore::default(),
             eeg: EegStore::default(),
             eye: EyeStore::default(),
+            resp: RespStore::default(),
             run_bundle_state: None,
         }
     }
@@ -85,6 +97,7 @@ impl Store {
             GuiTab::Hrv => self.stream.prepare_hrv(),
             GuiTab::Eeg => self.eeg.prepare(),
             GuiTab::Eye => self.eye.prepare(),
+            GuiTab::Resp => self.resp.prepare(self.stream.rr_series()),
             _ => {}
         }
     }
@@ -97,6 +110,12 @@ impl Store {
         self.stream.set_events(events);
     }
 
+    /// Reports the visible ECG time range and the plot width in pixels. Returns whether the
+    /// waveform will be re-fetched for it on the next `prepare`.
+    pub fn set_ecg_view(&mut self, range: [f64; 2], width_px: f32) -> bool {
+        self.stream.set_ecg_view(range, width_px)
+    }
+
     pub fn apply_stream_metrics(
         &mut self,
         rr: RRSeries,
@@ -116,8 +135,25 @@ impl Store {
         self.eeg.set_eeg_events(events);
     }
 
-    pub fn set_eye_samples(&mut self, samples: Vec<eye_io::PupilSample>) {
-        self.eye.set_samples(samples);
+    pub fn set_eye_samples(&mut self, samples: Vec<eye_io::PupilSample>, units: GazeUnits) {
+        self.eye.set_samples(samples, units);
+    }
+
+    pub fn set_resp(&mut self, ts: TimeSeries) {
+        self.resp.set_resp(ts);
+    }
+
+    /// Respiration trace above the breath-by-breath rate, on one time axis.
+    pub fn resp_figures(&self) -> Option<&Subplots> {
+        self.resp.figures.as_ref()
+    }
+
+    pub fn resp_result(&self) -> Option<&RespResult> {
+        self.resp.result.as_ref()
+    }
+
+    pub fn resp_rsa(&self) -> Option<&HRVRsa> {
+        self.resp.rsa.as_ref()
     }
 
     pub fn set_eye_threshold(&mut self, threshold: f32) {
@@ -136,10 +172,6 @@ impl Store {
         self.stream.events_len()
     }
 
-    pub fn event_seconds(&self) -> Vec<f64> {
-        self.stream.event_seconds()
-    }
-
     pub fn rr_series(&self) -> Option<&RRSeries> {
         self.stream.rr_series()
     }
@@ -211,6 +243,22 @@ impl Store {
         self.eye.figure()
     }
 
+    pub fn eye_scanpath(&self) -> Option<&Figure> {
+        self.eye.scanpath.as_ref()
+    }
+
+    pub fn eye_heatmap(&self) -> Option<&FixationHeatmap> {
+        self.eye.heatmap.as_ref()
+    }
+
+    pub fn eye_gaze_events(&self) -> Option<&GazeEvents> {
+        self.eye.gaze.as_ref()
+    }
+
+    pub fn eeg(&self) -> Option<&TimeSeries> {
+        self.eeg.ts.as_ref()
+    }
+
     pub fn eeg_figure(&self) -> Option<&Figure> {
         self.eeg.figure()
     }
@@ -252,6 +300,10 @@ struct StreamSnapshot {
     hrv_psd: Option<HRVPsd>,
     hrv_nonlinear: Option<HRVNonlinear>,
     sqi: Option<SQIResult>,
+    /// Built on first use after the ECG changes.
+    ecg_pyramid: Option<SignalPyramid>,
+    /// Time range and point budget the waveform figure was last fetched for.
+    ecg_view: Option<([f64; 2], usize)>,
     ecg_figure: Option<Figure>,
     rr_figure: Option<Figure>,
     psd_figure: Option<Figure>,
@@ -285,6 +337,7 @@ impl StreamDirtyFlags {
     }
 
     fn mark_events(&mut self) {
+        self.waveform = true;
         self.rr = true;
         self.rr_figure = true;
         self.hrv = true;
@@ -323,6 +376,7 @@ impl StreamStore {
 
     fn set_ecg(&mut self, ts: TimeSeries) {
         self.snapshot.ecg = Some(ts);
+        self.snapshot.ecg_pyramid = None;
         self.dirty.mark_ecg();
         self.snapshot.rr = None;
     }
@@ -369,17 +423,6 @@ impl StreamStore {
             .unwrap_or(0)
     }
 
-    fn event_seconds(&self) -> Vec<f64> {
-        if let (Some(events), Some(ts)) =
-            (self.snapshot.events.as_ref(), self.snapshot.ecg.as_ref())
-        {
-            let fs = ts.fs.max(1.0);
-            events.indices.iter().map(|idx| *idx as f64 / fs).collect()
-        } else {
-            Vec::new()
-        }
-    }
-
     fn rr_series(&self) -> Option<&RRSeries> {
         self.snapshot.rr.as_ref()
     }
@@ -433,15 +476,41 @@ impl StreamStore {
         self.snapshot.sqi.as_ref()
     }
 
+    fn set_ecg_view(&mut self, [t0, t1]: [f64; 2], width_px: f32) -> bool {
+        if !(t0.is_finite() && t1.is_finite() && t1 > t0) {
+            return false;
+        }
+        // Fetch half a screen either side so panning does not show empty edges, at two
+        // points (a min and a max) per pixel.
+        let pad = (t1 - t0) / 2.0;
+        let view = ([t0 - pad, t1 + pad], (width_px.max(1.0) as usize) * 4);
+        let unchanged = self.snapshot.ecg_view.is_some_and(|([a0, a1], points)| {
+            points == view.1 && a0 <= t0 && t1 <= a1 && (a1 - a0) < 4.0 * (t1 - t0)
+        });
+        if unchanged {
+            return false;
+        }
+        self.snapshot.ecg_view = Some(view);
+        self.dirty.waveform = true;
+        true
+    }
+
     fn ensure_waveform_figure(&mut self) {
         if !self.dirty.waveform {
             return;
         }
-        let figure =
-            self.snapshot.ecg.as_ref().map(|ts| {
-                figure_from_timeseries("ECG waveform", ts, MAX_WAVEFORM_POINTS, 0xFF3333)
-            });
-        self.snapshot.ecg_figure = figure;
+        let snapshot = &mut self.snapshot;
+        let events = snapshot.events.as_ref();
+        let figure = snapshot.ecg.as_ref().map(|ts| match snapshot.ecg_view {
+            Some((range, max_points)) => {
+                let pyramid = snapshot
+                    .ecg_pyramid
+                    .get_or_insert_with(|| SignalPyramid::new(ts));
+                figure_from_ecg_window(ts, pyramid, events, range, max_points)
+            }
+            None => figure_from_ecg(ts, events),
+        });
+        snapshot.ecg_figure = figure;
         self.dirty.waveform = false;
     }
 
@@ -511,9 +580,7 @@ impl StreamStore {
         if !self.dirty.psd_figure {
             return;
         }
-        let figure = self.snapshot.hrv_psd.as_ref().map(|psd| {
-            figure_from_points(Some("PSD".to_string()), "PSD", psd.points.clone(), 0x0077FF)
-        });
+        let figure = self.snapshot.hrv_psd.as_ref().map(figure_from_psd);
         self.snapshot.psd_figure = figure;
         self.dirty.psd_figure = false;
     }
@@ -571,16 +638,28 @@ impl EegStore {
 
     fn set_eeg_events(&mut self, events: Vec<f64>) {
         self.events = events;
+        self.dirty = true;
     }
 
     fn prepare(&mut self) {
         if !self.dirty {
             return;
         }
-        let figure = self
-            .ts
-            .as_ref()
-            .map(|ts| figure_from_timeseries("EEG trace", ts, MAX_EEG_POINTS, 0x33CCFF));
+        let figure = self.ts.as_ref().map(|ts| {
+            let mut fig = figure_from_timeseries("EEG trace", ts, MAX_EEG_POINTS, 0x33CCFF);
+            for &onset in &self.events {
+                fig.add_event(EventLine {
+                    x: onset,
+                    label: None,
+                    style: Style {
+                        width: 1.0,
+                        dash: None,
+                        color: Color(0xADD8E6),
+                    },
+                });
+            }
+            fig
+        });
         self.figure = figure;
         self.dirty = false;
     }
@@ -602,17 +681,85 @@ impl EegStore {
     }
 }
 
+#[derive(Default)]
+struct RespStore {
+    ts: Option<TimeSeries>,
+    result: Option<RespResult>,
+    figures: Option<Subplots>,
+    rsa: Option<HRVRsa>,
+    rr_len: usize,
+    dirty: bool,
+}
+
+impl RespStore {
+    fn set_resp(&mut self, ts: TimeSeries) {
+        self.ts = Some(ts);
+        self.dirty = true;
+    }
+
+    /// Breath detection on load; RSA is refreshed whenever the HRV tab's RR series changes.
+    fn prepare(&mut self, rr: Option<&RRSeries>) {
+        let rr_len = rr.map(|rr| rr.rr.len()).unwrap_or(0);
+        if !self.dirty && rr_len == self.rr_len {
+            return;
+        }
+        if self.dirty {
+            self.result = self
+                .ts
+                .as_ref()
+                .map(|ts| analyze_resp(ts, &RespConfig::default()));
+            self.figures = self.ts.as_ref().map(|ts| {
+                let mut plots = Subplots::new(None, true);
+                plots.add_panel(figure_from_timeseries(
+                    "Respiration",
+                    ts,
+                    MAX_RESP_POINTS,
+                    0x66DDAA,
+                ));
+                let points: Vec<[f64; 2]> = self
+                    .result
+                    .iter()
+                    .flat_map(|result| &result.breaths)
+                    .map(|breath| [breath.peak, breath.rate_bpm])
+                    .collect();
+                if !points.is_empty() {
+                    plots.add_panel(figure_from_points(
+                        Some("Breath-by-breath rate".to_string()),
+                        "Breaths/min",
+                        points,
+                        0xFF7799,
+                    ));
+                }
+                plots
+            });
+        }
+        self.rsa = match (rr, self.result.as_ref()) {
+            (Some(rr), Some(result)) if rr.rr.len() > 4 && result.metrics.resp_hz > 0.0 => {
+                Some(hrv_rsa(rr, 4.0, result.metrics.resp_hz, 0.05))
+            }
+            _ => None,
+        };
+        self.rr_len = rr_len;
+        self.dirty = false;
+    }
+}
+
 struct EyeStore {
     samples: Vec<eye_io::PupilSample>,
     filtered: Vec<eye_io::PupilSample>,
     figure: Option<Figure>,
+    units: GazeUnits,
+    gaze: Option<GazeEvents>,
+    scanpath: Option<Figure>,
+    heatmap: Option<FixationHeatmap>,
     dirty: bool,
     threshold: f32,
 }
 
 impl EyeStore {
-    fn set_samples(&mut self, samples: Vec<eye_io::PupilSample>) {
+    fn set_samples(&mut self, samples: Vec<eye_io::PupilSample>, units: GazeUnits) {
         self.samples = samples;
+        self.units = units;
         self.update_filter();
     }
 
@@ -643,7 +790,7 @@ impl EyeStore {
         let figure = if points.is_empty() {
             None
         } else {
-            let decimated = decimate_points(&points, MAX_EYE_POINTS);
+            let decimated = minmax_points(&points, MAX_EYE_POINTS);
             Some(figure_from_points(
                 Some("Pupil size".to_string()),
                 "Pupil",
@@ -652,9 +799,52 @@ impl EyeStore {
             ))
         };
         self.figure = figure;
+        self.prepare_gaze();
         self.dirty = false;
     }
 
+    /// Fixation scanpath and heatmap for the first eye that has gaze coordinates.
+    fn prepare_gaze(&mut self) {
+        self.gaze = None;
+        self.scanpath = None;
+        self.heatmap = None;
+        let screen = ScreenGeometry::default();
+        for eye in [
+            eye_io::Eye::Left,
+            eye_io::Eye::Right,
+            eye_io::Eye::Binocular,
+        ] {
+            let points = gaze_points(&self.samples, eye, self.units, &screen, self.threshold);
+            if !points.iter().any(|p| p.x.is_finite() && p.y.is_finite()) {
+                continue;
+            }
+            let events = detect_gaze_events(eye, &points, &screen, &GazeEventConfig::default());
+            // Flip y so the plot matches the screen (origin top-left).
+            let path: Vec<[f64; 2]> = events
+                .fixations
+                .iter()
+                .map(|f| [f.x, screen.height_px - f.y])
+                .collect();
+            if !path.is_empty() {
+                self.scanpath = Some(figure_from_points(
+                    Some("Scanpath".to_string()),
+                    "Fixations",
+                    path,
+                    0x33CCFF,
+                ));
+            }
+            self.heatmap = Some(fixation_heatmap(
+                &events.fixations,
+                &screen,
+                EYE_HEATMAP_COLS,
+                EYE_HEATMAP_ROWS,
+                screen.px_per_degree(),
+            ));
+            self.gaze = Some(events);
+            break;
+        }
+    }
+
     fn figure(&self) -> Option<&Figure> {
         self.figure.as_ref()
     }
@@ -674,48 +864,12 @@ impl Default for EyeStore {
             samples: Vec::new(),
             filtered: Vec::new(),
             figure: None,
+            units: GazeUnits::NormalizedBottomLeft,
+            gaze: None,
+            scanpath: None,
+            heatmap: None,
             dirty: true,
             threshold: 0.5,
         }
     }
 }
-
-fn sample_points(series: &TimeSeries, max_points: usize) -> Vec<[f64; 2]> {
-    let dt = 1.0 / series.fs.max(1.0);
-    let coords: Vec<[f64; 2]> = series
-        .data
-        .iter()
-        .enumerate()
-        .map(|(i, &value)| [i as f64 * dt, value])
-        .collect();
-    decimate_points(&coords, max_points)
-}
-
-fn figure_from_timeseries(
-    title: &str,
-    series: &TimeSeries,
-    max_points: usize,
-    color: u32,
-) -> Figure {
-    let points = sample_points(series, max_points);
-    figure_from_points(Some(title.to_string()), title, points, color)
-}
-
-fn figure_from_points(
-    title: Option<String>,
-    name: &str,
-    points: Vec<[f64; 2]>,
-    color: u32,
-) -> Figure {
-    let mut fig = Figure::new(title);
-    fig.add_series(Series::Line(LineSeries {
-        name: name.into(),
-        points,
-        style: Style {
-            width: 1.4,
-            dash: None,
-            color: Color(color),
-        },
-    }));
-    fig
-}
@@ -0,0 +1,211 @@
+use super::{
+    ecg::{detect_r_peaks_with_config, fallback_peak_picker, EcgPipelineConfig},
+    streaming::StreamingBeatDetector,
+};
+use crate::signal::{Events, TimeSeries};
+use serde::{Deserialize, Serialize};
+
+/// Default matching window of EC57.
+pub const DEFAULT_TOLERANCE_S: f64 = 0.15;
+
+/// QRS detectors available for benchmarking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QrsDetector {
+    /// The batch pipeline of [`detect_r_peaks_with_config`].
+    PanTompkins,
+    /// [`StreamingBeatDetector`] fed one-second blocks, as for chunked and live input.
+    Streaming,
+    /// Local maxima above a 150 ms moving average; the batch pipeline's fallback.
+    LocalMaxima,
+}
+
+impl QrsDetector {
+    pub const ALL: [QrsDetector; 3] = [
+        QrsDetector::PanTompkins,
+        QrsDetector::Streaming,
+        QrsDetector::LocalMaxima,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QrsDetector::PanTompkins => "pan-tompkins",
+            QrsDetector::Streaming => "streaming",
+            QrsDetector::LocalMaxima => "local-maxima",
+        }
+    }
+
+    pub fn detect(self, ts: &TimeSeries, cfg: &EcgPipelineConfig) -> Events {
+        match self {
+            QrsDetector::PanTompkins => detect_r_peaks_with_config(ts, cfg),
+            QrsDetector::Streaming => {
+                let mut detector = StreamingBeatDetector::new(ts.fs, cfg);
+                let block = (ts.fs.round() as usize).max(1);
+                let mut indices: Vec<usize> = ts
+                    .data
+                    .chunks(block)
+                    .flat_map(|chunk| detector.push(chunk))
+                    .collect();
+                indices.extend(detector.finish());
+                Events::from_indices(indices)
+            }
+            QrsDetector::LocalMaxima => Events::from_indices(fallback_peak_picker(ts, cfg)),
+        }
+    }
+}
+
+/// Beat-by-beat comparison of detections with reference beats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BeatMatch {
+    pub reference: usize,
+    pub detected: usize,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    /// Sum of detection minus reference time over the matched pairs (seconds).
+    pub error_sum_s: f64,
+    pub abs_error_sum_s: f64,
+}
+
+impl BeatMatch {
+    /// Se = TP / (TP + FN).
+    pub fn sensitivity(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    /// +P = TP / (TP + FP).
+    pub fn positive_predictivity(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// Harmonic mean of Se and +P: 2 TP / (2 TP + FP + FN).
+    pub fn f1(&self) -> f64 {
+        ratio(
+            2 * self.true_positives,
+            2 * self.true_positives + self.false_positives + self.false_negatives,
+        )
+    }
+
+    /// Mean absolute timing error of the matched beats (seconds).
+    pub fn mean_abs_error_s(&self) -> f64 {
+        self.abs_error_sum_s / self.true_positives.max(1) as f64
+    }
+
+    /// Mean signed timing error; positive when detections come late (seconds).
+    pub fn mean_error_s(&self) -> f64 {
+        self.error_sum_s / self.true_positives.max(1) as f64
+    }
+
+    /// Adds the counts of another record (gross statistics).
+    pub fn merge(&mut self, other: &BeatMatch) {
+        self.reference += other.reference;
+        self.detected += other.detected;
+        self.true_positives += other.true_positives;
+        self.false_positives += other.false_positives;
+        self.false_negatives += other.false_negatives;
+        self.error_sum_s += other.error_sum_s;
+        self.abs_error_sum_s += other.abs_error_sum_s;
+    }
+}
+
+fn ratio(num: usize, den: usize) -> f64 {
+    if den == 0 {
+        0.0
+    } else {
+        num as f64 / den as f64
+    }
+}
+
+/// Matches sorted `detected` to sorted `reference` sample indices. Each reference beat takes the
+/// closest unused detection within `tolerance_s`. Detections left over are false positives,
+/// and reference beats without a detection are false negatives.
+pub fn match_beats(
+    reference: &[usize],
+    detected: &[usize],
+    fs: f64,
+    tolerance_s: f64,
+) -> BeatMatch {
+    let fs = fs.max(f64::MIN_POSITIVE);
+    let tolerance = (tolerance_s * fs).round() as usize;
+    let mut result = BeatMatch {
+        reference: reference.len(),
+        detected: detected.len(),
+        ..BeatMatch::default()
+    };
+    let mut next = 0;
+    for &beat in reference {
+        while next < detected.len() && detected[next] + tolerance < beat {
+            next += 1;
+        }
+        let window_end = next + detected[next..].partition_point(|&d| d <= beat + tolerance);
+        let Some(best) = (next..window_end).min_by_key(|&k| detected[k].abs_diff(beat)) else {
+            continue;
+        };
+        let error = (detected[best] as f64 - beat as f64) / fs;
+        result.true_positives += 1;
+        result.error_sum_s += error;
+        result.abs_error_sum_s += error.abs();
+        next = best + 1;
+    }
+    result.false_negatives = result.reference - result.true_positives;
+    result.false_positives = result.detected - result.true_positives;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::{generate, SynthConfig};
+
+    #[test]
+    fn matching_counts_hits_misses_and_extras() {
+        let reference = [100, 300, 500, 700];
+        // 300 is missed, 505 is 20 ms late, 590 and 602 are extras, 699 matches 700.
+        let detected = [98, 505, 590, 602, 699];
+        let m = match_beats(&reference, &detected, 250.0, 0.15);
+        assert_eq!(m.true_positives, 3);
+        assert_eq!(m.false_negatives, 1);
+        assert_eq!(m.false_positives, 2);
+        assert!((m.sensitivity() - 0.75).abs() < 1e-12);
+        assert!((m.positive_predictivity() - 0.6).abs() < 1e-12);
+        assert!((m.f1() - 6.0 / 9.0).abs() < 1e-12);
+        assert!((m.mean_abs_error_s() - 8.0 / 750.0).abs() < 1e-12);
+        assert!((m.mean_error_s() - 2.0 / 750.0).abs() < 1e-12);
+
+        // A detection is used at most once.
+        let m = match_beats(&[100, 110], &[105], 250.0, 0.15);
+        assert_eq!((m.true_positives, m.false_negatives), (1, 1));
+
+        let mut total = m;
+        total.merge(&match_beats(&reference, &reference, 250.0, 0.15));
+        assert_eq!(total.true_positives, 5);
+        assert_eq!(total.reference, 6);
+    }
+
+    #[test]
+    fn every_detector_finds_clean_synthetic_beats() {
+        let rec = generate(&SynthConfig {
+            duration_s: 30.0,
+            ..SynthConfig::default()
+        })
+        .unwrap();
+        let ts = TimeSeries {
+            fs: rec.fs,
+            data: rec.ecg.clone(),
+        };
+        let reference = rec.beat_events().indices;
+        for detector in [QrsDetector::PanTompkins, QrsDetector::Streaming] {
+            let detected = detector.detect(&ts, &EcgPipelineConfig::default());
+            let m = match_beats(&reference, &detected.indices, ts.fs, DEFAULT_TOLERANCE_S);
+            assert!(m.sensitivity() > 0.99, "{}: {m:?}", detector.name());
+        }
+        let local = QrsDetector::LocalMaxima.detect(&ts, &EcgPipelineConfig::default());
+        assert!(!local.indices.is_empty());
+    }
+}
@@ -5,7 +5,8 @@ use crate::{
 use serde::{Deserialize, Serialize};
 
 /// Configurable parameters for the ECG beat detection + HRV pipeline.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct EcgPipelineConfig {
     /// Lower cutoff for the single-pole high-pass filter (Hz).
     pub lowcut_hz: f64,
@@ -93,6 +94,15 @@ pub fn run_beat_hrv_pipeline(ts: &TimeSeries, cfg: &EcgPipelineConfig) -> BeatHr
     BeatHrvPipelineResult::from_events(ts, events)
 }
 
+/// The pipeline's single-pole band-pass on its own; a cutoff of 0 (or a high cutoff at or
+/// above Nyquist) skips that side.
+pub fn bandpass_filter(ts: &TimeSeries, lowcut_hz: f64, highcut_hz: f64) -> TimeSeries {
+    TimeSeries {
+        fs: ts.fs,
+        data: bandpass(&ts.data, ts.fs.max(1.0), lowcut_hz, highcut_hz),
+    }
+}
+
 /// Builds the Pan–Tompkins-style envelope used for adaptive peak selection.
 /// The envelope is a bandpassed + derivative-squared sequence smoothed with a moving window
 /// (classic Pan & Tompkins 1985 preprocessing before thresholding). doi:10.1109/TBME.1985.325532
@@ -250,7 +260,7 @@ fn pick_peaks(
     peaks
 }
 
-fn fallback_peak_picker(ts: &TimeSeries, cfg: &EcgPipelineConfig) -> Vec<usize> {
+pub(crate) fn fallback_peak_picker(ts: &TimeSeries, cfg: &EcgPipelineConfig) -> Vec<usize> {
     // Very simple thresholding/minimum-distance routine used only when the adaptive pass fails.
     let min_gap = (cfg.min_rr_s * ts.fs).max(1.0) as usize;
     let data = &ts.data;
@@ -1 +1,3 @@
+pub mod bench;
 pub mod ecg;
+pub mod streaming;
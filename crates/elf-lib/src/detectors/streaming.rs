@@ -0,0 +1,347 @@
+use super::ecg::{BeatHrvPipelineResult, EcgPipelineConfig};
+use crate::{
+    io::chunked::ChunkedReader,
+    metrics::hrv::hrv_time,
+    signal::{Events, RRSeries},
+};
+use anyhow::Result;
+use std::collections::VecDeque;
+
+/// Single-pole high-pass followed by single-pole low-pass, as in the batch pipeline.
+#[derive(Debug, Clone)]
+pub struct StreamingBandpass {
+    highpass: Option<f64>,
+    lowpass: Option<f64>,
+    hp_state: Option<(f64, f64)>,
+    lp_state: Option<f64>,
+}
+
+impl StreamingBandpass {
+    pub fn new(fs: f64, low_hz: f64, high_hz: f64) -> Self {
+        let dt = 1.0 / fs;
+        let rc = |cutoff: f64| 1.0 / (2.0 * std::f64::consts::PI * cutoff.max(0.01));
+        Self {
+            highpass: (low_hz > 0.0).then(|| rc(low_hz) / (rc(low_hz) + dt)),
+            lowpass: (high_hz > 0.0 && high_hz < fs * 0.5).then(|| dt / (rc(high_hz) + dt)),
+            hp_state: None,
+            lp_state: None,
+        }
+    }
+
+    pub fn step(&mut self, x: f64) -> f64 {
+        let hp = match self.highpass {
+            Some(alpha) => {
+                let (prev_y, prev_x) = *self.hp_state.get_or_insert((x, x));
+                let y = alpha * (prev_y + x - prev_x);
+                self.hp_state = Some((y, x));
+                y
+            }
+            None => x,
+        };
+        match self.lowpass {
+            Some(alpha) => {
+                let prev = self.lp_state.get_or_insert(hp);
+                *prev += alpha * (hp - *prev);
+                *prev
+            }
+            None => hp,
+        }
+    }
+
+    pub fn process(&mut self, block: &[f64]) -> Vec<f64> {
+        block.iter().map(|&x| self.step(x)).collect()
+    }
+}
+
+/// Derivative, squaring and moving-window integration of the band-passed signal.
+#[derive(Debug, Clone)]
+pub struct StreamingEnvelope {
+    prev: Option<f64>,
+    window: VecDeque<f64>,
+    win: usize,
+    acc: f64,
+}
+
+impl StreamingEnvelope {
+    pub fn new(fs: f64, integration_window_s: f64) -> Self {
+        let win = ((integration_window_s * fs).round() as usize).max(1);
+        Self {
+            prev: None,
+            window: VecDeque::with_capacity(win + 1),
+            win,
+            acc: 0.0,
+        }
+    }
+
+    pub fn step(&mut self, bandpassed: f64) -> f64 {
+        let derivative = self.prev.map_or(0.0, |prev| bandpassed - prev);
+        self.prev = Some(bandpassed);
+        let squared = derivative * derivative;
+        if self.win <= 1 {
+            return squared;
+        }
+        self.acc += squared;
+        self.window.push_back(squared);
+        if self.window.len() > self.win {
+            self.acc -= self.window.pop_front().unwrap_or(0.0);
+        }
+        self.acc / self.win as f64
+    }
+}
+
+/// Adaptive-threshold peak picker with search-back on the band-passed signal.
+///
+/// The first second of the envelope seeds the signal and noise levels, so it is buffered
+/// before any decision is made. Peaks are released once no later detection can precede them.
+#[derive(Debug, Clone)]
+pub struct StreamingPeakPicker {
+    refractory: usize,
+    search: usize,
+    threshold_scale: f64,
+    init: usize,
+    warmup: Vec<(f64, f64)>,
+    started: bool,
+    signal_level: f64,
+    noise_level: f64,
+    threshold: f64,
+    last_peak_sample: usize,
+    any_peak: bool,
+    recent: VecDeque<f64>,
+    position: usize,
+    pending: Vec<usize>,
+}
+
+impl StreamingPeakPicker {
+    pub fn new(fs: f64, cfg: &EcgPipelineConfig) -> Self {
+        let search = (cfg.search_back_s * fs).round().max(1.0) as usize;
+        Self {
+            refractory: (cfg.min_rr_s * fs).round().clamp(1.0, f64::MAX) as usize,
+            search,
+            threshold_scale: cfg.threshold_scale,
+            init: (fs as usize).max(1),
+            warmup: Vec::new(),
+            started: false,
+            signal_level: 0.0,
+            noise_level: 0.0,
+            threshold: 0.0,
+            last_peak_sample: 0,
+            any_peak: false,
+            recent: VecDeque::with_capacity(search + 2),
+            position: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Feeds one `(band-passed, envelope)` sample; returns peaks that are now final.
+    pub fn push(&mut self, bandpassed: f64, envelope: f64) -> Vec<usize> {
+        if !self.started {
+            self.warmup.push((bandpassed, envelope));
+            if self.warmup.len() < self.init {
+                return Vec::new();
+            }
+            self.start();
+            let warmup = std::mem::take(&mut self.warmup);
+            return warmup
+                .into_iter()
+                .flat_map(|(bp, env)| self.step(bp, env))
+                .collect();
+        }
+        self.step(bandpassed, envelope)
+    }
+
+    /// Flushes the remaining peaks at the end of the signal.
+    pub fn finish(&mut self) -> Vec<usize> {
+        if !self.started && !self.warmup.is_empty() {
+            self.start();
+            for (bp, env) in std::mem::take(&mut self.warmup) {
+                self.step(bp, env);
+            }
+        }
+        std::mem::take(&mut self.pending)
+    }
+
+    fn start(&mut self) {
+        let avg = self.warmup.iter().map(|(_, env)| env).sum::<f64>() / self.warmup.len() as f64;
+        self.signal_level = avg;
+        self.noise_level = avg * 0.5;
+        self.update_threshold();
+        self.started = true;
+    }
+
+    fn update_threshold(&mut self) {
+        self.threshold = self.noise_level
+            + self.threshold_scale * (self.signal_level - self.noise_level).max(0.0);
+    }
+
+    fn step(&mut self, bandpassed: f64, sample: f64) -> Vec<usize> {
+        let i = self.position;
+        self.position += 1;
+        self.recent.push_back(bandpassed);
+        if self.recent.len() > self.search + 1 {
+            self.recent.pop_front();
+        }
+        let refractory_ok = !self.any_peak || i - self.last_peak_sample >= self.refractory;
+        if sample >= self.threshold && refractory_ok {
+            let start = i + 1 - self.recent.len();
+            let mut idx = start;
+            let mut max_val = f64::MIN;
+            for (k, &value) in self.recent.iter().enumerate() {
+                if value > max_val {
+                    max_val = value;
+                    idx = start + k;
+                }
+            }
+            if let Err(at) = self.pending.binary_search(&idx) {
+                self.pending.insert(at, idx);
+            }
+            self.any_peak = true;
+            self.last_peak_sample = i;
+            self.signal_level = 0.125 * sample + 0.875 * self.signal_level;
+        } else {
+            self.noise_level = 0.125 * sample + 0.875 * self.noise_level;
+        }
+        self.update_threshold();
+
+        // The next search window starts at i + 1 - search; earlier peaks can no longer move.
+        let horizon = (i + 1).saturating_sub(self.search);
+        let ready = self.pending.partition_point(|&p| p < horizon);
+        self.pending.drain(..ready).collect()
+    }
+}
+
+/// Band-pass, envelope and peak picking chained over consecutive blocks. Beats match the batch
+/// detector, except that there is no fallback pass when fewer than two beats are found.
+#[derive(Debug, Clone)]
+pub struct StreamingBeatDetector {
+    bandpass: StreamingBandpass,
+    envelope: StreamingEnvelope,
+    picker: StreamingPeakPicker,
+}
+
+impl StreamingBeatDetector {
+    pub fn new(fs: f64, cfg: &EcgPipelineConfig) -> Self {
+        let filter_fs = fs.max(1.0);
+        Self {
+            bandpass: StreamingBandpass::new(filter_fs, cfg.lowcut_hz, cfg.highcut_hz),
+            envelope: StreamingEnvelope::new(filter_fs, cfg.integration_window_s),
+            picker: StreamingPeakPicker::new(fs, cfg),
+        }
+    }
+
+    /// R-peak sample indices (counted from the first block) that are final after `block`.
+    pub fn push(&mut self, block: &[f64]) -> Vec<usize> {
+        let mut peaks = Vec::new();
+        for &x in block {
+            let bp = self.bandpass.step(x);
+            let env = self.envelope.step(bp);
+            peaks.extend(self.picker.push(bp, env));
+        }
+        peaks
+    }
+
+    pub fn finish(mut self) -> Vec<usize> {
+        self.picker.finish()
+    }
+}
+
+/// Turns a stream of beat indices into RR intervals (seconds).
+#[derive(Debug, Clone)]
+pub struct StreamingRr {
+    fs: f64,
+    last: Option<usize>,
+}
+
+impl StreamingRr {
+    pub fn new(fs: f64) -> Self {
+        Self { fs, last: None }
+    }
+
+    pub fn push(&mut self, peaks: &[usize]) -> Vec<f64> {
+        let mut rr = Vec::with_capacity(peaks.len());
+        for &peak in peaks {
+            if let Some(last) = self.last {
+                rr.push((peak as f64 - last as f64) / self.fs);
+            }
+            self.last = Some(peak);
+        }
+        rr
+    }
+}
+
+/// Runs beat detection → RR → time-domain HRV over a chunked reader. Only the beats and
+/// RR intervals are kept, so memory does not grow with the recording length.
+pub fn run_beat_hrv_pipeline_chunked(
+    reader: &mut dyn ChunkedReader,
+    cfg: &EcgPipelineConfig,
+    block_size: usize,
+) -> Result<BeatHrvPipelineResult> {
+    let fs = reader.fs();
+    let mut detector = StreamingBeatDetector::new(fs, cfg);
+    let mut rr_stage = StreamingRr::new(fs);
+    let mut indices = Vec::new();
+    let mut rr = Vec::new();
+    let mut sample_count = 0;
+    while let Some(block) = reader.next_block(block_size.max(1))? {
+        sample_count += block.len();
+        let peaks = detector.push(&block);
+        rr.extend(rr_stage.push(&peaks));
+        indices.extend(peaks);
+    }
+    let peaks = detector.finish();
+    rr.extend(rr_stage.push(&peaks));
+    indices.extend(peaks);
+    let rr = RRSeries { rr };
+    let hrv = hrv_time(&rr);
+    Ok(BeatHrvPipelineResult {
+        fs,
+        sample_count,
+        events: Events::from_indices(indices),
+        rr,
+        hrv,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::ecg::run_beat_hrv_pipeline;
+    use crate::io::{
+        chunked::{MemoryReader, WfdbChunkReader},
+        wfdb,
+    };
+    use std::path::PathBuf;
+
+    fn sample_path(relative: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace root")
+            .join(relative)
+    }
+
+    #[test]
+    fn chunked_pipeline_matches_batch_on_mitdb() {
+        let header = sample_path("test_data/mitdb/100.hea");
+        let ts = wfdb::load_wfdb_lead(&header, 0).unwrap();
+        let cfg = EcgPipelineConfig::default();
+        let batch = run_beat_hrv_pipeline(&ts, &cfg);
+
+        let mut reader = WfdbChunkReader::open(&header, 0).unwrap();
+        let chunked = run_beat_hrv_pipeline_chunked(&mut reader, &cfg, 4096).unwrap();
+        assert_eq!(chunked.sample_count, batch.sample_count);
+        assert_eq!(chunked.events.indices, batch.events.indices);
+        assert_eq!(chunked.rr.rr, batch.rr.rr);
+
+        // Block boundaries must not matter, down to one sample per block.
+        let short = crate::signal::TimeSeries {
+            fs: ts.fs,
+            data: ts.data[..20_000].to_vec(),
+        };
+        let expected = run_beat_hrv_pipeline(&short, &cfg).events.indices;
+        for block_size in [1, 37, 360] {
+            let mut reader = MemoryReader::new(short.clone());
+            let result = run_beat_hrv_pipeline_chunked(&mut reader, &cfg, block_size).unwrap();
+            assert_eq!(result.events.indices, expected, "block size {block_size}");
+        }
+    }
+}
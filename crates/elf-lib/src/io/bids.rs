@@ -0,0 +1,560 @@
+use crate::signal::TimeSeries;
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Top-level folders that are not part of the raw dataset.
+const SKIPPED_DIRS: [&str; 4] = ["derivatives", "sourcedata", "code", "stimuli"];
+
+/// Key-value entities parsed from a BIDS filename (`sub-01_ses-1_task-rest_run-2_...`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BidsEntities {
+    pub sub: Option<String>,
+    pub ses: Option<String>,
+    pub task: Option<String>,
+    pub acq: Option<String>,
+    pub run: Option<String>,
+    pub recording: Option<String>,
+    /// Entities without a dedicated field (`desc`, `space`, ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub other: BTreeMap<String, String>,
+}
+
+impl BidsEntities {
+    fn set(&mut self, key: &str, value: &str) {
+        let slot = match key {
+            "sub" => &mut self.sub,
+            "ses" => &mut self.ses,
+            "task" => &mut self.task,
+            "acq" => &mut self.acq,
+            "run" => &mut self.run,
+            "recording" => &mut self.recording,
+            _ => {
+                self.other.insert(key.to_string(), value.to_string());
+                return;
+            }
+        };
+        *slot = Some(value.to_string());
+    }
+
+    /// Entities in the canonical BIDS order.
+    pub fn pairs(&self) -> Vec<(&str, &str)> {
+        let mut out = Vec::new();
+        for (key, value) in [
+            ("sub", &self.sub),
+            ("ses", &self.ses),
+            ("task", &self.task),
+            ("acq", &self.acq),
+            ("run", &self.run),
+            ("recording", &self.recording),
+        ] {
+            if let Some(value) = value {
+                out.push((key, value.as_str()));
+            }
+        }
+        out.extend(self.other.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        out
+    }
+
+    /// True when every entity set here has the same value in `other` (sidecar inheritance).
+    fn is_subset_of(&self, other: &BidsEntities) -> bool {
+        let theirs = other.pairs();
+        self.pairs().iter().all(|pair| theirs.contains(pair))
+    }
+}
+
+/// What a BIDS file holds, as far as elf is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BidsKind {
+    /// `*_physio.tsv[.gz]` continuous recordings.
+    Physio,
+    /// `*_eeg.edf` / `*_eeg.bdf`.
+    Eeg,
+    /// `*_eyetrack.*` or physio files with an `eye*` recording entity.
+    EyeTrack,
+    /// `*_events.tsv`.
+    Events,
+    /// JSON sidecar.
+    Sidecar,
+    Other,
+}
+
+/// One file of the indexed dataset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BidsFile {
+    pub path: PathBuf,
+    pub entities: BidsEntities,
+    pub suffix: String,
+    /// Everything after the first dot, e.g. `.tsv.gz`.
+    pub extension: String,
+    /// Datatype folder (`beh`, `eeg`, `func`, ...) when the file sits in one.
+    pub datatype: Option<String>,
+}
+
+impl BidsFile {
+    fn parse(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (stem, extension) = match name.find('.') {
+            Some(idx) => (&name[..idx], name[idx..].to_string()),
+            None => (name, String::new()),
+        };
+        let mut entities = BidsEntities::default();
+        let mut suffix = None;
+        for part in stem.split('_') {
+            match part.split_once('-') {
+                Some((key, value)) if suffix.is_none() => entities.set(key, value),
+                Some(_) => return None,
+                None => suffix = Some(part.to_string()),
+            }
+        }
+        let datatype = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|p| p.to_str())
+            .filter(|dir| !dir.starts_with("sub-") && !dir.starts_with("ses-"))
+            .map(str::to_string);
+        Some(Self {
+            path: path.to_path_buf(),
+            entities,
+            suffix: suffix?,
+            extension,
+            datatype,
+        })
+    }
+
+    pub fn kind(&self) -> BidsKind {
+        let ext = self.extension.to_ascii_lowercase();
+        match self.suffix.as_str() {
+            _ if ext == ".json" => BidsKind::Sidecar,
+            "physio"
+                if self
+                    .entities
+                    .recording
+                    .as_deref()
+                    .is_some_and(|r| r.starts_with("eye")) =>
+            {
+                BidsKind::EyeTrack
+            }
+            "physio" if ext == ".tsv.gz" || ext == ".tsv" => BidsKind::Physio,
+            "eeg" if ext == ".edf" || ext == ".bdf" => BidsKind::Eeg,
+            "eyetrack" => BidsKind::EyeTrack,
+            "events" if ext == ".tsv" => BidsKind::Events,
+            _ => BidsKind::Other,
+        }
+    }
+
+    /// Filename stem without suffix, e.g. `sub-01_ses-1_task-rest`.
+    pub fn entity_stem(&self) -> String {
+        self.entities
+            .pairs()
+            .iter()
+            .map(|(k, v)| format!("{k}-{v}"))
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+}
+
+/// Filter for [`BidsLayout::query`]; unset fields match anything.
+#[derive(Debug, Clone, Default)]
+pub struct BidsQuery {
+    pub subject: Option<String>,
+    pub session: Option<String>,
+    pub task: Option<String>,
+    pub run: Option<String>,
+    pub acquisition: Option<String>,
+    pub kind: Option<BidsKind>,
+    pub suffix: Option<String>,
+}
+
+impl BidsQuery {
+    fn matches(&self, file: &BidsFile) -> bool {
+        let eq = |want: &Option<String>, have: &Option<String>| {
+            want.as_ref().is_none_or(|want| have.as_ref() == Some(want))
+        };
+        let e = &file.entities;
+        eq(&self.subject, &e.sub)
+            && eq(&self.session, &e.ses)
+            && eq(&self.task, &e.task)
+            && eq(&self.run, &e.run)
+            && eq(&self.acquisition, &e.acq)
+            && self.kind.is_none_or(|kind| file.kind() == kind)
+            && self
+                .suffix
+                .as_ref()
+                .is_none_or(|suffix| &file.suffix == suffix)
+    }
+}
+
+/// `*_physio.json` fields needed to decode the headerless TSV.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PhysioSidecar {
+    pub sampling_frequency: f64,
+    #[serde(default)]
+    pub start_time: f64,
+    pub columns: Vec<String>,
+}
+
+/// In-memory index of a BIDS dataset (Gorgolewski et al. 2016, doi:10.1038/sdata.2016.44).
+#[derive(Debug, Clone)]
+pub struct BidsLayout {
+    root: PathBuf,
+    files: Vec<BidsFile>,
+}
+
+impl BidsLayout {
+    /// Walk `root`, skipping hidden folders and `derivatives`/`sourcedata`/`code`.
+    pub fn index(root: &Path) -> Result<Self> {
+        if !root.join("dataset_description.json").exists() {
+            bail!(
+                "{} is not a BIDS dataset (no dataset_description.json)",
+                root.display()
+            );
+        }
+        let mut files = Vec::new();
+        let mut stack = vec![root.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            let entries =
+                fs::read_dir(&dir).with_context(|| format!("failed to list {}", dir.display()))?;
+            for entry in entries {
+                let path = entry?.path();
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                if name.starts_with('.') {
+                    continue;
+                }
+                if path.is_dir() {
+                    if dir == root && SKIPPED_DIRS.contains(&name) {
+                        continue;
+                    }
+                    stack.push(path);
+                } else if let Some(file) = BidsFile::parse(&path) {
+                    files.push(file);
+                }
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self {
+            root: root.to_path_buf(),
+            files,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn files(&self) -> &[BidsFile] {
+        &self.files
+    }
+
+    pub fn query(&self, query: &BidsQuery) -> Vec<&BidsFile> {
+        self.files.iter().filter(|f| query.matches(f)).collect()
+    }
+
+    pub fn subjects(&self) -> Vec<String> {
+        self.unique(|e| e.sub.as_ref())
+    }
+
+    pub fn sessions(&self) -> Vec<String> {
+        self.unique(|e| e.ses.as_ref())
+    }
+
+    pub fn tasks(&self) -> Vec<String> {
+        self.unique(|e| e.task.as_ref())
+    }
+
+    fn unique(&self, field: impl Fn(&BidsEntities) -> Option<&String>) -> Vec<String> {
+        let mut values: Vec<String> = self
+            .files
+            .iter()
+            .filter_map(|f| field(&f.entities).cloned())
+            .collect();
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    /// Nearest JSON sidecar following the inheritance principle: same suffix, entities a
+    /// subset of the file's, in the file's folder or an ancestor; deeper and more specific wins.
+    pub fn sidecar(&self, file: &BidsFile) -> Option<&BidsFile> {
+        let dir = file.path.parent()?;
+        self.files
+            .iter()
+            .filter(|c| c.kind() == BidsKind::Sidecar && c.suffix == file.suffix)
+            .filter(|c| c.entities.is_subset_of(&file.entities))
+            .filter(|c| c.path.parent().is_some_and(|p| dir.starts_with(p)))
+            .max_by_key(|c| (c.path.components().count(), c.entities.pairs().len()))
+    }
+
+    /// `*_events.tsv` of the same acquisition; events files omit `recording`, so the most
+    /// specific events file whose entities are a subset of `file`'s wins.
+    pub fn events_for(&self, file: &BidsFile) -> Option<&BidsFile> {
+        self.files
+            .iter()
+            .filter(|c| c.kind() == BidsKind::Events)
+            .filter(|c| c.entities.is_subset_of(&file.entities))
+            .max_by_key(|c| c.entities.pairs().len())
+    }
+
+    pub fn physio_sidecar(&self, file: &BidsFile) -> Result<PhysioSidecar> {
+        let sidecar = self
+            .sidecar(file)
+            .ok_or_else(|| anyhow!("no JSON sidecar for {}", file.path.display()))?;
+        let text = fs::read_to_string(&sidecar.path)
+            .with_context(|| format!("failed to read {}", sidecar.path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("invalid physio sidecar {}", sidecar.path.display()))
+    }
+
+    /// Load one column of a `*_physio.tsv.gz` file using its sidecar.
+    pub fn read_physio(
+        &self,
+        file: &BidsFile,
+        column: &str,
+    ) -> Result<(PhysioSidecar, TimeSeries)> {
+        let sidecar = self.physio_sidecar(file)?;
+        let idx = sidecar
+            .columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(column))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} has no {column} column (columns: {})",
+                    file.path.display(),
+                    sidecar.columns.join(", ")
+                )
+            })?;
+        let data = read_physio_column(&file.path, idx)?;
+        let ts = TimeSeries {
+            fs: sidecar.sampling_frequency,
+            data,
+        };
+        Ok((sidecar, ts))
+    }
+}
+
+/// Read column `idx` of a headerless, tab-separated physio file (gzip or plain).
+pub fn read_physio_column(path: &Path, idx: usize) -> Result<Vec<f64>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut out = Vec::new();
+    for (line_no, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = line
+            .split('\t')
+            .nth(idx)
+            .ok_or_else(|| anyhow!("line {} has no column {idx}", line_no + 1))?
+            .trim();
+        out.push(if value == "n/a" {
+            f64::NAN
+        } else {
+            value
+                .parse()
+                .with_context(|| format!("line {}: invalid value {value:?}", line_no + 1))?
+        });
+    }
+    Ok(out)
+}
+
+/// Writes elf outputs under `<dataset>/derivatives/elf/`, mirroring the source layout.
+pub struct DerivativesWriter {
+    root: PathBuf,
+    dataset_root: PathBuf,
+}
+
+impl DerivativesWriter {
+    /// Create `derivatives/elf/` with its `dataset_description.json`.
+    pub fn create(dataset_root: &Path, version: &str) -> Result<Self> {
+        let root = dataset_root.join("derivatives").join("elf");
+        fs::create_dir_all(&root)
+            .with_context(|| format!("failed to create {}", root.display()))?;
+        let description = serde_json::json!({
+            "Name": "elf derivatives",
+            "BIDSVersion": "1.9.0",
+            "DatasetType": "derivative",
+            "GeneratedBy": [{
+                "Name": "elf",
+                "Version": version,
+                "CodeURL": "https://github.com/monistowl/elf",
+            }],
+        });
+        fs::write(
+            root.join("dataset_description.json"),
+            serde_json::to_string_pretty(&description)?,
+        )?;
+        Ok(Self {
+            root,
+            dataset_root: dataset_root.to_path_buf(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Derivative path for `source`: same folders, entities plus `desc-<desc>`, new suffix.
+    pub fn path_for(
+        &self,
+        source: &BidsFile,
+        desc: &str,
+        suffix: &str,
+        extension: &str,
+    ) -> PathBuf {
+        let relative_dir = source
+            .path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&self.dataset_root).ok())
+            .unwrap_or_else(|| Path::new(""));
+        self.root.join(relative_dir).join(format!(
+            "{}_desc-{desc}_{suffix}{extension}",
+            source.entity_stem()
+        ))
+    }
+
+    pub fn write_json<T: Serialize>(
+        &self,
+        source: &BidsFile,
+        desc: &str,
+        suffix: &str,
+        value: &T,
+    ) -> Result<PathBuf> {
+        let path = self.path_for(source, desc, suffix, ".json");
+        self.write(&path, serde_json::to_string_pretty(value)?)?;
+        Ok(path)
+    }
+
+    /// Write a TSV with a header row next to the source's derivatives.
+    pub fn write_tsv(
+        &self,
+        source: &BidsFile,
+        desc: &str,
+        suffix: &str,
+        header: &[&str],
+        rows: &[Vec<String>],
+    ) -> Result<PathBuf> {
+        let path = self.path_for(source, desc, suffix, ".tsv");
+        self.write(&path, tsv_text(header, rows))?;
+        Ok(path)
+    }
+
+    /// Write a study-level TSV (e.g. a group summary) at the derivatives root.
+    pub fn write_summary_tsv(
+        &self,
+        file_name: &str,
+        header: &[&str],
+        rows: &[Vec<String>],
+    ) -> Result<PathBuf> {
+        let path = self.root.join(file_name);
+        self.write(&path, tsv_text(header, rows))?;
+        Ok(path)
+    }
+
+    fn write(&self, path: &Path, contents: String) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+fn tsv_text(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut text = header.join("\t") + "\n";
+    for row in rows {
+        text.push_str(&row.join("\t"));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn study() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace root")
+            .join("test_data/bids_study")
+    }
+
+    #[test]
+    fn parses_entities_and_kinds() {
+        let file = BidsFile::parse(Path::new(
+            "sub-01/ses-2/eeg/sub-01_ses-2_task-nback_run-3_eeg.edf",
+        ))
+        .unwrap();
+        assert_eq!(file.entities.sub.as_deref(), Some("01"));
+        assert_eq!(file.entities.run.as_deref(), Some("3"));
+        assert_eq!(file.datatype.as_deref(), Some("eeg"));
+        assert_eq!(file.kind(), BidsKind::Eeg);
+        assert_eq!(file.entity_stem(), "sub-01_ses-2_task-nback_run-3");
+        let eye = BidsFile::parse(Path::new("sub-01_task-x_recording-eye1_physio.tsv.gz")).unwrap();
+        assert_eq!(eye.kind(), BidsKind::EyeTrack);
+        assert!(BidsFile::parse(Path::new("README")).is_some_and(|f| f.kind() == BidsKind::Other));
+    }
+
+    #[test]
+    fn indexes_study_and_reads_physio() {
+        let layout = BidsLayout::index(&study()).unwrap();
+        assert_eq!(layout.subjects(), vec!["01", "02"]);
+        assert_eq!(layout.tasks(), vec!["rest"]);
+        let physio = layout.query(&BidsQuery {
+            kind: Some(BidsKind::Physio),
+            ..BidsQuery::default()
+        });
+        assert_eq!(physio.len(), 2);
+
+        // sub-01 has its own sidecar; sub-02 inherits the top-level one.
+        let (sidecar, ts) = layout.read_physio(physio[0], "cardiac").unwrap();
+        assert_eq!(sidecar.start_time, 0.0);
+        assert_eq!(ts.fs, 360.0);
+        assert_eq!(ts.len(), 360 * 30);
+        let (sidecar, _) = layout.read_physio(physio[1], "cardiac").unwrap();
+        assert_eq!(sidecar.start_time, -0.5);
+        let events = layout.events_for(physio[1]).unwrap();
+        assert!(events.path.ends_with("sub-02_task-rest_events.tsv"));
+
+        let only_02 = layout.query(&BidsQuery {
+            subject: Some("02".into()),
+            kind: Some(BidsKind::Physio),
+            ..BidsQuery::default()
+        });
+        assert_eq!(only_02.len(), 1);
+    }
+
+    #[test]
+    fn derivatives_mirror_source_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("dataset_description.json"), "{}").unwrap();
+        let source =
+            BidsFile::parse(&dir.path().join("sub-01/beh/sub-01_task-rest_physio.tsv.gz")).unwrap();
+        let writer = DerivativesWriter::create(dir.path(), "0.0.0").unwrap();
+        let path = writer
+            .write_json(&source, "hrv", "physio", &serde_json::json!({"sdnn": 42.0}))
+            .unwrap();
+        assert_eq!(
+            path,
+            dir.path()
+                .join("derivatives/elf/sub-01/beh/sub-01_task-rest_desc-hrv_physio.json")
+        );
+        let description: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(writer.root().join("dataset_description.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(description["DatasetType"], "derivative");
+    }
+}
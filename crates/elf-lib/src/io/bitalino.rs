@@ -1,11 +1,24 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use csv::ReaderBuilder;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 
 use crate::signal::TimeSeries;
 
-/// Load a BITalino/OpenSignals CSV and return a TimeSeries built from one analog column.
+/// First line of an OpenSignals text export.
+pub const OPENSIGNALS_MAGIC: &str = "# OpenSignals Text File Format";
+
+/// Load one channel of a BITalino/biosignalsplux recording.
+///
+/// OpenSignals text exports are detected from their header and `signal` may then be a column
+/// (`A2`), channel label or sensor type (`ECG`); known sensors come back in physical units.
+/// Anything else is read as a CSV with a `timestamp` column and a named analog column.
 pub fn read_bitalino_csv(path: &Path, signal: &str) -> Result<TimeSeries> {
+    if is_opensignals(path)? {
+        return read_opensignals(path)?.time_series(signal);
+    }
     let mut reader = ReaderBuilder::new()
         .has_headers(true)
         .from_path(path)
@@ -22,7 +35,7 @@ pub fn read_bitalino_csv(path: &Path, signal: &str) -> Result<TimeSeries> {
     let mut values = Vec::new();
     let mut last_ts = None;
     let mut fs = 0.0;
-    for record in reader.records() {
+    for (row, record) in reader.records().enumerate() {
         let record = record.context("reading record")?;
         let timestamp_str = record
             .get(timestamp_idx)
@@ -33,7 +46,9 @@ pub fn read_bitalino_csv(path: &Path, signal: &str) -> Result<TimeSeries> {
         let value_str = record
             .get(column)
             .ok_or_else(|| anyhow::anyhow!("missing signal column"))?;
-        let value = value_str.parse::<f64>().context("parsing signal value")?;
+        let value = value_str
+            .parse::<f64>()
+            .with_context(|| format!("parsing signal value {value_str:?} on line {}", row + 2))?;
         if let Some(prev) = last_ts {
             if fs == 0.0 {
                 fs = 1.0 / (ts - prev);
@@ -48,6 +63,309 @@ pub fn read_bitalino_csv(path: &Path, signal: &str) -> Result<TimeSeries> {
     Ok(TimeSeries { fs, data: values })
 }
 
+/// True when `path` starts with the OpenSignals text header.
+pub fn is_opensignals(path: &Path) -> Result<bool> {
+    let mut head = [0u8; 32];
+    let n = File::open(path)
+        .with_context(|| format!("opening {}", path.display()))?
+        .read(&mut head)?;
+    Ok(head[..n].starts_with(OPENSIGNALS_MAGIC.as_bytes()))
+}
+
+/// Acquisition hardware, which fixes the supply voltage and amplifier gains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluxDevice {
+    Bitalino,
+    BiosignalsPlux,
+}
+
+impl PluxDevice {
+    fn from_name(name: &str) -> Self {
+        if name.to_ascii_lowercase().contains("plux") {
+            Self::BiosignalsPlux
+        } else {
+            Self::Bitalino
+        }
+    }
+
+    fn vcc(self) -> f64 {
+        match self {
+            Self::Bitalino => 3.3,
+            Self::BiosignalsPlux => 3.0,
+        }
+    }
+}
+
+/// Sensor transfer function from the PLUX/BITalino datasheets: converts an `resolution`-bit
+/// ADC value into physical units. Returns `None` for sensors without a fixed transfer
+/// function (e.g. `RAW`, accelerometers that need calibration).
+pub fn adc_to_physical(
+    sensor: &str,
+    device: PluxDevice,
+    resolution: u32,
+    adc: f64,
+) -> Option<(f64, &'static str)> {
+    let fraction = adc / 2f64.powi(resolution as i32);
+    let vcc = device.vcc();
+    let sensor = sensor.to_ascii_uppercase();
+    let (gain_ecg, gain_emg, gain_eeg, eda_divisor) = match device {
+        PluxDevice::Bitalino => (1100.0, 1009.0, 41782.0, 0.132),
+        PluxDevice::BiosignalsPlux => (1019.0, 1000.0, 40000.0, 0.12),
+    };
+    let bipolar = |gain: f64| (fraction - 0.5) * vcc / gain;
+    match sensor.as_str() {
+        s if s.starts_with("ECG") => Some((bipolar(gain_ecg) * 1e3, "mV")),
+        s if s.starts_with("EMG") => Some((bipolar(gain_emg) * 1e3, "mV")),
+        s if s.starts_with("EEG") => Some((bipolar(gain_eeg) * 1e6, "uV")),
+        s if s.starts_with("EDA") => Some((fraction * vcc / eda_divisor, "uS")),
+        s if s.starts_with("RESP") || s.starts_with("PZT") => Some(((fraction - 0.5) * 100.0, "%")),
+        _ => None,
+    }
+}
+
+/// One analog channel of an OpenSignals export.
+#[derive(Debug, Clone)]
+pub struct OpenSignalsChannel {
+    /// Column name in the export (`A1`..`A6` for BITalino, `CH1`.. for biosignalsplux).
+    pub column: String,
+    pub label: String,
+    pub sensor: String,
+    pub resolution: u32,
+    /// Physical units when the sensor has a transfer function, otherwise `None` (raw ADC).
+    pub units: Option<&'static str>,
+    pub data: Vec<f64>,
+}
+
+/// One device block of an OpenSignals export.
+#[derive(Debug, Clone)]
+pub struct OpenSignalsDevice {
+    /// Device MAC address (the header key).
+    pub address: String,
+    pub device: PluxDevice,
+    pub fs: f64,
+    /// `date` and `time` header fields joined, as written by OpenSignals.
+    pub start: Option<String>,
+    pub channels: Vec<OpenSignalsChannel>,
+    /// Digital inputs/outputs (`I1`, `O1`, `DI`, ...).
+    pub digital: Vec<(String, Vec<f64>)>,
+    /// Samples missing from the `nSeq` counter; they are NaN in every channel.
+    pub dropped_samples: usize,
+}
+
+/// A parsed OpenSignals text export (one or more synchronised devices).
+#[derive(Debug, Clone)]
+pub struct OpenSignalsRecording {
+    pub devices: Vec<OpenSignalsDevice>,
+}
+
+impl OpenSignalsRecording {
+    /// Find a channel by column (`A2`), label or sensor type (`ECG`), case-insensitively.
+    /// `analogN` addresses the N-th (zero-based) analog channel, as in plain BITalino CSVs.
+    pub fn channel(&self, selector: &str) -> Option<(&OpenSignalsDevice, &OpenSignalsChannel)> {
+        let all = || {
+            self.devices
+                .iter()
+                .flat_map(|d| d.channels.iter().map(move |c| (d, c)))
+        };
+        let by = |f: &dyn Fn(&OpenSignalsChannel) -> &str| {
+            all().find(|(_, c)| f(c).eq_ignore_ascii_case(selector))
+        };
+        by(&|c| c.column.as_str())
+            .or_else(|| by(&|c| c.label.as_str()))
+            .or_else(|| by(&|c| c.sensor.as_str()))
+            .or_else(|| {
+                let lower = selector.to_ascii_lowercase();
+                let n: usize = lower.strip_prefix("analog")?.parse().ok()?;
+                all().nth(n)
+            })
+    }
+
+    pub fn time_series(&self, selector: &str) -> Result<TimeSeries> {
+        let (device, channel) = self.channel(selector).ok_or_else(|| {
+            let names: Vec<String> = self
+                .devices
+                .iter()
+                .flat_map(|d| &d.channels)
+                .map(|c| format!("{} ({})", c.column, c.sensor))
+                .collect();
+            anyhow!(
+                "missing signal column '{}' (available: {})",
+                selector,
+                names.join(", ")
+            )
+        })?;
+        Ok(TimeSeries {
+            fs: device.fs,
+            data: channel.data.clone(),
+        })
+    }
+}
+
+/// Parse an OpenSignals text export: `#`-prefixed JSON header, `# EndOfHeader`, then
+/// whitespace-separated integer rows with the devices' columns side by side.
+pub fn read_opensignals(path: &Path) -> Result<OpenSignalsRecording> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut header: Option<Value> = None;
+    let mut rows: Vec<Vec<f64>> = Vec::new();
+    let mut in_header = true;
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if in_header {
+            if let Some(rest) = trimmed.strip_prefix('#') {
+                let rest = rest.trim();
+                if rest.starts_with('{') {
+                    header = Some(
+                        serde_json::from_str(rest).context("parsing OpenSignals JSON header")?,
+                    );
+                } else if rest.eq_ignore_ascii_case("EndOfHeader") {
+                    in_header = false;
+                }
+                continue;
+            }
+            in_header = false;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+        let row = trimmed
+            .split_whitespace()
+            .map(|v| v.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("line {} is not numeric", idx + 1))?;
+        rows.push(row);
+    }
+    let header = header.ok_or_else(|| anyhow!("missing OpenSignals JSON header"))?;
+    let blocks = header
+        .as_object()
+        .ok_or_else(|| anyhow!("OpenSignals header is not an object"))?;
+
+    let mut specs: Vec<(&String, &Value)> = blocks.iter().collect();
+    specs.sort_by_key(|(_, v)| v["position"].as_u64().unwrap_or(0));
+    let mut devices = Vec::new();
+    let mut offset = 0;
+    for (address, spec) in specs {
+        let columns = string_list(&spec["column"]);
+        if columns.is_empty() {
+            bail!("device {} has no column list", address);
+        }
+        let fs = spec["sampling rate"]
+            .as_f64()
+            .ok_or_else(|| anyhow!("device {} has no sampling rate", address))?;
+        let device = PluxDevice::from_name(spec["device"].as_str().unwrap_or(""));
+        let labels = string_list(&spec["label"]);
+        let sensors = string_list(&spec["sensor"]);
+        let resolutions: Vec<u32> = spec["resolution"]
+            .as_array()
+            .map(|v| {
+                v.iter()
+                    .filter_map(|r| r.as_u64())
+                    .map(|r| r as u32)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if labels.len() > columns.len() {
+            bail!("device {} lists more labels than columns", address);
+        }
+        let n_analog = labels.len();
+        let first_analog = columns.len() - n_analog;
+
+        let cells = |row: &Vec<f64>, col: usize| row.get(offset + col).copied();
+        let seq_col = columns.iter().position(|c| c.eq_ignore_ascii_case("nSeq"));
+        let (slots, dropped_samples) = sample_slots(
+            &rows
+                .iter()
+                .map(|row| seq_col.and_then(|c| cells(row, c)).unwrap_or(f64::NAN))
+                .collect::<Vec<_>>(),
+        );
+        let column_data = |col: usize, convert: &dyn Fn(f64) -> f64| {
+            let mut data = vec![f64::NAN; slots.last().map_or(0, |s| s + 1)];
+            for (row, slot) in rows.iter().zip(&slots) {
+                if let Some(v) = cells(row, col) {
+                    data[*slot] = convert(v);
+                }
+            }
+            data
+        };
+
+        let channels = (0..n_analog)
+            .map(|i| {
+                let sensor = sensors.get(i).cloned().unwrap_or_else(|| "RAW".into());
+                let resolution = resolutions.get(i).copied().unwrap_or(16);
+                let units = adc_to_physical(&sensor, device, resolution, 0.0).map(|(_, u)| u);
+                let data = column_data(first_analog + i, &|adc| {
+                    adc_to_physical(&sensor, device, resolution, adc).map_or(adc, |(v, _)| v)
+                });
+                OpenSignalsChannel {
+                    column: columns[first_analog + i].clone(),
+                    label: labels[i].clone(),
+                    sensor,
+                    resolution,
+                    units,
+                    data,
+                }
+            })
+            .collect();
+        let digital = (0..first_analog)
+            .filter(|c| Some(*c) != seq_col)
+            .map(|c| (columns[c].clone(), column_data(c, &|v| v)))
+            .collect();
+        let start = match (spec["date"].as_str(), spec["time"].as_str()) {
+            (Some(date), Some(time)) => Some(format!("{date} {time}")),
+            _ => None,
+        };
+        devices.push(OpenSignalsDevice {
+            address: address.clone(),
+            device,
+            fs,
+            start,
+            channels,
+            digital,
+            dropped_samples,
+        });
+        offset += columns.len();
+    }
+    Ok(OpenSignalsRecording { devices })
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|v| {
+            v.iter()
+                .map(|s| match s {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Output position of every row given its wrapping sequence counter (NaN when absent).
+/// The counter period is the smallest power of two (at least 16) above the largest value;
+/// jumps of more than one step leave NaN-filled gaps and are counted as dropped samples.
+fn sample_slots(seq: &[f64]) -> (Vec<usize>, usize) {
+    if seq.iter().any(|s| !s.is_finite() || *s < 0.0) {
+        return ((0..seq.len()).collect(), 0);
+    }
+    let max = seq.iter().fold(0.0f64, |m, s| m.max(*s)) as u64;
+    let period = (max + 1).next_power_of_two().max(16);
+    let mut slots = Vec::with_capacity(seq.len());
+    let mut dropped = 0;
+    let mut slot = 0usize;
+    for (i, s) in seq.iter().enumerate() {
+        if i > 0 {
+            let step = (*s as u64 + period - seq[i - 1] as u64) % period;
+            let step = if step == 0 { period } else { step };
+            dropped += step as usize - 1;
+            slot += step as usize;
+        }
+        slots.push(slot);
+    }
+    (slots, dropped)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,6 +380,39 @@ mod tests {
         assert!((ts.data[0] - 1.2).abs() < 1e-6);
     }
 
+    #[test]
+    fn parses_opensignals_export_in_physical_units() {
+        let path = sample_path("test_data/opensignals_sample.txt");
+        let rec = read_opensignals(&path).expect("read export");
+        let device = &rec.devices[0];
+        assert_eq!(device.device, PluxDevice::Bitalino);
+        assert_eq!(device.fs, 1000.0);
+        assert_eq!(device.dropped_samples, 1);
+        assert_eq!(device.digital.len(), 4);
+        let (_, ecg) = rec.channel("ecg").unwrap();
+        assert_eq!(ecg.units, Some("mV"));
+        assert_eq!(ecg.data.len(), 21);
+        assert!(ecg.data[12].is_nan());
+        // 512/1024 is the ECG baseline: (0.5 - 0.5) * VCC / G = 0 mV.
+        assert!(ecg.data[0].abs() < 1e-12);
+        let eda = read_bitalino_csv(&path, "A3").unwrap();
+        assert!((eda.data[0] - 300.0 / 1024.0 * 3.3 / 0.132).abs() < 1e-9);
+        assert_eq!(
+            read_bitalino_csv(&path, "analog0").unwrap().data[5],
+            ecg.data[5]
+        );
+    }
+
+    #[test]
+    fn transfer_functions_match_datasheets() {
+        let (ecg, _) = adc_to_physical("ECG", PluxDevice::Bitalino, 10, 1023.0).unwrap();
+        assert!((ecg - (1023.0 / 1024.0 - 0.5) * 3.3 / 1100.0 * 1e3).abs() < 1e-12);
+        let (emg, units) = adc_to_physical("EMG", PluxDevice::BiosignalsPlux, 16, 0.0).unwrap();
+        assert!((emg + 1.5).abs() < 1e-12);
+        assert_eq!(units, "mV");
+        assert!(adc_to_physical("RAW", PluxDevice::Bitalino, 10, 5.0).is_none());
+    }
+
     fn sample_path(relative: &str) -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .parent()
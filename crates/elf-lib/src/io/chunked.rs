@@ -0,0 +1,627 @@
+use super::{edf, loader, wfdb};
+use crate::signal::TimeSeries;
+use anyhow::{anyhow, Context, Result};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Samples of one block with the index of its first sample in the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleBlock {
+    pub start: usize,
+    pub data: Vec<f64>,
+}
+
+/// One channel read block by block, so long recordings never have to fit in memory.
+pub trait ChunkedReader {
+    fn fs(&self) -> f64;
+
+    /// Total sample count when the format records it up front.
+    fn len_hint(&self) -> Option<usize>;
+
+    /// Next block of at most `max_samples` samples, `None` once the recording is exhausted.
+    fn next_block(&mut self, max_samples: usize) -> Result<Option<Vec<f64>>>;
+
+    /// Iterator over the remaining blocks.
+    fn blocks(self, block_size: usize) -> Blocks<Self>
+    where
+        Self: Sized,
+    {
+        Blocks {
+            reader: self,
+            block_size: block_size.max(1),
+            position: 0,
+        }
+    }
+}
+
+impl<R: ChunkedReader + ?Sized> ChunkedReader for Box<R> {
+    fn fs(&self) -> f64 {
+        (**self).fs()
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        (**self).len_hint()
+    }
+
+    fn next_block(&mut self, max_samples: usize) -> Result<Option<Vec<f64>>> {
+        (**self).next_block(max_samples)
+    }
+}
+
+pub struct Blocks<R> {
+    reader: R,
+    block_size: usize,
+    position: usize,
+}
+
+impl<R> Blocks<R> {
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: ChunkedReader> Iterator for Blocks<R> {
+    type Item = Result<SampleBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.next_block(self.block_size) {
+            Ok(Some(data)) => {
+                let start = self.position;
+                self.position += data.len();
+                Some(Ok(SampleBlock { start, data }))
+            }
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Drains a reader into a [`TimeSeries`]; mainly for tests and short files.
+pub fn read_all(reader: &mut dyn ChunkedReader, block_size: usize) -> Result<TimeSeries> {
+    let mut data = Vec::with_capacity(reader.len_hint().unwrap_or(0));
+    while let Some(block) = reader.next_block(block_size.max(1))? {
+        data.extend(block);
+    }
+    Ok(TimeSeries {
+        fs: reader.fs(),
+        data,
+    })
+}
+
+/// Serves an in-memory series block by block.
+pub struct MemoryReader {
+    ts: TimeSeries,
+    position: usize,
+}
+
+impl MemoryReader {
+    pub fn new(ts: TimeSeries) -> Self {
+        Self { ts, position: 0 }
+    }
+}
+
+impl ChunkedReader for MemoryReader {
+    fn fs(&self) -> f64 {
+        self.ts.fs
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        Some(self.ts.len())
+    }
+
+    fn next_block(&mut self, max_samples: usize) -> Result<Option<Vec<f64>>> {
+        if self.position >= self.ts.len() {
+            return Ok(None);
+        }
+        let end = (self.position + max_samples.max(1)).min(self.ts.len());
+        let block = self.ts.data[self.position..end].to_vec();
+        self.position = end;
+        Ok(Some(block))
+    }
+}
+
+/// One value per line, as read by [`super::text::read_f64_series`], streamed line by line.
+pub struct TextReader {
+    lines: std::io::Lines<BufReader<Box<dyn Read>>>,
+    fs: f64,
+    line_no: usize,
+}
+
+impl TextReader {
+    pub fn open(path: &Path, fs: f64) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Ok(Self::from_reader(Box::new(file), fs))
+    }
+
+    pub fn from_reader(reader: Box<dyn Read>, fs: f64) -> Self {
+        Self {
+            lines: BufReader::new(reader).lines(),
+            fs,
+            line_no: 0,
+        }
+    }
+}
+
+impl ChunkedReader for TextReader {
+    fn fs(&self) -> f64 {
+        self.fs
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        None
+    }
+
+    fn next_block(&mut self, max_samples: usize) -> Result<Option<Vec<f64>>> {
+        let mut block = Vec::with_capacity(max_samples.clamp(1, 1 << 16));
+        while block.len() < max_samples.max(1) {
+            let Some(line) = self.lines.next() else { break };
+            self.line_no += 1;
+            let line = line?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let value: f64 = trimmed
+                .parse()
+                .with_context(|| format!("line {} is not f64: {}", self.line_no, trimmed))?;
+            block.push(value);
+        }
+        Ok((!block.is_empty()).then_some(block))
+    }
+}
+
+/// One data signal of an EDF/EDF+/BDF file, read record by record.
+pub struct EdfChunkReader {
+    reader: edf::EdfReader,
+    signal: usize,
+    fs: f64,
+    total: usize,
+    position: usize,
+}
+
+impl EdfChunkReader {
+    /// `channel` counts data signals only, like [`edf::load_edf_channel`].
+    pub fn open(path: &Path, channel: usize) -> Result<Self> {
+        let reader = edf::EdfReader::open(path)?;
+        let signals = reader.data_signals();
+        let signal = *signals.get(channel).ok_or_else(|| {
+            anyhow!(
+                "EDF file has {} channels; channel {} is out of range",
+                signals.len(),
+                channel
+            )
+        })?;
+        Ok(Self::with_signal(reader, signal))
+    }
+
+    /// Data signal whose label contains `label` (case-insensitive).
+    pub fn open_label(path: &Path, label: &str) -> Result<Self> {
+        let reader = edf::EdfReader::open(path)?;
+        let needle = label.to_ascii_lowercase();
+        let signal = reader
+            .data_signals()
+            .into_iter()
+            .find(|&s| {
+                reader.header().signals[s]
+                    .label
+                    .to_ascii_lowercase()
+                    .contains(&needle)
+            })
+            .ok_or_else(|| anyhow!("EDF file has no channel labelled {label}"))?;
+        Ok(Self::with_signal(reader, signal))
+    }
+
+    fn with_signal(reader: edf::EdfReader, signal: usize) -> Self {
+        let fs = reader.header().fs(signal);
+        let total = (reader.duration_s() * fs).round() as usize;
+        Self {
+            reader,
+            signal,
+            fs,
+            total,
+            position: 0,
+        }
+    }
+}
+
+impl ChunkedReader for EdfChunkReader {
+    fn fs(&self) -> f64 {
+        self.fs
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        Some(self.total)
+    }
+
+    fn next_block(&mut self, max_samples: usize) -> Result<Option<Vec<f64>>> {
+        if self.position >= self.total {
+            return Ok(None);
+        }
+        let end = (self.position + max_samples.max(1)).min(self.total);
+        let mut block = self
+            .reader
+            .read_range(
+                self.signal,
+                self.position as f64 / self.fs,
+                end as f64 / self.fs,
+            )?
+            .data;
+        block.resize(end - self.position, f64::NAN);
+        self.position = end;
+        Ok(Some(block))
+    }
+}
+
+/// One lead of a WFDB record, decoded a block of frames at a time.
+///
+/// Multi-segment records are walked segment by segment; in variable-layout records the lead is
+/// matched by description and segments without it (and `~` gaps) read as NaN.
+pub struct WfdbChunkReader {
+    dir: PathBuf,
+    fs: f64,
+    total: Option<usize>,
+    /// Frame rate of the record; segment lengths count frames.
+    frame_fs: f64,
+    description: String,
+    lead: usize,
+    by_description: bool,
+    segments: VecDeque<wfdb::WfdbSegment>,
+    current: Option<SegmentReader>,
+}
+
+/// Remaining samples of one segment: decoded from a signal file or padded with NaN.
+enum SegmentReader {
+    File(LeadFile),
+    Gap(usize),
+}
+
+impl WfdbChunkReader {
+    pub fn open(header_path: &Path, lead: usize) -> Result<Self> {
+        let header_path = match header_path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("hea") => header_path.to_path_buf(),
+            _ => header_path.with_extension("hea"),
+        };
+        let header = wfdb::read_wfdb_header(&header_path)?;
+        let dir = header_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf();
+        if header.segments.is_empty() {
+            let file = LeadFile::open(&header, &dir, lead)?;
+            let fs = header.fs * header.signals[lead].samples_per_frame as f64;
+            return Ok(Self {
+                dir,
+                fs,
+                total: Some(file.remaining()),
+                frame_fs: header.fs,
+                description: header.signals[lead].description.clone(),
+                lead,
+                by_description: false,
+                segments: VecDeque::new(),
+                current: Some(SegmentReader::File(file)),
+            });
+        }
+
+        let mut segments: VecDeque<_> = header.segments.iter().cloned().collect();
+        let layout = match segments.front() {
+            Some(first) if first.n_samples == 0 => {
+                let first = segments.pop_front().expect("front segment");
+                Some(wfdb::read_wfdb_header(&dir.join(&first.name))?)
+            }
+            _ => None,
+        };
+        let spec = match &layout {
+            Some(layout) => layout.signals.get(lead).cloned(),
+            None => segments
+                .iter()
+                .find(|s| s.name != "~")
+                .map(|s| wfdb::read_wfdb_header(&dir.join(format!("{}.hea", s.name))))
+                .transpose()?
+                .and_then(|h| h.signals.get(lead).cloned()),
+        }
+        .ok_or_else(|| anyhow!("WFDB record has no lead {lead}"))?;
+        let fs = header.fs * spec.samples_per_frame as f64;
+        let frames: usize = segments.iter().map(|s| s.n_samples).sum();
+        Ok(Self {
+            dir,
+            fs,
+            total: Some((frames as f64 * fs / header.fs).round() as usize),
+            frame_fs: header.fs,
+            description: spec.description,
+            lead,
+            by_description: layout.is_some(),
+            segments,
+            current: None,
+        })
+    }
+
+    fn next_segment(&mut self) -> Result<Option<SegmentReader>> {
+        let Some(segment) = self.segments.pop_front() else {
+            return Ok(None);
+        };
+        let len = (segment.n_samples as f64 * self.fs / self.frame_fs).round() as usize;
+        if segment.name == "~" {
+            return Ok(Some(SegmentReader::Gap(len)));
+        }
+        let header = wfdb::read_wfdb_header(&self.dir.join(format!("{}.hea", segment.name)))?;
+        let lead = if self.by_description {
+            header
+                .signals
+                .iter()
+                .position(|s| s.description == self.description)
+        } else {
+            Some(self.lead).filter(|&l| l < header.signals.len())
+        };
+        Ok(Some(match lead {
+            Some(lead) => {
+                SegmentReader::File(LeadFile::open(&header, &self.dir, lead)?.padded(len))
+            }
+            None => SegmentReader::Gap(len),
+        }))
+    }
+}
+
+impl ChunkedReader for WfdbChunkReader {
+    fn fs(&self) -> f64 {
+        self.fs
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        self.total
+    }
+
+    fn next_block(&mut self, max_samples: usize) -> Result<Option<Vec<f64>>> {
+        loop {
+            if self.current.is_none() {
+                self.current = self.next_segment()?;
+            }
+            let block = match self.current.as_mut() {
+                None => return Ok(None),
+                Some(SegmentReader::Gap(left)) => {
+                    let n = (*left).min(max_samples.max(1));
+                    *left -= n;
+                    vec![f64::NAN; n]
+                }
+                Some(SegmentReader::File(file)) => file.read(max_samples.max(1))?,
+            };
+            if block.is_empty() {
+                self.current = None;
+                continue;
+            }
+            return Ok(Some(block));
+        }
+    }
+}
+
+/// Frames of one signal file, decoded for a single lead.
+struct LeadFile {
+    file: Option<File>,
+    format: wfdb::WfdbFormat,
+    byte_offset: usize,
+    frame_len: usize,
+    /// Position of the lead's first sample within a frame.
+    offset: usize,
+    spf: usize,
+    skew: usize,
+    frames: usize,
+    next_frame: usize,
+    baseline: i32,
+    adc_gain: f64,
+    /// Samples to emit in total (segment length); `None` for the natural length.
+    target: Option<usize>,
+    emitted: usize,
+}
+
+impl LeadFile {
+    fn open(header: &wfdb::WfdbHeader, dir: &Path, lead: usize) -> Result<Self> {
+        let spec = header.signals.get(lead).ok_or_else(|| {
+            anyhow!(
+                "WFDB record contains {} signals, but lead {} was requested",
+                header.signals.len(),
+                lead
+            )
+        })?;
+        let members: Vec<usize> = (0..header.signals.len())
+            .filter(|&i| header.signals[i].file_name == spec.file_name)
+            .collect();
+        let frame_len = members
+            .iter()
+            .map(|&i| header.signals[i].samples_per_frame)
+            .sum::<usize>()
+            .max(1);
+        let offset = members
+            .iter()
+            .take_while(|&&i| i != lead)
+            .map(|&i| header.signals[i].samples_per_frame)
+            .sum();
+        let first = &header.signals[members[0]];
+        let format = wfdb::WfdbFormat::from_code(first.format)?;
+        let (file, frames) = if spec.file_name == "~" {
+            (None, header.n_samples.unwrap_or(0))
+        } else {
+            let path = dir.join(&spec.file_name);
+            let file =
+                File::open(&path).with_context(|| format!("failed to read {}", path.display()))?;
+            let bytes = file.metadata()?.len() as usize;
+            let (group_samples, group_bytes) = format.packing();
+            let available =
+                bytes.saturating_sub(first.byte_offset) / group_bytes * group_samples / frame_len;
+            let frames = header.n_samples.unwrap_or(available).min(available);
+            (Some(file), frames)
+        };
+        Ok(Self {
+            file,
+            format,
+            byte_offset: first.byte_offset,
+            frame_len,
+            offset,
+            spf: spec.samples_per_frame.max(1),
+            skew: spec.skew,
+            frames,
+            next_frame: 0,
+            baseline: spec.baseline,
+            adc_gain: spec.adc_gain,
+            target: None,
+            emitted: 0,
+        })
+    }
+
+    fn padded(mut self, len: usize) -> Self {
+        self.target = Some(len);
+        self
+    }
+
+    fn remaining(&self) -> usize {
+        self.target.unwrap_or(self.frames * self.spf) - self.emitted
+    }
+
+    fn read(&mut self, max_samples: usize) -> Result<Vec<f64>> {
+        let natural = self.frames * self.spf;
+        let target = self.target.unwrap_or(natural);
+        let want = (target - self.emitted).min(max_samples);
+        let mut out = Vec::with_capacity(want);
+        let from_file = natural.saturating_sub(self.emitted).min(want);
+        if from_file > 0 {
+            let n_frames = from_file.div_ceil(self.spf);
+            out.extend(self.decode_frames(self.next_frame, n_frames)?);
+            out.truncate(from_file);
+            self.next_frame += n_frames;
+        }
+        out.resize(want, f64::NAN);
+        self.emitted += want;
+        Ok(out)
+    }
+
+    /// Physical samples of frames `[start, start + count)`, honouring the lead's skew.
+    fn decode_frames(&mut self, start: usize, count: usize) -> Result<Vec<f64>> {
+        let invalid = self.format.invalid();
+        let first = start + self.skew;
+        let last = (first + count).min(self.frames);
+        let mut out = Vec::with_capacity(count * self.spf);
+        if let Some(file) = self.file.as_mut().filter(|_| first < last) {
+            let (group_samples, group_bytes) = self.format.packing();
+            let first_sample = first * self.frame_len;
+            let end_sample = last * self.frame_len;
+            let first_group = first_sample / group_samples;
+            let end_group = end_sample.div_ceil(group_samples);
+            let mut buf = vec![0u8; (end_group - first_group) * group_bytes];
+            file.seek(SeekFrom::Start(
+                (self.byte_offset + first_group * group_bytes) as u64,
+            ))?;
+            file.read_exact(&mut buf)
+                .context("truncated WFDB signal file")?;
+            let samples = self.format.decode(&buf);
+            let skip = first_sample - first_group * group_samples;
+            for frame in 0..last - first {
+                for k in 0..self.spf {
+                    let value = samples[skip + frame * self.frame_len + self.offset + k];
+                    out.push(if value == invalid {
+                        f64::NAN
+                    } else {
+                        (value - self.baseline) as f64 / self.adc_gain
+                    });
+                }
+            }
+        }
+        out.resize(count * self.spf, f64::NAN);
+        Ok(out)
+    }
+}
+
+/// Opens `path` for chunked reading. `channel` is a label or index as in
+/// [`loader::Recording::channel`]; `fallback_fs` is the rate of plain sample files.
+///
+/// Text, WFDB and EDF files are streamed; any other format the loader registry knows is
+/// loaded whole and served from memory.
+pub fn open_chunked(
+    path: &Path,
+    channel: Option<&str>,
+    fallback_fs: Option<f64>,
+) -> Result<Box<dyn ChunkedReader>> {
+    let index = channel.and_then(|c| c.parse::<usize>().ok());
+    match loader::detect_format(path)?.as_str() {
+        "text" if channel.is_none() || index == Some(0) => {
+            let fs = fallback_fs
+                .filter(|fs| *fs > 0.0)
+                .ok_or_else(|| anyhow!("{} holds bare samples; pass --fs", path.display()))?;
+            Ok(Box::new(TextReader::open(path, fs)?))
+        }
+        "wfdb" if channel.is_none() || index.is_some() => {
+            Ok(Box::new(WfdbChunkReader::open(path, index.unwrap_or(0))?))
+        }
+        "edf" => Ok(Box::new(match (channel, index) {
+            (_, Some(index)) => EdfChunkReader::open(path, index)?,
+            (Some(label), None) => EdfChunkReader::open_label(path, label)?,
+            (None, None) => EdfChunkReader::open(path, 0)?,
+        })),
+        _ => {
+            let recording = loader::load_recording(path, &loader::LoadOptions { fallback_fs })?;
+            Ok(Box::new(MemoryReader::new(recording.time_series(channel)?)))
+        }
+    }
+}
+
+/// Samples per block used by the CLI when none is given: about 4 minutes at 250 Hz.
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_path(relative: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace root")
+            .join(relative)
+    }
+
+    #[test]
+    fn wfdb_blocks_match_whole_record() {
+        let header = sample_path("test_data/mitdb/100.hea");
+        let whole = wfdb::load_wfdb_lead(&header, 1).unwrap();
+        let mut reader = WfdbChunkReader::open(&header, 1).unwrap();
+        assert_eq!(reader.len_hint(), Some(whole.len()));
+        // An odd block size exercises format 212 groups split across blocks.
+        let chunked = read_all(&mut reader, 1001).unwrap();
+        assert_eq!(chunked.fs, whole.fs);
+        assert_eq!(chunked.data, whole.data);
+    }
+
+    #[test]
+    fn edf_and_text_blocks_match_whole_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<f64> = (0..2500).map(|i| ((i % 97) as f64 - 48.0) / 10.0).collect();
+        let edf_path = dir.path().join("rec.edf");
+        edf::write_edf_plus(
+            &edf_path,
+            &[edf::EdfWriteSignal {
+                label: "ECG",
+                physical_dimension: "mV",
+                prefiltering: "",
+                fs: 100.0,
+                data: &data,
+            }],
+            &[],
+            &edf::EdfWriteOptions::default(),
+        )
+        .unwrap();
+        let whole = edf::load_edf_channel(&edf_path, 0).unwrap();
+        let blocks: Vec<SampleBlock> = EdfChunkReader::open_label(&edf_path, "ecg")
+            .unwrap()
+            .blocks(333)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(blocks[1].start, 333);
+        let chunked: Vec<f64> = blocks.into_iter().flat_map(|b| b.data).collect();
+        assert_eq!(chunked, whole.data);
+
+        let text_path = sample_path("test_data/synthetic_recording_a.txt");
+        let whole = super::super::text::read_f64_series(&text_path).unwrap();
+        let mut reader = open_chunked(&text_path, None, Some(250.0)).unwrap();
+        assert_eq!(read_all(reader.as_mut(), 7).unwrap().data, whole);
+    }
+}
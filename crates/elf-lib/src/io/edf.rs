@@ -0,0 +1,942 @@
+use crate::signal::TimeSeries;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// Sample width of the data records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdfFormat {
+    /// 16-bit two's complement samples.
+    Edf,
+    /// BioSemi 24-bit two's complement samples.
+    Bdf,
+}
+
+impl EdfFormat {
+    fn sample_bytes(self) -> usize {
+        match self {
+            EdfFormat::Edf => 2,
+            EdfFormat::Bdf => 3,
+        }
+    }
+}
+
+/// Recording start from the header; EDF+ `Startdate` supplies the four-digit year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdfStart {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl EdfStart {
+    /// 01-JAN-1985, the EDF+ placeholder for an unknown or anonymised start date.
+    pub const UNKNOWN: Self = Self {
+        year: 1985,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+
+    /// Civil UTC time for a Unix timestamp (Hinnant's `civil_from_days`).
+    pub fn from_unix(seconds: f64) -> Self {
+        let secs = seconds.max(0.0) as i64;
+        let days = secs.div_euclid(86_400);
+        let rem = secs.rem_euclid(86_400);
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (rem / 3600) as u8,
+            minute: (rem / 60 % 60) as u8,
+            second: (rem % 60) as u8,
+        }
+    }
+
+    fn header_date(&self) -> String {
+        format!("{:02}.{:02}.{:02}", self.day, self.month, self.year % 100)
+    }
+
+    fn header_time(&self) -> String {
+        format!("{:02}.{:02}.{:02}", self.hour, self.minute, self.second)
+    }
+
+    fn plus_date(&self) -> String {
+        let month = MONTHS[(self.month.clamp(1, 12) - 1) as usize];
+        format!("{:02}-{}-{}", self.day, month, self.year)
+    }
+}
+
+impl fmt::Display for EdfStart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Per-signal header fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdfSignal {
+    pub label: String,
+    pub transducer: String,
+    pub physical_dimension: String,
+    pub physical_min: f64,
+    pub physical_max: f64,
+    pub digital_min: i32,
+    pub digital_max: i32,
+    pub prefiltering: String,
+    pub samples_per_record: usize,
+}
+
+impl EdfSignal {
+    /// `EDF Annotations` / `BDF Annotations` channels carry TALs instead of samples.
+    pub fn is_annotation(&self) -> bool {
+        self.label == "EDF Annotations" || self.label == "BDF Annotations"
+    }
+
+    fn scale(&self) -> (f64, f64) {
+        let digital_span = (self.digital_max - self.digital_min) as f64;
+        if digital_span == 0.0 {
+            return (1.0, 0.0);
+        }
+        let gain = (self.physical_max - self.physical_min) / digital_span;
+        (gain, self.physical_min - self.digital_min as f64 * gain)
+    }
+}
+
+/// Parsed EDF/EDF+/BDF header.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdfHeader {
+    pub format: EdfFormat,
+    pub patient: String,
+    pub recording: String,
+    pub start: EdfStart,
+    pub header_bytes: usize,
+    /// `EDF+C`, `EDF+D` (or `BDF+…`) for EDF+ files, empty for plain EDF.
+    pub reserved: String,
+    pub n_records: usize,
+    pub record_duration_s: f64,
+    pub signals: Vec<EdfSignal>,
+}
+
+impl EdfHeader {
+    pub fn is_plus(&self) -> bool {
+        self.reserved.starts_with("EDF+") || self.reserved.starts_with("BDF+")
+    }
+
+    /// EDF+D records are not contiguous; their onsets come from the time-keeping TALs.
+    pub fn is_discontinuous(&self) -> bool {
+        self.is_plus() && self.reserved[4..].starts_with('D')
+    }
+
+    pub fn fs(&self, signal: usize) -> f64 {
+        self.signals[signal].samples_per_record as f64 / self.record_duration_s
+    }
+
+    fn record_bytes(&self) -> usize {
+        self.signals
+            .iter()
+            .map(|s| s.samples_per_record)
+            .sum::<usize>()
+            * self.format.sample_bytes()
+    }
+
+    fn signal_offset(&self, signal: usize) -> usize {
+        self.signals[..signal]
+            .iter()
+            .map(|s| s.samples_per_record)
+            .sum::<usize>()
+            * self.format.sample_bytes()
+    }
+}
+
+/// EDF+ annotation from a time-stamped annotation list (TAL); onsets are seconds from start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdfAnnotation {
+    pub onset_s: f64,
+    pub duration_s: Option<f64>,
+    pub text: String,
+}
+
+/// Lazy EDF/EDF+/BDF reader: the header is parsed on open and data records are read on demand.
+pub struct EdfReader {
+    header: EdfHeader,
+    file: File,
+    record_onsets: Vec<f64>,
+}
+
+impl EdfReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut fixed = [0u8; 256];
+        file.read_exact(&mut fixed)
+            .with_context(|| format!("{} is too short for an EDF header", path.display()))?;
+        let n_signals: usize = ascii_field(&fixed[252..256])
+            .parse()
+            .context("invalid EDF signal count")?;
+        let mut signal_bytes = vec![0u8; n_signals * 256];
+        file.read_exact(&mut signal_bytes)
+            .context("truncated EDF signal header")?;
+        let file_len = file.metadata()?.len() as usize;
+        let mut header = parse_header(&fixed, &signal_bytes, n_signals)
+            .with_context(|| format!("invalid EDF header in {}", path.display()))?;
+        let record_bytes = header.record_bytes().max(1);
+        let available = file_len.saturating_sub(header.header_bytes) / record_bytes;
+        // -1 marks a recording that was not closed; trust the file length instead.
+        if header.n_records == usize::MAX || header.n_records > available {
+            header.n_records = available;
+        }
+
+        let mut reader = Self {
+            header,
+            file,
+            record_onsets: Vec::new(),
+        };
+        reader.record_onsets = if reader.header.is_discontinuous() {
+            reader.read_record_onsets()?
+        } else {
+            (0..reader.header.n_records)
+                .map(|r| r as f64 * reader.header.record_duration_s)
+                .collect()
+        };
+        Ok(reader)
+    }
+
+    pub fn header(&self) -> &EdfHeader {
+        &self.header
+    }
+
+    /// Onset (seconds from start) of every data record.
+    pub fn record_onsets(&self) -> &[f64] {
+        &self.record_onsets
+    }
+
+    /// Indices of signals that carry samples (annotation channels excluded).
+    pub fn data_signals(&self) -> Vec<usize> {
+        (0..self.header.signals.len())
+            .filter(|&i| !self.header.signals[i].is_annotation())
+            .collect()
+    }
+
+    pub fn duration_s(&self) -> f64 {
+        self.record_onsets
+            .last()
+            .map(|onset| onset + self.header.record_duration_s)
+            .unwrap_or(0.0)
+    }
+
+    fn read_record_bytes(&mut self, record: usize, signal: usize) -> Result<Vec<u8>> {
+        let spec = &self.header.signals[signal];
+        let len = spec.samples_per_record * self.header.format.sample_bytes();
+        let offset = self.header.header_bytes
+            + record * self.header.record_bytes()
+            + self.header.signal_offset(signal);
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        let mut buf = vec![0u8; len];
+        self.file
+            .read_exact(&mut buf)
+            .with_context(|| format!("truncated EDF data record {record}"))?;
+        Ok(buf)
+    }
+
+    fn read_record_physical(&mut self, record: usize, signal: usize) -> Result<Vec<f64>> {
+        let bytes = self.read_record_bytes(record, signal)?;
+        let spec = &self.header.signals[signal];
+        let (gain, offset) = spec.scale();
+        let valid = spec.digital_min.min(spec.digital_max)..=spec.digital_max.max(spec.digital_min);
+        let digital: Vec<i32> = match self.header.format {
+            EdfFormat::Edf => bytes
+                .chunks_exact(2)
+                .map(|c| i16::from_le_bytes([c[0], c[1]]) as i32)
+                .collect(),
+            EdfFormat::Bdf => bytes
+                .chunks_exact(3)
+                .map(|c| i32::from_le_bytes([0, c[0], c[1], c[2]]) >> 8)
+                .collect(),
+        };
+        Ok(digital
+            .into_iter()
+            .map(|d| {
+                if valid.contains(&d) {
+                    d as f64 * gain + offset
+                } else {
+                    f64::NAN
+                }
+            })
+            .collect())
+    }
+
+    fn annotation_signals(&self) -> Vec<usize> {
+        (0..self.header.signals.len())
+            .filter(|&i| self.header.signals[i].is_annotation())
+            .collect()
+    }
+
+    fn read_record_onsets(&mut self) -> Result<Vec<f64>> {
+        let Some(&signal) = self.annotation_signals().first() else {
+            bail!("EDF+D file has no annotation signal");
+        };
+        (0..self.header.n_records)
+            .map(|record| {
+                let bytes = self.read_record_bytes(record, signal)?;
+                parse_tals(&bytes)
+                    .0
+                    .ok_or_else(|| anyhow!("EDF+ record {record} lacks a time-keeping TAL"))
+            })
+            .collect()
+    }
+
+    /// Every annotation from all annotation signals, sorted by onset.
+    pub fn annotations(&mut self) -> Result<Vec<EdfAnnotation>> {
+        let mut out = Vec::new();
+        for signal in self.annotation_signals() {
+            for record in 0..self.header.n_records {
+                let bytes = self.read_record_bytes(record, signal)?;
+                out.extend(parse_tals(&bytes).1);
+            }
+        }
+        out.sort_by(|a, b| a.onset_s.total_cmp(&b.onset_s));
+        Ok(out)
+    }
+
+    /// Physical samples of `signal` between `start_s` and `end_s`, reading only the records
+    /// that overlap the window. Gaps between EDF+D records are NaN.
+    pub fn read_range(&mut self, signal: usize, start_s: f64, end_s: f64) -> Result<TimeSeries> {
+        let spec = self
+            .header
+            .signals
+            .get(signal)
+            .ok_or_else(|| {
+                anyhow!(
+                    "EDF file has {} signals; signal {} is out of range",
+                    self.header.signals.len(),
+                    signal
+                )
+            })?
+            .clone();
+        if spec.is_annotation() {
+            bail!("signal {signal} is an annotation channel");
+        }
+        let fs = self.header.fs(signal);
+        let start_s = start_s.max(0.0);
+        let end_s = end_s.min(self.duration_s());
+        let len = ((end_s - start_s) * fs).round().max(0.0) as usize;
+        let mut data = vec![f64::NAN; len];
+        let duration = self.header.record_duration_s;
+        for record in 0..self.header.n_records {
+            let onset = self.record_onsets[record];
+            if onset + duration <= start_s || onset >= end_s {
+                continue;
+            }
+            let samples = self.read_record_physical(record, signal)?;
+            let first = ((onset - start_s) * fs).round() as i64;
+            for (k, value) in samples.into_iter().enumerate() {
+                let idx = first + k as i64;
+                if idx >= 0 && (idx as usize) < len {
+                    data[idx as usize] = value;
+                }
+            }
+        }
+        Ok(TimeSeries { fs, data })
+    }
+
+    /// Whole recording of one signal.
+    pub fn read_signal(&mut self, signal: usize) -> Result<TimeSeries> {
+        let end = self.duration_s();
+        self.read_range(signal, 0.0, end)
+    }
+}
+
+fn ascii_field(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_string()
+}
+
+fn parse_header(fixed: &[u8], signal_bytes: &[u8], n: usize) -> Result<EdfHeader> {
+    let format = if fixed[0] == 0xFF && &fixed[1..8] == b"BIOSEMI" {
+        EdfFormat::Bdf
+    } else {
+        EdfFormat::Edf
+    };
+    let patient = ascii_field(&fixed[8..88]);
+    let recording = ascii_field(&fixed[88..168]);
+    let date = ascii_field(&fixed[168..176]);
+    let time = ascii_field(&fixed[176..184]);
+    let header_bytes = ascii_field(&fixed[184..192])
+        .parse()
+        .context("invalid header size")?;
+    let reserved = ascii_field(&fixed[192..236]);
+    let n_records: i64 = ascii_field(&fixed[236..244])
+        .parse()
+        .context("invalid record count")?;
+    let record_duration_s: f64 = ascii_field(&fixed[244..252])
+        .parse()
+        .context("invalid record duration")?;
+    if record_duration_s <= 0.0 {
+        bail!("record duration must be positive");
+    }
+    let start = parse_start(&date, &time, &recording)?;
+
+    // Signal fields are stored column-wise: all labels, then all transducers, ...
+    let mut offset = 0;
+    let mut column = |width: usize| -> Vec<String> {
+        let values = (0..n)
+            .map(|i| ascii_field(&signal_bytes[offset + i * width..offset + (i + 1) * width]))
+            .collect();
+        offset += width * n;
+        values
+    };
+    let labels = column(16);
+    let transducers = column(80);
+    let dimensions = column(8);
+    let physical_min = column(8);
+    let physical_max = column(8);
+    let digital_min = column(8);
+    let digital_max = column(8);
+    let prefiltering = column(80);
+    let samples = column(8);
+
+    let mut signals = Vec::with_capacity(n);
+    for i in 0..n {
+        let num = |values: &[String], name: &str| -> Result<f64> {
+            values[i]
+                .parse()
+                .with_context(|| format!("invalid {name} {:?} for signal {i}", values[i]))
+        };
+        signals.push(EdfSignal {
+            label: labels[i].clone(),
+            transducer: transducers[i].clone(),
+            physical_dimension: dimensions[i].clone(),
+            physical_min: num(&physical_min, "physical minimum")?,
+            physical_max: num(&physical_max, "physical maximum")?,
+            digital_min: num(&digital_min, "digital minimum")? as i32,
+            digital_max: num(&digital_max, "digital maximum")? as i32,
+            prefiltering: prefiltering[i].clone(),
+            samples_per_record: num(&samples, "samples per record")? as usize,
+        });
+    }
+
+    Ok(EdfHeader {
+        format,
+        patient,
+        recording,
+        start,
+        header_bytes,
+        reserved,
+        n_records: if n_records < 0 {
+            usize::MAX
+        } else {
+            n_records as usize
+        },
+        record_duration_s,
+        signals,
+    })
+}
+
+fn parse_start(date: &str, time: &str, recording: &str) -> Result<EdfStart> {
+    let parts = |value: &str| -> Result<Vec<u8>> {
+        value
+            .split(['.', ':'])
+            .map(|p| p.parse::<u8>().context("invalid start date/time"))
+            .collect()
+    };
+    let d = parts(date)?;
+    let t = parts(time)?;
+    if d.len() != 3 || t.len() != 3 {
+        bail!("invalid start date/time {date} {time}");
+    }
+    // EDF clipping date: yy 85-99 is 19yy, 00-84 is 20yy, unless EDF+ gives the full year.
+    let mut year = if d[2] >= 85 {
+        1900 + d[2] as u16
+    } else {
+        2000 + d[2] as u16
+    };
+    if let Some(rest) = recording.strip_prefix("Startdate ") {
+        if let Some(full) = rest
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.rsplit('-').next())
+            .and_then(|v| v.parse::<u16>().ok())
+        {
+            year = full;
+        }
+    }
+    Ok(EdfStart {
+        year,
+        month: d[1],
+        day: d[0],
+        hour: t[0],
+        minute: t[1],
+        second: t[2],
+    })
+}
+
+/// Split one record of an annotation signal into its time-keeping onset and annotations.
+fn parse_tals(bytes: &[u8]) -> (Option<f64>, Vec<EdfAnnotation>) {
+    let mut record_onset = None;
+    let mut annotations = Vec::new();
+    for tal in bytes.split(|&b| b == 0).filter(|tal| !tal.is_empty()) {
+        let mut fields = tal.split(|&b| b == 0x14);
+        let Some(timing) = fields.next() else {
+            continue;
+        };
+        let timing = String::from_utf8_lossy(timing);
+        let mut timing = timing.split('\u{15}');
+        let Some(onset) = timing.next().and_then(|v| v.parse::<f64>().ok()) else {
+            continue;
+        };
+        let duration_s = timing.next().and_then(|v| v.parse().ok());
+        let texts: Vec<String> = fields
+            .map(|text| String::from_utf8_lossy(text).to_string())
+            .filter(|text| !text.is_empty())
+            .collect();
+        if texts.is_empty() && record_onset.is_none() {
+            record_onset = Some(onset);
+        }
+        annotations.extend(texts.into_iter().map(|text| EdfAnnotation {
+            onset_s: onset,
+            duration_s,
+            text,
+        }));
+    }
+    (record_onset, annotations)
+}
+
+/// Load a single data channel (annotation channels are skipped in the numbering).
+pub fn load_edf_channel(path: &Path, channel: usize) -> Result<TimeSeries> {
+    let mut reader = EdfReader::open(path)?;
+    let signals = reader.data_signals();
+    let signal = *signals.get(channel).ok_or_else(|| {
+        anyhow!(
+            "EDF file has {} channels; channel {} is out of range",
+            signals.len(),
+            channel
+        )
+    })?;
+    reader.read_signal(signal)
+}
+
+/// Signal to store with [`write_edf_plus`].
+#[derive(Debug, Clone, Copy)]
+pub struct EdfWriteSignal<'a> {
+    pub label: &'a str,
+    pub physical_dimension: &'a str,
+    pub prefiltering: &'a str,
+    pub fs: f64,
+    pub data: &'a [f64],
+}
+
+/// Header fields for [`write_edf_plus`].
+#[derive(Debug, Clone)]
+pub struct EdfWriteOptions {
+    /// EDF+ patient field: code, sex, birthdate, name (`X` when unknown).
+    pub patient: String,
+    /// EDF+ recording field after `Startdate`: admin code, technician, equipment.
+    pub recording: String,
+    pub start: EdfStart,
+    pub record_duration_s: f64,
+}
+
+impl Default for EdfWriteOptions {
+    fn default() -> Self {
+        Self {
+            patient: "X X X X".into(),
+            recording: "X X elf".into(),
+            start: EdfStart::UNKNOWN,
+            record_duration_s: 1.0,
+        }
+    }
+}
+
+/// Write a continuous EDF+ file (16-bit) with an `EDF Annotations` channel.
+///
+/// Each signal is scaled over its own finite range onto -32767..=32767; NaN samples are written
+/// as -32768, outside the digital range, and read back as NaN. Sampling rates must give a whole
+/// number of samples per data record, and the start year must lie in 1985-2084.
+pub fn write_edf_plus(
+    path: &Path,
+    signals: &[EdfWriteSignal<'_>],
+    annotations: &[EdfAnnotation],
+    opts: &EdfWriteOptions,
+) -> Result<()> {
+    let duration = opts.record_duration_s;
+    if duration <= 0.0 {
+        bail!("record duration must be positive");
+    }
+    if !(1985..=2084).contains(&opts.start.year) {
+        bail!(
+            "EDF start year {} is outside the representable 1985-2084",
+            opts.start.year
+        );
+    }
+    let mut specs = Vec::with_capacity(signals.len() + 1);
+    let mut n_records = 1;
+    for signal in signals {
+        let exact = signal.fs * duration;
+        let spr = exact.round() as usize;
+        if spr == 0 || (exact - spr as f64).abs() > 1e-6 {
+            bail!(
+                "{} Hz is not a whole number of samples per {duration} s record",
+                signal.fs
+            );
+        }
+        n_records = n_records.max(signal.data.len().div_ceil(spr));
+        let (lo, hi) = signal
+            .data
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+        let (lo, hi) = if !lo.is_finite() {
+            (-1.0, 1.0)
+        } else if hi - lo < 1e-9 {
+            (lo - 1.0, hi + 1.0)
+        } else {
+            (lo, hi)
+        };
+        let (physical_min, min_text) = fit_number(lo, f64::floor);
+        let (physical_max, max_text) = fit_number(hi, f64::ceil);
+        specs.push((
+            EdfSignal {
+                label: signal.label.to_string(),
+                transducer: String::new(),
+                physical_dimension: signal.physical_dimension.to_string(),
+                physical_min,
+                physical_max,
+                digital_min: i16::MIN as i32 + 1,
+                digital_max: i16::MAX as i32,
+                prefiltering: signal.prefiltering.to_string(),
+                samples_per_record: spr,
+            },
+            min_text,
+            max_text,
+        ));
+    }
+
+    // Time-keeping TAL plus the annotations falling into each record.
+    let mut tals: Vec<Vec<u8>> = (0..n_records)
+        .map(|r| format!("+{}\x14\x14\0", r as f64 * duration).into_bytes())
+        .collect();
+    for ann in annotations {
+        let record = ((ann.onset_s / duration).floor().max(0.0) as usize).min(n_records - 1);
+        let timing = match ann.duration_s {
+            Some(d) => format!("{:+}\x15{}", ann.onset_s, d),
+            None => format!("{:+}", ann.onset_s),
+        };
+        // TAL delimiters inside the text would end the annotation early.
+        let text = ann.text.replace(['\x14', '\x15', '\0'], " ");
+        tals[record].extend(format!("{timing}\x14{text}\x14\0").into_bytes());
+    }
+    let annotation_spr = tals.iter().map(|t| t.len()).max().unwrap_or(0).div_ceil(2);
+    specs.push((
+        EdfSignal {
+            label: "EDF Annotations".into(),
+            transducer: String::new(),
+            physical_dimension: String::new(),
+            physical_min: -1.0,
+            physical_max: 1.0,
+            digital_min: i16::MIN as i32,
+            digital_max: i16::MAX as i32,
+            prefiltering: String::new(),
+            samples_per_record: annotation_spr,
+        },
+        "-1".into(),
+        "1".into(),
+    ));
+
+    let ns = specs.len();
+    let mut header = String::new();
+    let mut push = |value: &str, width: usize| {
+        let value: String = value.chars().filter(char::is_ascii).take(width).collect();
+        header.push_str(&format!("{value:<width$}"));
+    };
+    push("0", 8);
+    push(&opts.patient, 80);
+    push(
+        &format!("Startdate {} {}", opts.start.plus_date(), opts.recording),
+        80,
+    );
+    push(&opts.start.header_date(), 8);
+    push(&opts.start.header_time(), 8);
+    push(&(256 * (ns + 1)).to_string(), 8);
+    push("EDF+C", 44);
+    push(&n_records.to_string(), 8);
+    push(&duration.to_string(), 8);
+    push(&ns.to_string(), 4);
+    for (spec, _, _) in &specs {
+        push(&spec.label, 16);
+    }
+    for (spec, _, _) in &specs {
+        push(&spec.transducer, 80);
+    }
+    for (spec, _, _) in &specs {
+        push(&spec.physical_dimension, 8);
+    }
+    for (_, min_text, _) in &specs {
+        push(min_text, 8);
+    }
+    for (_, _, max_text) in &specs {
+        push(max_text, 8);
+    }
+    for (spec, _, _) in &specs {
+        push(&spec.digital_min.to_string(), 8);
+    }
+    for (spec, _, _) in &specs {
+        push(&spec.digital_max.to_string(), 8);
+    }
+    for (spec, _, _) in &specs {
+        push(&spec.prefiltering, 80);
+    }
+    for (spec, _, _) in &specs {
+        push(&spec.samples_per_record.to_string(), 8);
+    }
+    for _ in &specs {
+        push("", 32);
+    }
+
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(header.as_bytes())?;
+    for (record, tal) in tals.iter().enumerate() {
+        for (signal, (spec, _, _)) in signals.iter().zip(&specs) {
+            let (gain, offset) = spec.scale();
+            let spr = spec.samples_per_record;
+            for k in 0..spr {
+                let digital = match signal.data.get(record * spr + k) {
+                    Some(v) if v.is_finite() => ((v - offset) / gain)
+                        .round()
+                        .clamp(spec.digital_min as f64, spec.digital_max as f64)
+                        as i16,
+                    _ => i16::MIN,
+                };
+                out.write_all(&digital.to_le_bytes())?;
+            }
+        }
+        let mut tal = tal.clone();
+        tal.resize(annotation_spr * 2, 0);
+        out.write_all(&tal)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Format a physical bound into the 8-character header field, rounding away from the data.
+fn fit_number(value: f64, round: fn(f64) -> f64) -> (f64, String) {
+    for decimals in (0..=6).rev() {
+        let scale = 10f64.powi(decimals);
+        let rounded = round(value * scale) / scale;
+        let text = format!("{rounded:.*}", decimals as usize);
+        if text.len() <= 8 {
+            return (rounded, text);
+        }
+    }
+    let text = format!("{:.0}", round(value));
+    (round(value), text.chars().take(8).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(fs: f64, seconds: f64, hz: f64) -> Vec<f64> {
+        (0..(fs * seconds) as usize)
+            .map(|i| 50.0 * (2.0 * std::f64::consts::PI * hz * i as f64 / fs).sin())
+            .collect()
+    }
+
+    #[test]
+    fn edf_plus_round_trip_keeps_signals_and_annotations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rec.edf");
+        let eeg = sine(256.0, 10.0, 10.0);
+        let ecg = sine(128.0, 10.0, 1.2);
+        let signals = [
+            EdfWriteSignal {
+                label: "EEG Fz",
+                physical_dimension: "uV",
+                prefiltering: "HP:0.1Hz LP:70Hz",
+                fs: 256.0,
+                data: &eeg,
+            },
+            EdfWriteSignal {
+                label: "ECG",
+                physical_dimension: "mV",
+                prefiltering: "",
+                fs: 128.0,
+                data: &ecg,
+            },
+        ];
+        let annotations = vec![
+            EdfAnnotation {
+                onset_s: 1.5,
+                duration_s: None,
+                text: "stim".into(),
+            },
+            EdfAnnotation {
+                onset_s: 7.25,
+                duration_s: Some(2.0),
+                text: "Sleep stage W".into(),
+            },
+        ];
+        let opts = EdfWriteOptions {
+            start: EdfStart::from_unix(1_709_287_200.0),
+            ..EdfWriteOptions::default()
+        };
+        write_edf_plus(&path, &signals, &annotations, &opts).unwrap();
+
+        let mut reader = EdfReader::open(&path).unwrap();
+        let header = reader.header().clone();
+        assert!(header.is_plus() && !header.is_discontinuous());
+        assert_eq!(header.start.to_string(), "2024-03-01T10:00:00");
+        assert_eq!(reader.data_signals(), vec![0, 1]);
+        assert_eq!(header.signals[0].prefiltering, "HP:0.1Hz LP:70Hz");
+        assert_eq!(header.signals[1].physical_dimension, "mV");
+        assert_eq!(reader.annotations().unwrap(), annotations);
+
+        let fz = reader.read_signal(0).unwrap();
+        assert_eq!(fz.fs, 256.0);
+        assert_eq!(fz.len(), eeg.len());
+        let step = 100.0 / 65535.0;
+        assert!(fz.data.iter().zip(&eeg).all(|(a, b)| (a - b).abs() <= step));
+
+        let window = reader.read_range(1, 2.0, 3.0).unwrap();
+        assert_eq!(window.len(), 128);
+        assert!((window.data[0] - ecg[256]).abs() < 0.01);
+        assert_eq!(load_edf_channel(&path, 1).unwrap().len(), ecg.len());
+    }
+
+    #[test]
+    fn nan_samples_and_tal_delimiters_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gaps.edf");
+        let mut data = sine(10.0, 2.0, 1.0);
+        data[3] = f64::NAN;
+        let signals = [EdfWriteSignal {
+            label: "Resp",
+            physical_dimension: "",
+            prefiltering: "",
+            fs: 10.0,
+            data: &data,
+        }];
+        let annotations = [EdfAnnotation {
+            onset_s: 0.5,
+            duration_s: None,
+            text: "a\x14b\x15c\0d".into(),
+        }];
+        write_edf_plus(&path, &signals, &annotations, &EdfWriteOptions::default()).unwrap();
+
+        let mut reader = EdfReader::open(&path).unwrap();
+        assert_eq!(reader.header().start, EdfStart::UNKNOWN);
+        assert_eq!(reader.annotations().unwrap()[0].text, "a b c d");
+        let ts = reader.read_signal(0).unwrap();
+        assert!(ts.data[3].is_nan());
+        assert_eq!(ts.data.iter().filter(|v| v.is_nan()).count(), 1);
+        let lowest = data
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(f64::MAX, f64::min);
+        let read_lowest = ts
+            .data
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(f64::MAX, f64::min);
+        assert!((read_lowest - lowest).abs() < 0.01);
+
+        let opts = EdfWriteOptions {
+            start: EdfStart::from_unix(0.0),
+            ..EdfWriteOptions::default()
+        };
+        assert!(write_edf_plus(&path, &signals, &[], &opts).is_err());
+    }
+
+    #[test]
+    fn reads_bdf_and_discontinuous_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rec.bdf");
+        let values = [-8_388_608i32, -1, 0, 1, 8_388_607];
+        let mut fixed = [b' '; 256];
+        let put = |buf: &mut [u8], at: usize, value: &str| {
+            buf[at..at + value.len()].copy_from_slice(value.as_bytes());
+        };
+        fixed[0] = 0xFF;
+        put(&mut fixed, 1, "BIOSEMI");
+        put(&mut fixed, 88, "Startdate 02-JAN-1984 X X X");
+        put(&mut fixed, 168, "02.01.84");
+        put(&mut fixed, 176, "08.30.00");
+        put(&mut fixed, 184, "768");
+        put(&mut fixed, 192, "BDF+D");
+        put(&mut fixed, 236, "2");
+        put(&mut fixed, 244, "1");
+        put(&mut fixed, 252, "2");
+        let mut signal = vec![b' '; 512];
+        for (i, (width, a, b)) in [
+            (16, "Ch1", "BDF Annotations"),
+            (80, "", ""),
+            (8, "uV", ""),
+            (8, "-8388608", "-1"),
+            (8, "8388607", "1"),
+            (8, "-8388608", "-8388608"),
+            (8, "8388607", "8388607"),
+            (80, "", ""),
+            (8, "5", "10"),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let base: usize = [16, 80, 8, 8, 8, 8, 8, 80, 8][..i].iter().sum::<usize>() * 2;
+            put(&mut signal, base, a);
+            put(&mut signal, base + width, b);
+        }
+        let mut bytes = fixed.to_vec();
+        bytes.extend(signal);
+        for onset in ["+0", "+5"] {
+            for v in values {
+                bytes.extend(&v.to_le_bytes()[..3]);
+            }
+            let mut tal = format!("{onset}\x14\x14\0").into_bytes();
+            tal.resize(30, 0);
+            bytes.extend(tal);
+        }
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut reader = EdfReader::open(&path).unwrap();
+        assert_eq!(reader.header().format, EdfFormat::Bdf);
+        assert!(reader.header().is_discontinuous());
+        assert_eq!(reader.header().start.year, 1984);
+        assert_eq!(reader.record_onsets(), &[0.0, 5.0]);
+        let ts = reader.read_signal(0).unwrap();
+        assert_eq!(ts.len(), 30);
+        assert_eq!(&ts.data[..5], &[-8_388_608.0, -1.0, 0.0, 1.0, 8_388_607.0]);
+        assert!(ts.data[5..25].iter().all(|v| v.is_nan()));
+        assert_eq!(ts.data[25], -8_388_608.0);
+    }
+}
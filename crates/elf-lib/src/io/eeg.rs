@@ -1,61 +1,9 @@
-use crate::signal::{Events, TimeSeries};
+use crate::signal::Events;
 use anyhow::{anyhow, Context, Result};
 use csv::{ReaderBuilder, StringRecord};
-use edf_reader::file_reader::SyncFileReader;
-use edf_reader::sync_reader::SyncEDFReader;
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
-/// Helper implementing the EDF reader trait for on-disk files.
-struct DiskFileReader {
-    path: PathBuf,
-}
-
-impl DiskFileReader {
-    fn new(path: &Path) -> Self {
-        Self {
-            path: path.to_path_buf(),
-        }
-    }
-}
-
-impl SyncFileReader for DiskFileReader {
-    fn read(&self, offset: u64, length: u64) -> Result<Vec<u8>, std::io::Error> {
-        let mut file = File::open(&self.path)?;
-        file.seek(SeekFrom::Start(offset))?;
-        let mut buf = vec![0u8; length as usize];
-        file.read_exact(&mut buf)?;
-        Ok(buf)
-    }
-}
-
-/// Load a single EDF channel (by index) into a `TimeSeries`.
-///
-/// EDF (European Data Format) is a widely adopted archival format for electrophysiology;
-/// this helper follows the EDF/EDF+ spec to read the header + channel samples directly.
-pub fn load_edf_channel(path: &Path, channel: usize) -> Result<TimeSeries> {
-    let reader = SyncEDFReader::init_with_file_reader(DiskFileReader::new(path))?;
-    if channel >= reader.edf_header.channels.len() {
-        return Err(anyhow!(
-            "EDF file has {} channels; channel {} is out of range",
-            reader.edf_header.channels.len(),
-            channel
-        ));
-    }
-    let total_duration = reader.edf_header.block_duration * reader.edf_header.number_of_blocks;
-    let data_matrix = reader.read_data_window(0, total_duration)?;
-    let channel_data = data_matrix
-        .get(channel)
-        .ok_or_else(|| anyhow!("missing channel data"))?;
-    let hdr_chan = &reader.edf_header.channels[channel];
-    let fs = hdr_chan.number_of_samples_in_data_record as f64 * 1000.0
-        / reader.edf_header.block_duration as f64;
-    Ok(TimeSeries {
-        fs,
-        data: channel_data.iter().map(|value| *value as f64).collect(),
-    })
-}
+pub use super::edf::load_edf_channel;
 
 /// Simple BIDS event descriptor extracted from an `events.tsv` file.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -17,6 +17,48 @@ pub struct PupilSample {
     pub pupil_mm: Option<f32>,
     pub confidence: Option<f32>,
     pub eye: Eye,
+    /// Gaze position in the export's own coordinate system (pixels or normalized).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gaze_x: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gaze_y: Option<f32>,
+}
+
+/// Column names of an eye-tracker export. Optional columns that are absent from the header
+/// are skipped rather than treated as an error.
+#[derive(Debug, Clone, Copy)]
+pub struct EyeCsvLayout<'a> {
+    pub timestamp: &'a str,
+    pub pupil: Option<&'a str>,
+    pub confidence: Option<&'a str>,
+    pub eye: Option<&'a str>,
+    pub gaze_x: Option<&'a str>,
+    pub gaze_y: Option<&'a str>,
+    pub delimiter: u8,
+}
+
+impl EyeCsvLayout<'static> {
+    /// Pupil Labs Player `pupil_positions.csv`/`gaze_positions.csv` export.
+    pub const PUPIL_LABS: Self = Self {
+        timestamp: "timestamp",
+        pupil: Some("diameter"),
+        confidence: Some("confidence"),
+        eye: Some("eye"),
+        gaze_x: Some("norm_pos_x"),
+        gaze_y: Some("norm_pos_y"),
+        delimiter: b',',
+    };
+
+    /// Tobii Pro SDK tab-separated export.
+    pub const TOBII: Self = Self {
+        timestamp: "system_time_stamp",
+        pupil: Some("pupil_diameter_2d"),
+        confidence: Some("confidence"),
+        eye: Some("eye"),
+        gaze_x: Some("gaze_point_x"),
+        gaze_y: Some("gaze_point_y"),
+        delimiter: b'\t',
+    };
 }
 
 pub fn read_eye_csv(
@@ -26,18 +68,66 @@ pub fn read_eye_csv(
     confidence_col: Option<&str>,
     eye_label_col: Option<&str>,
     delimiter: u8,
+) -> Result<Vec<PupilSample>> {
+    read_eye_csv_layout(
+        path,
+        &EyeCsvLayout {
+            timestamp: timestamp_col,
+            pupil: Some(pupil_col),
+            confidence: confidence_col,
+            eye: eye_label_col,
+            gaze_x: None,
+            gaze_y: None,
+            delimiter,
+        },
+    )
+}
+
+/// Reads pupil samples using an explicit column layout. A named pupil column must be present.
+pub fn read_eye_csv_layout(path: &Path, layout: &EyeCsvLayout) -> Result<Vec<PupilSample>> {
+    read_layout(path, layout, true)
+}
+
+/// Reads gaze samples using an explicit column layout, with pupil diameters when the export
+/// has them.
+pub fn read_gaze_csv_layout(path: &Path, layout: &EyeCsvLayout) -> Result<Vec<PupilSample>> {
+    read_layout(path, layout, false)
+}
+
+fn read_layout(
+    path: &Path,
+    layout: &EyeCsvLayout,
+    pupil_required: bool,
 ) -> Result<Vec<PupilSample>> {
     let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
     let mut reader = ReaderBuilder::new()
-        .delimiter(delimiter)
+        .delimiter(layout.delimiter)
         .has_headers(true)
         .from_reader(file);
     let headers = reader.headers().context("reading header")?.clone();
 
-    let ts_idx = locate_column(&headers, timestamp_col, "timestamp")?;
-    let pupil_idx = locate_column(&headers, pupil_col, "pupil diameter")?;
-    let conf_idx = confidence_col.and_then(|col| locate_column(&headers, col, "confidence").ok());
-    let eye_idx = eye_label_col.and_then(|col| locate_column(&headers, col, "eye label").ok());
+    let ts_idx = locate_column(&headers, layout.timestamp, "timestamp")?;
+    let optional =
+        |col: Option<&str>, hint: &str| col.and_then(|col| locate_column(&headers, col, hint).ok());
+    let pupil_idx = match layout.pupil {
+        Some(col) if pupil_required => Some(locate_column(&headers, col, "pupil diameter")?),
+        col => optional(col, "pupil diameter"),
+    };
+    let conf_idx = optional(layout.confidence, "confidence");
+    let eye_idx = optional(layout.eye, "eye label");
+    let gaze_x_idx = optional(layout.gaze_x, "gaze x");
+    let gaze_y_idx = optional(layout.gaze_y, "gaze y");
+    if pupil_idx.is_none() && (gaze_x_idx.is_none() || gaze_y_idx.is_none()) {
+        return Err(match layout.pupil {
+            Some(col) => anyhow::anyhow!("missing pupil diameter column ({})", col),
+            None => anyhow::anyhow!("missing pupil diameter and gaze x/y columns"),
+        });
+    }
+    let parse_f32 = |record: &csv::StringRecord, idx: Option<usize>| {
+        idx.and_then(|idx| record.get(idx))
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .filter(|v| v.is_finite())
+    };
 
     let mut samples = Vec::new();
     for result in reader.records() {
@@ -47,10 +137,8 @@ pub fn read_eye_csv(
             .ok_or_else(|| anyhow::anyhow!("missing timestamp"))?
             .parse::<f64>()
             .context("parsing timestamp")?;
-        let pupil = record.get(pupil_idx).and_then(|v| v.parse::<f32>().ok());
-        let confidence = conf_idx
-            .and_then(|idx| record.get(idx))
-            .and_then(|v| v.parse::<f32>().ok());
+        let pupil = parse_f32(&record, pupil_idx);
+        let confidence = parse_f32(&record, conf_idx);
         let eye = eye_idx
             .and_then(|idx| record.get(idx))
             .map(|value| match value.to_lowercase().as_str() {
@@ -64,6 +152,8 @@ pub fn read_eye_csv(
             pupil_mm: pupil,
             confidence,
             eye,
+            gaze_x: parse_f32(&record, gaze_x_idx),
+            gaze_y: parse_f32(&record, gaze_y_idx),
         });
     }
     Ok(samples)
@@ -156,6 +246,49 @@ mod tests {
         assert_eq!(samples[0].pupil_mm, Some(4.12));
     }
 
+    #[test]
+    fn reads_gaze_columns() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let path = manifest_dir
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace")
+            .join("test_data/pupil_labs_sample.csv");
+        let samples = read_eye_csv_layout(
+            &path,
+            &EyeCsvLayout {
+                timestamp: "timestamp",
+                pupil: None,
+                confidence: Some("confidence"),
+                eye: Some("eye"),
+                gaze_x: Some("norm_pos_x"),
+                gaze_y: Some("norm_pos_y"),
+                delimiter: b',',
+            },
+        )
+        .unwrap();
+        assert_eq!(samples.len(), 3);
+        assert!(samples[0].pupil_mm.is_none());
+        assert_eq!(samples[0].gaze_x, Some(0.51));
+        assert_eq!(samples[2].gaze_y, Some(0.47));
+    }
+
+    #[test]
+    fn pupil_column_is_optional_only_for_gaze() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gaze_only.csv");
+        std::fs::write(
+            &path,
+            "timestamp,confidence,eye,norm_pos_x,norm_pos_y\n0.0,0.9,left,0.5,0.5\n",
+        )
+        .unwrap();
+        let err = read_eye_csv_layout(&path, &EyeCsvLayout::PUPIL_LABS).unwrap_err();
+        assert!(err.to_string().contains("diameter"), "{err}");
+        let samples = read_gaze_csv_layout(&path, &EyeCsvLayout::PUPIL_LABS).unwrap();
+        assert_eq!(samples.len(), 1);
+        assert!(samples[0].pupil_mm.is_none());
+    }
+
     #[test]
     fn filters_confidence() {
         let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -0,0 +1,834 @@
+use super::{bitalino, edf, eye, openbci, parquet, text, wfdb, xdf};
+use crate::signal::TimeSeries;
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Bytes read from the start of a file for sniffing.
+const PROBE_BYTES: usize = 8192;
+
+/// One channel of a loaded recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingChannel {
+    pub name: String,
+    /// Other names the channel answers to, e.g. the sensor type of an OpenSignals column.
+    pub aliases: Vec<String>,
+    pub units: Option<String>,
+    pub fs: f64,
+    pub data: Vec<f64>,
+}
+
+impl RecordingChannel {
+    pub fn to_time_series(&self) -> TimeSeries {
+        TimeSeries {
+            fs: self.fs,
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// Annotation stored inside the file (e.g. EDF+ TALs); onsets are seconds from the start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingAnnotation {
+    pub onset_s: f64,
+    pub duration_s: Option<f64>,
+    pub label: String,
+}
+
+/// Common result of every loader.
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    /// Name of the loader that produced the recording.
+    pub format: String,
+    pub channels: Vec<RecordingChannel>,
+    pub annotations: Vec<RecordingAnnotation>,
+    /// Pupil/gaze samples of eye-tracker exports.
+    pub eye: Vec<eye::PupilSample>,
+}
+
+impl Recording {
+    pub fn channel_names(&self) -> Vec<&str> {
+        self.channels.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// Look a channel up by name, then alias (both case-insensitive), then zero-based index.
+    pub fn channel(&self, selector: &str) -> Option<&RecordingChannel> {
+        self.channels
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(selector))
+            .or_else(|| {
+                self.channels
+                    .iter()
+                    .find(|c| c.aliases.iter().any(|a| a.eq_ignore_ascii_case(selector)))
+            })
+            .or_else(|| {
+                selector
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| self.channels.get(i))
+            })
+    }
+
+    /// The selected channel, or the first one when `selector` is `None`.
+    pub fn time_series(&self, selector: Option<&str>) -> Result<TimeSeries> {
+        let channel = match selector {
+            Some(selector) => self.channel(selector).ok_or_else(|| {
+                anyhow!(
+                    "channel '{}' not found in {} recording (available: {})",
+                    selector,
+                    self.format,
+                    self.channel_names().join(", ")
+                )
+            })?,
+            None => self
+                .channels
+                .first()
+                .ok_or_else(|| anyhow!("{} recording has no signal channels", self.format))?,
+        };
+        Ok(channel.to_time_series())
+    }
+}
+
+/// Settings that apply to formats without their own timing information.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadOptions {
+    /// Sampling rate for plain sample files, which do not store one.
+    pub fallback_fs: Option<f64>,
+}
+
+/// What a loader sees when asked whether it can read a file.
+pub struct Probe<'a> {
+    pub path: &'a Path,
+    /// Lower-case extension without the dot, empty when there is none.
+    pub extension: String,
+    /// First bytes of the file.
+    pub head: &'a [u8],
+}
+
+impl Probe<'_> {
+    pub fn starts_with(&self, magic: &[u8]) -> bool {
+        self.head.starts_with(magic)
+    }
+
+    /// True when the head looks like text (no NUL bytes).
+    pub fn is_text(&self) -> bool {
+        !self.head.contains(&0)
+    }
+
+    /// First line that is neither blank nor a `#` comment.
+    pub fn first_line(&self) -> Option<String> {
+        if !self.is_text() {
+            return None;
+        }
+        String::from_utf8_lossy(self.head)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+    }
+
+    /// Header columns of a comma- or tab-separated file, lower-cased and unquoted.
+    pub fn csv_columns(&self) -> Vec<String> {
+        let Some(line) = self.first_line() else {
+            return Vec::new();
+        };
+        let delimiter = if line.contains('\t') { '\t' } else { ',' };
+        if !line.contains(delimiter) {
+            return Vec::new();
+        }
+        line.split(delimiter)
+            .map(|c| c.trim().trim_matches('"').to_ascii_lowercase())
+            .collect()
+    }
+}
+
+/// A file format the registry can detect and read.
+pub trait FormatLoader: Send + Sync {
+    /// Short identifier, e.g. `wfdb`.
+    fn name(&self) -> &str;
+
+    /// Confidence (0 = cannot read, 100 = certain) that this loader handles the probed file.
+    fn sniff(&self, probe: &Probe) -> u8;
+
+    fn load(&self, path: &Path, options: &LoadOptions) -> Result<Recording>;
+}
+
+/// Ordered set of loaders; the highest sniff score wins and ties go to the most recently
+/// registered loader, so third-party loaders can override built-in ones.
+#[derive(Clone)]
+pub struct LoaderRegistry {
+    loaders: Vec<Arc<dyn FormatLoader>>,
+}
+
+impl Default for LoaderRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(TextLoader);
+        registry.register(WfdbLoader);
+        registry.register(EdfLoader);
+        registry.register(BitalinoLoader);
+        registry.register(OpenBciLoader);
+        registry.register(EyeLoader::pupil_labs());
+        registry.register(EyeLoader::tobii());
+        registry.register(TableLoader);
+        registry.register(XdfLoader);
+        registry
+    }
+}
+
+impl LoaderRegistry {
+    pub fn empty() -> Self {
+        Self {
+            loaders: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, loader: impl FormatLoader + 'static) {
+        self.loaders.insert(0, Arc::new(loader));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.loaders.iter().map(|l| l.name()).collect()
+    }
+
+    /// Pick the loader for `path`.
+    pub fn detect(&self, path: &Path) -> Result<Arc<dyn FormatLoader>> {
+        let mut head = Vec::with_capacity(PROBE_BYTES);
+        File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?
+            .take(PROBE_BYTES as u64)
+            .read_to_end(&mut head)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let probe = Probe {
+            path,
+            extension: path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_ascii_lowercase(),
+            head: &head,
+        };
+        let mut best: Option<(u8, &Arc<dyn FormatLoader>)> = None;
+        for loader in &self.loaders {
+            let score = loader.sniff(&probe);
+            if score > 0 && best.is_none_or(|(s, _)| score > s) {
+                best = Some((score, loader));
+            }
+        }
+        best.map(|(_, loader)| Arc::clone(loader)).ok_or_else(|| {
+            anyhow!(
+                "unrecognised file format: {} (known formats: {})",
+                path.display(),
+                self.names().join(", ")
+            )
+        })
+    }
+
+    pub fn load(&self, path: &Path, options: &LoadOptions) -> Result<Recording> {
+        let loader = self.detect(path)?;
+        load_with(loader.as_ref(), path, options)
+    }
+
+    /// Load with a named loader, skipping detection.
+    pub fn load_as(&self, name: &str, path: &Path, options: &LoadOptions) -> Result<Recording> {
+        let loader = self
+            .loaders
+            .iter()
+            .find(|l| l.name() == name)
+            .ok_or_else(|| anyhow!("no loader named '{}'", name))?;
+        load_with(loader.as_ref(), path, options)
+    }
+}
+
+fn load_with(loader: &dyn FormatLoader, path: &Path, options: &LoadOptions) -> Result<Recording> {
+    let mut recording = loader
+        .load(path, options)
+        .with_context(|| format!("reading {} as {}", path.display(), loader.name()))?;
+    if recording.format.is_empty() {
+        recording.format = loader.name().to_string();
+    }
+    Ok(recording)
+}
+
+fn global() -> &'static RwLock<LoaderRegistry> {
+    static REGISTRY: OnceLock<RwLock<LoaderRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(LoaderRegistry::default()))
+}
+
+/// Add a loader to the process-wide registry used by [`load_recording`].
+pub fn register_loader(loader: impl FormatLoader + 'static) {
+    global()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(loader);
+}
+
+/// Name of the loader the process-wide registry picks for `path`.
+pub fn detect_format(path: &Path) -> Result<String> {
+    let registry = global().read().unwrap_or_else(|e| e.into_inner());
+    Ok(registry.detect(path)?.name().to_string())
+}
+
+/// Detect the format of `path` and load it with the process-wide registry.
+pub fn load_recording(path: &Path, options: &LoadOptions) -> Result<Recording> {
+    let loader = global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .detect(path)?;
+    load_with(loader.as_ref(), path, options)
+}
+
+/// Load `path` with the process-wide registry's loader called `name`, skipping detection.
+pub fn load_recording_as(name: &str, path: &Path, options: &LoadOptions) -> Result<Recording> {
+    global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .load_as(name, path, options)
+}
+
+/// Newline-delimited samples; the fallback for anything text-like.
+struct TextLoader;
+
+impl FormatLoader for TextLoader {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        match probe.first_line() {
+            Some(line) if line.parse::<f64>().is_ok() => 20,
+            // Let the text parser report the offending line rather than "unrecognised".
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
+    fn load(&self, path: &Path, options: &LoadOptions) -> Result<Recording> {
+        let fs = options
+            .fallback_fs
+            .ok_or_else(|| anyhow!("plain sample files need a sampling rate (--fs)"))?;
+        Ok(Recording {
+            channels: vec![RecordingChannel {
+                name: "signal".into(),
+                aliases: Vec::new(),
+                units: None,
+                fs,
+                data: text::read_f64_series(path)?,
+            }],
+            ..Recording::default()
+        })
+    }
+}
+
+struct WfdbLoader;
+
+impl FormatLoader for WfdbLoader {
+    fn name(&self) -> &str {
+        "wfdb"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        match probe.extension.as_str() {
+            "hea" => 100,
+            "dat" if probe.path.with_extension("hea").exists() => 90,
+            _ => 0,
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        let record = wfdb::read_wfdb_record(path)?;
+        Ok(Recording {
+            channels: record
+                .signals
+                .into_iter()
+                .map(|s| RecordingChannel {
+                    name: s.description,
+                    aliases: Vec::new(),
+                    units: Some(s.units).filter(|u| !u.is_empty()),
+                    fs: s.fs,
+                    data: s.data,
+                })
+                .collect(),
+            ..Recording::default()
+        })
+    }
+}
+
+struct EdfLoader;
+
+impl FormatLoader for EdfLoader {
+    fn name(&self) -> &str {
+        "edf"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        if probe.starts_with(b"0       ") || probe.starts_with(b"\xffBIOSEMI") {
+            100
+        } else if matches!(probe.extension.as_str(), "edf" | "bdf" | "rec") {
+            60
+        } else {
+            0
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        let mut reader = edf::EdfReader::open(path)?;
+        let mut channels = Vec::new();
+        for signal in reader.data_signals() {
+            let info = reader.header().signals[signal].clone();
+            let ts = reader.read_signal(signal)?;
+            channels.push(RecordingChannel {
+                name: info.label,
+                aliases: Vec::new(),
+                units: Some(info.physical_dimension).filter(|u| !u.is_empty()),
+                fs: ts.fs,
+                data: ts.data,
+            });
+        }
+        let annotations = reader
+            .annotations()?
+            .into_iter()
+            .map(|a| RecordingAnnotation {
+                onset_s: a.onset_s,
+                duration_s: a.duration_s,
+                label: a.text,
+            })
+            .collect();
+        Ok(Recording {
+            channels,
+            annotations,
+            ..Recording::default()
+        })
+    }
+}
+
+/// Reads every numeric column of a timestamped CSV with `read` (one call per column).
+fn timestamped_csv_channels(
+    path: &Path,
+    read: fn(&Path, &str) -> Result<TimeSeries>,
+) -> Result<Vec<RecordingChannel>> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let first = reader.records().next().transpose()?.unwrap_or_default();
+    // Columns whose first value is not a number (e.g. formatted dates) are not channels.
+    let channels = headers
+        .iter()
+        .enumerate()
+        .filter(|(i, h)| {
+            !h.eq_ignore_ascii_case("timestamp")
+                && first.get(*i).is_some_and(|v| v.parse::<f64>().is_ok())
+        })
+        .map(|(_, h)| {
+            let ts = read(path, h).with_context(|| format!("reading column {h}"))?;
+            Ok(RecordingChannel {
+                name: h.to_string(),
+                aliases: Vec::new(),
+                units: None,
+                fs: ts.fs,
+                data: ts.data,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if channels.is_empty() {
+        bail!("no numeric columns in {}", path.display());
+    }
+    Ok(channels)
+}
+
+fn has_column(columns: &[String], pred: impl Fn(&str) -> bool) -> bool {
+    columns.iter().any(|c| pred(c))
+}
+
+fn numbered(column: &str, prefix: &str) -> bool {
+    column
+        .strip_prefix(prefix)
+        .is_some_and(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()))
+}
+
+struct BitalinoLoader;
+
+impl FormatLoader for BitalinoLoader {
+    fn name(&self) -> &str {
+        "bitalino"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        if probe.starts_with(bitalino::OPENSIGNALS_MAGIC.as_bytes()) {
+            return 100;
+        }
+        let columns = probe.csv_columns();
+        let analog = has_column(&columns, |c| c.starts_with("analog") || numbered(c, "a"));
+        if analog && has_column(&columns, |c| c == "timestamp") {
+            80
+        } else {
+            0
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        if bitalino::is_opensignals(path)? {
+            let export = bitalino::read_opensignals(path)?;
+            let mut channels = Vec::new();
+            for device in export.devices {
+                for c in device.channels {
+                    channels.push(RecordingChannel {
+                        aliases: vec![c.sensor, c.label],
+                        name: c.column,
+                        units: c.units.map(str::to_string),
+                        fs: device.fs,
+                        data: c.data,
+                    });
+                }
+            }
+            return Ok(Recording {
+                format: "opensignals".into(),
+                channels,
+                ..Recording::default()
+            });
+        }
+        Ok(Recording {
+            channels: timestamped_csv_channels(path, bitalino::read_bitalino_csv)?,
+            ..Recording::default()
+        })
+    }
+}
+
+struct OpenBciLoader;
+
+impl FormatLoader for OpenBciLoader {
+    fn name(&self) -> &str {
+        "openbci"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        if probe.starts_with(b"%OpenBCI") {
+            return 100;
+        }
+        let columns = probe.csv_columns();
+        let exg = has_column(&columns, |c| {
+            numbered(c, "ch") || c.starts_with("exg channel")
+        });
+        if exg && has_column(&columns, |c| c == "timestamp") {
+            80
+        } else {
+            0
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        if openbci::is_openbci_gui(path)? {
+            let export = openbci::read_openbci_gui(path)?;
+            let mut exg = 0;
+            let channels = export
+                .channels
+                .into_iter()
+                .map(|c| {
+                    let mut aliases = Vec::new();
+                    if c.kind == openbci::OpenBciChannelKind::Exg {
+                        exg += 1;
+                        aliases.push(format!("Ch{exg}"));
+                    }
+                    RecordingChannel {
+                        name: c.name,
+                        aliases,
+                        units: c.units.map(str::to_string),
+                        fs: export.fs,
+                        data: c.data,
+                    }
+                })
+                .collect();
+            return Ok(Recording {
+                channels,
+                ..Recording::default()
+            });
+        }
+        Ok(Recording {
+            channels: timestamped_csv_channels(path, openbci::read_openbci_csv)?,
+            ..Recording::default()
+        })
+    }
+}
+
+struct EyeLoader {
+    name: &'static str,
+    layout: eye::EyeCsvLayout<'static>,
+}
+
+impl EyeLoader {
+    fn pupil_labs() -> Self {
+        Self {
+            name: "pupil-labs",
+            layout: eye::EyeCsvLayout::PUPIL_LABS,
+        }
+    }
+
+    fn tobii() -> Self {
+        Self {
+            name: "tobii",
+            layout: eye::EyeCsvLayout::TOBII,
+        }
+    }
+}
+
+impl FormatLoader for EyeLoader {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        let columns = probe.csv_columns();
+        let has = |col: Option<&str>| col.is_some_and(|col| columns.iter().any(|c| c == col));
+        if has(Some(self.layout.timestamp))
+            && (has(self.layout.pupil) || (has(self.layout.gaze_x) && has(self.layout.gaze_y)))
+        {
+            90
+        } else {
+            0
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        Ok(Recording {
+            eye: eye::read_gaze_csv_layout(path, &self.layout)?,
+            ..Recording::default()
+        })
+    }
+}
+
+struct TableLoader;
+
+impl FormatLoader for TableLoader {
+    fn name(&self) -> &str {
+        "parquet"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        if parquet::is_table_path(probe.path) {
+            100
+        } else {
+            0
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        let table = parquet::read_signals(path)?;
+        Ok(Recording {
+            channels: table
+                .channels
+                .into_iter()
+                .map(|c| RecordingChannel {
+                    name: c.name,
+                    aliases: Vec::new(),
+                    units: c.units,
+                    fs: table.fs,
+                    data: c.data,
+                })
+                .collect(),
+            ..Recording::default()
+        })
+    }
+}
+
+/// XDF sessions: every numeric channel becomes a recording channel named `stream/label`
+/// (answering to its label and stream type too) and marker streams become annotations.
+/// Channels are padded with NaN so that sample 0 of every channel is the first sample of the
+/// earliest stream.
+struct XdfLoader;
+
+impl FormatLoader for XdfLoader {
+    fn name(&self) -> &str {
+        "xdf"
+    }
+
+    fn sniff(&self, probe: &Probe) -> u8 {
+        if probe.starts_with(b"XDF:") {
+            100
+        } else if probe.extension == "xdf" {
+            60
+        } else {
+            0
+        }
+    }
+
+    fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+        let file = xdf::read_xdf(path)?;
+        let zero = file.first_sample_time().unwrap_or(0.0);
+        let mut channels = Vec::new();
+        let mut annotations = Vec::new();
+        for stream in &file.streams {
+            if stream.is_markers() {
+                annotations.extend(stream.markers().into_iter().map(|(t, label)| {
+                    RecordingAnnotation {
+                        onset_s: t - zero,
+                        duration_s: None,
+                        label: label.to_string(),
+                    }
+                }));
+                continue;
+            }
+            let fs = stream.fs();
+            let pad = stream
+                .timestamps
+                .first()
+                .map_or(0, |t| ((t - zero) * fs).round().max(0.0) as usize);
+            for (idx, label) in stream.channel_labels().into_iter().enumerate() {
+                let ts = stream.time_series(&idx.to_string())?;
+                let mut data = vec![f64::NAN; pad];
+                data.extend(ts.data);
+                channels.push(RecordingChannel {
+                    name: format!("{}/{}", stream.name, label),
+                    aliases: vec![label, stream.stream_type.clone()],
+                    units: stream.channels.get(idx).and_then(|c| c.unit.clone()),
+                    fs,
+                    data,
+                });
+            }
+        }
+        annotations.sort_by(|a, b| a.onset_s.total_cmp(&b.onset_s));
+        Ok(Recording {
+            channels,
+            annotations,
+            ..Recording::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_path(relative: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace root")
+            .join(relative)
+    }
+
+    #[test]
+    fn detects_builtin_formats() {
+        let cases = [
+            ("test_data/mitdb/100.hea", "wfdb"),
+            ("test_data/mitdb/100.dat", "wfdb"),
+            ("test_data/bitalino_sample.csv", "bitalino"),
+            ("test_data/openbci_sample.csv", "openbci"),
+            ("test_data/opensignals_sample.txt", "bitalino"),
+            ("test_data/openbci_gui_sample.txt", "openbci"),
+            ("test_data/pupil_labs_sample.csv", "pupil-labs"),
+            ("test_data/tobii_sample.tsv", "tobii"),
+            ("test_data/synthetic_recording_a.txt", "text"),
+            ("test_data/xdf_sample.xdf", "xdf"),
+        ];
+        let registry = LoaderRegistry::default();
+        for (path, expected) in cases {
+            let loader = registry.detect(&sample_path(path)).unwrap();
+            assert_eq!(loader.name(), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn loads_channels_by_name_or_index() {
+        let rec = load_recording(
+            &sample_path("test_data/bitalino_sample.csv"),
+            &LoadOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(rec.format, "bitalino");
+        assert_eq!(rec.channel_names(), vec!["analog0", "analog1"]);
+        assert_eq!(rec.time_series(Some("ANALOG1")).unwrap().data[0], 0.4);
+        assert_eq!(rec.time_series(Some("0")).unwrap().data[0], 1.2);
+        assert!(rec.time_series(Some("analog7")).is_err());
+
+        let text = sample_path("test_data/tiny_rr.txt");
+        assert!(load_recording(&text, &LoadOptions::default()).is_err());
+        let rec = load_recording(
+            &text,
+            &LoadOptions {
+                fallback_fs: Some(4.0),
+            },
+        )
+        .unwrap();
+        assert_eq!(rec.channels[0].fs, 4.0);
+    }
+
+    #[test]
+    fn bad_values_in_numeric_columns_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bitalino.csv");
+        std::fs::write(&path, "timestamp,analog0,label\n0.0,1.0,a\n0.001,oops,b\n").unwrap();
+        let err = load_recording(&path, &LoadOptions::default()).unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("analog0"), "{message}");
+        assert!(message.contains("line 3"), "{message}");
+    }
+
+    #[test]
+    fn device_exports_answer_to_sensor_and_channel_aliases() {
+        let options = LoadOptions::default();
+        let rec =
+            load_recording(&sample_path("test_data/opensignals_sample.txt"), &options).unwrap();
+        assert_eq!(rec.format, "opensignals");
+        let ecg = rec.channel("ecg").unwrap();
+        assert_eq!(ecg.name, "A2");
+        assert_eq!(ecg.units.as_deref(), Some("mV"));
+        let rec =
+            load_recording(&sample_path("test_data/openbci_gui_sample.txt"), &options).unwrap();
+        assert_eq!(rec.channel("ch3").unwrap().name, "EXG Channel 2");
+        assert_eq!(rec.channels[0].fs, 250.0);
+        let rec = load_recording(&sample_path("test_data/xdf_sample.xdf"), &options).unwrap();
+        assert_eq!(rec.channel_names(), vec!["ECG/ECG", "ECG/Resp"]);
+        assert_eq!(rec.channel("resp").unwrap().units.as_deref(), Some("a.u."));
+        let labels: Vec<&str> = rec.annotations.iter().map(|a| a.label.as_str()).collect();
+        assert_eq!(labels, vec!["start", "stim", "end"]);
+        assert!((rec.annotations[1].onset_s - 1.0).abs() < 1e-9);
+    }
+
+    struct Upper;
+
+    impl FormatLoader for Upper {
+        fn name(&self) -> &str {
+            "upper"
+        }
+
+        fn sniff(&self, probe: &Probe) -> u8 {
+            if probe.starts_with(b"UPPER") {
+                100
+            } else {
+                0
+            }
+        }
+
+        fn load(&self, path: &Path, _options: &LoadOptions) -> Result<Recording> {
+            let text = std::fs::read_to_string(path)?;
+            Ok(Recording {
+                channels: vec![RecordingChannel {
+                    name: "x".into(),
+                    aliases: Vec::new(),
+                    units: None,
+                    fs: 1.0,
+                    data: text.lines().skip(1).map(|l| l.parse().unwrap()).collect(),
+                }],
+                ..Recording::default()
+            })
+        }
+    }
+
+    #[test]
+    fn third_party_loaders_take_part_in_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.dat");
+        std::fs::write(&path, "UPPER\n1\n2\n").unwrap();
+        let mut registry = LoaderRegistry::default();
+        assert_eq!(registry.detect(&path).unwrap().name(), "text");
+        registry.register(Upper);
+        let rec = registry.load(&path, &LoadOptions::default()).unwrap();
+        assert_eq!(rec.format, "upper");
+        assert_eq!(rec.channels[0].data, vec![1.0, 2.0]);
+    }
+}
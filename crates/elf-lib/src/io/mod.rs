@@ -1,7 +1,14 @@
+pub mod bids;
+pub mod chunked;
 pub mod csv;
+pub mod edf;
 pub mod eeg;
+pub mod loader;
+pub mod parquet;
+pub mod rr;
 pub mod text;
 pub mod wfdb;
+pub mod xdf;
 
 pub mod bitalino;
 pub mod eye;
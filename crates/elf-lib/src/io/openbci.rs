@@ -1,11 +1,20 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use csv::ReaderBuilder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
 
 use crate::signal::TimeSeries;
 
-/// Load OpenBCI CSV (first data row is header) and return channel time series.
+/// Load one OpenBCI channel.
+///
+/// OpenBCI GUI exports (`%`-prefixed metadata) are parsed with [`read_openbci_gui`]; `channel`
+/// is then a column name (`EXG Channel 0`, `Accel Channel 1`) or `ChN` for the N-th (one-based)
+/// EXG channel. Anything else is read as a CSV with a `timestamp` column and named channels.
 pub fn read_openbci_csv(path: &Path, channel: &str) -> Result<TimeSeries> {
+    if is_openbci_gui(path)? {
+        return read_openbci_gui(path)?.time_series(channel);
+    }
     let mut reader = ReaderBuilder::new()
         .has_headers(true)
         .from_path(path)
@@ -22,7 +31,7 @@ pub fn read_openbci_csv(path: &Path, channel: &str) -> Result<TimeSeries> {
     let mut values = Vec::new();
     let mut last_ts = None;
     let mut fs = 0.0;
-    for record in reader.records() {
+    for (row, record) in reader.records().enumerate() {
         let record = record.context("reading record")?;
         let ts_str = record
             .get(ts_idx)
@@ -33,7 +42,9 @@ pub fn read_openbci_csv(path: &Path, channel: &str) -> Result<TimeSeries> {
         let value_str = record
             .get(column)
             .ok_or_else(|| anyhow::anyhow!("missing channel column"))?;
-        let value = value_str.parse::<f64>().context("parsing channel value")?;
+        let value = value_str
+            .parse::<f64>()
+            .with_context(|| format!("parsing channel value {value_str:?} on line {}", row + 2))?;
         if let Some(prev) = last_ts {
             if fs == 0.0 {
                 fs = 1.0 / (ts - prev);
@@ -48,6 +59,267 @@ pub fn read_openbci_csv(path: &Path, channel: &str) -> Result<TimeSeries> {
     Ok(TimeSeries { fs, data: values })
 }
 
+/// True when `path` starts with OpenBCI GUI `%` metadata.
+pub fn is_openbci_gui(path: &Path) -> Result<bool> {
+    let mut head = [0u8; 8];
+    let n = File::open(path)
+        .with_context(|| format!("opening {}", path.display()))?
+        .read(&mut head)?;
+    Ok(head[..n].starts_with(b"%OpenBCI"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenBciChannelKind {
+    /// EEG/EMG/ECG inputs, in microvolts.
+    Exg,
+    /// Accelerometer axes, in g.
+    Accel,
+    /// Analog read pins (raw counts).
+    Analog,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenBciChannel {
+    pub name: String,
+    pub kind: OpenBciChannelKind,
+    pub units: Option<&'static str>,
+    pub data: Vec<f64>,
+}
+
+/// A parsed OpenBCI GUI recording (`OpenBCI-RAW-*.txt`, GUI v4 and v5 layouts).
+#[derive(Debug, Clone)]
+pub struct OpenBciRecording {
+    /// `%Board` metadata, e.g. `OpenBCI_GUI$BoardCytonSerial`.
+    pub board: Option<String>,
+    pub fs: f64,
+    pub channels: Vec<OpenBciChannel>,
+    /// Unix timestamps in seconds (NaN in gaps); empty when the export has none.
+    pub timestamps: Vec<f64>,
+    /// Samples missing from the wrapping sample index; they are NaN in every channel.
+    pub dropped_samples: usize,
+}
+
+impl OpenBciRecording {
+    pub fn channel(&self, selector: &str) -> Option<&OpenBciChannel> {
+        self.channels
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(selector))
+            .or_else(|| {
+                let lower = selector.to_ascii_lowercase();
+                let n: usize = lower.strip_prefix("ch")?.trim().parse().ok()?;
+                self.channels
+                    .iter()
+                    .filter(|c| c.kind == OpenBciChannelKind::Exg)
+                    .nth(n.checked_sub(1)?)
+            })
+    }
+
+    pub fn time_series(&self, selector: &str) -> Result<TimeSeries> {
+        let channel = self.channel(selector).ok_or_else(|| {
+            let names: Vec<&str> = self.channels.iter().map(|c| c.name.as_str()).collect();
+            anyhow!(
+                "missing channel column '{}' (available: {})",
+                selector,
+                names.join(", ")
+            )
+        })?;
+        Ok(TimeSeries {
+            fs: self.fs,
+            data: channel.data.clone(),
+        })
+    }
+}
+
+enum GuiColumn {
+    Index,
+    Timestamp,
+    Channel(OpenBciChannelKind),
+    Skip,
+}
+
+fn classify(name: &str) -> GuiColumn {
+    let lower = name.to_ascii_lowercase();
+    if lower == "sample index" || lower == "sampleindex" {
+        GuiColumn::Index
+    } else if lower == "timestamp" {
+        GuiColumn::Timestamp
+    } else if lower.starts_with("exg channel") || lower.starts_with("eeg channel") {
+        GuiColumn::Channel(OpenBciChannelKind::Exg)
+    } else if lower.starts_with("accel channel") {
+        GuiColumn::Channel(OpenBciChannelKind::Accel)
+    } else if lower.starts_with("analog channel") {
+        GuiColumn::Channel(OpenBciChannelKind::Analog)
+    } else {
+        GuiColumn::Skip
+    }
+}
+
+/// Column names of a GUI v4 export, which has no header row: index, EXG channels, three
+/// accelerometer axes, a formatted time and a unix timestamp in milliseconds.
+fn v4_columns(width: usize, n_channels: usize) -> Vec<String> {
+    let mut names = vec!["Sample Index".to_string()];
+    names.extend((0..n_channels).map(|i| format!("EXG Channel {i}")));
+    names.extend((0..3).map(|i| format!("Accel Channel {i}")));
+    while names.len() + 1 < width {
+        names.push("Other".into());
+    }
+    names.push("Timestamp".into());
+    names.truncate(width);
+    names
+}
+
+/// Parse an OpenBCI GUI recording.
+pub fn read_openbci_gui(path: &Path) -> Result<OpenBciRecording> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut board = None;
+    let mut fs = None;
+    let mut n_channels = None;
+    let mut header: Option<Vec<String>> = None;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(meta) = trimmed.strip_prefix('%') {
+            if let Some((key, value)) = meta.split_once('=') {
+                let value = value.trim();
+                match key.trim().to_ascii_lowercase().as_str() {
+                    "sample rate" => {
+                        fs = value.trim_end_matches("Hz").trim().parse::<f64>().ok();
+                    }
+                    "number of channels" => n_channels = value.parse::<usize>().ok(),
+                    "board" => board = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        let cells: Vec<String> = trimmed.split(',').map(|c| c.trim().to_string()).collect();
+        if header.is_none() && rows.is_empty() && cells[0].parse::<f64>().is_err() {
+            header = Some(cells);
+            continue;
+        }
+        rows.push(cells);
+    }
+    if rows.is_empty() {
+        bail!("{} has no samples", path.display());
+    }
+    let header = match header {
+        Some(header) => header,
+        None => {
+            let n = n_channels.ok_or_else(|| anyhow!("missing '%Number of channels' line"))?;
+            v4_columns(rows[0].len(), n)
+        }
+    };
+    let cell = |row: &[String], col: usize| {
+        row.get(col)
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(f64::NAN)
+    };
+    let kinds: Vec<GuiColumn> = header.iter().map(|h| classify(h)).collect();
+
+    let index_col = kinds.iter().position(|k| matches!(k, GuiColumn::Index));
+    let index: Vec<f64> = rows
+        .iter()
+        .map(|r| index_col.map_or(f64::NAN, |c| cell(r, c)))
+        .collect();
+    let (slots, dropped_samples) = unwrap_sample_index(&index);
+    let len = slots.last().map_or(0, |s| s + 1);
+    let column_data = |col: usize, scale: f64| {
+        let mut data = vec![f64::NAN; len];
+        for (row, slot) in rows.iter().zip(&slots) {
+            data[*slot] = cell(row, col) * scale;
+        }
+        data
+    };
+
+    let mut channels = Vec::new();
+    let mut timestamps = Vec::new();
+    for (col, kind) in kinds.iter().enumerate() {
+        match kind {
+            GuiColumn::Channel(kind) => channels.push(OpenBciChannel {
+                name: header[col].clone(),
+                kind: *kind,
+                units: match kind {
+                    OpenBciChannelKind::Exg => Some("uV"),
+                    OpenBciChannelKind::Accel => Some("g"),
+                    OpenBciChannelKind::Analog => None,
+                },
+                data: column_data(col, 1.0),
+            }),
+            GuiColumn::Timestamp if timestamps.is_empty() => {
+                // GUI v4 writes milliseconds, v5 seconds.
+                let first = rows.iter().map(|r| cell(r, col)).find(|v| v.is_finite());
+                let scale = if first.is_some_and(|t| t > 1e11) {
+                    1e-3
+                } else {
+                    1.0
+                };
+                timestamps = column_data(col, scale);
+            }
+            _ => {}
+        }
+    }
+    if channels.is_empty() {
+        bail!("{} has no EXG/accel/analog columns", path.display());
+    }
+    let fs = match fs {
+        Some(fs) if fs > 0.0 => fs,
+        _ => median_rate(&timestamps).unwrap_or(250.0),
+    };
+    Ok(OpenBciRecording {
+        board,
+        fs,
+        channels,
+        timestamps,
+        dropped_samples,
+    })
+}
+
+fn median_rate(timestamps: &[f64]) -> Option<f64> {
+    let mut steps: Vec<f64> = timestamps
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .filter(|d| d.is_finite() && *d > 0.0)
+        .collect();
+    if steps.is_empty() {
+        return None;
+    }
+    steps.sort_by(|a, b| a.total_cmp(b));
+    Some(1.0 / steps[steps.len() / 2])
+}
+
+/// Output position of every row from the board's wrapping sample counter (Cyton counts 0–255).
+/// The counter period is taken as the largest index + 1 and the nominal step as the most
+/// common increment (2 for daisy-merged Cyton data); larger jumps leave NaN gaps.
+fn unwrap_sample_index(index: &[f64]) -> (Vec<usize>, usize) {
+    if index.len() < 2 || index.iter().any(|i| !i.is_finite() || *i < 0.0) {
+        return ((0..index.len()).collect(), 0);
+    }
+    let period = index.iter().fold(0.0f64, |m, i| m.max(*i)) as u64 + 1;
+    let diffs: Vec<u64> = index
+        .windows(2)
+        .map(|w| (w[1] as u64 + period - w[0] as u64) % period)
+        .collect();
+    let mut counts = std::collections::BTreeMap::new();
+    for d in diffs.iter().filter(|d| **d > 0) {
+        *counts.entry(*d).or_insert(0usize) += 1;
+    }
+    let Some((&step, _)) = counts.iter().max_by_key(|(_, n)| **n) else {
+        return ((0..index.len()).collect(), 0);
+    };
+    let mut slots = vec![0];
+    let mut dropped = 0;
+    for d in diffs {
+        let advance = (d / step).max(1) as usize;
+        dropped += advance - 1;
+        slots.push(slots.last().unwrap() + advance);
+    }
+    (slots, dropped)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,6 +334,45 @@ mod tests {
         assert!((ts.data[0] - 120.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn parses_gui_v5_export_with_wraparound() {
+        let path = sample_path("test_data/openbci_gui_sample.txt");
+        let rec = read_openbci_gui(&path).expect("read export");
+        assert_eq!(rec.fs, 250.0);
+        assert_eq!(rec.board.as_deref(), Some("OpenBCI_GUI$BoardCytonSerial"));
+        assert_eq!(rec.dropped_samples, 1);
+        assert_eq!(rec.channels.len(), 14);
+        let exg = rec.channel("Ch1").unwrap();
+        assert_eq!(exg.name, "EXG Channel 0");
+        assert_eq!(exg.units, Some("uV"));
+        assert_eq!(exg.data.len(), 12);
+        assert!(exg.data[7].is_nan());
+        assert!((exg.data[0] + 1200.5).abs() < 1e-9);
+        assert!((rec.timestamps[8] - (1_710_411_667.0 + 8.0 / 250.0)).abs() < 1e-6);
+        let accel = read_openbci_csv(&path, "accel channel 2").unwrap();
+        assert!((accel.data[0] - 0.998).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parses_gui_v4_export_without_header_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("OpenBCI-RAW-v4.txt");
+        std::fs::write(
+            &path,
+            "%OpenBCI Raw EEG Data\n%Number of channels = 4\n%Sample Rate = 200.0 Hz\n\
+             %First Column = SampleIndex\n%Last Column = Timestamp\n\
+             199, 1.0, 2.0, 3.0, 4.0, 0.0, 0.0, 0.0, 12:00:00.000, 1589000000000\n\
+             200, 1.5, 2.5, 3.5, 4.5, 0.0, 0.0, 0.0, 12:00:00.005, 1589000000005\n\
+             0, 2.0, 3.0, 4.0, 5.0, 0.0, 0.0, 0.0, 12:00:00.010, 1589000000010\n",
+        )
+        .unwrap();
+        let rec = read_openbci_gui(&path).unwrap();
+        assert_eq!(rec.fs, 200.0);
+        assert_eq!(rec.dropped_samples, 0);
+        assert_eq!(rec.time_series("Ch4").unwrap().data, vec![4.0, 4.5, 5.0]);
+        assert!((rec.timestamps[2] - 1_589_000_000.01).abs() < 1e-6);
+    }
+
     fn sample_path(relative: &str) -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .parent()
@@ -0,0 +1,1003 @@
+use crate::metrics::hrv::{HRVNonlinear, HRVPsd, HRVTime};
+use crate::signal::{Events, RRSeries, TimeSeries};
+use anyhow::{anyhow, bail, Context, Result};
+use arrow::array::{Array, PrimitiveArray, Utf8Array};
+use arrow::chunk::Chunk;
+use arrow::datatypes::{DataType, Field, Metadata, Schema};
+use arrow::io::ipc;
+use arrow::io::parquet::{read as pq_read, write as pq_write};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+pub const SCHEMA_VERSION: &str = "1";
+pub const SCHEMA_VERSION_KEY: &str = "elf.schema_version";
+pub const KIND_KEY: &str = "elf.kind";
+pub const FS_KEY: &str = "elf.fs";
+pub const UNITS_KEY_PREFIX: &str = "elf.units.";
+
+pub const KIND_TIMESERIES: &str = "timeseries";
+pub const KIND_EVENTS: &str = "events";
+pub const KIND_RR: &str = "rr";
+pub const KIND_HRV: &str = "hrv";
+pub const KIND_RECORDS: &str = "records";
+
+const SAMPLE_INDEX: &str = "sample_index";
+const TIMESTAMP: &str = "timestamp";
+const RR_COLUMN: &str = "rr_s";
+
+/// Container format of a table file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Parquet,
+    ArrowIpc,
+}
+
+impl TableFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "parquet" | "pq" => Some(Self::Parquet),
+            "arrow" | "ipc" | "feather" => Some(Self::ArrowIpc),
+            _ => None,
+        }
+    }
+}
+
+/// True when the path has a Parquet or Arrow IPC extension.
+pub fn is_table_path(path: &Path) -> bool {
+    TableFormat::from_path(path).is_some()
+}
+
+/// One named channel of a signal table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalChannel {
+    pub name: String,
+    pub units: Option<String>,
+    pub data: Vec<f64>,
+}
+
+/// Equally sampled channels sharing one sampling rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalTable {
+    pub fs: f64,
+    /// Timestamp of the first sample in seconds.
+    pub start_time: f64,
+    pub channels: Vec<SignalChannel>,
+}
+
+impl SignalTable {
+    pub fn from_time_series(ts: &TimeSeries, channel: &str, units: Option<&str>) -> Self {
+        Self {
+            fs: ts.fs,
+            start_time: 0.0,
+            channels: vec![SignalChannel {
+                name: channel.to_string(),
+                units: units.map(str::to_string),
+                data: ts.data.clone(),
+            }],
+        }
+    }
+
+    pub fn channel(&self, name: &str) -> Option<&SignalChannel> {
+        self.channels.iter().find(|c| c.name == name)
+    }
+
+    /// Select a channel by name, or the first channel when `name` is `None`.
+    pub fn to_time_series(&self, name: Option<&str>) -> Result<TimeSeries> {
+        let channel = match name {
+            Some(name) => self.channel(name).ok_or_else(|| {
+                let names: Vec<&str> = self.channels.iter().map(|c| c.name.as_str()).collect();
+                anyhow!(
+                    "Channel '{}' not found (available: {})",
+                    name,
+                    names.join(", ")
+                )
+            })?,
+            None => self
+                .channels
+                .first()
+                .ok_or_else(|| anyhow!("Table has no signal channels"))?,
+        };
+        Ok(TimeSeries {
+            fs: self.fs,
+            data: channel.data.clone(),
+        })
+    }
+}
+
+/// One row of an HRV summary table.
+#[derive(Debug, Clone)]
+pub struct HrvTableRow {
+    pub label: String,
+    pub time: HRVTime,
+    pub psd: Option<HRVPsd>,
+    pub nonlinear: Option<HRVNonlinear>,
+}
+
+/// Typed column of a table; floats store NaN as null.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Text(Vec<String>),
+}
+
+impl Column {
+    fn len(&self) -> usize {
+        match self {
+            Column::Int(v) => v.len(),
+            Column::Float(v) => v.len(),
+            Column::Text(v) => v.len(),
+        }
+    }
+
+    fn field(&self, name: &str) -> Field {
+        match self {
+            Column::Int(_) => Field::new(name, DataType::Int64, false),
+            Column::Float(_) => Field::new(name, DataType::Float64, true),
+            Column::Text(_) => Field::new(name, DataType::Utf8, false),
+        }
+    }
+
+    fn to_array(&self) -> Box<dyn Array> {
+        match self {
+            Column::Int(v) => Box::new(PrimitiveArray::<i64>::from_vec(v.clone())),
+            Column::Float(v) => Box::new(PrimitiveArray::<f64>::from(
+                v.iter()
+                    .map(|x| if x.is_nan() { None } else { Some(*x) })
+                    .collect::<Vec<_>>(),
+            )),
+            Column::Text(v) => Box::new(Utf8Array::<i32>::from_slice(v)),
+        }
+    }
+}
+
+struct Table {
+    columns: Vec<(String, Column)>,
+    metadata: Metadata,
+}
+
+impl Table {
+    fn new(kind: &str) -> Self {
+        let mut metadata = Metadata::new();
+        metadata.insert(SCHEMA_VERSION_KEY.to_string(), SCHEMA_VERSION.to_string());
+        metadata.insert(KIND_KEY.to_string(), kind.to_string());
+        Self {
+            columns: Vec::new(),
+            metadata,
+        }
+    }
+
+    fn push(&mut self, name: &str, column: Column) {
+        self.columns.push((name.to_string(), column));
+    }
+
+    fn kind(&self) -> Option<&str> {
+        self.metadata.get(KIND_KEY).map(String::as_str)
+    }
+
+    fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|(n, _)| n == name).map(|(_, c)| c)
+    }
+
+    fn floats(&self, name: &str) -> Result<Vec<f64>> {
+        match self.column(name) {
+            Some(Column::Float(v)) => Ok(v.clone()),
+            Some(Column::Int(v)) => Ok(v.iter().map(|x| *x as f64).collect()),
+            Some(Column::Text(_)) => bail!("Column '{}' is not numeric", name),
+            None => bail!("Column '{}' not found", name),
+        }
+    }
+
+    fn schema(&self) -> Schema {
+        let fields: Vec<Field> = self.columns.iter().map(|(n, c)| c.field(n)).collect();
+        Schema::from(fields).with_metadata(self.metadata.clone())
+    }
+
+    fn chunk(&self) -> Result<Chunk<Box<dyn Array>>> {
+        let rows = self.columns.first().map(|(_, c)| c.len()).unwrap_or(0);
+        if let Some((name, _)) = self.columns.iter().find(|(_, c)| c.len() != rows) {
+            bail!("Column '{}' length differs from the other columns", name);
+        }
+        Ok(Chunk::try_new(
+            self.columns.iter().map(|(_, c)| c.to_array()).collect(),
+        )?)
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let format = TableFormat::from_path(path).ok_or_else(|| {
+            anyhow!(
+                "Unsupported table extension for {} (use .parquet or .arrow)",
+                path.display()
+            )
+        })?;
+        self.write_as(path, format)
+    }
+
+    fn write_as(&self, path: &Path, format: TableFormat) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let schema = self.schema();
+        let chunk = self.chunk()?;
+        match format {
+            TableFormat::Parquet => {
+                let options = parquet_options();
+                let encodings = schema
+                    .fields
+                    .iter()
+                    .map(|_| vec![pq_write::Encoding::Plain])
+                    .collect();
+                let row_groups = pq_write::RowGroupIterator::try_new(
+                    std::iter::once(Ok(chunk)),
+                    &schema,
+                    options,
+                    encodings,
+                )?;
+                let mut writer =
+                    pq_write::FileWriter::try_new(BufWriter::new(file), schema.clone(), options)?;
+                for group in row_groups {
+                    writer.write(group?)?;
+                }
+                writer.end(Some(key_values(&self.metadata)))?;
+            }
+            TableFormat::ArrowIpc => {
+                let mut writer = ipc::write::FileWriter::try_new(
+                    BufWriter::new(file),
+                    schema,
+                    None,
+                    ipc::write::WriteOptions { compression: None },
+                )?;
+                writer.write(&chunk, None)?;
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let format = TableFormat::from_path(path).ok_or_else(|| {
+            anyhow!(
+                "Unsupported table extension for {} (use .parquet or .arrow)",
+                path.display()
+            )
+        })?;
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let (schema, chunks) = match format {
+            TableFormat::Parquet => {
+                let metadata = pq_read::read_metadata(&mut reader).with_context(|| {
+                    format!("Failed to read Parquet footer of {}", path.display())
+                })?;
+                let mut schema = pq_read::infer_schema(&metadata)?;
+                if let Some(pairs) = metadata.key_value_metadata() {
+                    for kv in pairs {
+                        if let Some(value) = &kv.value {
+                            schema
+                                .metadata
+                                .entry(kv.key.clone())
+                                .or_insert_with(|| value.clone());
+                        }
+                    }
+                }
+                let chunks = pq_read::FileReader::new(
+                    reader,
+                    metadata.row_groups,
+                    schema.clone(),
+                    None,
+                    None,
+                    None,
+                )
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+                (schema, chunks)
+            }
+            TableFormat::ArrowIpc => {
+                let metadata = ipc::read::read_file_metadata(&mut reader).with_context(|| {
+                    format!("Failed to read Arrow IPC footer of {}", path.display())
+                })?;
+                let schema = metadata.schema.clone();
+                let chunks = ipc::read::FileReader::new(reader, metadata, None, None)
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                (schema, chunks)
+            }
+        };
+
+        let mut columns: Vec<(String, Option<Column>)> = schema
+            .fields
+            .iter()
+            .map(|f| (f.name.clone(), None))
+            .collect();
+        for chunk in &chunks {
+            for (slot, array) in columns.iter_mut().zip(chunk.arrays()) {
+                let Some(part) = array_to_column(array.as_ref()) else {
+                    continue;
+                };
+                slot.1 = Some(match (slot.1.take(), part) {
+                    (None, part) => part,
+                    (Some(Column::Int(mut a)), Column::Int(b)) => {
+                        a.extend(b);
+                        Column::Int(a)
+                    }
+                    (Some(Column::Float(mut a)), Column::Float(b)) => {
+                        a.extend(b);
+                        Column::Float(a)
+                    }
+                    (Some(Column::Text(mut a)), Column::Text(b)) => {
+                        a.extend(b);
+                        Column::Text(a)
+                    }
+                    _ => bail!("Column '{}' changes type between batches", slot.0),
+                });
+            }
+        }
+        let columns = columns
+            .into_iter()
+            .filter_map(|(name, column)| {
+                let column = column.or_else(|| {
+                    // Columns of a supported type in a table without rows.
+                    schema
+                        .fields
+                        .iter()
+                        .find(|f| f.name == name)
+                        .and_then(|f| empty_column(f.data_type()))
+                })?;
+                Some((name, column))
+            })
+            .collect();
+        Ok(Self {
+            columns,
+            metadata: schema.metadata,
+        })
+    }
+}
+
+fn parquet_options() -> pq_write::WriteOptions {
+    pq_write::WriteOptions {
+        write_statistics: true,
+        version: pq_write::Version::V2,
+        compression: pq_write::CompressionOptions::Uncompressed,
+        data_pagesize_limit: None,
+    }
+}
+
+fn key_values(metadata: &Metadata) -> Vec<pq_write::KeyValue> {
+    metadata
+        .iter()
+        .map(|(k, v)| pq_write::KeyValue {
+            key: k.clone(),
+            value: Some(v.clone()),
+        })
+        .collect()
+}
+
+fn empty_column(data_type: &DataType) -> Option<Column> {
+    match data_type {
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => Some(Column::Int(Vec::new())),
+        DataType::Float32 | DataType::Float64 => Some(Column::Float(Vec::new())),
+        DataType::Utf8 | DataType::LargeUtf8 => Some(Column::Text(Vec::new())),
+        _ => None,
+    }
+}
+
+/// Convert a supported array into a column; nulls in float columns become NaN. Unsupported
+/// types (lists, structs, ...) are skipped by the caller.
+fn array_to_column(array: &dyn Array) -> Option<Column> {
+    macro_rules! ints {
+        ($t:ty) => {{
+            let a = array.as_any().downcast_ref::<PrimitiveArray<$t>>()?;
+            Column::Int(
+                a.iter()
+                    .map(|v| v.map(|x| *x as i64).unwrap_or(0))
+                    .collect(),
+            )
+        }};
+    }
+    macro_rules! floats {
+        ($t:ty) => {{
+            let a = array.as_any().downcast_ref::<PrimitiveArray<$t>>()?;
+            Column::Float(
+                a.iter()
+                    .map(|v| v.map(|x| *x as f64).unwrap_or(f64::NAN))
+                    .collect(),
+            )
+        }};
+    }
+    Some(match array.data_type() {
+        DataType::Int8 => ints!(i8),
+        DataType::Int16 => ints!(i16),
+        DataType::Int32 => ints!(i32),
+        DataType::Int64 => ints!(i64),
+        DataType::UInt8 => ints!(u8),
+        DataType::UInt16 => ints!(u16),
+        DataType::UInt32 => ints!(u32),
+        DataType::UInt64 => ints!(u64),
+        DataType::Float32 => floats!(f32),
+        DataType::Float64 => floats!(f64),
+        DataType::Utf8 => {
+            let a = array.as_any().downcast_ref::<Utf8Array<i32>>()?;
+            Column::Text(a.iter().map(|v| v.unwrap_or("").to_string()).collect())
+        }
+        DataType::LargeUtf8 => {
+            let a = array.as_any().downcast_ref::<Utf8Array<i64>>()?;
+            Column::Text(a.iter().map(|v| v.unwrap_or("").to_string()).collect())
+        }
+        _ => return None,
+    })
+}
+
+fn check_kind(table: &Table, expected: &str, path: &Path) -> Result<()> {
+    match table.kind() {
+        Some(kind) if kind != expected => bail!(
+            "{} holds an '{}' table, expected '{}'",
+            path.display(),
+            kind,
+            expected
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Write equally sampled channels with `sample_index`/`timestamp` columns.
+pub fn write_signals(path: &Path, signals: &SignalTable) -> Result<()> {
+    if !signals.fs.is_finite() || signals.fs <= 0.0 {
+        bail!("Sampling rate must be positive");
+    }
+    let n = signals.channels.first().map(|c| c.data.len()).unwrap_or(0);
+    let mut table = Table::new(KIND_TIMESERIES);
+    table
+        .metadata
+        .insert(FS_KEY.to_string(), signals.fs.to_string());
+    table.push(SAMPLE_INDEX, Column::Int((0..n as i64).collect()));
+    table.push(
+        TIMESTAMP,
+        Column::Float(
+            (0..n)
+                .map(|i| signals.start_time + i as f64 / signals.fs)
+                .collect(),
+        ),
+    );
+    for channel in &signals.channels {
+        if channel.name == SAMPLE_INDEX || channel.name == TIMESTAMP {
+            bail!("Channel name '{}' is reserved", channel.name);
+        }
+        if let Some(units) = &channel.units {
+            table
+                .metadata
+                .insert(format!("{UNITS_KEY_PREFIX}{}", channel.name), units.clone());
+        }
+        table.push(&channel.name, Column::Float(channel.data.clone()));
+    }
+    table.write(path)
+}
+
+/// Read a signal table. Every float column other than `timestamp` becomes a channel; the
+/// sampling rate comes from `elf.fs`, falling back to the median `timestamp` step.
+pub fn read_signals(path: &Path) -> Result<SignalTable> {
+    let table = Table::read(path)?;
+    check_kind(&table, KIND_TIMESERIES, path)?;
+    let timestamps = table.floats(TIMESTAMP).ok();
+    let fs = match table.metadata.get(FS_KEY) {
+        Some(value) => value
+            .parse::<f64>()
+            .with_context(|| format!("Invalid {} value '{}'", FS_KEY, value))?,
+        None => timestamps
+            .as_deref()
+            .and_then(fs_from_timestamps)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} has no '{}' metadata and no usable '{}' column",
+                    path.display(),
+                    FS_KEY,
+                    TIMESTAMP
+                )
+            })?,
+    };
+    let start_time = timestamps
+        .as_ref()
+        .and_then(|t| t.first().copied())
+        .filter(|t| t.is_finite())
+        .unwrap_or(0.0);
+    let channels = table
+        .columns
+        .iter()
+        .filter(|(name, column)| {
+            name != SAMPLE_INDEX && name != TIMESTAMP && matches!(column, Column::Float(_))
+        })
+        .map(|(name, column)| SignalChannel {
+            name: name.clone(),
+            units: table
+                .metadata
+                .get(&format!("{UNITS_KEY_PREFIX}{name}"))
+                .cloned(),
+            data: match column {
+                Column::Float(v) => v.clone(),
+                _ => unreachable!(),
+            },
+        })
+        .collect();
+    Ok(SignalTable {
+        fs,
+        start_time,
+        channels,
+    })
+}
+
+fn fs_from_timestamps(timestamps: &[f64]) -> Option<f64> {
+    let mut steps: Vec<f64> = timestamps
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .filter(|d| d.is_finite() && *d > 0.0)
+        .collect();
+    if steps.is_empty() {
+        return None;
+    }
+    steps.sort_by(|a, b| a.total_cmp(b));
+    Some(1.0 / steps[steps.len() / 2])
+}
+
+pub fn write_timeseries(
+    path: &Path,
+    ts: &TimeSeries,
+    channel: &str,
+    units: Option<&str>,
+) -> Result<()> {
+    write_signals(path, &SignalTable::from_time_series(ts, channel, units))
+}
+
+/// Read one channel (or the first) of a signal table.
+pub fn read_timeseries(path: &Path, channel: Option<&str>) -> Result<TimeSeries> {
+    read_signals(path)?.to_time_series(channel)
+}
+
+/// Write event sample indices; `fs` fills the `timestamp` column.
+pub fn write_events(path: &Path, events: &Events, fs: f64) -> Result<()> {
+    if !fs.is_finite() || fs <= 0.0 {
+        bail!("Sampling rate must be positive");
+    }
+    let mut table = Table::new(KIND_EVENTS);
+    table.metadata.insert(FS_KEY.to_string(), fs.to_string());
+    table.push(
+        SAMPLE_INDEX,
+        Column::Int(events.indices.iter().map(|i| *i as i64).collect()),
+    );
+    table.push(
+        TIMESTAMP,
+        Column::Float(events.indices.iter().map(|i| *i as f64 / fs).collect()),
+    );
+    table.write(path)
+}
+
+/// Read events and the sampling rate they were recorded at (when stored).
+pub fn read_events(path: &Path) -> Result<(Events, Option<f64>)> {
+    let table = Table::read(path)?;
+    check_kind(&table, KIND_EVENTS, path)?;
+    let fs = table
+        .metadata
+        .get(FS_KEY)
+        .and_then(|v| v.parse::<f64>().ok());
+    let indices = match (table.column(SAMPLE_INDEX), fs) {
+        (Some(Column::Int(v)), _) => v
+            .iter()
+            .map(|i| usize::try_from(*i).context("Negative event index"))
+            .collect::<Result<Vec<_>>>()?,
+        (_, Some(fs)) => table
+            .floats(TIMESTAMP)?
+            .iter()
+            .map(|t| (t * fs).round().max(0.0) as usize)
+            .collect(),
+        _ => bail!(
+            "{} needs an integer '{}' column or '{}' metadata",
+            path.display(),
+            SAMPLE_INDEX,
+            FS_KEY
+        ),
+    };
+    Ok((Events::from_indices(indices), fs))
+}
+
+pub fn write_rr(path: &Path, rr: &RRSeries) -> Result<()> {
+    let mut table = Table::new(KIND_RR);
+    table
+        .metadata
+        .insert(format!("{UNITS_KEY_PREFIX}{RR_COLUMN}"), "s".to_string());
+    table.push("beat", Column::Int((0..rr.rr.len() as i64).collect()));
+    table.push(RR_COLUMN, Column::Float(rr.rr.clone()));
+    table.write(path)
+}
+
+/// Read RR intervals (seconds) from the `rr_s` column; null entries are dropped.
+pub fn read_rr(path: &Path) -> Result<RRSeries> {
+    let table = Table::read(path)?;
+    check_kind(&table, KIND_RR, path)?;
+    let rr = table
+        .floats(RR_COLUMN)
+        .with_context(|| format!("{} is not an RR table", path.display()))?;
+    Ok(RRSeries {
+        rr: rr.into_iter().filter(|v| v.is_finite()).collect(),
+    })
+}
+
+/// Plain values from a table: RR intervals for RR tables, otherwise the first signal channel.
+pub fn read_values(path: &Path) -> Result<Vec<f64>> {
+    let table = Table::read(path)?;
+    if table.kind() == Some(KIND_RR) || table.column(RR_COLUMN).is_some() {
+        return Ok(table
+            .floats(RR_COLUMN)?
+            .into_iter()
+            .filter(|v| v.is_finite())
+            .collect());
+    }
+    check_kind(&table, KIND_TIMESERIES, path)?;
+    table
+        .columns
+        .iter()
+        .find_map(|(name, column)| match column {
+            Column::Float(v) if name != TIMESTAMP => Some(v.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("{} has no value column", path.display()))
+}
+
+const HRV_COLUMNS: [&str; 14] = [
+    "n",
+    "avnn",
+    "sdnn",
+    "rmssd",
+    "pnn50",
+    "vlf",
+    "lf",
+    "hf",
+    "lf_hf",
+    "total_power",
+    "sd1",
+    "sd2",
+    "samp_entropy",
+    "dfa_alpha1",
+];
+
+/// Writes arbitrary named columns as a table of kind `records` in the given container,
+/// whatever the extension of `path`.
+pub fn write_records(
+    path: &Path,
+    format: TableFormat,
+    columns: Vec<(String, Column)>,
+) -> Result<()> {
+    let mut table = Table::new(KIND_RECORDS);
+    table.columns = columns;
+    table.write_as(path, format)
+}
+
+/// Reads the named columns of any table, such as one written by [`write_records`].
+pub fn read_records(path: &Path) -> Result<Vec<(String, Column)>> {
+    Ok(Table::read(path)?.columns)
+}
+
+/// Write one row per recording or window; absent PSD/nonlinear results are stored as nulls.
+pub fn write_hrv_table(path: &Path, rows: &[HrvTableRow]) -> Result<()> {
+    let mut table = Table::new(KIND_HRV);
+    for (name, units) in [
+        ("avnn", "s"),
+        ("sdnn", "s"),
+        ("rmssd", "s"),
+        ("pnn50", "%"),
+        ("vlf", "s^2"),
+        ("lf", "s^2"),
+        ("hf", "s^2"),
+        ("total_power", "s^2"),
+    ] {
+        table
+            .metadata
+            .insert(format!("{UNITS_KEY_PREFIX}{name}"), units.to_string());
+    }
+    table.push(
+        "label",
+        Column::Text(rows.iter().map(|r| r.label.clone()).collect()),
+    );
+    for (col, name) in HRV_COLUMNS.iter().enumerate() {
+        let values = rows.iter().map(|row| hrv_value(row, col)).collect();
+        table.push(name, Column::Float(values));
+    }
+    table.write(path)
+}
+
+fn hrv_value(row: &HrvTableRow, col: usize) -> f64 {
+    let psd = row.psd.as_ref();
+    let nl = row.nonlinear.as_ref();
+    match col {
+        0 => row.time.n as f64,
+        1 => row.time.avnn,
+        2 => row.time.sdnn,
+        3 => row.time.rmssd,
+        4 => row.time.pnn50,
+        5 => psd.map_or(f64::NAN, |p| p.vlf),
+        6 => psd.map_or(f64::NAN, |p| p.lf),
+        7 => psd.map_or(f64::NAN, |p| p.hf),
+        8 => psd.map_or(f64::NAN, |p| p.lf_hf),
+        9 => psd.map_or(f64::NAN, |p| p.total_power),
+        10 => nl.map_or(f64::NAN, |n| n.sd1),
+        11 => nl.map_or(f64::NAN, |n| n.sd2),
+        12 => nl.map_or(f64::NAN, |n| n.samp_entropy),
+        _ => nl.map_or(f64::NAN, |n| n.dfa_alpha1),
+    }
+}
+
+/// Read an HRV table. PSD spectra are not stored, so `points` comes back empty.
+pub fn read_hrv_table(path: &Path) -> Result<Vec<HrvTableRow>> {
+    let table = Table::read(path)?;
+    check_kind(&table, KIND_HRV, path)?;
+    let labels = match table.column("label") {
+        Some(Column::Text(v)) => v.clone(),
+        _ => bail!("{} has no 'label' column", path.display()),
+    };
+    let cols = HRV_COLUMNS
+        .iter()
+        .map(|name| table.floats(name))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(labels
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let v = |c: usize| cols[c][i];
+            let psd = (5..10).any(|c| !v(c).is_nan()).then(|| HRVPsd {
+                vlf: v(5),
+                lf: v(6),
+                hf: v(7),
+                lf_hf: v(8),
+                total_power: v(9),
+                points: Vec::new(),
+            });
+            let nonlinear = (10..14).any(|c| !v(c).is_nan()).then(|| HRVNonlinear {
+                sd1: v(10),
+                sd2: v(11),
+                samp_entropy: v(12),
+                dfa_alpha1: v(13),
+            });
+            HrvTableRow {
+                label,
+                time: HRVTime {
+                    n: v(0) as usize,
+                    avnn: v(1),
+                    sdnn: v(2),
+                    rmssd: v(3),
+                    pnn50: v(4),
+                },
+                psd,
+                nonlinear,
+            }
+        })
+        .collect())
+}
+
+/// Incremental single-channel Parquet writer: each `append` becomes one row group, so a
+/// recording stays readable up to the last flushed block if the process dies.
+pub struct ParquetSignalWriter {
+    writer: pq_write::FileWriter<BufWriter<File>>,
+    schema: Schema,
+    metadata: Metadata,
+    next_index: i64,
+    fs: f64,
+}
+
+impl ParquetSignalWriter {
+    pub fn create(path: &Path, fs: f64, channel: &str, units: Option<&str>) -> Result<Self> {
+        if !fs.is_finite() || fs <= 0.0 {
+            bail!("Recording requires a positive sampling rate");
+        }
+        let mut table = Table::new(KIND_TIMESERIES);
+        table.metadata.insert(FS_KEY.to_string(), fs.to_string());
+        if let Some(units) = units {
+            table
+                .metadata
+                .insert(format!("{UNITS_KEY_PREFIX}{channel}"), units.to_string());
+        }
+        table.push(SAMPLE_INDEX, Column::Int(Vec::new()));
+        table.push(TIMESTAMP, Column::Float(Vec::new()));
+        table.push(channel, Column::Float(Vec::new()));
+        let schema = table.schema();
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create Parquet recording at {}", path.display()))?;
+        let writer =
+            pq_write::FileWriter::try_new(BufWriter::new(file), schema.clone(), parquet_options())
+                .context("Failed to initialize Parquet writer")?;
+        Ok(Self {
+            writer,
+            schema,
+            metadata: table.metadata,
+            next_index: 0,
+            fs,
+        })
+    }
+
+    pub fn fs(&self) -> f64 {
+        self.fs
+    }
+
+    /// Number of samples written so far.
+    pub fn len(&self) -> usize {
+        self.next_index as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next_index == 0
+    }
+
+    pub fn append(&mut self, samples: &[f64]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        let indices: Vec<i64> = (0..samples.len() as i64)
+            .map(|offset| self.next_index + offset)
+            .collect();
+        let timestamps = indices.iter().map(|i| *i as f64 / self.fs).collect();
+        let table = Table {
+            columns: vec![
+                (SAMPLE_INDEX.to_string(), Column::Int(indices)),
+                (TIMESTAMP.to_string(), Column::Float(timestamps)),
+                (String::new(), Column::Float(samples.to_vec())),
+            ],
+            metadata: Metadata::new(),
+        };
+        let encodings = (0..3).map(|_| vec![pq_write::Encoding::Plain]).collect();
+        let row_groups = pq_write::RowGroupIterator::try_new(
+            std::iter::once(Ok(table.chunk()?)),
+            &self.schema,
+            parquet_options(),
+            encodings,
+        )?;
+        for group in row_groups {
+            self.writer
+                .write(group?)
+                .context("Failed to write Parquet row group")?;
+        }
+        self.next_index += samples.len() as i64;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer
+            .end(Some(key_values(&self.metadata)))
+            .context("Failed to finalize Parquet file")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_table() -> SignalTable {
+        SignalTable {
+            fs: 250.0,
+            start_time: 0.0,
+            channels: vec![
+                SignalChannel {
+                    name: "ecg".into(),
+                    units: Some("mV".into()),
+                    data: vec![0.1, 0.2, f64::NAN, 0.4],
+                },
+                SignalChannel {
+                    name: "resp".into(),
+                    units: None,
+                    data: vec![1.0, 2.0, 3.0, 4.0],
+                },
+            ],
+        }
+    }
+
+    fn same(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(x, y)| (x.is_nan() && y.is_nan()) || x == y)
+    }
+
+    #[test]
+    fn signal_tables_round_trip_in_both_containers() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = sample_table();
+        for name in ["sig.parquet", "sig.arrow"] {
+            let path = dir.path().join(name);
+            write_signals(&path, &table).unwrap();
+            let back = read_signals(&path).unwrap();
+            assert_eq!(back.fs, 250.0);
+            assert_eq!(back.channels.len(), 2);
+            assert_eq!(back.channels[0].units.as_deref(), Some("mV"));
+            assert!(same(&back.channels[0].data, &table.channels[0].data));
+            let resp = read_timeseries(&path, Some("resp")).unwrap();
+            assert_eq!(resp.data, vec![1.0, 2.0, 3.0, 4.0]);
+            assert!(read_timeseries(&path, Some("eda")).is_err());
+        }
+    }
+
+    #[test]
+    fn events_rr_and_hrv_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let events = Events::from_indices(vec![10, 250, 495]);
+        let path = dir.path().join("beats.parquet");
+        write_events(&path, &events, 250.0).unwrap();
+        let (back, fs) = read_events(&path).unwrap();
+        assert_eq!(back.indices, events.indices);
+        assert_eq!(fs, Some(250.0));
+        assert!(read_signals(&path).is_err());
+
+        let rr = RRSeries {
+            rr: vec![0.8, 0.82, 0.79],
+        };
+        let path = dir.path().join("rr.arrow");
+        write_rr(&path, &rr).unwrap();
+        assert_eq!(read_rr(&path).unwrap().rr, rr.rr);
+        assert_eq!(read_values(&path).unwrap(), rr.rr);
+
+        let rows = vec![
+            HrvTableRow {
+                label: "sub-01".into(),
+                time: HRVTime {
+                    n: 3,
+                    avnn: 0.8,
+                    sdnn: 0.02,
+                    rmssd: 0.03,
+                    pnn50: 0.0,
+                },
+                psd: None,
+                nonlinear: Some(HRVNonlinear {
+                    sd1: 0.02,
+                    sd2: 0.03,
+                    samp_entropy: 1.2,
+                    dfa_alpha1: 0.9,
+                }),
+            },
+            HrvTableRow {
+                label: "sub-02".into(),
+                time: HRVTime {
+                    n: 10,
+                    avnn: 0.9,
+                    sdnn: 0.05,
+                    rmssd: 0.04,
+                    pnn50: 12.5,
+                },
+                psd: Some(HRVPsd {
+                    lf: 1e-3,
+                    hf: 2e-3,
+                    vlf: 5e-4,
+                    lf_hf: 0.5,
+                    total_power: 3.5e-3,
+                    points: vec![[0.1, 1.0]],
+                }),
+                nonlinear: None,
+            },
+        ];
+        let path = dir.path().join("hrv.parquet");
+        write_hrv_table(&path, &rows).unwrap();
+        let back = read_hrv_table(&path).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[0].label, "sub-01");
+        assert!(back[0].psd.is_none());
+        assert_eq!(back[0].nonlinear.as_ref().unwrap().dfa_alpha1, 0.9);
+        assert_eq!(back[1].time.n, 10);
+        assert_eq!(back[1].psd.as_ref().unwrap().lf_hf, 0.5);
+        assert!(back[1].nonlinear.is_none());
+    }
+
+    #[test]
+    fn streamed_recording_reads_back_as_one_channel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rec.parquet");
+        let mut writer = ParquetSignalWriter::create(&path, 100.0, "value", None).unwrap();
+        writer.append(&[1.0, 2.0]).unwrap();
+        writer.append(&[3.0]).unwrap();
+        assert_eq!(writer.len(), 3);
+        writer.finish().unwrap();
+        let ts = read_timeseries(&path, None).unwrap();
+        assert_eq!(ts.fs, 100.0);
+        assert_eq!(ts.data, vec![1.0, 2.0, 3.0]);
+        assert_eq!(read_values(&path).unwrap(), vec![1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,486 @@
+use crate::signal::RRSeries;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use super::edf::EdfStart;
+
+/// Unix time of the FIT epoch, 1989-12-31T00:00:00Z.
+const FIT_EPOCH_UNIX: f64 = 631_065_600.0;
+const FIT_MESG_FILE_ID: u16 = 0;
+const FIT_MESG_HRV: u16 = 78;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RrFormat {
+    Text,
+    PolarHrm,
+    GarminFit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RrUnit {
+    Seconds,
+    Milliseconds,
+}
+
+impl RrUnit {
+    /// Physiological RR intervals are 0.2–3 s, so anything with a median above 10 is ms.
+    pub fn detect(values: &[f64]) -> Self {
+        let mut finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        if finite.is_empty() {
+            return Self::Seconds;
+        }
+        finite.sort_by(|a, b| a.total_cmp(b));
+        if finite[finite.len() / 2] > 10.0 {
+            Self::Milliseconds
+        } else {
+            Self::Seconds
+        }
+    }
+
+    /// Unit named by a whole word of a column label, e.g. `RR-interval [ms]` or `ibi_s`.
+    fn from_label(label: &str) -> Option<Self> {
+        label
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .find_map(|token| match token.to_ascii_lowercase().as_str() {
+                "ms" | "msec" | "msecs" | "millisecond" | "milliseconds" => {
+                    Some(Self::Milliseconds)
+                }
+                "s" | "sec" | "secs" | "second" | "seconds" => Some(Self::Seconds),
+                _ => None,
+            })
+    }
+
+    fn to_seconds(self, value: f64) -> f64 {
+        match self {
+            Self::Seconds => value,
+            Self::Milliseconds => value / 1000.0,
+        }
+    }
+}
+
+/// RR intervals read from a device or app export.
+#[derive(Debug, Clone, Serialize)]
+pub struct RrImport {
+    pub format: RrFormat,
+    /// Unit the file stored RR intervals in (they are always returned in seconds).
+    pub unit: RrUnit,
+    pub rr: RRSeries,
+    /// Time of each beat that ends an interval, in seconds from the start of the recording:
+    /// the file's own time column when it has one, otherwise the cumulative RR sum.
+    pub beat_times: Vec<f64>,
+    /// Recording start as written by the device, when the format stores one.
+    pub start: Option<String>,
+}
+
+impl RrImport {
+    fn from_intervals(
+        format: RrFormat,
+        unit: RrUnit,
+        raw: &[f64],
+        times: Option<Vec<f64>>,
+        start: Option<String>,
+    ) -> Result<Self> {
+        let mut rr = Vec::with_capacity(raw.len());
+        let mut beat_times = Vec::with_capacity(raw.len());
+        let mut elapsed = 0.0;
+        for (i, value) in raw.iter().enumerate() {
+            let seconds = unit.to_seconds(*value);
+            if !seconds.is_finite() || seconds <= 0.0 {
+                continue;
+            }
+            elapsed += seconds;
+            rr.push(seconds);
+            beat_times.push(
+                times
+                    .as_ref()
+                    .and_then(|t| t.get(i).copied())
+                    .filter(|t| t.is_finite())
+                    .unwrap_or(elapsed),
+            );
+        }
+        if rr.is_empty() {
+            bail!("no RR intervals found");
+        }
+        Ok(Self {
+            format,
+            unit,
+            rr: RRSeries { rr },
+            beat_times,
+            start,
+        })
+    }
+}
+
+/// Detect the export format of `path` and read its RR intervals.
+pub fn read_rr_file(path: &Path) -> Result<RrImport> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let is_hrm = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("hrm"));
+    if bytes.get(8..12) == Some(b".FIT") {
+        parse_fit_hrv(&bytes).with_context(|| format!("reading FIT file {}", path.display()))
+    } else if is_hrm || bytes.starts_with(b"[Params]") {
+        parse_polar_hrm(&String::from_utf8_lossy(&bytes))
+            .with_context(|| format!("reading Polar HRM file {}", path.display()))
+    } else {
+        parse_rr_text(&String::from_utf8_lossy(&bytes))
+            .with_context(|| format!("reading RR text {}", path.display()))
+    }
+}
+
+/// Polar HRM: `[Params]` key/value pairs and an `[HRData]` section. Only R-R recordings
+/// (`Interval=238`) are accepted; the first column of each data row is the RR interval in ms.
+pub fn parse_polar_hrm(text: &str) -> Result<RrImport> {
+    let mut section = String::new();
+    let mut interval = None;
+    let mut date = None;
+    let mut start_time = None;
+    let mut raw = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_ascii_lowercase();
+            continue;
+        }
+        match section.as_str() {
+            "params" => {
+                if let Some((key, value)) = line.split_once('=') {
+                    let value = value.trim().to_string();
+                    match key.trim().to_ascii_lowercase().as_str() {
+                        "interval" => interval = Some(value),
+                        "date" => date = Some(value),
+                        "starttime" => start_time = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            "hrdata" => {
+                let first = line.split_whitespace().next().unwrap_or("");
+                raw.push(
+                    first
+                        .parse::<f64>()
+                        .with_context(|| format!("invalid HRData row: {line}"))?,
+                );
+            }
+            _ => {}
+        }
+    }
+    if interval.as_deref() != Some("238") {
+        bail!(
+            "HRM file holds heart-rate samples (Interval={}), not R-R data (Interval=238)",
+            interval.unwrap_or_else(|| "?".into())
+        );
+    }
+    let start = match (date, start_time) {
+        (Some(d), Some(t)) if d.len() == 8 => {
+            Some(format!("{}-{}-{}T{}", &d[0..4], &d[4..6], &d[6..8], t))
+        }
+        (Some(d), _) => Some(d),
+        _ => None,
+    };
+    RrImport::from_intervals(RrFormat::PolarHrm, RrUnit::Milliseconds, &raw, None, start)
+}
+
+/// Delimited RR text. Header rows name the columns; the RR column is the one mentioning
+/// `rr`, `ibi` or `interval`, and a column mentioning `time` supplies beat times (in seconds,
+/// or milliseconds when labelled so). Without a header a single column is RR and two or more
+/// columns are read as Kubios-style `time, rr`. Lines starting with `#` or `%` are comments.
+pub fn parse_rr_text(text: &str) -> Result<RrImport> {
+    let mut header: Option<Vec<String>> = None;
+    let mut rows: Vec<Vec<f64>> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('%') {
+            continue;
+        }
+        let cells = split_cells(line);
+        let numbers: Vec<Option<f64>> = cells.iter().map(|c| c.parse::<f64>().ok()).collect();
+        if numbers.iter().all(Option::is_none) {
+            if rows.is_empty() {
+                header = Some(cells);
+            }
+            continue;
+        }
+        rows.push(numbers.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect());
+    }
+    if rows.is_empty() {
+        bail!("no RR intervals found");
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let (rr_col, time_col, rr_label, time_label) = match &header {
+        Some(names) => {
+            let find = |keys: &[&str]| {
+                names.iter().position(|n| {
+                    let n = n.to_ascii_lowercase();
+                    keys.iter().any(|k| n.contains(k))
+                })
+            };
+            let rr = find(&["rr", "ibi", "interval"])
+                .or(if names.len() == 1 { Some(0) } else { None })
+                .ok_or_else(|| anyhow!("no RR column in header: {}", names.join(", ")))?;
+            let time = find(&["time"]).filter(|t| *t != rr);
+            (
+                rr,
+                time,
+                names.get(rr).cloned(),
+                time.and_then(|t| names.get(t).cloned()),
+            )
+        }
+        None if width >= 2 => (1, Some(0), None, None),
+        None => (0, None, None, None),
+    };
+    let column = |col: usize| -> Vec<f64> {
+        rows.iter()
+            .map(|r| r.get(col).copied().unwrap_or(f64::NAN))
+            .collect()
+    };
+    let raw = column(rr_col);
+    let unit = rr_label
+        .as_deref()
+        .and_then(RrUnit::from_label)
+        .unwrap_or_else(|| RrUnit::detect(&raw));
+    let times = time_col.map(|c| {
+        let t = column(c);
+        let time_unit = time_label
+            .as_deref()
+            .and_then(RrUnit::from_label)
+            .unwrap_or(RrUnit::Seconds);
+        t.into_iter().map(|v| time_unit.to_seconds(v)).collect()
+    });
+    RrImport::from_intervals(RrFormat::Text, unit, &raw, times, None)
+}
+
+/// Split on the first of `,` `;` tab found (keeping empty cells), else on whitespace.
+fn split_cells(line: &str) -> Vec<String> {
+    let delimiter = [',', ';', '\t'].into_iter().find(|d| line.contains(*d));
+    match delimiter {
+        Some(d) => line.split(d).map(|c| c.trim().to_string()).collect(),
+        None => line.split_whitespace().map(str::to_string).collect(),
+    }
+}
+
+struct FitField {
+    number: u8,
+    size: usize,
+}
+
+struct FitDefinition {
+    global: u16,
+    big_endian: bool,
+    fields: Vec<FitField>,
+    developer_bytes: usize,
+}
+
+impl FitDefinition {
+    fn data_len(&self) -> usize {
+        self.fields.iter().map(|f| f.size).sum::<usize>() + self.developer_bytes
+    }
+}
+
+/// Garmin FIT: walks definition/data records and collects `hrv` message (78) `time` values,
+/// uint16 arrays of RR intervals in ms (`0xFFFF` marks unused slots). The start time comes
+/// from `file_id.time_created`.
+pub fn parse_fit_hrv(bytes: &[u8]) -> Result<RrImport> {
+    let header_len = *bytes.first().ok_or_else(|| anyhow!("empty FIT file"))? as usize;
+    if header_len < 12 || bytes.len() < header_len || &bytes[8..12] != b".FIT" {
+        bail!("not a FIT file");
+    }
+    let data_size = u32::from_le_bytes(bytes[4..8].try_into()?) as usize;
+    let end = (header_len + data_size).min(bytes.len());
+    let mut definitions: [Option<FitDefinition>; 16] = Default::default();
+    let mut pos = header_len;
+    let mut raw = Vec::new();
+    let mut created = None;
+    while pos < end {
+        let record = bytes[pos];
+        pos += 1;
+        let (local, is_definition, has_developer) = if record & 0x80 != 0 {
+            // Compressed timestamp header: always a data message.
+            (((record >> 5) & 0x03) as usize, false, false)
+        } else {
+            (
+                (record & 0x0F) as usize,
+                record & 0x40 != 0,
+                record & 0x20 != 0,
+            )
+        };
+        if is_definition {
+            let fixed = bytes
+                .get(pos..pos + 5)
+                .ok_or_else(|| anyhow!("truncated FIT definition"))?;
+            let big_endian = fixed[1] == 1;
+            let global = if big_endian {
+                u16::from_be_bytes([fixed[2], fixed[3]])
+            } else {
+                u16::from_le_bytes([fixed[2], fixed[3]])
+            };
+            let n_fields = fixed[4] as usize;
+            pos += 5;
+            let field_bytes = bytes
+                .get(pos..pos + 3 * n_fields)
+                .ok_or_else(|| anyhow!("truncated FIT field definitions"))?;
+            let fields = field_bytes
+                .chunks(3)
+                .map(|f| FitField {
+                    number: f[0],
+                    size: f[1] as usize,
+                })
+                .collect();
+            pos += 3 * n_fields;
+            let mut developer_bytes = 0;
+            if has_developer {
+                let n_dev = *bytes
+                    .get(pos)
+                    .ok_or_else(|| anyhow!("truncated FIT developer fields"))?
+                    as usize;
+                pos += 1;
+                let dev = bytes
+                    .get(pos..pos + 3 * n_dev)
+                    .ok_or_else(|| anyhow!("truncated FIT developer fields"))?;
+                developer_bytes = dev.chunks(3).map(|f| f[1] as usize).sum();
+                pos += 3 * n_dev;
+            }
+            definitions[local] = Some(FitDefinition {
+                global,
+                big_endian,
+                fields,
+                developer_bytes,
+            });
+            continue;
+        }
+        let def = definitions[local]
+            .as_ref()
+            .ok_or_else(|| anyhow!("FIT data message before its definition"))?;
+        let len = def.data_len();
+        let data = bytes
+            .get(pos..pos + len)
+            .ok_or_else(|| anyhow!("truncated FIT data message"))?;
+        let mut offset = 0;
+        for field in &def.fields {
+            let value = &data[offset..offset + field.size];
+            offset += field.size;
+            match (def.global, field.number) {
+                (FIT_MESG_HRV, 0) => {
+                    for pair in value.chunks_exact(2) {
+                        let v = if def.big_endian {
+                            u16::from_be_bytes([pair[0], pair[1]])
+                        } else {
+                            u16::from_le_bytes([pair[0], pair[1]])
+                        };
+                        if v != u16::MAX {
+                            raw.push(f64::from(v));
+                        }
+                    }
+                }
+                (FIT_MESG_FILE_ID, 4) if field.size == 4 => {
+                    let arr = [value[0], value[1], value[2], value[3]];
+                    let v = if def.big_endian {
+                        u32::from_be_bytes(arr)
+                    } else {
+                        u32::from_le_bytes(arr)
+                    };
+                    if v != u32::MAX {
+                        created = Some(FIT_EPOCH_UNIX + f64::from(v));
+                    }
+                }
+                _ => {}
+            }
+        }
+        pos += len;
+    }
+    if raw.is_empty() {
+        bail!("FIT file has no HRV messages; enable HRV logging on the device");
+    }
+    let start = created.map(|t| EdfStart::from_unix(t).to_string());
+    RrImport::from_intervals(RrFormat::GarminFit, RrUnit::Milliseconds, &raw, None, start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_exports_detect_units_and_time_columns() {
+        let elite = parse_rr_text("812\n790\n805\n").unwrap();
+        assert_eq!(elite.unit, RrUnit::Milliseconds);
+        assert_eq!(elite.rr.rr, vec![0.812, 0.79, 0.805]);
+        assert!((elite.beat_times[2] - 2.407).abs() < 1e-9);
+
+        let kubios = parse_rr_text("% Kubios RR\n0.812 0.812\n1.602 0.790\n").unwrap();
+        assert_eq!(kubios.unit, RrUnit::Seconds);
+        assert_eq!(kubios.beat_times, vec![0.812, 1.602]);
+
+        let logger =
+            parse_rr_text("Phone timestamp;RR-interval [ms]\n2024-03-14T10:21:07;812\n;790\n")
+                .unwrap();
+        assert_eq!(logger.unit, RrUnit::Milliseconds);
+        assert_eq!(logger.rr.rr, vec![0.812, 0.79]);
+
+        let seconds = parse_rr_text("time_ms,ibi_s\n800,0.8\n1620,0.82\n").unwrap();
+        assert_eq!(seconds.unit, RrUnit::Seconds);
+        assert_eq!(seconds.beat_times, vec![0.8, 1.62]);
+
+        // Units are whole words: `msec` in `RR (msec)` counts, `ms` inside `Rooms` does not.
+        assert_eq!(RrUnit::from_label("RR (msec)"), Some(RrUnit::Milliseconds));
+        assert_eq!(RrUnit::from_label("Interval [s]"), Some(RrUnit::Seconds));
+        assert_eq!(RrUnit::from_label("Rooms"), None);
+        assert_eq!(RrUnit::from_label("RR intervals"), None);
+    }
+
+    #[test]
+    fn polar_hrm_requires_rr_mode() {
+        let hrm = "[Params]\nVersion=106\nInterval=238\nDate=20240314\nStartTime=10:21:07.0\n\n\
+                   [HRData]\n812\t0\n0\n790\t0\n";
+        let import = parse_polar_hrm(hrm).unwrap();
+        assert_eq!(import.rr.rr, vec![0.812, 0.79]);
+        assert_eq!(import.start.as_deref(), Some("2024-03-14T10:21:07.0"));
+        assert!(parse_polar_hrm("[Params]\nInterval=1\n[HRData]\n60\n").is_err());
+    }
+
+    fn fit_file(messages: &[u8]) -> Vec<u8> {
+        let mut out = vec![14, 0x20, 0x08, 0x08];
+        out.extend_from_slice(&(messages.len() as u32).to_le_bytes());
+        out.extend_from_slice(b".FIT");
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(messages);
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    #[test]
+    fn garmin_fit_hrv_messages() {
+        let mut msgs = Vec::new();
+        // file_id definition (local 0): type (enum), time_created (uint32).
+        msgs.extend_from_slice(&[0x40, 0, 0, 0, 0, 2, 0, 1, 0x00, 4, 4, 0x86]);
+        msgs.push(0x00);
+        msgs.push(4);
+        msgs.extend_from_slice(&1_000_000_000u32.to_le_bytes());
+        // hrv definition (local 1): time, 5 x uint16.
+        msgs.extend_from_slice(&[0x41, 0, 0, 78, 0, 1, 0, 10, 0x84]);
+        for block in [
+            [812u16, 790, 805, 0xFFFF, 0xFFFF],
+            [799, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF],
+        ] {
+            msgs.push(0x01);
+            for v in block {
+                msgs.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("activity.fit");
+        std::fs::write(&path, fit_file(&msgs)).unwrap();
+        let import = read_rr_file(&path).unwrap();
+        assert_eq!(import.format, RrFormat::GarminFit);
+        assert_eq!(import.rr.rr, vec![0.812, 0.79, 0.805, 0.799]);
+        assert_eq!(import.start.as_deref(), Some("2021-09-08T01:46:40"));
+    }
+}
@@ -1,98 +1,952 @@
 use crate::signal::{Events, TimeSeries};
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-/// Simple WFDB annotation entry.
-#[derive(Debug, Clone)]
+/// Default ADC gain (units per mV) when a header leaves it at 0 or omits it.
+const DEFAULT_GAIN: f64 = 200.0;
+
+/// Annotation codes that are pseudo-annotations rather than labels.
+const SKIP: u8 = 59;
+const NUM: u8 = 60;
+const SUB: u8 = 61;
+const CHN: u8 = 62;
+const AUX: u8 = 63;
+
+/// MIT annotation code of a normal beat.
+pub const NORMAL: u8 = 1;
+/// MIT annotation code of a premature ventricular contraction.
+pub const PVC: u8 = 5;
+/// MIT annotation code of a rhythm change; the rhythm label lives in `aux`.
+pub const RHYTHM: u8 = 28;
+
+/// WFDB annotation entry.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct WfdbAnnotation {
     pub sample: usize,
     pub code: u8,
+    pub subtype: i8,
+    pub chan: u8,
+    pub num: i8,
+    /// Auxiliary string, e.g. rhythm labels such as `(AFIB` on [`RHYTHM`] annotations.
+    pub aux: Option<String>,
 }
 
 impl WfdbAnnotation {
+    pub fn new(sample: usize, code: u8) -> Self {
+        Self {
+            sample,
+            code,
+            ..Self::default()
+        }
+    }
+
+    /// True for QRS annotation codes (WFDB `isqrs`); rhythm, noise and wave labels are not beats.
     pub fn is_beat(&self) -> bool {
-        self.code > 0 && self.code < 59
+        matches!(self.code, 1..=13 | 25 | 30 | 34 | 35 | 37 | 38 | 41)
+    }
+
+    /// Mnemonic used by WFDB tools (`rdann`) for this annotation code.
+    pub fn mnemonic(&self) -> &'static str {
+        match self.code {
+            1 => "N",
+            2 => "L",
+            3 => "R",
+            4 => "a",
+            5 => "V",
+            6 => "F",
+            7 => "J",
+            8 => "A",
+            9 => "S",
+            10 => "E",
+            11 => "j",
+            12 => "/",
+            13 => "Q",
+            14 => "~",
+            16 => "|",
+            18 => "s",
+            19 => "T",
+            20 => "*",
+            21 => "D",
+            22 => "\"",
+            23 => "=",
+            24 => "p",
+            25 => "B",
+            26 => "^",
+            27 => "t",
+            28 => "+",
+            29 => "u",
+            30 => "?",
+            31 => "!",
+            32 => "[",
+            33 => "]",
+            34 => "e",
+            35 => "n",
+            36 => "@",
+            37 => "x",
+            38 => "f",
+            39 => "(",
+            40 => ")",
+            41 => "r",
+            _ => "",
+        }
+    }
+
+    /// Rhythm label carried by a [`RHYTHM`] annotation.
+    pub fn rhythm(&self) -> Option<&str> {
+        (self.code == RHYTHM)
+            .then_some(self.aux.as_deref())
+            .flatten()
+    }
+}
+
+/// Storage format of a WFDB signal file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WfdbFormat {
+    /// 8-bit offset binary.
+    F80,
+    /// 16-bit two's complement, little-endian.
+    F16,
+    /// 16-bit two's complement, big-endian.
+    F61,
+    /// 16-bit offset binary.
+    F160,
+    /// Two 12-bit samples packed into three bytes.
+    F212,
+    /// Three 10-bit samples packed into a 32-bit word (bits 1-10, 12-21, 23-32).
+    F310,
+    /// Three 10-bit samples packed into the low 30 bits of a 32-bit word.
+    F311,
+    /// 24-bit two's complement, little-endian.
+    F24,
+    /// 32-bit two's complement, little-endian.
+    F32,
+}
+
+impl WfdbFormat {
+    pub fn from_code(code: u32) -> Result<Self> {
+        Ok(match code {
+            80 => Self::F80,
+            16 => Self::F16,
+            61 => Self::F61,
+            160 => Self::F160,
+            212 => Self::F212,
+            310 => Self::F310,
+            311 => Self::F311,
+            24 => Self::F24,
+            32 => Self::F32,
+            other => bail!("unsupported WFDB signal format {other}"),
+        })
+    }
+
+    pub fn code(self) -> u32 {
+        match self {
+            Self::F80 => 80,
+            Self::F16 => 16,
+            Self::F61 => 61,
+            Self::F160 => 160,
+            Self::F212 => 212,
+            Self::F310 => 310,
+            Self::F311 => 311,
+            Self::F24 => 24,
+            Self::F32 => 32,
+        }
+    }
+
+    /// ADC resolution in bits.
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::F80 => 8,
+            Self::F16 | Self::F61 | Self::F160 => 16,
+            Self::F212 => 12,
+            Self::F310 | Self::F311 => 10,
+            Self::F24 => 24,
+            Self::F32 => 32,
+        }
+    }
+
+    /// Digital value WFDB reserves for missing samples (the most negative value).
+    pub fn invalid(self) -> i32 {
+        if self.bits() == 32 {
+            i32::MIN
+        } else {
+            -(1 << (self.bits() - 1))
+        }
+    }
+
+    /// Smallest `(samples, bytes)` unit the format packs samples into.
+    pub(crate) fn packing(self) -> (usize, usize) {
+        match self {
+            Self::F80 => (1, 1),
+            Self::F16 | Self::F61 | Self::F160 => (1, 2),
+            Self::F212 => (2, 3),
+            Self::F310 | Self::F311 => (3, 4),
+            Self::F24 => (1, 3),
+            Self::F32 => (1, 4),
+        }
+    }
+
+    pub(crate) fn decode(self, bytes: &[u8]) -> Vec<i32> {
+        match self {
+            Self::F80 => bytes.iter().map(|&b| b as i32 - 128).collect(),
+            Self::F16 => bytes
+                .chunks_exact(2)
+                .map(|c| i16::from_le_bytes([c[0], c[1]]) as i32)
+                .collect(),
+            Self::F61 => bytes
+                .chunks_exact(2)
+                .map(|c| i16::from_be_bytes([c[0], c[1]]) as i32)
+                .collect(),
+            Self::F160 => bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]) as i32 - 32768)
+                .collect(),
+            Self::F212 => {
+                let mut out = Vec::with_capacity(bytes.len() / 3 * 2);
+                for c in bytes.chunks_exact(3) {
+                    let (b0, b1, b2) = (c[0] as i32, c[1] as i32, c[2] as i32);
+                    out.push(sign_extend(b0 | ((b1 & 0x0F) << 8), 12));
+                    out.push(sign_extend(b2 | ((b1 & 0xF0) << 4), 12));
+                }
+                out
+            }
+            Self::F310 => {
+                let mut out = Vec::with_capacity(bytes.len() / 4 * 3);
+                for c in bytes.chunks_exact(4) {
+                    let (b0, b1, b2, b3) = (c[0] as i32, c[1] as i32, c[2] as i32, c[3] as i32);
+                    out.push(sign_extend((b0 >> 1) | ((b1 & 0x07) << 7), 10));
+                    out.push(sign_extend((b2 >> 1) | ((b3 & 0x07) << 7), 10));
+                    out.push(sign_extend(((b1 & 0xF8) >> 3) | ((b3 & 0xF8) << 2), 10));
+                }
+                out
+            }
+            Self::F311 => {
+                let mut out = Vec::with_capacity(bytes.len() / 4 * 3);
+                for c in bytes.chunks_exact(4) {
+                    let word = u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as i32;
+                    out.push(sign_extend(word & 0x3FF, 10));
+                    out.push(sign_extend((word >> 10) & 0x3FF, 10));
+                    out.push(sign_extend((word >> 20) & 0x3FF, 10));
+                }
+                out
+            }
+            Self::F24 => bytes
+                .chunks_exact(3)
+                .map(|c| sign_extend(c[0] as i32 | (c[1] as i32) << 8 | (c[2] as i32) << 16, 24))
+                .collect(),
+            Self::F32 => bytes
+                .chunks_exact(4)
+                .map(|c| i32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        }
+    }
+
+    fn encode(self, samples: &[i32]) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Self::F80 => out.extend(samples.iter().map(|&s| (s + 128) as u8)),
+            Self::F16 => samples
+                .iter()
+                .for_each(|&s| out.extend((s as i16).to_le_bytes())),
+            Self::F61 => samples
+                .iter()
+                .for_each(|&s| out.extend((s as i16).to_be_bytes())),
+            Self::F160 => samples
+                .iter()
+                .for_each(|&s| out.extend(((s + 32768) as u16).to_le_bytes())),
+            Self::F212 => {
+                for pair in samples.chunks(2) {
+                    let s0 = pair[0] & 0xFFF;
+                    let s1 = pair.get(1).copied().unwrap_or(0) & 0xFFF;
+                    out.push((s0 & 0xFF) as u8);
+                    out.push(((s0 >> 8) | ((s1 >> 4) & 0xF0)) as u8);
+                    out.push((s1 & 0xFF) as u8);
+                }
+            }
+            Self::F310 => {
+                for triple in samples.chunks(3) {
+                    let s = |i: usize| triple.get(i).copied().unwrap_or(0) & 0x3FF;
+                    let (s0, s1, s2) = (s(0), s(1), s(2));
+                    out.push(((s0 << 1) & 0xFE) as u8);
+                    out.push(((s0 >> 7) | ((s2 & 0x1F) << 3)) as u8);
+                    out.push(((s1 << 1) & 0xFE) as u8);
+                    out.push(((s1 >> 7) | ((s2 >> 5) << 3)) as u8);
+                }
+            }
+            Self::F311 => {
+                for triple in samples.chunks(3) {
+                    let s = |i: usize| (triple.get(i).copied().unwrap_or(0) & 0x3FF) as u32;
+                    let word = s(0) | (s(1) << 10) | (s(2) << 20);
+                    out.extend(word.to_le_bytes());
+                }
+            }
+            Self::F24 => samples
+                .iter()
+                .for_each(|&s| out.extend(&s.to_le_bytes()[..3])),
+            Self::F32 => samples.iter().for_each(|&s| out.extend(s.to_le_bytes())),
+        }
+        out
     }
 }
 
-/// Load the specified signal (lead) from a WFDB header/data pair into a TimeSeries.
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+/// Signal line of a WFDB header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfdbSignalSpec {
+    /// Signal file name relative to the header, or `~` for a signal without samples.
+    pub file_name: String,
+    pub format: u32,
+    pub samples_per_frame: usize,
+    pub skew: usize,
+    pub byte_offset: usize,
+    pub adc_gain: f64,
+    pub baseline: i32,
+    pub units: String,
+    pub adc_resolution: u32,
+    pub adc_zero: i32,
+    pub initial_value: i32,
+    pub checksum: Option<i16>,
+    pub description: String,
+}
+
+/// Segment of a multi-segment record; `~` names a gap without samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfdbSegment {
+    pub name: String,
+    pub n_samples: usize,
+}
+
+/// Parsed WFDB header (`.hea`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WfdbHeader {
+    pub record_name: String,
+    pub fs: f64,
+    pub n_samples: Option<usize>,
+    pub base_time: Option<String>,
+    pub base_date: Option<String>,
+    pub signals: Vec<WfdbSignalSpec>,
+    /// Non-empty for multi-segment records.
+    pub segments: Vec<WfdbSegment>,
+    pub comments: Vec<String>,
+}
+
+/// Parse the text of a WFDB header following `header(5)`.
+pub fn parse_wfdb_header(text: &str) -> Result<WfdbHeader> {
+    let mut comments = Vec::new();
+    let mut lines = text.lines().filter_map(|line| {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            comments.push(comment.trim().to_string());
+            None
+        } else if line.is_empty() {
+            None
+        } else {
+            Some(line.to_string())
+        }
+    });
+
+    let record_line = lines.next().ok_or_else(|| anyhow!("empty WFDB header"))?;
+    let fields: Vec<&str> = record_line.split_whitespace().collect();
+    let (record_name, n_segments) = match fields[0].split_once('/') {
+        Some((name, segs)) => (
+            name.to_string(),
+            segs.parse::<usize>()
+                .with_context(|| format!("invalid segment count in {record_line:?}"))?,
+        ),
+        None => (fields[0].to_string(), 0),
+    };
+    let n_signals: usize = match fields.get(1) {
+        Some(value) => value
+            .parse()
+            .with_context(|| format!("invalid signal count in {record_line:?}"))?,
+        None => 0,
+    };
+    // fs may carry a counter frequency and base counter: `360/1(0)`.
+    let fs = match fields.get(2) {
+        Some(value) => value
+            .split('/')
+            .next()
+            .unwrap_or(value)
+            .parse()
+            .with_context(|| format!("invalid sampling frequency in {record_line:?}"))?,
+        None => 250.0,
+    };
+    let n_samples = fields.get(3).and_then(|v| v.parse().ok());
+    let base_time = fields.get(4).map(|v| v.to_string());
+    let base_date = fields.get(5).map(|v| v.to_string());
+
+    let mut segments = Vec::with_capacity(n_segments);
+    for _ in 0..n_segments {
+        let line = lines
+            .next()
+            .ok_or_else(|| anyhow!("WFDB header lists fewer than {n_segments} segments"))?;
+        let mut parts = line.split_whitespace();
+        let name = parts.next().unwrap_or("~").to_string();
+        let n_samples = parts
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| anyhow!("invalid segment line {line:?}"))?;
+        segments.push(WfdbSegment { name, n_samples });
+    }
+
+    let mut signals = Vec::with_capacity(n_signals);
+    if n_segments == 0 {
+        for _ in 0..n_signals {
+            let line = lines
+                .next()
+                .ok_or_else(|| anyhow!("WFDB header lists fewer than {n_signals} signals"))?;
+            signals.push(parse_signal_line(&line)?);
+        }
+    }
+
+    Ok(WfdbHeader {
+        record_name,
+        fs,
+        n_samples,
+        base_time,
+        base_date,
+        signals,
+        segments,
+        comments,
+    })
+}
+
+fn parse_signal_line(line: &str) -> Result<WfdbSignalSpec> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 2 {
+        bail!("invalid WFDB signal line {line:?}");
+    }
+    // format[xspf][:skew][+offset]
+    let mut spec = fields[1];
+    let mut byte_offset = 0;
+    if let Some((head, offset)) = spec.split_once('+') {
+        byte_offset = offset.parse().context("invalid byte offset")?;
+        spec = head;
+    }
+    let mut skew = 0;
+    if let Some((head, value)) = spec.split_once(':') {
+        skew = value.parse().context("invalid skew")?;
+        spec = head;
+    }
+    let mut samples_per_frame = 1;
+    if let Some((head, value)) = spec.split_once('x') {
+        samples_per_frame = value.parse().context("invalid samples per frame")?;
+        spec = head;
+    }
+    let format: u32 = spec
+        .parse()
+        .with_context(|| format!("invalid signal format in {line:?}"))?;
+
+    // gain[(baseline)][/units]
+    let mut adc_gain = DEFAULT_GAIN;
+    let mut baseline = None;
+    let mut units = "mV".to_string();
+    if let Some(gain_field) = fields.get(2) {
+        let mut gain = *gain_field;
+        if let Some((head, unit)) = gain.split_once('/') {
+            units = unit.to_string();
+            gain = head;
+        }
+        if let Some((head, base)) = gain.split_once('(') {
+            baseline = Some(
+                base.trim_end_matches(')')
+                    .parse()
+                    .context("invalid baseline")?,
+            );
+            gain = head;
+        }
+        let value: f64 = gain.parse().context("invalid ADC gain")?;
+        if value != 0.0 {
+            adc_gain = value;
+        }
+    }
+    let int_field = |idx: usize| -> Result<Option<i64>> {
+        fields
+            .get(idx)
+            .map(|v| {
+                v.parse::<i64>()
+                    .with_context(|| format!("invalid field {v:?}"))
+            })
+            .transpose()
+    };
+    let adc_resolution = int_field(3)?.unwrap_or(0) as u32;
+    let adc_zero = int_field(4)?.unwrap_or(0) as i32;
+    let initial_value = int_field(5)?.map(|v| v as i32).unwrap_or(adc_zero);
+    let checksum = int_field(6)?.map(|v| v as i16);
+    let description = if fields.len() > 8 {
+        fields[8..].join(" ")
+    } else {
+        String::new()
+    };
+
+    Ok(WfdbSignalSpec {
+        file_name: fields[0].to_string(),
+        format,
+        samples_per_frame,
+        skew,
+        byte_offset,
+        adc_gain,
+        baseline: baseline.unwrap_or(adc_zero),
+        units,
+        adc_resolution,
+        adc_zero,
+        initial_value,
+        checksum,
+        description,
+    })
+}
+
+/// Read and parse a WFDB header; a record path without extension gets `.hea`.
+pub fn read_wfdb_header(path: &Path) -> Result<WfdbHeader> {
+    let path = header_path(path);
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read WFDB header {}", path.display()))?;
+    parse_wfdb_header(&text).with_context(|| format!("invalid WFDB header {}", path.display()))
+}
+
+fn header_path(path: &Path) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("hea") => path.to_path_buf(),
+        _ => path.with_extension("hea"),
+    }
+}
+
+/// Physical signal of a WFDB record; invalid samples and segment gaps are NaN.
+#[derive(Debug, Clone)]
+pub struct WfdbSignal {
+    pub description: String,
+    pub units: String,
+    pub fs: f64,
+    pub data: Vec<f64>,
+}
+
+impl WfdbSignal {
+    pub fn to_time_series(&self) -> TimeSeries {
+        TimeSeries {
+            fs: self.fs,
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// WFDB record with every signal converted to physical units.
+#[derive(Debug, Clone)]
+pub struct WfdbRecord {
+    pub header: WfdbHeader,
+    pub signals: Vec<WfdbSignal>,
+}
+
+/// Read a single- or multi-segment WFDB record.
+///
+/// Multi-segment records are concatenated; in variable-layout records (first segment is a
+/// `_layout` header with zero samples) signals are matched by description and are NaN in
+/// segments that lack them.
+pub fn read_wfdb_record(path: &Path) -> Result<WfdbRecord> {
+    let path = header_path(path);
+    let header = read_wfdb_header(&path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    if header.segments.is_empty() {
+        let signals = read_single_segment(&header, dir)?;
+        return Ok(WfdbRecord { header, signals });
+    }
+
+    let mut segments = header.segments.iter().peekable();
+    let layout = match segments.peek() {
+        Some(first) if first.n_samples == 0 => {
+            let first = segments.next().expect("peeked segment");
+            Some(read_wfdb_header(&dir.join(&first.name))?)
+        }
+        _ => None,
+    };
+
+    let mut signals: Vec<WfdbSignal> = layout
+        .as_ref()
+        .map(|layout| {
+            layout
+                .signals
+                .iter()
+                .map(|spec| WfdbSignal {
+                    description: spec.description.clone(),
+                    units: spec.units.clone(),
+                    fs: header.fs * spec.samples_per_frame as f64,
+                    data: Vec::new(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for segment in segments {
+        if segment.name == "~" {
+            for signal in &mut signals {
+                let len = (segment.n_samples as f64 * signal.fs / header.fs).round() as usize;
+                signal.data.extend(std::iter::repeat_n(f64::NAN, len));
+            }
+            continue;
+        }
+        let seg_path = dir.join(format!("{}.hea", segment.name));
+        let seg_header = read_wfdb_header(&seg_path)?;
+        let mut seg_signals = read_single_segment(&seg_header, dir)?;
+        if signals.is_empty() && layout.is_none() {
+            signals = seg_signals
+                .iter()
+                .map(|s| WfdbSignal {
+                    data: Vec::new(),
+                    ..s.clone()
+                })
+                .collect();
+        }
+        let mut by_description: HashMap<String, usize> = HashMap::new();
+        for (idx, signal) in seg_signals.iter().enumerate() {
+            by_description
+                .entry(signal.description.clone())
+                .or_insert(idx);
+        }
+        for (idx, signal) in signals.iter_mut().enumerate() {
+            let source = if layout.is_some() {
+                by_description.get(&signal.description).copied()
+            } else {
+                Some(idx).filter(|&i| i < seg_signals.len())
+            };
+            let len = (segment.n_samples as f64 * signal.fs / header.fs).round() as usize;
+            match source {
+                Some(i) => {
+                    let mut data = std::mem::take(&mut seg_signals[i].data);
+                    data.resize(len, f64::NAN);
+                    signal.data.extend(data);
+                }
+                None => signal.data.extend(std::iter::repeat_n(f64::NAN, len)),
+            }
+        }
+    }
+    Ok(WfdbRecord { header, signals })
+}
+
+fn read_single_segment(header: &WfdbHeader, dir: &Path) -> Result<Vec<WfdbSignal>> {
+    let mut signals: Vec<WfdbSignal> = header
+        .signals
+        .iter()
+        .map(|spec| WfdbSignal {
+            description: spec.description.clone(),
+            units: spec.units.clone(),
+            fs: header.fs * spec.samples_per_frame as f64,
+            data: Vec::new(),
+        })
+        .collect();
+
+    // Signals sharing a file are interleaved frame by frame in header order.
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (idx, spec) in header.signals.iter().enumerate() {
+        match groups.iter_mut().find(|(name, _)| *name == spec.file_name) {
+            Some((_, members)) => members.push(idx),
+            None => groups.push((spec.file_name.clone(), vec![idx])),
+        }
+    }
+
+    for (file_name, members) in groups {
+        let first = &header.signals[members[0]];
+        if file_name == "~" {
+            let len = header.n_samples.unwrap_or(0);
+            for &idx in &members {
+                let spf = header.signals[idx].samples_per_frame;
+                signals[idx].data = vec![f64::NAN; len * spf];
+            }
+            continue;
+        }
+        let format = WfdbFormat::from_code(first.format)?;
+        let path = dir.join(&file_name);
+        let bytes = read_exact(&path)?;
+        let bytes = bytes.get(first.byte_offset..).unwrap_or(&[]);
+        let samples = format.decode(bytes);
+        let frame_len: usize = members
+            .iter()
+            .map(|&idx| header.signals[idx].samples_per_frame)
+            .sum();
+        let available = samples.len() / frame_len.max(1);
+        let frames = header.n_samples.unwrap_or(available).min(available);
+        let invalid = format.invalid();
+
+        for (pos, &idx) in members.iter().enumerate() {
+            let spec = &header.signals[idx];
+            let offset: usize = members[..pos]
+                .iter()
+                .map(|&i| header.signals[i].samples_per_frame)
+                .sum();
+            let spf = spec.samples_per_frame;
+            let mut data = Vec::with_capacity(frames * spf);
+            for frame in spec.skew..frames + spec.skew {
+                for k in 0..spf {
+                    let value = if frame < frames {
+                        samples[frame * frame_len + offset + k]
+                    } else {
+                        invalid
+                    };
+                    data.push(if value == invalid {
+                        f64::NAN
+                    } else {
+                        (value - spec.baseline) as f64 / spec.adc_gain
+                    });
+                }
+            }
+            signals[idx].data = data;
+        }
+    }
+    Ok(signals)
+}
+
+/// Load the specified signal (lead) from a WFDB record into a TimeSeries.
 pub fn load_wfdb_lead(header_path: &Path, lead: usize) -> Result<TimeSeries> {
-    let (header, signals) = wfdb_rust::parse_wfdb(header_path);
-    if lead >= signals.len() {
-        anyhow::bail!(
+    let record = read_wfdb_record(header_path)?;
+    let signal = record.signals.get(lead).ok_or_else(|| {
+        anyhow!(
             "WFDB record contains {} signals, but lead {} was requested",
-            signals.len(),
+            record.signals.len(),
             lead
-        );
-    }
-    let spec = &header.signal_specs[lead];
-    let raw = &signals[lead];
-    let gain = spec.adc_gain.unwrap_or(1.0) as f64;
-    let baseline = spec.baseline.or(spec.adc_zero).unwrap_or(0) as f64;
-    let fs = header
-        .record
-        .sampling_frequency
-        .map(|f| f as f64)
-        .unwrap_or(250.0);
-    let data = raw
-        .iter()
-        .map(|&sample| (sample as f64 - baseline) / gain)
-        .collect();
-    Ok(TimeSeries { fs, data })
+        )
+    })?;
+    Ok(signal.to_time_series())
+}
+
+/// Signal to store with [`write_wfdb_record`].
+#[derive(Debug, Clone, Copy)]
+pub struct WfdbWriteSignal<'a> {
+    pub description: &'a str,
+    pub units: &'a str,
+    pub data: &'a [f64],
 }
 
-/// Parse MIT annotation binary stream into samples & codes.
+/// Write `<record>.hea` and `<record>.dat` into `dir`, returning the header path.
+///
+/// Gain and baseline are chosen per signal so the finite range spans the format's digital
+/// range; NaN samples are stored as the format's invalid value.
+pub fn write_wfdb_record(
+    dir: &Path,
+    record: &str,
+    fs: f64,
+    signals: &[WfdbWriteSignal<'_>],
+    format: WfdbFormat,
+) -> Result<PathBuf> {
+    if signals.is_empty() {
+        bail!("cannot write a WFDB record without signals");
+    }
+    let n_samples = signals[0].data.len();
+    if signals.iter().any(|s| s.data.len() != n_samples) {
+        bail!("all WFDB signals must have the same length");
+    }
+
+    let invalid = format.invalid() as i64;
+    let max_digital = -invalid - 1;
+    let mut digital = Vec::with_capacity(signals.len());
+    let mut lines = vec![format!("{record} {} {fs} {n_samples}", signals.len())];
+    for signal in signals {
+        let (lo, hi) = signal
+            .data
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+        let (gain, baseline) = if lo.is_finite() && hi > lo {
+            // Leave one step of headroom on each side for rounding.
+            let gain = (2 * max_digital - 2) as f64 / (hi - lo);
+            (gain, (-(lo + hi) / 2.0 * gain).round() as i64)
+        } else if lo.is_finite() && lo != 0.0 {
+            let gain = (max_digital / 2) as f64 / lo.abs();
+            (gain, 0)
+        } else {
+            (DEFAULT_GAIN, 0)
+        };
+        let values: Vec<i32> = signal
+            .data
+            .iter()
+            .map(|&v| {
+                if v.is_finite() {
+                    ((v * gain).round() as i64 + baseline).clamp(-max_digital, max_digital) as i32
+                } else {
+                    invalid as i32
+                }
+            })
+            .collect();
+        let checksum = values
+            .iter()
+            .fold(0i16, |acc, &v| acc.wrapping_add(v as i16));
+        let initial = values.first().copied().unwrap_or(0);
+        lines.push(format!(
+            "{record}.dat {} {gain}({baseline})/{} {} 0 {initial} {checksum} 0 {}",
+            format.code(),
+            signal.units,
+            format.bits(),
+            signal.description
+        ));
+        digital.push(values);
+    }
+
+    let mut interleaved = Vec::with_capacity(n_samples * signals.len());
+    for i in 0..n_samples {
+        interleaved.extend(digital.iter().map(|values| values[i]));
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let dat_path = dir.join(format!("{record}.dat"));
+    fs::write(&dat_path, format.encode(&interleaved))
+        .with_context(|| format!("failed to write {}", dat_path.display()))?;
+    let header_path = dir.join(format!("{record}.hea"));
+    fs::write(&header_path, lines.join("\n") + "\n")
+        .with_context(|| format!("failed to write {}", header_path.display()))?;
+    Ok(header_path)
+}
+
+/// Parse MIT annotation binary stream into annotations.
+///
+/// SUB/CHN/NUM/AUX pseudo-annotations modify the annotation they follow; `chan` and `num`
+/// carry over to later annotations as in the WFDB library.
 pub fn parse_wfdb_annotations(buf: &[u8]) -> Vec<WfdbAnnotation> {
-    let mut out = Vec::new();
+    let mut out: Vec<WfdbAnnotation> = Vec::new();
     let mut idx = 0;
     let mut sample: usize = 0;
+    let mut chan = 0u8;
+    let mut num = 0i8;
     while idx + 2 <= buf.len() {
         let word = u16::from_le_bytes([buf[idx], buf[idx + 1]]);
         idx += 2;
         let code = (word >> 10) as u8;
-        let diff = (word & 0x03FF) as usize;
-        if code == 0 && diff == 0 {
+        let value = (word & 0x03FF) as usize;
+        if code == 0 && value == 0 {
             break;
         }
         match code {
-            59 => {
+            SKIP => {
                 if idx + 4 > buf.len() {
                     break;
                 }
                 let high = u16::from_le_bytes([buf[idx], buf[idx + 1]]) as u32;
                 let low = u16::from_le_bytes([buf[idx + 2], buf[idx + 3]]) as u32;
                 idx += 4;
-                let skip = (high << 16) | low;
-                sample = sample.wrapping_add(skip as usize);
+                let skip = ((high << 16) | low) as i32;
+                sample = sample.wrapping_add_signed(skip as isize);
             }
-            60..=62 => {
-                // NUM/SUB/CHN: just ignore the payload
-                sample = sample.wrapping_add(diff);
+            NUM => {
+                num = value as u8 as i8;
+                if let Some(last) = out.last_mut() {
+                    last.num = num;
+                }
+            }
+            SUB => {
+                if let Some(last) = out.last_mut() {
+                    last.subtype = value as u8 as i8;
+                }
+            }
+            CHN => {
+                chan = value as u8;
+                if let Some(last) = out.last_mut() {
+                    last.chan = chan;
+                }
             }
-            63 => {
-                idx += diff as usize;
-                if diff % 2 != 0 && idx < buf.len() {
+            AUX => {
+                let end = (idx + value).min(buf.len());
+                if let Some(last) = out.last_mut() {
+                    let text = String::from_utf8_lossy(&buf[idx..end]);
+                    last.aux = Some(text.trim_end_matches('\0').to_string());
+                }
+                idx = end;
+                if !value.is_multiple_of(2) && idx < buf.len() {
                     idx += 1;
                 }
             }
             _ => {
-                sample = sample.wrapping_add(diff);
-                out.push(WfdbAnnotation { sample, code });
+                sample = sample.wrapping_add(value);
+                out.push(WfdbAnnotation {
+                    sample,
+                    code,
+                    subtype: 0,
+                    chan,
+                    num,
+                    aux: None,
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Encode annotations (sorted by sample) as an MIT annotation stream.
+pub fn encode_wfdb_annotations(annotations: &[WfdbAnnotation]) -> Vec<u8> {
+    let mut sorted: Vec<&WfdbAnnotation> = annotations.iter().collect();
+    sorted.sort_by_key(|ann| ann.sample);
+
+    let mut out = Vec::new();
+    let word = |out: &mut Vec<u8>, code: u8, value: usize| {
+        out.extend((((code as u16) << 10) | (value as u16 & 0x03FF)).to_le_bytes());
+    };
+    let mut prev = 0usize;
+    let mut chan = 0u8;
+    let mut num = 0i8;
+    for ann in sorted {
+        let mut diff = ann.sample - prev;
+        if diff > 0x03FF {
+            word(&mut out, SKIP, 0);
+            let skip = diff as u32;
+            out.extend(((skip >> 16) as u16).to_le_bytes());
+            out.extend(((skip & 0xFFFF) as u16).to_le_bytes());
+            diff = 0;
+        }
+        word(&mut out, ann.code, diff);
+        prev = ann.sample;
+        if ann.subtype != 0 {
+            word(&mut out, SUB, ann.subtype as u8 as usize);
+        }
+        if ann.chan != chan {
+            chan = ann.chan;
+            word(&mut out, CHN, chan as usize);
+        }
+        if ann.num != num {
+            num = ann.num;
+            word(&mut out, NUM, num as u8 as usize);
+        }
+        if let Some(aux) = ann.aux.as_deref().filter(|aux| !aux.is_empty()) {
+            let bytes = &aux.as_bytes()[..aux.len().min(0x03FF)];
+            word(&mut out, AUX, bytes.len());
+            out.extend(bytes);
+            if !bytes.len().is_multiple_of(2) {
+                out.push(0);
             }
         }
     }
+    out.extend(0u16.to_le_bytes());
     out
 }
 
+/// Write annotations to an MIT-format annotation file such as `<record>.atr`.
+pub fn write_wfdb_annotations(path: &Path, annotations: &[WfdbAnnotation]) -> Result<()> {
+    fs::write(path, encode_wfdb_annotations(annotations))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Label detected beats as normal (`N`) annotations.
+pub fn events_to_annotations(events: &Events) -> Vec<WfdbAnnotation> {
+    events
+        .indices
+        .iter()
+        .map(|&sample| WfdbAnnotation::new(sample, NORMAL))
+        .collect()
+}
+
 fn read_exact(path: &Path) -> Result<Vec<u8>> {
     fs::read(path).with_context(|| format!("failed to read {}", path.display()))
 }
 
+/// Read every annotation from a WFDB annotation file.
+pub fn load_wfdb_annotations(path: &Path) -> Result<Vec<WfdbAnnotation>> {
+    Ok(parse_wfdb_annotations(&read_exact(path)?))
+}
+
 /// Read WFDB annotation file (ATR) and convert to beat events.
 pub fn load_wfdb_events(path: &Path) -> Result<Events> {
-    let buf = read_exact(path)?;
-    let beat_samples: Vec<usize> = parse_wfdb_annotations(&buf)
+    let beat_samples: Vec<usize> = load_wfdb_annotations(path)?
         .into_iter()
         .filter(WfdbAnnotation::is_beat)
         .map(|ann| ann.sample)
@@ -105,6 +959,15 @@ mod tests {
     use super::*;
     use std::path::PathBuf;
 
+    fn workspace_root() -> PathBuf {
+        let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        manifest
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace root")
+            .to_path_buf()
+    }
+
     #[test]
     fn parses_simple_annotation_stream() {
         let mut bytes = vec![];
@@ -127,12 +990,7 @@ mod tests {
 
     #[test]
     fn reads_mitdb_record() {
-        let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let root = manifest
-            .parent()
-            .and_then(|p| p.parent())
-            .expect("workspace root")
-            .to_path_buf();
+        let root = workspace_root();
         let header = root.join("test_data/mitdb/100.hea");
         let atr = root.join("test_data/mitdb/100.atr");
         let ts = load_wfdb_lead(&header, 0).expect("load sample lead");
@@ -141,4 +999,141 @@ mod tests {
         let events = load_wfdb_events(&atr).expect("load annotations");
         assert!(!events.indices.is_empty());
     }
+
+    #[test]
+    fn format_212_matches_header_checksum() {
+        let root = workspace_root();
+        let header = read_wfdb_header(&root.join("test_data/mitdb/100")).unwrap();
+        let bytes = fs::read(root.join("test_data/mitdb/100.dat")).unwrap();
+        let samples = WfdbFormat::F212.decode(&bytes);
+        for (lead, spec) in header.signals.iter().enumerate() {
+            let lead_samples: Vec<i32> = samples.iter().skip(lead).step_by(2).copied().collect();
+            assert_eq!(lead_samples[0], spec.initial_value);
+            let checksum = lead_samples
+                .iter()
+                .fold(0i16, |acc, &v| acc.wrapping_add(v as i16));
+            assert_eq!(Some(checksum), spec.checksum);
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        for format in [
+            WfdbFormat::F80,
+            WfdbFormat::F16,
+            WfdbFormat::F61,
+            WfdbFormat::F160,
+            WfdbFormat::F212,
+            WfdbFormat::F310,
+            WfdbFormat::F311,
+            WfdbFormat::F24,
+            WfdbFormat::F32,
+        ] {
+            let max = (1i64 << (format.bits() - 1)) - 1;
+            let samples: Vec<i32> = [0, 1, -1, max, -max, 100, -100]
+                .iter()
+                .map(|&v| v.clamp(-max, max) as i32)
+                .collect();
+            let decoded = format.decode(&format.encode(&samples));
+            assert_eq!(&decoded[..samples.len()], &samples[..], "format {format:?}");
+        }
+    }
+
+    #[test]
+    fn writes_and_reads_records_and_annotations() {
+        let dir = tempfile::tempdir().unwrap();
+        let ecg: Vec<f64> = (0..1000).map(|i| (i as f64 * 0.05).sin()).collect();
+        let mut resp: Vec<f64> = (0..1000).map(|i| 2.0 + (i as f64 * 0.01).cos()).collect();
+        resp[10] = f64::NAN;
+        let signals = [
+            WfdbWriteSignal {
+                description: "ECG",
+                units: "mV",
+                data: &ecg,
+            },
+            WfdbWriteSignal {
+                description: "Resp",
+                units: "V",
+                data: &resp,
+            },
+        ];
+        let header =
+            write_wfdb_record(dir.path(), "rec", 250.0, &signals, WfdbFormat::F212).unwrap();
+        let record = read_wfdb_record(&header).unwrap();
+        assert_eq!(record.header.fs, 250.0);
+        assert_eq!(record.signals[1].description, "Resp");
+        assert_eq!(record.signals[1].units, "V");
+        assert!(record.signals[1].data[10].is_nan());
+        for (read, written) in record.signals[0].data.iter().zip(&ecg) {
+            assert!((read - written).abs() < 1e-3);
+        }
+
+        let annotations = vec![
+            WfdbAnnotation::new(5, NORMAL),
+            WfdbAnnotation {
+                aux: Some("(AFIB".into()),
+                ..WfdbAnnotation::new(40, RHYTHM)
+            },
+            WfdbAnnotation {
+                subtype: 2,
+                chan: 1,
+                num: 3,
+                ..WfdbAnnotation::new(100_000, 5)
+            },
+        ];
+        let atr = dir.path().join("rec.atr");
+        write_wfdb_annotations(&atr, &annotations).unwrap();
+        let read = load_wfdb_annotations(&atr).unwrap();
+        assert_eq!(read, annotations);
+        assert_eq!(read[1].rhythm(), Some("(AFIB"));
+        assert_eq!(read[2].mnemonic(), "V");
+        assert_eq!(load_wfdb_events(&atr).unwrap().indices, vec![5, 100_000]);
+    }
+
+    #[test]
+    fn reads_variable_layout_multi_segment_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let a: Vec<f64> = (0..100).map(|i| i as f64 / 100.0).collect();
+        let b: Vec<f64> = (0..100).map(|i| -(i as f64) / 100.0).collect();
+        let sig = |description, data| WfdbWriteSignal {
+            description,
+            units: "mV",
+            data,
+        };
+        write_wfdb_record(
+            dir.path(),
+            "multi_1",
+            100.0,
+            &[sig("II", &a), sig("V", &b)],
+            WfdbFormat::F16,
+        )
+        .unwrap();
+        write_wfdb_record(
+            dir.path(),
+            "multi_2",
+            100.0,
+            &[sig("V", &b)],
+            WfdbFormat::F16,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("multi_layout.hea"),
+            "multi_layout 2 100 0\n~ 0 200/mV 16 0 0 0 0 II\n~ 0 200/mV 16 0 0 0 0 V\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("multi.hea"),
+            "multi/4 2 100 250\nmulti_layout 0\nmulti_1 100\n~ 50\nmulti_2 100\n",
+        )
+        .unwrap();
+
+        let record = read_wfdb_record(&dir.path().join("multi.hea")).unwrap();
+        let (ii, v) = (&record.signals[0].data, &record.signals[1].data);
+        assert_eq!(ii.len(), 250);
+        assert_eq!(v.len(), 250);
+        assert!((ii[50] - 0.5).abs() < 1e-3);
+        assert!(ii[120].is_nan() && ii[200].is_nan());
+        assert!(v[120].is_nan());
+        assert!((v[200] + 0.5).abs() < 1e-3);
+    }
 }
@@ -0,0 +1,689 @@
+use crate::signal::TimeSeries;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"XDF:";
+const TAG_FILE_HEADER: u16 = 1;
+const TAG_STREAM_HEADER: u16 = 2;
+const TAG_SAMPLES: u16 = 3;
+const TAG_CLOCK_OFFSET: u16 = 4;
+const TAG_BOUNDARY: u16 = 5;
+const TAG_STREAM_FOOTER: u16 = 6;
+
+/// Content of every boundary chunk; used to resynchronise after a damaged chunk.
+pub const BOUNDARY_UUID: [u8; 16] = [
+    0x43, 0xA5, 0x46, 0xDC, 0xCB, 0xF5, 0x41, 0x0F, 0xB3, 0x0E, 0xD5, 0x46, 0x73, 0x83, 0xCB, 0xE4,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XdfChannelFormat {
+    Float32,
+    Double64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    String,
+}
+
+impl XdfChannelFormat {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "float32" => Self::Float32,
+            "double64" => Self::Double64,
+            "int8" => Self::Int8,
+            "int16" => Self::Int16,
+            "int32" => Self::Int32,
+            "int64" => Self::Int64,
+            "string" => Self::String,
+            other => bail!("unsupported XDF channel_format {other}"),
+        })
+    }
+
+    fn value_bytes(self) -> usize {
+        match self {
+            Self::Int8 => 1,
+            Self::Int16 => 2,
+            Self::Float32 | Self::Int32 => 4,
+            Self::Double64 | Self::Int64 => 8,
+            Self::String => 0,
+        }
+    }
+}
+
+/// Channel description from `<desc><channels>`, when the stream provides one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct XdfChannel {
+    pub label: String,
+    pub unit: Option<String>,
+    pub kind: Option<String>,
+}
+
+/// Samples of one stream, stored channel-major.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XdfData {
+    Numeric(Vec<Vec<f64>>),
+    Markers(Vec<Vec<String>>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XdfStream {
+    pub id: u32,
+    pub name: String,
+    /// LSL content type, e.g. `EEG`, `ECG`, `Gaze` or `Markers`.
+    pub stream_type: String,
+    pub source_id: String,
+    pub channel_count: usize,
+    /// Declared rate; 0 for irregular streams such as markers.
+    pub nominal_srate: f64,
+    pub channel_format: XdfChannelFormat,
+    pub channels: Vec<XdfChannel>,
+    /// Sample times in seconds on the recording computer's clock.
+    pub timestamps: Vec<f64>,
+    pub data: XdfData,
+    /// `(collection_time, offset)` pairs as recorded, in seconds.
+    pub clock_offsets: Vec<(f64, f64)>,
+    pub header_xml: String,
+    pub footer_xml: Option<String>,
+}
+
+impl XdfStream {
+    pub fn is_markers(&self) -> bool {
+        matches!(self.data, XdfData::Markers(_))
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Rate measured from the timestamps; 0 with fewer than two samples.
+    pub fn effective_srate(&self) -> f64 {
+        match (self.timestamps.first(), self.timestamps.last()) {
+            (Some(first), Some(last)) if last > first => {
+                (self.timestamps.len() - 1) as f64 / (last - first)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Nominal rate, or the effective rate for irregular numeric streams.
+    pub fn fs(&self) -> f64 {
+        if self.nominal_srate > 0.0 {
+            self.nominal_srate
+        } else {
+            self.effective_srate()
+        }
+    }
+
+    /// Channel labels, falling back to `ch1..chN` when the header has no description.
+    pub fn channel_labels(&self) -> Vec<String> {
+        (0..self.channel_count)
+            .map(|i| {
+                self.channels
+                    .get(i)
+                    .map(|c| c.label.clone())
+                    .filter(|l| !l.is_empty())
+                    .unwrap_or_else(|| format!("ch{}", i + 1))
+            })
+            .collect()
+    }
+
+    /// Numeric channel by label (case-insensitive) or zero-based index.
+    pub fn time_series(&self, selector: &str) -> Result<TimeSeries> {
+        let XdfData::Numeric(channels) = &self.data else {
+            bail!("stream {} holds markers, not samples", self.name);
+        };
+        let labels = self.channel_labels();
+        let idx = labels
+            .iter()
+            .position(|l| l.eq_ignore_ascii_case(selector))
+            .or_else(|| selector.parse::<usize>().ok())
+            .filter(|i| *i < channels.len())
+            .ok_or_else(|| anyhow!("stream {} has no channel {selector}", self.name))?;
+        Ok(TimeSeries {
+            fs: self.fs(),
+            data: channels[idx].clone(),
+        })
+    }
+
+    /// `(time, label)` of each marker, taking the first channel of string streams.
+    pub fn markers(&self) -> Vec<(f64, &str)> {
+        match &self.data {
+            XdfData::Markers(channels) => channels
+                .first()
+                .map(|c| {
+                    self.timestamps
+                        .iter()
+                        .zip(c)
+                        .map(|(t, label)| (*t, label.as_str()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            XdfData::Numeric(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XdfFile {
+    pub version: Option<String>,
+    pub datetime: Option<String>,
+    pub streams: Vec<XdfStream>,
+    /// Chunks skipped because they were truncated or malformed.
+    pub skipped_chunks: usize,
+}
+
+impl XdfFile {
+    /// Stream by name or type (case-insensitive), or by numeric stream id.
+    pub fn stream(&self, selector: &str) -> Option<&XdfStream> {
+        self.streams
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(selector))
+            .or_else(|| {
+                self.streams
+                    .iter()
+                    .find(|s| s.stream_type.eq_ignore_ascii_case(selector))
+            })
+            .or_else(|| {
+                let id = selector.parse::<u32>().ok()?;
+                self.streams.iter().find(|s| s.id == id)
+            })
+    }
+
+    /// Earliest timestamp of any sample stream, the natural zero for a session.
+    pub fn first_sample_time(&self) -> Option<f64> {
+        self.streams
+            .iter()
+            .filter(|s| !s.is_markers())
+            .filter_map(|s| s.timestamps.first().copied())
+            .min_by(|a, b| a.total_cmp(b))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct XdfOptions {
+    /// Map stream clocks onto the recorder's clock using the clock-offset chunks.
+    pub synchronize_clocks: bool,
+}
+
+impl Default for XdfOptions {
+    fn default() -> Self {
+        Self {
+            synchronize_clocks: true,
+        }
+    }
+}
+
+pub fn is_xdf(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut f| std::io::Read::read_exact(&mut f, &mut magic))
+        .is_ok()
+        && &magic == MAGIC
+}
+
+/// Reads every stream of an XDF (Lab Streaming Layer) file.
+pub fn read_xdf(path: &Path) -> Result<XdfFile> {
+    read_xdf_with(path, &XdfOptions::default())
+}
+
+pub fn read_xdf_with(path: &Path, options: &XdfOptions) -> Result<XdfFile> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_xdf(&bytes, options).with_context(|| format!("reading XDF file {}", path.display()))
+}
+
+struct Builder {
+    header_xml: String,
+    info: XdfStream,
+    footer_xml: Option<String>,
+}
+
+pub fn parse_xdf(bytes: &[u8], options: &XdfOptions) -> Result<XdfFile> {
+    if !bytes.starts_with(MAGIC) {
+        bail!("missing XDF: magic");
+    }
+    let mut file = XdfFile {
+        version: None,
+        datetime: None,
+        streams: Vec::new(),
+        skipped_chunks: 0,
+    };
+    let mut streams: BTreeMap<u32, Builder> = BTreeMap::new();
+    let mut pos = MAGIC.len();
+    while pos < bytes.len() {
+        match read_chunk(bytes, pos) {
+            Some((tag, content, next)) => {
+                if handle_chunk(tag, content, &mut file, &mut streams).is_err() {
+                    file.skipped_chunks += 1;
+                }
+                pos = next;
+            }
+            None => {
+                // Damaged length field: skip ahead to the next boundary chunk, if any.
+                file.skipped_chunks += 1;
+                match find(&bytes[pos + 1..], &BOUNDARY_UUID) {
+                    Some(offset) => pos = pos + 1 + offset + BOUNDARY_UUID.len(),
+                    None => break,
+                }
+            }
+        }
+    }
+    for (_, builder) in streams {
+        let mut stream = builder.info;
+        stream.header_xml = builder.header_xml;
+        stream.footer_xml = builder.footer_xml;
+        if options.synchronize_clocks && !stream.clock_offsets.is_empty() {
+            let (intercept, slope) = fit_offsets(&stream.clock_offsets);
+            for t in &mut stream.timestamps {
+                *t += intercept + slope * *t;
+            }
+        }
+        file.streams.push(stream);
+    }
+    Ok(file)
+}
+
+/// Returns `(tag, content, next_pos)` of the chunk at `pos`.
+fn read_chunk(bytes: &[u8], pos: usize) -> Option<(u16, &[u8], usize)> {
+    let (len, after_len) = read_varlen(bytes, pos)?;
+    let end = after_len.checked_add(usize::try_from(len).ok()?)?;
+    if len < 2 || end > bytes.len() {
+        return None;
+    }
+    let tag = u16::from_le_bytes([bytes[after_len], bytes[after_len + 1]]);
+    Some((tag, &bytes[after_len + 2..end], end))
+}
+
+fn read_varlen(bytes: &[u8], pos: usize) -> Option<(u64, usize)> {
+    let width = *bytes.get(pos)? as usize;
+    if !matches!(width, 1 | 4 | 8) {
+        return None;
+    }
+    let raw = bytes.get(pos + 1..pos + 1 + width)?;
+    let mut buf = [0u8; 8];
+    buf[..width].copy_from_slice(raw);
+    Some((u64::from_le_bytes(buf), pos + 1 + width))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn stream_id(content: &[u8]) -> Result<u32> {
+    let raw = content
+        .get(..4)
+        .ok_or_else(|| anyhow!("chunk too short for a stream id"))?;
+    Ok(u32::from_le_bytes(raw.try_into()?))
+}
+
+fn handle_chunk(
+    tag: u16,
+    content: &[u8],
+    file: &mut XdfFile,
+    streams: &mut BTreeMap<u32, Builder>,
+) -> Result<()> {
+    match tag {
+        TAG_FILE_HEADER => {
+            let xml = String::from_utf8_lossy(content);
+            file.version = xml_text(&xml, "version");
+            file.datetime = xml_text(&xml, "datetime");
+        }
+        TAG_STREAM_HEADER => {
+            let id = stream_id(content)?;
+            let xml = String::from_utf8_lossy(&content[4..]).into_owned();
+            let info = parse_stream_header(id, &xml)?;
+            streams.insert(
+                id,
+                Builder {
+                    header_xml: xml,
+                    info,
+                    footer_xml: None,
+                },
+            );
+        }
+        TAG_SAMPLES => {
+            let id = stream_id(content)?;
+            let builder = streams
+                .get_mut(&id)
+                .ok_or_else(|| anyhow!("samples for unknown stream {id}"))?;
+            read_samples(&content[4..], &mut builder.info)?;
+        }
+        TAG_CLOCK_OFFSET => {
+            let id = stream_id(content)?;
+            let raw = content
+                .get(4..20)
+                .ok_or_else(|| anyhow!("clock offset chunk too short"))?;
+            let collection = f64::from_le_bytes(raw[..8].try_into()?);
+            let offset = f64::from_le_bytes(raw[8..].try_into()?);
+            if let Some(builder) = streams.get_mut(&id) {
+                builder.info.clock_offsets.push((collection, offset));
+            }
+        }
+        TAG_STREAM_FOOTER => {
+            let id = stream_id(content)?;
+            if let Some(builder) = streams.get_mut(&id) {
+                builder.footer_xml = Some(String::from_utf8_lossy(&content[4..]).into_owned());
+            }
+        }
+        TAG_BOUNDARY => {}
+        other => bail!("unknown XDF chunk tag {other}"),
+    }
+    Ok(())
+}
+
+fn parse_stream_header(id: u32, xml: &str) -> Result<XdfStream> {
+    // Keep `<type>` of the stream apart from the `<type>` of each channel description.
+    let (info, desc) = match xml.find("<desc") {
+        Some(at) => (&xml[..at], &xml[at..]),
+        None => (xml, ""),
+    };
+    let field = |tag: &str| xml_text(info, tag).unwrap_or_default();
+    let channel_count: usize = field("channel_count")
+        .parse()
+        .context("stream header without a valid channel_count")?;
+    let channel_format = XdfChannelFormat::parse(&field("channel_format"))?;
+    let channels = xml_elements(desc, "channel")
+        .into_iter()
+        .map(|c| XdfChannel {
+            label: xml_text(c, "label").unwrap_or_default(),
+            unit: xml_text(c, "unit").filter(|u| !u.is_empty()),
+            kind: xml_text(c, "type").filter(|t| !t.is_empty()),
+        })
+        .collect();
+    let data = if channel_format == XdfChannelFormat::String {
+        XdfData::Markers(vec![Vec::new(); channel_count])
+    } else {
+        XdfData::Numeric(vec![Vec::new(); channel_count])
+    };
+    Ok(XdfStream {
+        id,
+        name: field("name"),
+        stream_type: field("type"),
+        source_id: field("source_id"),
+        channel_count,
+        nominal_srate: field("nominal_srate").parse().unwrap_or(0.0),
+        channel_format,
+        channels,
+        timestamps: Vec::new(),
+        data,
+        clock_offsets: Vec::new(),
+        header_xml: String::new(),
+        footer_xml: None,
+    })
+}
+
+fn read_samples(content: &[u8], stream: &mut XdfStream) -> Result<()> {
+    let truncated = || anyhow!("truncated sample chunk for stream {}", stream.id);
+    let (count, mut pos) = read_varlen(content, 0).ok_or_else(truncated)?;
+    let step = if stream.nominal_srate > 0.0 {
+        1.0 / stream.nominal_srate
+    } else {
+        0.0
+    };
+    let width = stream.channel_format.value_bytes();
+    // Decode the whole chunk before touching the stream so a damaged chunk adds nothing.
+    let mut timestamps = Vec::new();
+    let mut data = match &stream.data {
+        XdfData::Numeric(channels) => XdfData::Numeric(vec![Vec::new(); channels.len()]),
+        XdfData::Markers(channels) => XdfData::Markers(vec![Vec::new(); channels.len()]),
+    };
+    let mut previous = stream.timestamps.last().copied();
+    for _ in 0..count {
+        let ts_bytes = *content.get(pos).ok_or_else(truncated)?;
+        pos += 1;
+        let timestamp = match ts_bytes {
+            8 => {
+                let raw = content.get(pos..pos + 8).ok_or_else(truncated)?;
+                pos += 8;
+                f64::from_le_bytes(raw.try_into()?)
+            }
+            // Omitted timestamps continue the previous one at the nominal rate.
+            0 => previous.map_or(0.0, |t| t + step),
+            other => bail!(
+                "invalid timestamp size {other} in sample chunk for stream {}",
+                stream.id
+            ),
+        };
+        match &mut data {
+            XdfData::Numeric(channels) => {
+                for channel in channels.iter_mut() {
+                    let raw = content.get(pos..pos + width).ok_or_else(truncated)?;
+                    pos += width;
+                    channel.push(decode_value(stream.channel_format, raw));
+                }
+            }
+            XdfData::Markers(channels) => {
+                for channel in channels.iter_mut() {
+                    let (len, start) = read_varlen(content, pos).ok_or_else(truncated)?;
+                    let end = usize::try_from(len)
+                        .ok()
+                        .and_then(|len| start.checked_add(len))
+                        .ok_or_else(truncated)?;
+                    let raw = content.get(start..end).ok_or_else(truncated)?;
+                    channel.push(String::from_utf8_lossy(raw).into_owned());
+                    pos = end;
+                }
+            }
+        }
+        timestamps.push(timestamp);
+        previous = Some(timestamp);
+    }
+    match (&mut stream.data, data) {
+        (XdfData::Numeric(channels), XdfData::Numeric(chunk)) => {
+            for (channel, values) in channels.iter_mut().zip(chunk) {
+                channel.extend(values);
+            }
+        }
+        (XdfData::Markers(channels), XdfData::Markers(chunk)) => {
+            for (channel, values) in channels.iter_mut().zip(chunk) {
+                channel.extend(values);
+            }
+        }
+        _ => unreachable!("chunk buffers mirror the stream data"),
+    }
+    stream.timestamps.extend(timestamps);
+    Ok(())
+}
+
+fn decode_value(format: XdfChannelFormat, raw: &[u8]) -> f64 {
+    match format {
+        XdfChannelFormat::Float32 => {
+            f64::from(f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+        }
+        XdfChannelFormat::Double64 => f64::from_le_bytes(raw.try_into().unwrap_or([0; 8])),
+        XdfChannelFormat::Int8 => f64::from(raw[0] as i8),
+        XdfChannelFormat::Int16 => f64::from(i16::from_le_bytes([raw[0], raw[1]])),
+        XdfChannelFormat::Int32 => f64::from(i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]])),
+        XdfChannelFormat::Int64 => i64::from_le_bytes(raw.try_into().unwrap_or([0; 8])) as f64,
+        XdfChannelFormat::String => f64::NAN,
+    }
+}
+
+/// Least-squares `offset = intercept + slope * time`; a constant when the times coincide.
+fn fit_offsets(offsets: &[(f64, f64)]) -> (f64, f64) {
+    let n = offsets.len() as f64;
+    let mean_t = offsets.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_o = offsets.iter().map(|(_, o)| o).sum::<f64>() / n;
+    let var = offsets
+        .iter()
+        .map(|(t, _)| (t - mean_t).powi(2))
+        .sum::<f64>();
+    if var <= f64::EPSILON {
+        return (mean_o, 0.0);
+    }
+    let cov = offsets
+        .iter()
+        .map(|(t, o)| (t - mean_t) * (o - mean_o))
+        .sum::<f64>();
+    let slope = cov / var;
+    (mean_o - slope * mean_t, slope)
+}
+
+/// Text of the first `<tag>…</tag>` element, with entities decoded.
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    xml_elements(xml, tag)
+        .first()
+        .map(|inner| unescape(inner.trim()))
+}
+
+/// Inner text of every `<tag>…</tag>` element (not nested in one another).
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut out = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // Skip longer tag names sharing the prefix, e.g. `<channels>` when looking for `<channel>`.
+        if !after.starts_with(['>', ' ', '/']) {
+            rest = after;
+            continue;
+        }
+        let Some(gt) = after.find('>') else { break };
+        if after[..gt].ends_with('/') {
+            out.push("");
+            rest = &after[gt + 1..];
+            continue;
+        }
+        let body = &after[gt + 1..];
+        let Some(end) = body.find(&close) else { break };
+        out.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    out
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_path(relative: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .and_then(|p| p.parent())
+            .expect("workspace root")
+            .join(relative)
+    }
+
+    #[test]
+    fn reads_signal_and_marker_streams() {
+        let file = read_xdf(&sample_path("test_data/xdf_sample.xdf")).unwrap();
+        assert_eq!(file.version.as_deref(), Some("1.0"));
+        assert_eq!(file.streams.len(), 2);
+        assert_eq!(file.skipped_chunks, 0);
+
+        let ecg = file.stream("ECG").unwrap();
+        assert_eq!(ecg.channel_labels(), vec!["ECG", "Resp"]);
+        assert_eq!(ecg.channels[0].unit.as_deref(), Some("mV"));
+        assert_eq!(ecg.sample_count(), 200);
+        assert_eq!(ecg.fs(), 100.0);
+        assert!((ecg.effective_srate() - 100.0).abs() < 1e-6);
+        // Clock offsets of -0.5 s shift the device clock (starting at 10.0) to 9.5.
+        assert!((ecg.timestamps[0] - 9.5).abs() < 1e-9);
+        let resp = ecg.time_series("resp").unwrap();
+        assert_eq!(resp.data.len(), 200);
+
+        let markers = file.stream("Markers").unwrap();
+        assert!(markers.is_markers());
+        let labels: Vec<&str> = markers.markers().iter().map(|m| m.1).collect();
+        assert_eq!(labels, vec!["start", "stim", "end"]);
+        assert!((markers.markers()[1].0 - 10.5).abs() < 1e-9);
+
+        let raw = read_xdf_with(
+            &sample_path("test_data/xdf_sample.xdf"),
+            &XdfOptions {
+                synchronize_clocks: false,
+            },
+        )
+        .unwrap();
+        assert!((raw.stream("ECG").unwrap().timestamps[0] - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn resynchronises_at_boundary_chunks() {
+        let mut bytes = std::fs::read(sample_path("test_data/xdf_sample.xdf")).unwrap();
+        let good = parse_xdf(&bytes, &XdfOptions::default()).unwrap();
+        // Corrupt the length field of the first marker chunk; reading resumes at the boundary.
+        let mut pos = MAGIC.len();
+        loop {
+            let (tag, content, next) = read_chunk(&bytes, pos).unwrap();
+            if tag == TAG_SAMPLES && stream_id(content).unwrap() == 2 {
+                break;
+            }
+            pos = next;
+        }
+        bytes[pos] = 3;
+        let damaged = parse_xdf(&bytes, &XdfOptions::default()).unwrap();
+        assert_eq!(damaged.skipped_chunks, 1);
+        let markers = damaged.stream("Markers").unwrap();
+        assert!(markers.sample_count() < good.stream("Markers").unwrap().sample_count());
+        // The damaged chunk is dropped whole: every channel stays aligned with its timestamps.
+        for stream in &damaged.streams {
+            match &stream.data {
+                XdfData::Numeric(channels) => {
+                    assert!(channels.iter().all(|c| c.len() == stream.timestamps.len()))
+                }
+                XdfData::Markers(channels) => {
+                    assert!(channels.iter().all(|c| c.len() == stream.timestamps.len()))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_sample_chunk_adds_nothing() {
+        let header = "<info><channel_count>2</channel_count>\
+                      <channel_format>int16</channel_format><nominal_srate>100</nominal_srate></info>";
+        let mut stream = parse_stream_header(1, header).unwrap();
+        // Two samples with omitted timestamps; the second loses its last channel value.
+        let chunk = [1, 2, 0, 1, 0, 2, 0, 0, 3, 0];
+        assert!(read_samples(&chunk, &mut stream).is_err());
+        assert_eq!(stream.sample_count(), 0);
+        let XdfData::Numeric(channels) = &stream.data else {
+            panic!("int16 stream holds numeric data");
+        };
+        assert!(channels.iter().all(Vec::is_empty));
+        let mut whole = chunk.to_vec();
+        whole.extend([4, 0]);
+        read_samples(&whole, &mut stream).unwrap();
+        assert_eq!(stream.timestamps, vec![0.0, 0.01]);
+    }
+
+    #[test]
+    fn corrupt_sample_chunks_are_errors() {
+        let header = "<info><channel_count>1</channel_count>\
+                      <channel_format>string</channel_format></info>";
+        let mut stream = parse_stream_header(1, header).unwrap();
+        // A string length that overflows the chunk offset.
+        let chunk = [1, 1, 0, 8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(read_samples(&chunk, &mut stream).is_err());
+        // Timestamps are either omitted (0) or 8 bytes.
+        let chunk = [1, 1, 4, 0, 0, 0, 0, 1, 1, b'x'];
+        let err = read_samples(&chunk, &mut stream).unwrap_err();
+        assert!(err.to_string().contains("timestamp size 4"), "{err}");
+        assert_eq!(stream.sample_count(), 0);
+    }
+
+    #[test]
+    fn xml_helpers_skip_prefix_tags() {
+        let xml = "<desc><channels><channel><label>A &amp; B</label></channel>\
+                   <channel><label>C</label></channel></channels></desc>";
+        let channels = xml_elements(xml, "channel");
+        assert_eq!(channels.len(), 2);
+        assert_eq!(xml_text(channels[0], "label").as_deref(), Some("A & B"));
+    }
+}
@@ -3,6 +3,7 @@ pub mod io;
 pub mod metrics;
 pub mod plot;
 pub mod signal;
+pub mod synth;
 
 pub use detectors::*;
 pub use metrics::*;
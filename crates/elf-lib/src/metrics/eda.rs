@@ -0,0 +1,366 @@
+use crate::metrics::stats::{centered_mean, group_by_condition, mean};
+use crate::signal::TimeSeries;
+use serde::{Deserialize, Serialize};
+
+/// Parameters for the median/smoothing EDA decomposition and SCR scoring.
+#[derive(Debug, Clone, Copy)]
+pub struct EdaConfig {
+    /// EDA is analysed at (at most) this rate after block-averaging the raw signal.
+    pub analysis_fs: f64,
+    /// Moving-average window applied before decomposition (seconds).
+    pub smoothing_window_s: f64,
+    /// Moving-median window that tracks the tonic level (seconds).
+    pub tonic_window_s: f64,
+    /// Minimum onset-to-peak rise for an SCR (µS).
+    pub min_amplitude: f64,
+    /// Longest onset-to-peak rise time considered a single SCR (seconds).
+    pub max_rise_s: f64,
+    /// Event-related SCRs must start within this window after the event (seconds).
+    pub latency_min_s: f64,
+    pub latency_max_s: f64,
+}
+
+impl Default for EdaConfig {
+    fn default() -> Self {
+        Self {
+            analysis_fs: 32.0,
+            smoothing_window_s: 0.5,
+            tonic_window_s: 10.0,
+            min_amplitude: 0.02,
+            max_rise_s: 4.0,
+            latency_min_s: 1.0,
+            latency_max_s: 4.0,
+        }
+    }
+}
+
+/// Tonic (skin conductance level) and phasic (responses) components on a common grid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdaDecomposition {
+    pub tonic: TimeSeries,
+    pub phasic: TimeSeries,
+}
+
+/// One skin conductance response.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScrPeak {
+    /// Onset time (seconds from recording start).
+    pub onset: f64,
+    pub peak: f64,
+    /// Peak minus onset value of the phasic component (µS).
+    pub amplitude: f64,
+    pub rise_time_s: f64,
+    /// Time from peak until the response has decayed by half, if it does before the next SCR.
+    pub half_recovery_s: Option<f64>,
+}
+
+/// Stimulus onset (seconds on the EDA clock) plus its condition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrEvent {
+    pub onset: f64,
+    pub condition: String,
+}
+
+/// SCR scored in an event's latency window; amplitude is 0 when no response started there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrEventResponse {
+    pub onset: f64,
+    pub condition: String,
+    pub responded: bool,
+    pub amplitude: f64,
+    pub latency_s: Option<f64>,
+    pub rise_time_s: Option<f64>,
+    /// Mean tonic level during the response window.
+    pub tonic: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrConditionSummary {
+    pub condition: String,
+    pub events: usize,
+    pub responses: usize,
+    /// Mean amplitude including non-responses as zero.
+    pub mean_amplitude: f64,
+    /// Mean amplitude over responses only.
+    pub mean_magnitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdaSummary {
+    pub duration_s: f64,
+    pub scr_count: usize,
+    pub scr_rate_per_min: f64,
+    pub mean_amplitude: f64,
+    pub mean_rise_time_s: f64,
+    pub mean_tonic: f64,
+    /// Linear trend of the tonic level (µS per minute).
+    pub tonic_slope_per_min: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdaResult {
+    pub summary: EdaSummary,
+    pub scrs: Vec<ScrPeak>,
+    pub events: Vec<ScrEventResponse>,
+    pub conditions: Vec<ScrConditionSummary>,
+}
+
+/// Splits skin conductance into tonic and phasic parts.
+///
+/// The signal is smoothed and block-averaged to `analysis_fs`; the tonic level is a moving
+/// median over `tonic_window_s` and the phasic part is the remainder (the "smoothmedian"
+/// approach of NeuroKit2, Makowski et al. 2021, doi:10.3758/s13428-020-01516-y). Responses
+/// longer than half the median window are partly absorbed into the tonic level, so phasic
+/// amplitudes are conservative.
+pub fn decompose_eda(ts: &TimeSeries, cfg: &EdaConfig) -> EdaDecomposition {
+    let factor = ((ts.fs / cfg.analysis_fs).floor() as usize).max(1);
+    let fs = ts.fs / factor as f64;
+    let smoothed = centered_mean(&ts.data, ((cfg.smoothing_window_s * ts.fs) as usize).max(1));
+    let reduced: Vec<f64> = smoothed
+        .chunks(factor)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+    let tonic = centered_median(&reduced, ((cfg.tonic_window_s * fs) as usize).max(1));
+    let phasic = reduced.iter().zip(&tonic).map(|(x, t)| x - t).collect();
+    EdaDecomposition {
+        tonic: TimeSeries { fs, data: tonic },
+        phasic: TimeSeries { fs, data: phasic },
+    }
+}
+
+/// Finds SCRs as phasic troughs followed by a rise of at least `min_amplitude`.
+pub fn detect_scr(phasic: &TimeSeries, cfg: &EdaConfig) -> Vec<ScrPeak> {
+    let data = &phasic.data;
+    let fs = phasic.fs;
+    let max_rise = (cfg.max_rise_s * fs).round() as usize;
+    let mut peaks: Vec<(usize, usize)> = Vec::new();
+    for i in 1..data.len().saturating_sub(1) {
+        if !(data[i] > data[i - 1] && data[i] >= data[i + 1]) {
+            continue;
+        }
+        let floor = i.saturating_sub(max_rise);
+        let mut onset = i;
+        while onset > floor && data[onset - 1] < data[onset] {
+            onset -= 1;
+        }
+        // A rise that never bottoms out within `max_rise_s` is drift, not a response.
+        let bottomed_out = onset > floor || floor == 0;
+        if bottomed_out && data[i] - data[onset] >= cfg.min_amplitude {
+            peaks.push((onset, i));
+        }
+    }
+
+    peaks
+        .iter()
+        .enumerate()
+        .map(|(k, &(onset, peak))| {
+            let amplitude = data[peak] - data[onset];
+            let half = data[peak] - amplitude / 2.0;
+            let limit = peaks.get(k + 1).map(|p| p.0).unwrap_or(data.len());
+            let half_recovery_s = (peak..limit)
+                .find(|&j| data[j] <= half)
+                .map(|j| (j - peak) as f64 / fs);
+            ScrPeak {
+                onset: onset as f64 / fs,
+                peak: peak as f64 / fs,
+                amplitude,
+                rise_time_s: (peak - onset) as f64 / fs,
+                half_recovery_s,
+            }
+        })
+        .collect()
+}
+
+/// Decomposes the signal, scores SCRs and, when events are given, the largest SCR whose onset
+/// falls in `[latency_min_s, latency_max_s]` after each event.
+pub fn analyze_eda(ts: &TimeSeries, events: &[ScrEvent], cfg: &EdaConfig) -> EdaResult {
+    let decomposition = decompose_eda(ts, cfg);
+    let scrs = detect_scr(&decomposition.phasic, cfg);
+    let tonic = &decomposition.tonic;
+
+    let responses: Vec<ScrEventResponse> = events
+        .iter()
+        .map(|event| {
+            let window = event.onset + cfg.latency_min_s..=event.onset + cfg.latency_max_s;
+            let best = scrs
+                .iter()
+                .filter(|scr| window.contains(&scr.onset))
+                .max_by(|a, b| a.amplitude.total_cmp(&b.amplitude));
+            let from = (window.start() * tonic.fs).max(0.0) as usize;
+            let to = ((window.end() * tonic.fs) as usize).min(tonic.data.len());
+            ScrEventResponse {
+                onset: event.onset,
+                condition: event.condition.clone(),
+                responded: best.is_some(),
+                amplitude: best.map(|scr| scr.amplitude).unwrap_or(0.0),
+                latency_s: best.map(|scr| scr.onset - event.onset),
+                rise_time_s: best.map(|scr| scr.rise_time_s),
+                tonic: mean(tonic.data.get(from..to).unwrap_or(&[]).iter().copied()),
+            }
+        })
+        .collect();
+
+    let conditions = group_by_condition(&responses, |r| &r.condition)
+        .into_iter()
+        .map(|(label, members)| ScrConditionSummary {
+            condition: label.to_string(),
+            events: members.len(),
+            responses: members.iter().filter(|r| r.responded).count(),
+            mean_amplitude: mean(members.iter().map(|r| r.amplitude)),
+            mean_magnitude: mean(members.iter().filter(|r| r.responded).map(|r| r.amplitude)),
+        })
+        .collect();
+
+    let duration_s = ts.duration();
+    let summary = EdaSummary {
+        duration_s,
+        scr_count: scrs.len(),
+        scr_rate_per_min: if duration_s > 0.0 {
+            scrs.len() as f64 * 60.0 / duration_s
+        } else {
+            0.0
+        },
+        mean_amplitude: mean(scrs.iter().map(|scr| scr.amplitude)),
+        mean_rise_time_s: mean(scrs.iter().map(|scr| scr.rise_time_s)),
+        mean_tonic: mean(tonic.data.iter().copied()),
+        tonic_slope_per_min: slope(tonic) * 60.0,
+    };
+
+    EdaResult {
+        summary,
+        scrs,
+        events: responses,
+        conditions,
+    }
+}
+
+fn centered_median(data: &[f64], win: usize) -> Vec<f64> {
+    let half = win / 2;
+    let mut window = Vec::with_capacity(win + 1);
+    (0..data.len())
+        .map(|i| {
+            let lo = i.saturating_sub(half);
+            let hi = (i + half + 1).min(data.len());
+            window.clear();
+            window.extend_from_slice(&data[lo..hi]);
+            window.sort_by(f64::total_cmp);
+            let mid = window.len() / 2;
+            if window.len().is_multiple_of(2) {
+                0.5 * (window[mid - 1] + window[mid])
+            } else {
+                window[mid]
+            }
+        })
+        .collect()
+}
+
+/// Least-squares slope per second.
+fn slope(ts: &TimeSeries) -> f64 {
+    let n = ts.data.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean_t = (n - 1) as f64 / 2.0;
+    let mean_y = mean(ts.data.iter().copied());
+    let (num, den) = ts
+        .data
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(num, den), (i, y)| {
+            let dt = i as f64 - mean_t;
+            (num + dt * (y - mean_y), den + dt * dt)
+        });
+    num / den * ts.fs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bateman-shaped SCR (rise ~1 s, slow decay) scaled to `amplitude` µS.
+    fn scr_shape(t: f64, onset: f64, amplitude: f64) -> f64 {
+        let x = t - onset;
+        if x <= 0.0 {
+            return 0.0;
+        }
+        let raw = (-x / 2.0).exp() - (-x / 0.75).exp();
+        // Maximum of the Bateman function for these time constants.
+        amplitude * raw / 0.3
+    }
+
+    fn synthetic_eda(responses: &[(f64, f64)]) -> TimeSeries {
+        let fs = 100.0;
+        let data = (0..(60.0 * fs) as usize)
+            .map(|i| {
+                let t = i as f64 / fs;
+                2.0 + 0.01 * t
+                    + responses
+                        .iter()
+                        .map(|&(onset, amp)| scr_shape(t, onset, amp))
+                        .sum::<f64>()
+            })
+            .collect();
+        TimeSeries { fs, data }
+    }
+
+    #[test]
+    fn detects_scr_onsets_and_amplitudes() {
+        let ts = synthetic_eda(&[(10.0, 0.5), (30.0, 0.2), (45.0, 0.8)]);
+        let cfg = EdaConfig::default();
+        let result = analyze_eda(&ts, &[], &cfg);
+        assert_eq!(result.scrs.len(), 3, "{:?}", result.scrs);
+        for (scr, (onset, _)) in result
+            .scrs
+            .iter()
+            .zip([(10.0, 0.5), (30.0, 0.2), (45.0, 0.8)])
+        {
+            assert!((scr.onset - onset).abs() < 0.5, "onset {}", scr.onset);
+            assert!(scr.rise_time_s > 0.5 && scr.rise_time_s < 2.5);
+        }
+        assert!(result.scrs[2].amplitude > result.scrs[0].amplitude);
+        assert!(result.scrs[0].amplitude > result.scrs[1].amplitude);
+        assert!(result.scrs[0].amplitude > 0.3 && result.scrs[0].amplitude <= 0.5);
+        assert!((result.summary.mean_tonic - 2.3).abs() < 0.2);
+        assert!((result.summary.tonic_slope_per_min - 0.6).abs() < 0.2);
+    }
+
+    #[test]
+    fn scores_event_related_responses() {
+        let ts = synthetic_eda(&[(11.5, 0.5), (41.5, 0.3)]);
+        let events = vec![
+            ScrEvent {
+                onset: 10.0,
+                condition: "threat".into(),
+            },
+            ScrEvent {
+                onset: 25.0,
+                condition: "safe".into(),
+            },
+            ScrEvent {
+                onset: 40.0,
+                condition: "threat".into(),
+            },
+        ];
+        let result = analyze_eda(&ts, &events, &EdaConfig::default());
+        assert!(result.events[0].responded);
+        assert!(!result.events[1].responded);
+        assert_eq!(result.events[1].amplitude, 0.0);
+        let latency = result.events[0].latency_s.unwrap();
+        assert!((latency - 1.5).abs() < 0.5);
+        let threat = &result.conditions[0];
+        assert_eq!(threat.condition, "threat");
+        assert_eq!((threat.events, threat.responses), (2, 2));
+        assert_eq!(result.conditions[1].responses, 0);
+    }
+
+    #[test]
+    fn flat_signal_has_no_responses() {
+        let ts = TimeSeries {
+            fs: 50.0,
+            data: vec![5.0; 1000],
+        };
+        let result = analyze_eda(&ts, &[], &EdaConfig::default());
+        assert!(result.scrs.is_empty());
+        assert_eq!(result.summary.scr_rate_per_min, 0.0);
+    }
+}
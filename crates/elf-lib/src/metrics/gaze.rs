@@ -0,0 +1,551 @@
+use crate::io::eye::{Eye, PupilSample};
+use crate::metrics::stats::mean;
+use serde::{Deserialize, Serialize};
+
+/// Coordinate system of the gaze columns in an export.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GazeUnits {
+    /// Screen pixels, origin top-left.
+    Pixels,
+    /// 0..1 of the display area, origin top-left (Tobii `gaze_point_on_display_area`).
+    Normalized,
+    /// 0..1 of the display area, origin bottom-left (Pupil Labs `norm_pos`).
+    NormalizedBottomLeft,
+}
+
+/// Display geometry used to convert pixels into degrees of visual angle.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScreenGeometry {
+    pub width_px: f64,
+    pub height_px: f64,
+    pub width_mm: f64,
+    pub height_mm: f64,
+    /// Eye-to-screen distance.
+    pub distance_mm: f64,
+}
+
+impl Default for ScreenGeometry {
+    fn default() -> Self {
+        Self {
+            width_px: 1920.0,
+            height_px: 1080.0,
+            width_mm: 531.0,
+            height_mm: 299.0,
+            distance_mm: 600.0,
+        }
+    }
+}
+
+impl ScreenGeometry {
+    /// Pixels per degree of visual angle at the screen centre.
+    pub fn px_per_degree(&self) -> f64 {
+        let half_angle = (0.5 * self.width_mm / self.distance_mm).atan().to_degrees();
+        self.width_px / (2.0 * half_angle)
+    }
+
+    fn degrees_between(&self, a: &GazePoint, b: &GazePoint) -> f64 {
+        (a.x - b.x).hypot(a.y - b.y) / self.px_per_degree()
+    }
+}
+
+/// Gaze position in screen pixels; `x`/`y` are NaN when the tracker lost the eye.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GazePoint {
+    pub timestamp: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+impl GazePoint {
+    fn is_valid(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FixationMethod {
+    /// Velocity threshold identification (Salvucci & Goldberg 2000, doi:10.1145/355017.355028).
+    Ivt { velocity_threshold_deg_s: f64 },
+    /// Dispersion threshold identification (Salvucci & Goldberg 2000).
+    Idt { dispersion_deg: f64 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GazeEventConfig {
+    pub method: FixationMethod,
+    pub min_fixation_s: f64,
+    /// Lost-gaze runs within this duration range are reported as blinks.
+    pub min_blink_s: f64,
+    pub max_blink_s: f64,
+}
+
+impl Default for GazeEventConfig {
+    fn default() -> Self {
+        Self {
+            method: FixationMethod::Ivt {
+                velocity_threshold_deg_s: 30.0,
+            },
+            min_fixation_s: 0.08,
+            min_blink_s: 0.05,
+            max_blink_s: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixation {
+    pub start: f64,
+    pub end: f64,
+    pub duration: f64,
+    /// Centroid in screen pixels.
+    pub x: f64,
+    pub y: f64,
+    pub dispersion_deg: f64,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Saccade {
+    pub start: f64,
+    pub end: f64,
+    pub duration: f64,
+    pub start_x: f64,
+    pub start_y: f64,
+    pub end_x: f64,
+    pub end_y: f64,
+    pub amplitude_deg: f64,
+    pub peak_velocity_deg_s: f64,
+    pub mean_velocity_deg_s: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blink {
+    pub start: f64,
+    pub end: f64,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GazeSummary {
+    pub duration_s: f64,
+    pub fixations: usize,
+    pub mean_fixation_s: f64,
+    pub saccades: usize,
+    pub mean_saccade_amplitude_deg: f64,
+    pub mean_peak_velocity_deg_s: f64,
+    pub blinks: usize,
+    pub blink_rate_per_min: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GazeEvents {
+    pub eye: Eye,
+    pub fixations: Vec<Fixation>,
+    pub saccades: Vec<Saccade>,
+    pub blinks: Vec<Blink>,
+    pub summary: GazeSummary,
+}
+
+/// Fixation density on a `cols` x `rows` grid covering the screen, row-major from the top-left
+/// and scaled so the densest cell is 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixationHeatmap {
+    pub cols: usize,
+    pub rows: usize,
+    pub values: Vec<f64>,
+}
+
+/// Extracts one eye's gaze samples in screen pixels, sorted by time. Samples without gaze or
+/// below `min_confidence` are kept as NaN points so gaps remain visible to the detectors.
+pub fn gaze_points(
+    samples: &[PupilSample],
+    eye: Eye,
+    units: GazeUnits,
+    screen: &ScreenGeometry,
+    min_confidence: f32,
+) -> Vec<GazePoint> {
+    let mut points: Vec<GazePoint> = samples
+        .iter()
+        .filter(|sample| sample.eye == eye)
+        .map(|sample| {
+            let confident = sample.confidence.unwrap_or(1.0) >= min_confidence;
+            let (x, y) = match (sample.gaze_x, sample.gaze_y) {
+                (Some(x), Some(y)) if confident => (x as f64, y as f64),
+                _ => (f64::NAN, f64::NAN),
+            };
+            let (x, y) = match units {
+                GazeUnits::Pixels => (x, y),
+                GazeUnits::Normalized => (x * screen.width_px, y * screen.height_px),
+                GazeUnits::NormalizedBottomLeft => {
+                    (x * screen.width_px, (1.0 - y) * screen.height_px)
+                }
+            };
+            GazePoint {
+                timestamp: sample.timestamp,
+                x,
+                y,
+            }
+        })
+        .collect();
+    points.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    points
+}
+
+/// Classifies fixations, saccades and blinks in a gaze trace.
+///
+/// Saccades are reported between consecutive fixations that are not separated by lost gaze;
+/// angular distances use the small-angle approximation around the screen centre.
+pub fn detect_gaze_events(
+    eye: Eye,
+    points: &[GazePoint],
+    screen: &ScreenGeometry,
+    cfg: &GazeEventConfig,
+) -> GazeEvents {
+    let velocities = sample_velocities(points, screen);
+    let mut fixations = Vec::new();
+    let mut saccades = Vec::new();
+    let mut blinks = Vec::new();
+
+    let mut i = 0;
+    while i < points.len() {
+        if !points[i].is_valid() {
+            let start = i;
+            while i < points.len() && !points[i].is_valid() {
+                i += 1;
+            }
+            // A run touching either end of the recording has no reliable duration.
+            if start > 0 && i < points.len() {
+                let duration = points[i].timestamp - points[start].timestamp;
+                if duration >= cfg.min_blink_s && duration <= cfg.max_blink_s {
+                    blinks.push(Blink {
+                        start: points[start].timestamp,
+                        end: points[i].timestamp,
+                        duration,
+                    });
+                }
+            }
+            continue;
+        }
+        let start = i;
+        while i < points.len() && points[i].is_valid() {
+            i += 1;
+        }
+        let segment = start..i;
+        let spans = match cfg.method {
+            FixationMethod::Ivt {
+                velocity_threshold_deg_s,
+            } => ivt_spans(
+                points,
+                &velocities,
+                segment,
+                velocity_threshold_deg_s,
+                cfg.min_fixation_s,
+            ),
+            FixationMethod::Idt { dispersion_deg } => {
+                idt_spans(points, screen, segment, dispersion_deg, cfg.min_fixation_s)
+            }
+        };
+        for (k, &(a, b)) in spans.iter().enumerate() {
+            fixations.push(fixation(points, screen, a, b));
+            if let Some(&(next, _)) = spans.get(k + 1) {
+                saccades.push(saccade(points, &velocities, screen, b, next));
+            }
+        }
+    }
+
+    let duration_s = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => last.timestamp - first.timestamp,
+        _ => 0.0,
+    };
+    let summary = GazeSummary {
+        duration_s,
+        fixations: fixations.len(),
+        mean_fixation_s: mean(fixations.iter().map(|f| f.duration)),
+        saccades: saccades.len(),
+        mean_saccade_amplitude_deg: mean(saccades.iter().map(|s| s.amplitude_deg)),
+        mean_peak_velocity_deg_s: mean(saccades.iter().map(|s| s.peak_velocity_deg_s)),
+        blinks: blinks.len(),
+        blink_rate_per_min: if duration_s > 0.0 {
+            blinks.len() as f64 * 60.0 / duration_s
+        } else {
+            0.0
+        },
+    };
+    GazeEvents {
+        eye,
+        fixations,
+        saccades,
+        blinks,
+        summary,
+    }
+}
+
+/// Duration-weighted Gaussian fixation density over the screen.
+pub fn fixation_heatmap(
+    fixations: &[Fixation],
+    screen: &ScreenGeometry,
+    cols: usize,
+    rows: usize,
+    sigma_px: f64,
+) -> FixationHeatmap {
+    let mut values = vec![0.0; cols * rows];
+    let cell_w = screen.width_px / cols.max(1) as f64;
+    let cell_h = screen.height_px / rows.max(1) as f64;
+    let denom = 2.0 * sigma_px.max(1.0).powi(2);
+    for row in 0..rows {
+        let cy = (row as f64 + 0.5) * cell_h;
+        for col in 0..cols {
+            let cx = (col as f64 + 0.5) * cell_w;
+            values[row * cols + col] = fixations
+                .iter()
+                .map(|f| f.duration * (-((f.x - cx).powi(2) + (f.y - cy).powi(2)) / denom).exp())
+                .sum();
+        }
+    }
+    let max = values.iter().copied().fold(0.0, f64::max);
+    if max > 0.0 {
+        values.iter_mut().for_each(|v| *v /= max);
+    }
+    FixationHeatmap { cols, rows, values }
+}
+
+/// Point-to-point angular velocity (deg/s) from central differences; NaN next to gaps.
+fn sample_velocities(points: &[GazePoint], screen: &ScreenGeometry) -> Vec<f64> {
+    (0..points.len())
+        .map(|i| {
+            let a = &points[i.saturating_sub(1)];
+            let b = &points[(i + 1).min(points.len() - 1)];
+            let dt = b.timestamp - a.timestamp;
+            if !a.is_valid() || !b.is_valid() || dt <= 0.0 {
+                f64::NAN
+            } else {
+                screen.degrees_between(a, b) / dt
+            }
+        })
+        .collect()
+}
+
+fn ivt_spans(
+    points: &[GazePoint],
+    velocities: &[f64],
+    segment: std::ops::Range<usize>,
+    threshold: f64,
+    min_fixation_s: f64,
+) -> Vec<(usize, usize)> {
+    // NaN velocities next to gaps count as slow so fixations run up to the gap.
+    let fast = |i: usize| velocities[i] >= threshold;
+    let mut spans = Vec::new();
+    let end = segment.end;
+    let mut i = segment.start;
+    while i < end {
+        if fast(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < end && !fast(i) {
+            i += 1;
+        }
+        if points[i - 1].timestamp - points[start].timestamp >= min_fixation_s {
+            spans.push((start, i - 1));
+        }
+    }
+    spans
+}
+
+fn idt_spans(
+    points: &[GazePoint],
+    screen: &ScreenGeometry,
+    segment: std::ops::Range<usize>,
+    dispersion_deg: f64,
+    min_fixation_s: f64,
+) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let end = segment.end;
+    let mut i = segment.start;
+    while i < end {
+        let mut j = i;
+        while j < end && points[j].timestamp - points[i].timestamp < min_fixation_s {
+            j += 1;
+        }
+        if j >= end {
+            break;
+        }
+        if dispersion(points, screen, i, j) > dispersion_deg {
+            i += 1;
+            continue;
+        }
+        while j + 1 < end && dispersion(points, screen, i, j + 1) <= dispersion_deg {
+            j += 1;
+        }
+        spans.push((i, j));
+        i = j + 1;
+    }
+    spans
+}
+
+/// (max x - min x) + (max y - min y) over `points[a..=b]`, in degrees.
+fn dispersion(points: &[GazePoint], screen: &ScreenGeometry, a: usize, b: usize) -> f64 {
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for p in &points[a..=b] {
+        min_x = min_x.min(p.x);
+        max_x = max_x.max(p.x);
+        min_y = min_y.min(p.y);
+        max_y = max_y.max(p.y);
+    }
+    ((max_x - min_x) + (max_y - min_y)) / screen.px_per_degree()
+}
+
+fn fixation(points: &[GazePoint], screen: &ScreenGeometry, a: usize, b: usize) -> Fixation {
+    let span = &points[a..=b];
+    let n = span.len() as f64;
+    Fixation {
+        start: points[a].timestamp,
+        end: points[b].timestamp,
+        duration: points[b].timestamp - points[a].timestamp,
+        x: span.iter().map(|p| p.x).sum::<f64>() / n,
+        y: span.iter().map(|p| p.y).sum::<f64>() / n,
+        dispersion_deg: dispersion(points, screen, a, b),
+        samples: span.len(),
+    }
+}
+
+fn saccade(
+    points: &[GazePoint],
+    velocities: &[f64],
+    screen: &ScreenGeometry,
+    from: usize,
+    to: usize,
+) -> Saccade {
+    let (a, b) = (&points[from], &points[to]);
+    let duration = b.timestamp - a.timestamp;
+    let amplitude_deg = screen.degrees_between(a, b);
+    Saccade {
+        start: a.timestamp,
+        end: b.timestamp,
+        duration,
+        start_x: a.x,
+        start_y: a.y,
+        end_x: b.x,
+        end_y: b.y,
+        amplitude_deg,
+        peak_velocity_deg_s: velocities[from..=to]
+            .iter()
+            .copied()
+            .filter(|v| v.is_finite())
+            .fold(0.0, f64::max),
+        mean_velocity_deg_s: if duration > 0.0 {
+            amplitude_deg / duration
+        } else {
+            0.0
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 500 Hz trace: fixate left, 40 ms saccade right, fixate, lose the eye for 150 ms, fixate.
+    fn scripted_points() -> Vec<GazePoint> {
+        let fs = 500.0;
+        (0..1000)
+            .map(|i| {
+                let t = i as f64 / fs;
+                let (x, y) = if t < 0.5 {
+                    (500.0, 540.0)
+                } else if t < 0.54 {
+                    (500.0 + (t - 0.5) / 0.04 * 800.0, 540.0)
+                } else if (1.2..1.35).contains(&t) {
+                    (f64::NAN, f64::NAN)
+                } else {
+                    (1300.0, 540.0)
+                };
+                // Small tremor keeps the fixations realistic without crossing thresholds.
+                let jitter = if i % 2 == 0 { 0.5 } else { -0.5 };
+                GazePoint {
+                    timestamp: t,
+                    x: x + jitter,
+                    y: y - jitter,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ivt_finds_fixations_saccade_and_blink() {
+        let screen = ScreenGeometry::default();
+        let events = detect_gaze_events(
+            Eye::Left,
+            &scripted_points(),
+            &screen,
+            &GazeEventConfig::default(),
+        );
+        assert_eq!(events.fixations.len(), 3);
+        assert_eq!(events.saccades.len(), 1);
+        assert_eq!(events.blinks.len(), 1);
+        assert!((events.blinks[0].duration - 0.15).abs() < 0.01);
+        let saccade = &events.saccades[0];
+        let expected = 800.0 / screen.px_per_degree();
+        assert!((saccade.amplitude_deg - expected).abs() < 1.0);
+        assert!(saccade.peak_velocity_deg_s > 200.0);
+        assert!((events.fixations[0].x - 500.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn idt_agrees_with_ivt_on_clean_trace() {
+        let screen = ScreenGeometry::default();
+        let cfg = GazeEventConfig {
+            method: FixationMethod::Idt {
+                dispersion_deg: 1.0,
+            },
+            ..GazeEventConfig::default()
+        };
+        let events = detect_gaze_events(Eye::Left, &scripted_points(), &screen, &cfg);
+        assert_eq!(events.fixations.len(), 3);
+        assert_eq!(events.saccades.len(), 1);
+        assert!((events.fixations[1].x - 1300.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn converts_normalized_units_to_pixels() {
+        let sample = PupilSample {
+            timestamp: 0.0,
+            pupil_mm: None,
+            confidence: Some(1.0),
+            eye: Eye::Right,
+            gaze_x: Some(0.25),
+            gaze_y: Some(0.25),
+        };
+        let screen = ScreenGeometry::default();
+        let points = gaze_points(
+            std::slice::from_ref(&sample),
+            Eye::Right,
+            GazeUnits::NormalizedBottomLeft,
+            &screen,
+            0.5,
+        );
+        assert_eq!(points[0].x, 480.0);
+        assert_eq!(points[0].y, 810.0);
+    }
+
+    #[test]
+    fn heatmap_peaks_at_fixation() {
+        let screen = ScreenGeometry::default();
+        let events = detect_gaze_events(
+            Eye::Left,
+            &scripted_points(),
+            &screen,
+            &GazeEventConfig::default(),
+        );
+        let heatmap = fixation_heatmap(&events.fixations, &screen, 16, 9, 60.0);
+        let (peak, _) = heatmap
+            .values
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        // Most dwell time is at x = 1300 px, y = 540 px -> column 10, row 4.
+        assert_eq!((peak % 16, peak / 16), (10, 4));
+    }
+}
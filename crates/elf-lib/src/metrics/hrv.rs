@@ -30,6 +30,20 @@ pub struct HRVNonlinear {
     pub dfa_alpha1: f64,
 }
 
+/// Respiratory sinus arrhythmia as HRV power in a band centred on the measured breathing rate.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HRVRsa {
+    pub resp_hz: f64,
+    pub band: [f64; 2],
+    pub power: f64,
+    pub ln_power: f64,
+    /// Share of LF+HF power (0.04–0.4 Hz) inside the respiratory band.
+    pub fraction: f64,
+    /// False when breathing falls outside the canonical HF band (0.15–0.4 Hz), where HF power
+    /// no longer reflects RSA.
+    pub within_hf: bool,
+}
+
 /// Computes standard time-domain HRV markers (AVNN, SDNN, RMSSD, pNN50) from clean RR intervals.
 pub fn hrv_time(rr: &RRSeries) -> HRVTime {
     let n = rr.rr.len();
@@ -70,17 +84,99 @@ pub fn hrv_time(rr: &RRSeries) -> HRVTime {
     }
 }
 
+/// Limits for [`clean_rr`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RrCleanConfig {
+    /// Shortest physiological interval (seconds).
+    pub min_rr_s: f64,
+    /// Longest physiological interval (seconds).
+    pub max_rr_s: f64,
+    /// Largest allowed relative deviation from the local median (0.2 = 20 %).
+    pub max_deviation: f64,
+    /// Intervals on each side used for the local median.
+    pub half_window: usize,
+    /// Replace rejected intervals with the local median instead of dropping them.
+    pub interpolate: bool,
+}
+
+impl Default for RrCleanConfig {
+    fn default() -> Self {
+        Self {
+            min_rr_s: 0.3,
+            max_rr_s: 2.0,
+            max_deviation: 0.2,
+            half_window: 5,
+            interpolate: false,
+        }
+    }
+}
+
+/// Cleaned RR series plus the positions (in the input series) that were rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RrCleanResult {
+    pub rr: RRSeries,
+    pub rejected: Vec<usize>,
+}
+
+/// Removes ectopic beats and detection artifacts: intervals outside the physiological range,
+/// or further than `max_deviation` from the median of their neighbours (a moving-median
+/// filter in the spirit of Malik et al. 1996 / Task Force guidelines).
+pub fn clean_rr(rr: &RRSeries, cfg: &RrCleanConfig) -> RrCleanResult {
+    let in_range = |x: f64| x.is_finite() && x >= cfg.min_rr_s && x <= cfg.max_rr_s;
+    let mut out = Vec::with_capacity(rr.rr.len());
+    let mut rejected = Vec::new();
+    for (i, &x) in rr.rr.iter().enumerate() {
+        let lo = i.saturating_sub(cfg.half_window);
+        let hi = (i + cfg.half_window + 1).min(rr.rr.len());
+        let mut neighbours: Vec<f64> = (lo..hi)
+            .filter(|&j| j != i)
+            .map(|j| rr.rr[j])
+            .filter(|&v| in_range(v))
+            .collect();
+        let median = median_of(&mut neighbours);
+        let ok = in_range(x) && median.is_none_or(|m| (x - m).abs() <= cfg.max_deviation * m);
+        if ok {
+            out.push(x);
+        } else {
+            rejected.push(i);
+            if let Some(m) = median.filter(|_| cfg.interpolate) {
+                out.push(m);
+            }
+        }
+    }
+    RrCleanResult {
+        rr: RRSeries { rr: out },
+        rejected,
+    }
+}
+
+fn median_of(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        0.5 * (values[mid - 1] + values[mid])
+    } else {
+        values[mid]
+    })
+}
+
+/// Canonical HRV frequency bands in Hz (Task Force 1996), as integrated by [`hrv_psd`].
+pub const VLF_BAND: (f64, f64) = (0.003, 0.04);
+pub const LF_BAND: (f64, f64) = (0.04, 0.15);
+pub const HF_BAND: (f64, f64) = (0.15, 0.4);
+
 /// Computes Welch-periodogram based PSD (doi:10.1109/PROC.1967.4503) and integrates canonical
 /// LF/HF/VLF bands for autonomic balance summaries.
 pub fn hrv_psd(rr: &RRSeries, fs_interp: f64) -> HRVPsd {
     let (freqs, powers) = welch_psd(rr, fs_interp);
     let total_power: f64 = powers.iter().sum();
-    let lf_range = (0.04, 0.15);
-    let hf_range = (0.15, 0.4);
-    let vlf_range = (0.003, 0.04);
-    let lf = integrate_band(&freqs, &powers, lf_range);
-    let hf = integrate_band(&freqs, &powers, hf_range);
-    let vlf = integrate_band(&freqs, &powers, vlf_range);
+    let lf = integrate_band(&freqs, &powers, LF_BAND);
+    let hf = integrate_band(&freqs, &powers, HF_BAND);
+    let vlf = integrate_band(&freqs, &powers, VLF_BAND);
     let lf_hf = if hf > 0.0 { lf / hf } else { 0.0 };
     HRVPsd {
         lf,
@@ -96,6 +192,32 @@ pub fn hrv_psd(rr: &RRSeries, fs_interp: f64) -> HRVPsd {
     }
 }
 
+/// Integrates the Welch PSD over `resp_hz ± half_width_hz`, so RSA is measured at the actual
+/// breathing frequency instead of the fixed HF band (Berntson et al. 1997,
+/// doi:10.1111/j.1469-8986.1997.tb02140.x).
+pub fn hrv_rsa(rr: &RRSeries, fs_interp: f64, resp_hz: f64, half_width_hz: f64) -> HRVRsa {
+    let (freqs, powers) = welch_psd(rr, fs_interp);
+    let reference = integrate_band(&freqs, &powers, (0.04, 0.4));
+    let band = ((resp_hz - half_width_hz).max(0.0), resp_hz + half_width_hz);
+    let power = integrate_band(&freqs, &powers, band);
+    HRVRsa {
+        resp_hz,
+        band: [band.0, band.1],
+        power,
+        ln_power: if power > 0.0 {
+            power.ln()
+        } else {
+            f64::NEG_INFINITY
+        },
+        fraction: if reference > 0.0 {
+            power / reference
+        } else {
+            0.0
+        },
+        within_hf: (0.15..=0.4).contains(&resp_hz),
+    }
+}
+
 /// Nonlinear HRV descriptors: Poincaré SD1/SD2, sample entropy (Richman & Moorman 2000,
 /// doi:10.1016/S1361-6528(01)00113-3), and DFA α1 to capture short-term fractal scaling.
 pub fn hrv_nonlinear(rr: &RRSeries) -> HRVNonlinear {
@@ -382,6 +504,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn clean_rr_rejects_ectopic_and_missed_beats() {
+        let mut rr = rr_series();
+        rr.rr[5] = 0.45;
+        rr.rr[12] = 1.64;
+        let cleaned = clean_rr(&rr, &RrCleanConfig::default());
+        assert_eq!(cleaned.rejected, vec![5, 12]);
+        assert_eq!(cleaned.rr.rr.len(), rr.rr.len() - 2);
+
+        let cfg = RrCleanConfig {
+            interpolate: true,
+            ..RrCleanConfig::default()
+        };
+        let interpolated = clean_rr(&rr, &cfg);
+        assert_eq!(interpolated.rr.rr.len(), rr.rr.len());
+        assert!((interpolated.rr.rr[5] - 0.815).abs() < 1e-9);
+    }
+
     #[test]
     fn welch_psd_regression_snapshot() {
         let rr = rr_series();
@@ -438,4 +578,24 @@ mod tests {
         assert_close(metrics.samp_entropy, 0.0, 1e-6);
         assert_close(metrics.dfa_alpha1, 0.9329347133704009, 1e-6);
     }
+
+    #[test]
+    fn rsa_band_follows_breathing_rate() {
+        // RR modulated at 0.3 Hz (18 breaths/min) around 0.8 s.
+        let mut t = 0.0;
+        let rr: Vec<f64> = (0..400)
+            .map(|_| {
+                let interval = 0.8 + 0.04 * (2.0 * std::f64::consts::PI * 0.3 * t).sin();
+                t += interval;
+                interval
+            })
+            .collect();
+        let rr = RRSeries { rr };
+        let at_breath = hrv_rsa(&rr, 4.0, 0.3, 0.05);
+        let off_breath = hrv_rsa(&rr, 4.0, 0.1, 0.05);
+        assert!(at_breath.within_hf);
+        assert!(!off_breath.within_hf);
+        assert!(at_breath.fraction > 0.8, "fraction {}", at_breath.fraction);
+        assert!(at_breath.power > 10.0 * off_breath.power);
+    }
 }
@@ -1,2 +1,7 @@
+pub mod eda;
+pub mod gaze;
 pub mod hrv;
+pub mod pupil;
+pub mod resp;
 pub mod sqi;
+mod stats;
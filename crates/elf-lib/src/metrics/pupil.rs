@@ -0,0 +1,817 @@
+use crate::io::eye::{Eye, PupilSample};
+use crate::metrics::stats::{group_by_condition, median, nan_mean};
+use crate::signal::TimeSeries;
+use serde::{Deserialize, Serialize};
+
+/// Interpolation kernel used to bridge blinks and rejected samples.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PupilInterpolation {
+    Linear,
+    /// Cubic Hermite (Catmull–Rom) spline through the neighbouring valid samples.
+    Cubic,
+}
+
+/// Configurable parameters for the pupillometry preprocessing pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PupilPipelineConfig {
+    /// Samples below this tracker confidence are treated as missing.
+    pub min_confidence: f32,
+    /// Physiologically plausible pupil diameter range (mm).
+    pub min_pupil_mm: f64,
+    pub max_pupil_mm: f64,
+    /// Longest run of missing samples still classified as a blink (seconds).
+    pub max_blink_s: f64,
+    /// Samples removed before/after each blink or dropout to drop lid-occlusion artifacts (seconds).
+    pub blink_pad_before_s: f64,
+    pub blink_pad_after_s: f64,
+    /// MAD multiplier for the dilation-speed outlier filter.
+    pub dilation_speed_mad: f64,
+    /// Gaps longer than this are left as NaN instead of interpolated (seconds).
+    pub max_gap_s: f64,
+    pub interpolation: PupilInterpolation,
+    /// Centered moving-average window applied after resampling (seconds, 0 disables).
+    pub smoothing_window_s: f64,
+    /// Output sampling rate of the uniform trace (Hz).
+    pub resample_hz: f64,
+}
+
+impl Default for PupilPipelineConfig {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.6,
+            min_pupil_mm: 1.5,
+            max_pupil_mm: 9.0,
+            max_blink_s: 0.5,
+            blink_pad_before_s: 0.05,
+            blink_pad_after_s: 0.1,
+            dilation_speed_mad: 16.0,
+            max_gap_s: 1.0,
+            interpolation: PupilInterpolation::Linear,
+            smoothing_window_s: 0.05,
+            resample_hz: 60.0,
+        }
+    }
+}
+
+/// Reason a stretch of the recording had to be reconstructed or dropped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PupilGapKind {
+    Blink,
+    Missing,
+    Outlier,
+}
+
+/// One contiguous gap in the raw pupil trace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilGap {
+    /// Gap boundaries in tracker time (seconds), padding included.
+    pub start: f64,
+    pub end: f64,
+    pub kind: PupilGapKind,
+    /// `false` when the gap exceeded `max_gap_s` and was left as NaN.
+    pub interpolated: bool,
+}
+
+impl PupilGap {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Summary of everything the pipeline removed or reconstructed for one eye.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilGapReport {
+    pub raw_samples: usize,
+    pub blinks: usize,
+    pub outliers: usize,
+    pub interpolated_s: f64,
+    pub missing_s: f64,
+    /// Fraction of the output trace backed by valid (non-NaN) samples.
+    pub valid_fraction: f64,
+    pub gaps: Vec<PupilGap>,
+}
+
+/// Clean, uniformly resampled pupil trace for one eye.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilTrace {
+    pub eye: Eye,
+    /// Tracker timestamp (seconds) of the first output sample.
+    pub start_time: f64,
+    /// Uniform trace in mm; samples inside unrecoverable gaps are NaN.
+    pub series: TimeSeries,
+    pub report: PupilGapReport,
+}
+
+impl PupilTrace {
+    /// Tracker timestamp of output sample `idx`.
+    pub fn time_at(&self, idx: usize) -> f64 {
+        self.start_time + idx as f64 / self.series.fs
+    }
+}
+
+/// Runs the full preprocessing pipeline separately for every eye present in `samples`.
+///
+/// The steps follow Kret & Sjak-Shie (2019, doi:10.3758/s13428-018-1075-y): invalidate
+/// low-confidence/implausible samples, detect blinks and pad them, reject dilation-speed
+/// outliers, interpolate across short gaps, then resample and smooth onto a uniform grid.
+pub fn preprocess_pupil(samples: &[PupilSample], cfg: &PupilPipelineConfig) -> Vec<PupilTrace> {
+    [Eye::Left, Eye::Right, Eye::Binocular]
+        .into_iter()
+        .filter_map(|eye| preprocess_pupil_eye(samples, eye, cfg))
+        .collect()
+}
+
+/// Runs the preprocessing pipeline on the samples recorded for a single eye.
+pub fn preprocess_pupil_eye(
+    samples: &[PupilSample],
+    eye: Eye,
+    cfg: &PupilPipelineConfig,
+) -> Option<PupilTrace> {
+    let mut eye_samples: Vec<&PupilSample> = samples
+        .iter()
+        .filter(|sample| sample.eye == eye && sample.timestamp.is_finite())
+        .collect();
+    if eye_samples.len() < 2 {
+        return None;
+    }
+    eye_samples.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    let times: Vec<f64> = eye_samples.iter().map(|s| s.timestamp).collect();
+    let mut values: Vec<Option<f64>> = eye_samples
+        .iter()
+        .map(|sample| {
+            let conf_ok = sample.confidence.unwrap_or(1.0) >= cfg.min_confidence;
+            sample
+                .pupil_mm
+                .map(|p| p as f64)
+                .filter(|p| conf_ok && *p >= cfg.min_pupil_mm && *p <= cfg.max_pupil_mm)
+        })
+        .collect();
+
+    let mut gaps = missing_runs(&times, &values, cfg.max_blink_s);
+    pad_gaps(&times, &mut values, &mut gaps, cfg);
+    let outliers = reject_speed_outliers(&times, &mut values, cfg.dilation_speed_mad);
+    gaps.extend(outliers.iter().map(|&idx| PupilGap {
+        start: times[idx],
+        end: times[idx],
+        kind: PupilGapKind::Outlier,
+        interpolated: true,
+    }));
+    gaps.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let valid: Vec<(f64, f64)> = times
+        .iter()
+        .zip(values.iter())
+        .filter_map(|(&t, v)| v.map(|v| (t, v)))
+        .collect();
+    if valid.is_empty() {
+        return None;
+    }
+
+    let fs = cfg.resample_hz.max(1.0);
+    let start_time = times[0];
+    let n = ((times[times.len() - 1] - start_time) * fs).floor() as usize + 1;
+    let mut data = Vec::with_capacity(n);
+    for i in 0..n {
+        let t = start_time + i as f64 / fs;
+        data.push(interpolate_at(&valid, t, cfg.max_gap_s, cfg.interpolation));
+    }
+    let win = (cfg.smoothing_window_s * fs).round() as usize;
+    if win > 1 {
+        data = nan_moving_average(&data, win);
+    }
+
+    for gap in gaps.iter_mut() {
+        gap.interpolated = gap_bridged(&valid, gap, cfg.max_gap_s);
+    }
+    let interpolated_s = gaps
+        .iter()
+        .filter(|gap| gap.interpolated)
+        .map(PupilGap::duration)
+        .sum();
+    let missing_s = gaps
+        .iter()
+        .filter(|gap| !gap.interpolated)
+        .map(PupilGap::duration)
+        .sum();
+    let valid_count = data.iter().filter(|v| v.is_finite()).count();
+    let report = PupilGapReport {
+        raw_samples: eye_samples.len(),
+        blinks: gaps
+            .iter()
+            .filter(|gap| gap.kind == PupilGapKind::Blink)
+            .count(),
+        outliers: outliers.len(),
+        interpolated_s,
+        missing_s,
+        valid_fraction: valid_count as f64 / data.len().max(1) as f64,
+        gaps,
+    };
+    Some(PupilTrace {
+        eye,
+        start_time,
+        series: TimeSeries { fs, data },
+        report,
+    })
+}
+
+/// Groups consecutive invalid samples into gaps spanning the last valid sample before to the
+/// first valid sample after; short runs are blinks, long runs are signal loss.
+fn missing_runs(times: &[f64], values: &[Option<f64>], max_blink_s: f64) -> Vec<PupilGap> {
+    let mut gaps = Vec::new();
+    let mut idx = 0;
+    while idx < values.len() {
+        if values[idx].is_some() {
+            idx += 1;
+            continue;
+        }
+        let first = idx;
+        while idx < values.len() && values[idx].is_none() {
+            idx += 1;
+        }
+        let start = times[first.saturating_sub(1)];
+        let end = times[idx.min(times.len() - 1)];
+        let kind = if end - start <= max_blink_s {
+            PupilGapKind::Blink
+        } else {
+            PupilGapKind::Missing
+        };
+        gaps.push(PupilGap {
+            start,
+            end,
+            kind,
+            interpolated: true,
+        });
+    }
+    gaps
+}
+
+fn pad_gaps(
+    times: &[f64],
+    values: &mut [Option<f64>],
+    gaps: &mut [PupilGap],
+    cfg: &PupilPipelineConfig,
+) {
+    for gap in gaps.iter_mut() {
+        gap.start -= cfg.blink_pad_before_s;
+        gap.end += cfg.blink_pad_after_s;
+        for (t, value) in times.iter().zip(values.iter_mut()) {
+            if *t > gap.start && *t < gap.end {
+                *value = None;
+            }
+        }
+    }
+}
+
+/// Dilation-speed filter: flags samples whose speed to either valid neighbour exceeds
+/// `median + n * MAD` of all speeds (Kret & Sjak-Shie 2019).
+fn reject_speed_outliers(times: &[f64], values: &mut [Option<f64>], n_mad: f64) -> Vec<usize> {
+    let valid: Vec<usize> = (0..values.len()).filter(|&i| values[i].is_some()).collect();
+    if valid.len() < 3 || n_mad <= 0.0 {
+        return Vec::new();
+    }
+    let step_speed = |a: usize, b: usize| -> f64 {
+        let dt = (times[b] - times[a]).abs().max(1e-9);
+        (values[b].unwrap_or(0.0) - values[a].unwrap_or(0.0)).abs() / dt
+    };
+    let speeds: Vec<f64> = valid
+        .iter()
+        .enumerate()
+        .map(|(k, &i)| {
+            let back = if k > 0 {
+                step_speed(valid[k - 1], i)
+            } else {
+                0.0
+            };
+            let fwd = if k + 1 < valid.len() {
+                step_speed(i, valid[k + 1])
+            } else {
+                0.0
+            };
+            back.max(fwd)
+        })
+        .collect();
+    let med = median(&speeds);
+    let mad = median(&speeds.iter().map(|s| (s - med).abs()).collect::<Vec<_>>());
+    let threshold = med + n_mad * mad;
+    if mad <= 0.0 {
+        return Vec::new();
+    }
+    let rejected: Vec<usize> = valid
+        .iter()
+        .zip(speeds.iter())
+        .filter(|(_, &speed)| speed > threshold)
+        .map(|(&i, _)| i)
+        .collect();
+    for &i in &rejected {
+        values[i] = None;
+    }
+    rejected
+}
+
+fn interpolate_at(valid: &[(f64, f64)], t: f64, max_gap_s: f64, kind: PupilInterpolation) -> f64 {
+    let upper = valid.partition_point(|(vt, _)| *vt < t);
+    if upper < valid.len() && (valid[upper].0 - t).abs() < 1e-12 {
+        return valid[upper].1;
+    }
+    if upper == 0 || upper == valid.len() {
+        return f64::NAN;
+    }
+    let (t0, y0) = valid[upper - 1];
+    let (t1, y1) = valid[upper];
+    if t1 - t0 > max_gap_s {
+        return f64::NAN;
+    }
+    let u = (t - t0) / (t1 - t0);
+    match kind {
+        PupilInterpolation::Linear => y0 + u * (y1 - y0),
+        PupilInterpolation::Cubic => {
+            // Catmull–Rom tangents from the neighbouring valid samples, scaled to the interval.
+            let slope = |a: (f64, f64), b: (f64, f64)| (b.1 - a.1) / (b.0 - a.0).max(1e-9);
+            let m0 = if upper >= 2 {
+                slope(valid[upper - 2], valid[upper])
+            } else {
+                slope(valid[upper - 1], valid[upper])
+            } * (t1 - t0);
+            let m1 = if upper + 1 < valid.len() {
+                slope(valid[upper - 1], valid[upper + 1])
+            } else {
+                slope(valid[upper - 1], valid[upper])
+            } * (t1 - t0);
+            let u2 = u * u;
+            let u3 = u2 * u;
+            (2.0 * u3 - 3.0 * u2 + 1.0) * y0
+                + (u3 - 2.0 * u2 + u) * m0
+                + (-2.0 * u3 + 3.0 * u2) * y1
+                + (u3 - u2) * m1
+        }
+    }
+}
+
+fn gap_bridged(valid: &[(f64, f64)], gap: &PupilGap, max_gap_s: f64) -> bool {
+    let mid = 0.5 * (gap.start + gap.end);
+    let upper = valid.partition_point(|(vt, _)| *vt < mid);
+    if upper == 0 || upper == valid.len() {
+        return false;
+    }
+    valid[upper].0 - valid[upper - 1].0 <= max_gap_s
+}
+
+/// Centered moving average that ignores NaN samples and keeps NaN where no valid data exists.
+fn nan_moving_average(data: &[f64], win: usize) -> Vec<f64> {
+    let half = win / 2;
+    (0..data.len())
+        .map(|i| {
+            if !data[i].is_finite() {
+                return f64::NAN;
+            }
+            let lo = i.saturating_sub(half);
+            let hi = (i + half + 1).min(data.len());
+            let (sum, count) = data[lo..hi]
+                .iter()
+                .filter(|v| v.is_finite())
+                .fold((0.0, 0usize), |(s, c), v| (s + v, c + 1));
+            sum / count as f64
+        })
+        .collect()
+}
+
+/// Baseline correction applied to each epoch before averaging (Mathôt et al. 2018,
+/// doi:10.3758/s13428-017-1007-2 recommend subtractive correction).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BaselineCorrection {
+    None,
+    /// `x - baseline`, in mm.
+    Subtractive,
+    /// `x / baseline - 1`, as a fractional change.
+    Divisive,
+}
+
+/// Epoching parameters for task-evoked pupil responses.
+#[derive(Debug, Clone, Copy)]
+pub struct PupilErpConfig {
+    /// Baseline window length before each onset (seconds).
+    pub pre_s: f64,
+    /// Response window length after each onset (seconds).
+    pub post_s: f64,
+    pub baseline: BaselineCorrection,
+    /// Epochs with more missing samples than this fraction are rejected.
+    pub max_missing_fraction: f64,
+}
+
+impl Default for PupilErpConfig {
+    fn default() -> Self {
+        Self {
+            pre_s: 0.5,
+            post_s: 3.0,
+            baseline: BaselineCorrection::Subtractive,
+            max_missing_fraction: 0.5,
+        }
+    }
+}
+
+/// Stimulus onset (seconds on the event clock) plus the condition it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilEpochEvent {
+    pub onset: f64,
+    pub condition: String,
+}
+
+/// Summary of one response curve (single epoch or condition average).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PupilResponseMetrics {
+    /// Largest baseline-corrected value after onset.
+    pub peak: f64,
+    /// Time of the peak relative to onset (seconds).
+    pub peak_latency_s: f64,
+    /// Trapezoidal area under the corrected curve from onset to `post_s`.
+    pub auc: f64,
+    /// Mean corrected value after onset.
+    pub mean: f64,
+}
+
+/// One baseline-corrected epoch cut from a cleaned pupil trace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilEpoch {
+    pub onset: f64,
+    pub condition: String,
+    pub baseline: f64,
+    pub missing_fraction: f64,
+    pub rejected: bool,
+    pub data: Vec<f64>,
+    pub metrics: Option<PupilResponseMetrics>,
+}
+
+/// Average response for one condition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilConditionResponse {
+    pub condition: String,
+    pub epochs: usize,
+    pub rejected: usize,
+    pub mean: Vec<f64>,
+    pub sem: Vec<f64>,
+    pub metrics: Option<PupilResponseMetrics>,
+}
+
+/// Event-locked pupil dilation analysis for one eye.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PupilErpResult {
+    pub eye: Eye,
+    pub fs: f64,
+    pub baseline: BaselineCorrection,
+    /// Epoch time axis relative to onset (seconds).
+    pub times: Vec<f64>,
+    pub conditions: Vec<PupilConditionResponse>,
+    pub epochs: Vec<PupilEpoch>,
+}
+
+/// Cuts baseline-corrected epochs around each event.
+///
+/// `clock_offset` is the tracker timestamp that corresponds to time zero on the event clock,
+/// so an event at `onset` is located at tracker time `clock_offset + onset`.
+pub fn epoch_pupil(
+    trace: &PupilTrace,
+    events: &[PupilEpochEvent],
+    clock_offset: f64,
+    cfg: &PupilErpConfig,
+) -> Vec<PupilEpoch> {
+    let fs = trace.series.fs;
+    let pre = (cfg.pre_s * fs).round() as usize;
+    let post = (cfg.post_s * fs).round() as usize;
+    events
+        .iter()
+        .map(|event| {
+            let onset_time = clock_offset + event.onset;
+            let raw: Vec<f64> = (0..pre + post + 1)
+                .map(|k| {
+                    let t = onset_time + (k as f64 - pre as f64) / fs;
+                    sample_trace(trace, t)
+                })
+                .collect();
+            let baseline = nan_mean(&raw[..pre.max(1).min(raw.len())]);
+            let missing = raw.iter().filter(|v| !v.is_finite()).count();
+            let missing_fraction = missing as f64 / raw.len() as f64;
+            let baseline_ok = match cfg.baseline {
+                BaselineCorrection::None => true,
+                BaselineCorrection::Subtractive => baseline.is_finite(),
+                BaselineCorrection::Divisive => baseline.is_finite() && baseline > 0.0,
+            };
+            let rejected = !baseline_ok || missing_fraction > cfg.max_missing_fraction;
+            let data: Vec<f64> = raw
+                .iter()
+                .map(|&v| match cfg.baseline {
+                    BaselineCorrection::None => v,
+                    BaselineCorrection::Subtractive => v - baseline,
+                    BaselineCorrection::Divisive => v / baseline - 1.0,
+                })
+                .collect();
+            let metrics = if rejected {
+                None
+            } else {
+                response_metrics(&data[pre..], fs)
+            };
+            PupilEpoch {
+                onset: event.onset,
+                condition: event.condition.clone(),
+                baseline,
+                missing_fraction,
+                rejected,
+                data,
+                metrics,
+            }
+        })
+        .collect()
+}
+
+/// Epochs the trace, averages accepted epochs per condition and reports peak dilation,
+/// latency and area under the curve for every condition.
+pub fn pupil_erp(
+    trace: &PupilTrace,
+    events: &[PupilEpochEvent],
+    clock_offset: f64,
+    cfg: &PupilErpConfig,
+) -> PupilErpResult {
+    let fs = trace.series.fs;
+    let pre = (cfg.pre_s * fs).round() as usize;
+    let post = (cfg.post_s * fs).round() as usize;
+    let times: Vec<f64> = (0..pre + post + 1)
+        .map(|k| (k as f64 - pre as f64) / fs)
+        .collect();
+    let epochs = epoch_pupil(trace, events, clock_offset, cfg);
+
+    let conditions = group_by_condition(&epochs, |epoch| &epoch.condition)
+        .into_iter()
+        .map(|(label, members)| {
+            let accepted: Vec<&PupilEpoch> = members
+                .iter()
+                .copied()
+                .filter(|epoch| !epoch.rejected)
+                .collect();
+            let mut mean = Vec::with_capacity(times.len());
+            let mut sem = Vec::with_capacity(times.len());
+            for k in 0..times.len() {
+                let column: Vec<f64> = accepted
+                    .iter()
+                    .map(|epoch| epoch.data[k])
+                    .filter(|v| v.is_finite())
+                    .collect();
+                let m = nan_mean(&column);
+                mean.push(m);
+                sem.push(if column.len() > 1 {
+                    let var = column.iter().map(|v| (v - m).powi(2)).sum::<f64>()
+                        / (column.len() as f64 - 1.0);
+                    (var / column.len() as f64).sqrt()
+                } else {
+                    f64::NAN
+                });
+            }
+            let metrics = if accepted.is_empty() {
+                None
+            } else {
+                response_metrics(&mean[pre..], fs)
+            };
+            PupilConditionResponse {
+                condition: label.to_string(),
+                epochs: members.len(),
+                rejected: members.len() - accepted.len(),
+                mean,
+                sem,
+                metrics,
+            }
+        })
+        .collect();
+
+    PupilErpResult {
+        eye: trace.eye,
+        fs,
+        baseline: cfg.baseline,
+        times,
+        conditions,
+        epochs,
+    }
+}
+
+/// Linear interpolation on the uniform trace; NaN outside the trace or inside gaps.
+fn sample_trace(trace: &PupilTrace, t: f64) -> f64 {
+    let pos = (t - trace.start_time) * trace.series.fs;
+    let data = &trace.series.data;
+    if pos < 0.0 || pos > (data.len() - 1) as f64 {
+        return f64::NAN;
+    }
+    let lo = pos.floor() as usize;
+    let hi = (lo + 1).min(data.len() - 1);
+    let frac = pos - lo as f64;
+    data[lo] + frac * (data[hi] - data[lo])
+}
+
+fn response_metrics(post: &[f64], fs: f64) -> Option<PupilResponseMetrics> {
+    let (peak_idx, peak) = post
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, v)| v.is_finite())
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let dt = 1.0 / fs;
+    let auc = post
+        .windows(2)
+        .filter(|w| w[0].is_finite() && w[1].is_finite())
+        .map(|w| 0.5 * (w[0] + w[1]) * dt)
+        .sum();
+    Some(PupilResponseMetrics {
+        peak,
+        peak_latency_s: peak_idx as f64 * dt,
+        auc,
+        mean: nan_mean(post),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: f64, pupil: Option<f32>, confidence: f32, eye: Eye) -> PupilSample {
+        PupilSample {
+            timestamp,
+            pupil_mm: pupil,
+            confidence: Some(confidence),
+            eye,
+            gaze_x: None,
+            gaze_y: None,
+        }
+    }
+
+    fn synthetic_trace(eye: Eye) -> Vec<PupilSample> {
+        // 4 s at 100 Hz with a slow 0.5 Hz dilation, a 150 ms blink at 1.0 s and one spike.
+        (0..400)
+            .map(|i| {
+                let t = i as f64 / 100.0;
+                let base = 4.0 + 0.3 * (2.0 * std::f64::consts::PI * 0.5 * t).sin();
+                if (1.0..1.15).contains(&t) {
+                    sample(t, None, 0.0, eye)
+                } else if i == 250 {
+                    sample(t, Some(7.5), 0.99, eye)
+                } else {
+                    sample(t, Some(base as f32), 0.99, eye)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_blink_and_bridges_it() {
+        let samples = synthetic_trace(Eye::Left);
+        let trace = preprocess_pupil_eye(&samples, Eye::Left, &PupilPipelineConfig::default())
+            .expect("trace");
+        assert_eq!(trace.report.blinks, 1);
+        assert!(trace.report.valid_fraction > 0.99);
+        assert!(trace.series.data.iter().all(|v| v.is_finite()));
+        let blink = trace
+            .report
+            .gaps
+            .iter()
+            .find(|gap| gap.kind == PupilGapKind::Blink)
+            .unwrap();
+        assert!(blink.start < 1.0 && blink.end > 1.15);
+        assert!(blink.interpolated);
+    }
+
+    #[test]
+    fn rejects_dilation_speed_spikes() {
+        let samples = synthetic_trace(Eye::Right);
+        let trace = preprocess_pupil_eye(&samples, Eye::Right, &PupilPipelineConfig::default())
+            .expect("trace");
+        assert!(trace.report.outliers >= 1);
+        let max = trace
+            .series
+            .data
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert!(max < 4.5, "spike survived preprocessing: {max}");
+    }
+
+    #[test]
+    fn resamples_each_eye_to_requested_rate() {
+        let mut samples = synthetic_trace(Eye::Left);
+        samples.extend(synthetic_trace(Eye::Right));
+        let cfg = PupilPipelineConfig {
+            resample_hz: 50.0,
+            interpolation: PupilInterpolation::Cubic,
+            ..PupilPipelineConfig::default()
+        };
+        let traces = preprocess_pupil(&samples, &cfg);
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].eye, Eye::Left);
+        assert_eq!(traces[1].eye, Eye::Right);
+        assert_eq!(traces[0].series.fs, 50.0);
+        assert_eq!(traces[0].series.len(), 200);
+    }
+
+    #[test]
+    fn leaves_long_gaps_missing() {
+        let samples: Vec<PupilSample> = (0..300)
+            .map(|i| {
+                let t = i as f64 / 100.0;
+                let pupil = if (1.0..2.5).contains(&t) {
+                    None
+                } else {
+                    Some(4.0)
+                };
+                sample(t, pupil, 0.95, Eye::Binocular)
+            })
+            .collect();
+        let trace = preprocess_pupil_eye(&samples, Eye::Binocular, &PupilPipelineConfig::default())
+            .expect("trace");
+        assert_eq!(trace.report.blinks, 0);
+        assert!(trace.report.missing_s > 1.4);
+        assert!(trace.report.valid_fraction < 0.6);
+        assert!(trace.series.data.iter().any(|v| v.is_nan()));
+    }
+
+    fn flat_trace_with_responses(onsets: &[(f64, f64)]) -> PupilTrace {
+        // 60 Hz trace at 4 mm; each (onset, amplitude) adds a 1 s-wide bump peaking 1 s later.
+        let fs = 60.0;
+        let data = (0..(20.0 * fs) as usize)
+            .map(|i| {
+                let t = i as f64 / fs;
+                4.0 + onsets
+                    .iter()
+                    .map(|&(onset, amp)| amp * (-((t - onset - 1.0) / 0.4).powi(2)).exp())
+                    .sum::<f64>()
+            })
+            .collect();
+        PupilTrace {
+            eye: Eye::Left,
+            start_time: 100.0,
+            series: TimeSeries { fs, data },
+            report: PupilGapReport {
+                raw_samples: 0,
+                blinks: 0,
+                outliers: 0,
+                interpolated_s: 0.0,
+                missing_s: 0.0,
+                valid_fraction: 1.0,
+                gaps: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn averages_responses_per_condition() {
+        let trace = flat_trace_with_responses(&[(2.0, 0.5), (8.0, 0.5), (14.0, 0.2)]);
+        let events = vec![
+            PupilEpochEvent {
+                onset: 2.0,
+                condition: "hard".into(),
+            },
+            PupilEpochEvent {
+                onset: 8.0,
+                condition: "hard".into(),
+            },
+            PupilEpochEvent {
+                onset: 14.0,
+                condition: "easy".into(),
+            },
+        ];
+        let result = pupil_erp(&trace, &events, 100.0, &PupilErpConfig::default());
+        assert_eq!(result.conditions.len(), 2);
+        let hard = &result.conditions[0];
+        assert_eq!(hard.condition, "hard");
+        assert_eq!(hard.epochs, 2);
+        let metrics = hard.metrics.expect("hard metrics");
+        assert!((metrics.peak - 0.5).abs() < 0.05, "peak {}", metrics.peak);
+        assert!((metrics.peak_latency_s - 1.0).abs() < 0.05);
+        let easy = result.conditions[1].metrics.expect("easy metrics");
+        assert!(easy.peak < metrics.peak);
+        assert!(easy.auc < metrics.auc);
+    }
+
+    #[test]
+    fn divisive_baseline_reports_fractional_change() {
+        let trace = flat_trace_with_responses(&[(5.0, 0.4)]);
+        let events = vec![PupilEpochEvent {
+            onset: 5.0,
+            condition: "stim".into(),
+        }];
+        let cfg = PupilErpConfig {
+            baseline: BaselineCorrection::Divisive,
+            ..PupilErpConfig::default()
+        };
+        let result = pupil_erp(&trace, &events, 100.0, &cfg);
+        let metrics = result.conditions[0].metrics.unwrap();
+        assert!((metrics.peak - 0.1).abs() < 0.01, "peak {}", metrics.peak);
+    }
+
+    #[test]
+    fn rejects_epochs_outside_trace() {
+        let trace = flat_trace_with_responses(&[]);
+        let events = vec![PupilEpochEvent {
+            onset: 19.5,
+            condition: "late".into(),
+        }];
+        let epochs = epoch_pupil(&trace, &events, 100.0, &PupilErpConfig::default());
+        assert!(epochs[0].rejected);
+        assert!(epochs[0].metrics.is_none());
+    }
+}
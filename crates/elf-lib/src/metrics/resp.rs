@@ -0,0 +1,309 @@
+use crate::metrics::stats::{centered_mean, mean};
+use crate::signal::TimeSeries;
+use serde::{Deserialize, Serialize};
+
+/// Breath detection parameters for respiration belt / thermistor signals.
+#[derive(Debug, Clone, Copy)]
+pub struct RespConfig {
+    /// Moving-average window that suppresses cardiac and sensor noise (seconds).
+    pub smoothing_window_s: f64,
+    /// Moving-average window removed as baseline drift (seconds).
+    pub baseline_window_s: f64,
+    /// Breaths shorter than this are merged into their neighbours (seconds).
+    pub min_breath_s: f64,
+    /// Breaths whose tidal amplitude is below this fraction of the median are merged.
+    pub min_amplitude_fraction: f64,
+}
+
+impl Default for RespConfig {
+    fn default() -> Self {
+        Self {
+            smoothing_window_s: 0.4,
+            baseline_window_s: 10.0,
+            min_breath_s: 1.0,
+            min_amplitude_fraction: 0.3,
+        }
+    }
+}
+
+/// One breath from inspiration onset (trough) through the end-inspiratory peak to the next trough.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Breath {
+    pub onset: f64,
+    pub peak: f64,
+    pub end: f64,
+    /// Inspiration time.
+    pub ti_s: f64,
+    /// Expiration time.
+    pub te_s: f64,
+    pub duration_s: f64,
+    /// Instantaneous rate, breaths per minute.
+    pub rate_bpm: f64,
+    /// Peak minus mean of the surrounding troughs, in signal units.
+    pub tidal_amplitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RespMetrics {
+    pub breaths: usize,
+    pub rate_bpm: f64,
+    pub rate_sd_bpm: f64,
+    /// Mean breathing frequency (Hz), for band-centred RSA.
+    pub resp_hz: f64,
+    pub ti_mean_s: f64,
+    pub te_mean_s: f64,
+    pub ti_te: f64,
+    /// Ti / Ttot.
+    pub duty_cycle: f64,
+    pub tidal_amplitude_mean: f64,
+    /// Coefficient of variation of tidal amplitude.
+    pub tidal_amplitude_cv: f64,
+    /// Coefficient of variation of breath duration.
+    pub duration_cv: f64,
+    /// RMS of successive breath-duration differences relative to the mean duration.
+    pub irregularity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RespResult {
+    pub metrics: RespMetrics,
+    pub breaths: Vec<Breath>,
+}
+
+/// Detects breaths with the zero-crossing method of Schäfer & Kratky (2008,
+/// doi:10.1007/s10439-007-9428-1): the detrended signal's extrema between consecutive
+/// crossings are peaks and troughs, and low-amplitude or too-short cycles are merged away.
+pub fn detect_breaths(ts: &TimeSeries, cfg: &RespConfig) -> Vec<Breath> {
+    let fs = ts.fs;
+    let smoothed = centered_mean(&ts.data, (cfg.smoothing_window_s * fs) as usize);
+    let baseline = centered_mean(&smoothed, (cfg.baseline_window_s * fs) as usize);
+    let x: Vec<f64> = smoothed.iter().zip(&baseline).map(|(s, b)| s - b).collect();
+
+    // Alternating extrema between sign changes: max on positive half-waves, min on negative.
+    let mut extrema: Vec<(usize, bool)> = Vec::new();
+    let mut start = 0;
+    for i in 1..=x.len() {
+        let boundary = i == x.len() || (x[i] >= 0.0) != (x[start] >= 0.0);
+        if !boundary {
+            continue;
+        }
+        let positive = x[start] >= 0.0;
+        let idx = (start..i)
+            .max_by(|&a, &b| {
+                let (va, vb) = if positive {
+                    (x[a], x[b])
+                } else {
+                    (-x[a], -x[b])
+                };
+                va.total_cmp(&vb)
+            })
+            .unwrap_or(start);
+        extrema.push((idx, positive));
+        start = i;
+    }
+
+    let min_len = (cfg.min_breath_s * fs) as usize;
+    loop {
+        let cycles = cycles_from(&extrema, &x);
+        if cycles.is_empty() {
+            break;
+        }
+        let mut amplitudes: Vec<f64> = cycles.iter().map(|c| c.3).collect();
+        amplitudes.sort_by(f64::total_cmp);
+        let median = amplitudes[amplitudes.len() / 2];
+        let weak = cycles.iter().position(|&(onset, _, end, amp, _)| {
+            amp < cfg.min_amplitude_fraction * median || end - onset < min_len
+        });
+        let Some(k) = weak else { break };
+        // Drop the smaller peak/trough pair so the neighbouring breaths absorb it.
+        let peak_pos = cycles[k].4;
+        let drop = if peak_pos + 1 < extrema.len() {
+            peak_pos
+        } else {
+            peak_pos - 1
+        };
+        extrema.drain(drop..(drop + 2).min(extrema.len()));
+        merge_same_sign(&mut extrema, &x);
+    }
+
+    cycles_from(&extrema, &x)
+        .into_iter()
+        .map(|(onset, peak, end, tidal_amplitude, _)| {
+            let duration_s = (end - onset) as f64 / fs;
+            Breath {
+                onset: onset as f64 / fs,
+                peak: peak as f64 / fs,
+                end: end as f64 / fs,
+                ti_s: (peak - onset) as f64 / fs,
+                te_s: (end - peak) as f64 / fs,
+                duration_s,
+                rate_bpm: 60.0 / duration_s,
+                tidal_amplitude,
+            }
+        })
+        .collect()
+}
+
+/// Breath detection plus rate, timing and variability summaries.
+pub fn analyze_resp(ts: &TimeSeries, cfg: &RespConfig) -> RespResult {
+    let breaths = detect_breaths(ts, cfg);
+    let durations: Vec<f64> = breaths.iter().map(|b| b.duration_s).collect();
+    let rates: Vec<f64> = breaths.iter().map(|b| b.rate_bpm).collect();
+    let amplitudes: Vec<f64> = breaths.iter().map(|b| b.tidal_amplitude).collect();
+    let mean_duration = mean(durations.iter().copied());
+    let ti_mean_s = mean(breaths.iter().map(|b| b.ti_s));
+    let te_mean_s = mean(breaths.iter().map(|b| b.te_s));
+    let successive = if durations.len() > 1 {
+        let sum: f64 = durations.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum();
+        (sum / (durations.len() - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    let ratio = |num: f64, den: f64| if den > 0.0 { num / den } else { 0.0 };
+    let metrics = RespMetrics {
+        breaths: breaths.len(),
+        rate_bpm: ratio(60.0, mean_duration),
+        rate_sd_bpm: std_dev(&rates),
+        resp_hz: ratio(1.0, mean_duration),
+        ti_mean_s,
+        te_mean_s,
+        ti_te: ratio(ti_mean_s, te_mean_s),
+        duty_cycle: ratio(ti_mean_s, mean_duration),
+        tidal_amplitude_mean: mean(amplitudes.iter().copied()),
+        tidal_amplitude_cv: ratio(std_dev(&amplitudes), mean(amplitudes.iter().copied())),
+        duration_cv: ratio(std_dev(&durations), mean_duration),
+        irregularity: ratio(successive, mean_duration),
+    };
+    RespResult { metrics, breaths }
+}
+
+/// Peak-valley RSA (Grossman et al. 1990, doi:10.1111/j.1469-8986.1990.tb01966.x): mean over
+/// breaths of the longest minus shortest RR interval ending within each breath, in seconds.
+/// `beat_times` are R-peak times on the same clock as the breaths.
+pub fn rsa_peak_valley(beat_times: &[f64], breaths: &[Breath]) -> Option<f64> {
+    let spans: Vec<f64> = breaths
+        .iter()
+        .filter_map(|breath| {
+            let rr: Vec<f64> = beat_times
+                .windows(2)
+                .filter(|w| w[1] >= breath.onset && w[1] < breath.end)
+                .map(|w| w[1] - w[0])
+                .collect();
+            if rr.len() < 2 {
+                return None;
+            }
+            let max = rr.iter().copied().fold(f64::MIN, f64::max);
+            let min = rr.iter().copied().fold(f64::MAX, f64::min);
+            Some(max - min)
+        })
+        .collect();
+    if spans.is_empty() {
+        None
+    } else {
+        Some(mean(spans))
+    }
+}
+
+/// Full breaths as (onset trough, peak, end trough, tidal amplitude, index of peak in extrema).
+fn cycles_from(extrema: &[(usize, bool)], x: &[f64]) -> Vec<(usize, usize, usize, f64, usize)> {
+    extrema
+        .windows(3)
+        .enumerate()
+        .filter(|(_, w)| !w[0].1 && w[1].1 && !w[2].1)
+        .map(|(k, w)| {
+            let amp = x[w[1].0] - 0.5 * (x[w[0].0] + x[w[2].0]);
+            (w[0].0, w[1].0, w[2].0, amp, k + 1)
+        })
+        .collect()
+}
+
+/// Collapses adjacent extrema of the same polarity into the more extreme one.
+fn merge_same_sign(extrema: &mut Vec<(usize, bool)>, x: &[f64]) {
+    let mut merged: Vec<(usize, bool)> = Vec::with_capacity(extrema.len());
+    for &(idx, positive) in extrema.iter() {
+        if let Some(last) = merged.last_mut() {
+            if last.1 == positive {
+                let better = if positive {
+                    x[idx] > x[last.0]
+                } else {
+                    x[idx] < x[last.0]
+                };
+                if better {
+                    last.0 = idx;
+                }
+                continue;
+            }
+        }
+        merged.push((idx, positive));
+    }
+    *extrema = merged;
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(values.iter().copied());
+    (values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn belt(fs: f64, seconds: f64, rate_hz: impl Fn(f64) -> f64) -> TimeSeries {
+        let mut phase = 0.0;
+        let data = (0..(seconds * fs) as usize)
+            .map(|i| {
+                let t = i as f64 / fs;
+                phase += 2.0 * PI * rate_hz(t) / fs;
+                // Drift plus a small cardiac ripple that must not create extra breaths.
+                5.0 + 0.02 * t - phase.cos() + 0.05 * (2.0 * PI * 1.2 * t).sin()
+            })
+            .collect();
+        TimeSeries { fs, data }
+    }
+
+    #[test]
+    fn counts_regular_breaths() {
+        let ts = belt(50.0, 60.0, |_| 0.25);
+        let result = analyze_resp(&ts, &RespConfig::default());
+        // 15 cycles in a minute; partial cycles at the edges are not reported.
+        assert!(
+            (13..=15).contains(&result.metrics.breaths),
+            "{}",
+            result.metrics.breaths
+        );
+        assert!((result.metrics.rate_bpm - 15.0).abs() < 0.5);
+        assert!((result.metrics.resp_hz - 0.25).abs() < 0.01);
+        assert!((result.metrics.duty_cycle - 0.5).abs() < 0.1);
+        assert!(result.metrics.irregularity < 0.05);
+        assert!(result.metrics.tidal_amplitude_cv < 0.1);
+    }
+
+    #[test]
+    fn tracks_rate_changes() {
+        let ts = belt(50.0, 120.0, |t| if t < 60.0 { 0.2 } else { 0.4 });
+        let result = analyze_resp(&ts, &RespConfig::default());
+        let early = result.breaths.iter().find(|b| b.onset > 20.0).unwrap();
+        let late = result.breaths.iter().find(|b| b.onset > 90.0).unwrap();
+        assert!((early.rate_bpm - 12.0).abs() < 1.0);
+        assert!((late.rate_bpm - 24.0).abs() < 2.0);
+        assert!(result.metrics.duration_cv > 0.2);
+    }
+
+    #[test]
+    fn peak_valley_rsa_follows_breathing() {
+        let ts = belt(50.0, 60.0, |_| 0.25);
+        let breaths = detect_breaths(&ts, &RespConfig::default());
+        // Heart period swings ±50 ms with each breath around 0.8 s.
+        let mut beats = vec![0.0];
+        while *beats.last().unwrap() < 60.0 {
+            let t: f64 = *beats.last().unwrap();
+            beats.push(t + 0.8 + 0.05 * (2.0 * PI * 0.25 * t).sin());
+        }
+        let rsa = rsa_peak_valley(&beats, &breaths).unwrap();
+        assert!(rsa > 0.06 && rsa < 0.11, "rsa {rsa}");
+    }
+}
@@ -0,0 +1,99 @@
+/// Arithmetic mean, or 0 when there are no values.
+pub(crate) fn mean(values: impl IntoIterator<Item = f64>) -> f64 {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0.0, 0usize), |(s, c), v| (s + v, c + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+/// Mean of the finite values, or NaN when there are none.
+pub(crate) fn nan_mean(values: &[f64]) -> f64 {
+    let (sum, count) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((0.0, 0usize), |(s, c), v| (s + v, c + 1));
+    if count == 0 {
+        f64::NAN
+    } else {
+        sum / count as f64
+    }
+}
+
+/// Median, or 0 when there are no values.
+pub(crate) fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        0.5 * (sorted[mid - 1] + sorted[mid])
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Centered moving average over `win` samples, shrinking the window at the edges.
+pub(crate) fn centered_mean(data: &[f64], win: usize) -> Vec<f64> {
+    if win <= 1 {
+        return data.to_vec();
+    }
+    let half = win / 2;
+    let mut prefix = Vec::with_capacity(data.len() + 1);
+    prefix.push(0.0);
+    for &x in data {
+        prefix.push(prefix.last().copied().unwrap_or(0.0) + x);
+    }
+    (0..data.len())
+        .map(|i| {
+            let lo = i.saturating_sub(half);
+            let hi = (i + half + 1).min(data.len());
+            (prefix[hi] - prefix[lo]) / (hi - lo) as f64
+        })
+        .collect()
+}
+
+/// Groups items by condition label, in order of each label's first appearance.
+pub(crate) fn group_by_condition<'a, T>(
+    items: &'a [T],
+    condition: impl Fn(&'a T) -> &'a str,
+) -> Vec<(&'a str, Vec<&'a T>)> {
+    let mut groups: Vec<(&str, Vec<&T>)> = Vec::new();
+    for item in items {
+        let label = condition(item);
+        match groups.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, members)) => members.push(item),
+            None => groups.push((label, vec![item])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_keep_first_appearance_order() {
+        let items = [("b", 1), ("a", 2), ("b", 3)];
+        let groups = group_by_condition(&items, |item| item.0);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "b");
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[1].0, "a");
+    }
+
+    #[test]
+    fn empty_inputs_have_defined_summaries() {
+        assert_eq!(mean(std::iter::empty()), 0.0);
+        assert!(nan_mean(&[f64::NAN]).is_nan());
+        assert_eq!(median(&[]), 0.0);
+        assert_eq!(median(&[3.0, 1.0, 2.0, 4.0]), 2.5);
+        assert_eq!(centered_mean(&[1.0, 2.0, 3.0], 3), vec![1.5, 2.0, 2.5]);
+    }
+}
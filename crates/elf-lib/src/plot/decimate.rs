@@ -0,0 +1,292 @@
+use super::decimate_points;
+use crate::signal::TimeSeries;
+use serde::{Deserialize, Serialize};
+
+/// How a series is reduced to a point budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decimation {
+    /// Every Nth point ([`decimate_points`]).
+    Stride,
+    /// Lowest and highest point per bucket ([`minmax_points`]).
+    #[default]
+    MinMax,
+    /// Largest-Triangle-Three-Buckets ([`lttb_points`]).
+    Lttb,
+}
+
+impl Decimation {
+    pub fn apply(self, points: &[[f64; 2]], max_points: usize) -> Vec<[f64; 2]> {
+        match self {
+            Decimation::Stride => decimate_points(points, max_points),
+            Decimation::MinMax => minmax_points(points, max_points),
+            Decimation::Lttb => lttb_points(points, max_points),
+        }
+    }
+}
+
+/// At most `max_points` points: the lowest and highest `y` of each bucket, in their original
+/// order. Spikes survive however far the series is reduced.
+pub fn minmax_points(points: &[[f64; 2]], max_points: usize) -> Vec<[f64; 2]> {
+    if points.len() <= max_points {
+        return points.to_vec();
+    }
+    let buckets = (max_points / 2).max(1);
+    let mut result = Vec::with_capacity(buckets * 2);
+    for b in 0..buckets {
+        let start = b * points.len() / buckets;
+        let end = (b + 1) * points.len() / buckets;
+        let bucket = &points[start..end];
+        let (Some(lo), Some(hi)) = (extreme(bucket, |a, b| a < b), extreme(bucket, |a, b| a > b))
+        else {
+            continue;
+        };
+        result.push(bucket[lo.min(hi)]);
+        if lo != hi {
+            result.push(bucket[lo.max(hi)]);
+        }
+    }
+    result
+}
+
+/// Index of the point whose `y` wins `better` against every other; NaNs never win.
+fn extreme(points: &[[f64; 2]], better: impl Fn(f64, f64) -> bool) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for (i, p) in points.iter().enumerate() {
+        if best.is_none_or(|b| better(p[1], points[b][1]) || points[b][1].is_nan()) {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+/// Largest-Triangle-Three-Buckets (Steinarsson, 2013): keeps the first and last point and, from
+/// each bucket in between, the point forming the largest triangle with the previously kept
+/// point and the mean of the next bucket.
+pub fn lttb_points(points: &[[f64; 2]], max_points: usize) -> Vec<[f64; 2]> {
+    if points.len() <= max_points || max_points < 3 {
+        return decimate_points(points, max_points);
+    }
+    let last = points.len() - 1;
+    let buckets = max_points - 2;
+    // Bucket `b` covers the points between the first and the last.
+    let bounds = |b: usize| 1 + b * (last - 1) / buckets..1 + (b + 1) * (last - 1) / buckets;
+    let mut result = Vec::with_capacity(max_points);
+    result.push(points[0]);
+    let mut anchor = points[0];
+    for b in 0..buckets {
+        let next = if b + 1 < buckets {
+            &points[bounds(b + 1)]
+        } else {
+            &points[last..]
+        };
+        let n = next.len() as f64;
+        let mean = next
+            .iter()
+            .fold([0.0, 0.0], |acc, p| [acc[0] + p[0] / n, acc[1] + p[1] / n]);
+        let area = |p: &[f64; 2]| {
+            ((anchor[0] - mean[0]) * (p[1] - anchor[1])
+                - (anchor[0] - p[0]) * (mean[1] - anchor[1]))
+                .abs()
+        };
+        let chosen =
+            points[bounds(b)]
+                .iter()
+                .copied()
+                .reduce(|best, p| if area(&p) > area(&best) { p } else { best });
+        if let Some(p) = chosen {
+            result.push(p);
+            anchor = p;
+        }
+    }
+    result.push(points[last]);
+    result
+}
+
+/// Smallest pyramid block, in samples. Blocks of two would just repeat the raw samples.
+const BASE_BLOCK: usize = 4;
+
+/// Min/max pyramid over a uniformly sampled series.
+///
+/// Level `k` holds, for every block of `4 << k` samples, the sample indices of the block's
+/// minimum and maximum, so a window is served at screen resolution by visiting about as many
+/// blocks as there are pixels. The pyramid stores indices only (about half the memory of the
+/// samples) and reads values from the series passed to [`SignalPyramid::window`]. Indices are
+/// `u32`, which covers about 4.3 billion samples (over 49 days at 1 kHz).
+#[derive(Debug, Clone, Default)]
+pub struct SignalPyramid {
+    len: usize,
+    fs: f64,
+    levels: Vec<Vec<[u32; 2]>>,
+}
+
+impl SignalPyramid {
+    pub fn new(series: &TimeSeries) -> Self {
+        let data = &series.data;
+        let pick = |a: usize, b: usize, lower: bool| {
+            let (va, vb) = (data[a], data[b]);
+            let b_wins = if lower { vb < va } else { vb > va };
+            if b_wins || va.is_nan() {
+                b
+            } else {
+                a
+            }
+        };
+        let mut levels: Vec<Vec<[u32; 2]>> = Vec::new();
+        let base: Vec<[u32; 2]> = data
+            .chunks(BASE_BLOCK)
+            .enumerate()
+            .map(|(block, chunk)| {
+                let start = block * BASE_BLOCK;
+                let (mut lo, mut hi) = (start, start);
+                for i in start + 1..start + chunk.len() {
+                    lo = pick(lo, i, true);
+                    hi = pick(hi, i, false);
+                }
+                [lo as u32, hi as u32]
+            })
+            .collect();
+        if base.len() > 1 {
+            levels.push(base);
+        }
+        while let Some(prev) = levels.last().filter(|level| level.len() > 1) {
+            let next = prev
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => [
+                        pick(a[0] as usize, b[0] as usize, true) as u32,
+                        pick(a[1] as usize, b[1] as usize, false) as u32,
+                    ],
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self {
+            len: data.len(),
+            fs: series.fs.max(1.0),
+            levels,
+        }
+    }
+
+    /// Number of samples the pyramid was built over.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Points of `series` between `t0` and `t1` seconds, reduced to at most about
+    /// `max_points` (never fewer than two per block). Every returned point is a real sample,
+    /// so peaks keep their exact time and amplitude. `series` must be the series the pyramid
+    /// was built from.
+    pub fn window(
+        &self,
+        series: &TimeSeries,
+        t0: f64,
+        t1: f64,
+        max_points: usize,
+    ) -> Vec<[f64; 2]> {
+        debug_assert_eq!(series.len(), self.len, "pyramid built from another series");
+        let len = self.len.min(series.len());
+        if len == 0 {
+            return Vec::new();
+        }
+        let start = ((t0.min(t1) * self.fs).floor().max(0.0) as usize).min(len);
+        let end = ((t0.max(t1) * self.fs).ceil().max(0.0) as usize).min(len - 1) + 1;
+        if start >= end {
+            return Vec::new();
+        }
+        let point = |i: usize| [i as f64 / self.fs, series.data[i]];
+        if end - start <= max_points.max(2) || self.levels.is_empty() {
+            return (start..end).map(point).collect();
+        }
+        let mut block = BASE_BLOCK;
+        let mut level = 0;
+        while level + 1 < self.levels.len() && 2 * (end - start).div_ceil(block) > max_points {
+            block *= 2;
+            level += 1;
+        }
+        let mut points = Vec::with_capacity(2 * (end - start).div_ceil(block) + 2);
+        for &[lo, hi] in &self.levels[level][start / block..=(end - 1) / block] {
+            let (first, second) = (lo.min(hi) as usize, lo.max(hi) as usize);
+            // Partial blocks at the edges may reach outside the window.
+            for i in [first, second] {
+                if (start..end).contains(&i) && points.last() != Some(&point(i)) {
+                    points.push(point(i));
+                }
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flat baseline with one-sample spikes every 250 samples.
+    fn spiky(len: usize) -> Vec<[f64; 2]> {
+        (0..len)
+            .map(|i| [i as f64, if i % 250 == 125 { 10.0 } else { 0.0 }])
+            .collect()
+    }
+
+    #[test]
+    fn minmax_keeps_every_spike() {
+        let points = spiky(10_000);
+        let reduced = minmax_points(&points, 200);
+        assert!(reduced.len() <= 200);
+        assert_eq!(reduced.iter().filter(|p| p[1] == 10.0).count(), 40);
+        assert!(reduced.windows(2).all(|w| w[0][0] < w[1][0]));
+        // Stride decimation lands on none of them.
+        let strided = decimate_points(&points, 200);
+        assert!(strided.iter().all(|p| p[1] == 0.0));
+    }
+
+    #[test]
+    fn lttb_keeps_endpoints_and_budget() {
+        let points: Vec<[f64; 2]> = (0..5000)
+            .map(|i| [i as f64, (i as f64 / 300.0).sin()])
+            .collect();
+        let reduced = lttb_points(&points, 100);
+        assert_eq!(reduced.len(), 100);
+        assert_eq!(reduced[0], points[0]);
+        assert_eq!(reduced[99], points[4999]);
+        assert!(reduced.windows(2).all(|w| w[0][0] < w[1][0]));
+        let peak = reduced.iter().map(|p| p[1]).fold(f64::MIN, f64::max);
+        assert!(peak > 0.99, "{peak}");
+    }
+
+    #[test]
+    fn pyramid_windows_match_the_full_resolution_extremes() {
+        let fs = 250.0;
+        let series = TimeSeries {
+            fs,
+            data: spiky(1_000_003).into_iter().map(|p| p[1]).collect(),
+        };
+        let pyramid = SignalPyramid::new(&series);
+        assert_eq!(pyramid.len(), series.len());
+
+        let whole = pyramid.window(&series, 0.0, f64::MAX, 1000);
+        assert!(whole.len() <= 1000);
+        assert!(whole.iter().any(|p| p[1] == 10.0));
+
+        // Zoomed in: every spike in the window, at its exact time.
+        let zoom = pyramid.window(&series, 100.0, 140.0, 400);
+        assert!(zoom.len() <= 400);
+        let spikes: Vec<f64> = zoom.iter().filter(|p| p[1] == 10.0).map(|p| p[0]).collect();
+        let expected: Vec<f64> = (100 * 250..=140 * 250)
+            .filter(|i| i % 250 == 125)
+            .map(|i| i as f64 / fs)
+            .collect();
+        assert_eq!(spikes, expected);
+        assert!(zoom.iter().all(|p| (100.0..=140.0).contains(&p[0])));
+
+        // Few enough samples: the raw samples come back.
+        let raw = pyramid.window(&series, 10.0, 10.5, 1000);
+        assert_eq!(raw.len(), 126);
+    }
+}
@@ -0,0 +1,468 @@
+use super::{Figure, MarkerShape, PlotBackend, Series, Subplots};
+use anyhow::{anyhow, bail, Result};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    /// Format implied by the file extension (`.png` or `.svg`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// Renders figures to one image file; each `draw` overwrites it.
+#[derive(Debug, Clone)]
+pub struct PlottersBackend {
+    path: PathBuf,
+    format: ImageFormat,
+    size: (u32, u32),
+}
+
+impl PlottersBackend {
+    pub const DEFAULT_SIZE: (u32, u32) = (800, 480);
+
+    /// Backend for `path`, with the format taken from its extension.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let Some(format) = ImageFormat::from_path(&path) else {
+            bail!(
+                "cannot tell the image format of {} (use .png or .svg)",
+                path.display()
+            );
+        };
+        Ok(Self::with_format(path, format))
+    }
+
+    pub fn with_format(path: impl Into<PathBuf>, format: ImageFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            size: Self::DEFAULT_SIZE,
+        }
+    }
+
+    /// Image size in pixels (SVG user units).
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width.max(1), height.max(1));
+        self
+    }
+}
+
+impl PlotBackend for PlottersBackend {
+    fn draw(&mut self, fig: &Figure) -> Result<()> {
+        match self.format {
+            ImageFormat::Png => draw_figure(
+                BitMapBackend::new(&self.path, self.size).into_drawing_area(),
+                fig,
+            ),
+            ImageFormat::Svg => draw_figure(
+                SVGBackend::new(&self.path, self.size).into_drawing_area(),
+                fig,
+            ),
+        }
+    }
+
+    fn draw_subplots(&mut self, plots: &Subplots) -> Result<()> {
+        match self.format {
+            ImageFormat::Png => draw_stack(
+                BitMapBackend::new(&self.path, self.size).into_drawing_area(),
+                plots,
+            ),
+            ImageFormat::Svg => draw_stack(
+                SVGBackend::new(&self.path, self.size).into_drawing_area(),
+                plots,
+            ),
+        }
+    }
+}
+
+/// Renders a figure to an SVG document in memory.
+pub fn figure_to_svg(fig: &Figure, size: (u32, u32)) -> Result<String> {
+    let mut svg = String::new();
+    draw_figure(
+        SVGBackend::with_string(&mut svg, size).into_drawing_area(),
+        fig,
+    )?;
+    Ok(svg)
+}
+
+/// Renders a subplot stack to an SVG document in memory.
+pub fn subplots_to_svg(plots: &Subplots, size: (u32, u32)) -> Result<String> {
+    let mut svg = String::new();
+    draw_stack(
+        SVGBackend::with_string(&mut svg, size).into_drawing_area(),
+        plots,
+    )?;
+    Ok(svg)
+}
+
+fn rgb(color: super::Color) -> RGBColor {
+    RGBColor(
+        ((color.0 >> 16) & 0xFF) as u8,
+        ((color.0 >> 8) & 0xFF) as u8,
+        (color.0 & 0xFF) as u8,
+    )
+}
+
+/// Pads a range by `margin` of its span, or by ±0.5 (scaled to the value) when it is flat.
+fn padded([lo, hi]: [f64; 2], margin: f64) -> (f64, f64) {
+    if lo < hi {
+        let pad = (hi - lo) * margin;
+        (lo - pad, hi + pad)
+    } else {
+        let pad = lo.abs().max(1.0) * 0.5;
+        (lo - pad, hi + pad)
+    }
+}
+
+// Backend errors are not `Send + Sync` for every backend; keep their message only.
+fn area_error<E: std::error::Error + Send + Sync>(e: DrawingAreaErrorKind<E>) -> anyhow::Error {
+    anyhow!("drawing figure: {e}")
+}
+
+fn draw_figure<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, fig: &Figure) -> Result<()> {
+    root.fill(&WHITE).map_err(area_error)?;
+    draw_panel(&root, fig, None, true)?;
+    root.present().map_err(area_error)
+}
+
+fn draw_stack<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, plots: &Subplots) -> Result<()> {
+    root.fill(&WHITE).map_err(area_error)?;
+    let area = match &plots.title {
+        Some(title) => root.titled(title, ("sans-serif", 24)).map_err(area_error)?,
+        None => root.clone(),
+    };
+    let shared = plots.shared_x();
+    let last = plots.panels.len().saturating_sub(1);
+    let cells = area.split_evenly((plots.panels.len().max(1), 1));
+    for (i, (cell, fig)) in cells.iter().zip(&plots.panels).enumerate() {
+        draw_panel(cell, fig, shared, !plots.share_x || i == last)?;
+    }
+    root.present().map_err(area_error)
+}
+
+/// One chart in `area`. `x_range` overrides the figure's own range (shared axes), and
+/// `x_labels = false` leaves out the x tick labels and axis title.
+fn draw_panel<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    fig: &Figure,
+    x_range: Option<[f64; 2]>,
+    x_labels: bool,
+) -> Result<()> {
+    let bounds = fig.data_bounds();
+    let x_data = x_range.or(bounds.map(|(x, _)| x)).or_else(|| {
+        fig.spans
+            .iter()
+            .map(|s| [s.from, s.to])
+            .chain(fig.events.iter().map(|e| [e.x, e.x]))
+            .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+    });
+    let (x0, x1) = padded(x_data.unwrap_or([0.0, 1.0]), 0.0);
+    let (y0, y1) = padded(bounds.map_or([0.0, 1.0], |(_, y)| y), 0.05);
+
+    let mut builder = ChartBuilder::on(area);
+    builder
+        .margin(12)
+        .x_label_area_size(match (x_labels, fig.x.label.is_some()) {
+            (false, _) => 8,
+            (true, true) => 44,
+            (true, false) => 30,
+        })
+        .y_label_area_size(if fig.y.label.is_some() { 64 } else { 50 });
+    if let Some(title) = &fig.title {
+        builder.caption(title, ("sans-serif", 22));
+    }
+    let mut chart = builder
+        .build_cartesian_2d(x0..x1, y0..y1)
+        .map_err(area_error)?;
+    let hidden = |_: &f64| String::new();
+    let mut mesh = chart.configure_mesh();
+    mesh.light_line_style(RGBColor(246, 246, 246))
+        .bold_line_style(RGBColor(214, 214, 214))
+        .label_style(("sans-serif", 14));
+    if !x_labels {
+        mesh.x_label_formatter(&hidden);
+    } else if let Some(label) = &fig.x.label {
+        mesh.x_desc(label);
+    }
+    if let Some(label) = &fig.y.label {
+        mesh.y_desc(label);
+    }
+    mesh.draw().map_err(area_error)?;
+
+    let label_style = |hpos| {
+        TextStyle::from(("sans-serif", 13).into_font())
+            .color(&RGBColor(90, 90, 90))
+            .pos(Pos::new(hpos, VPos::Top))
+    };
+    for span in &fig.spans {
+        let (from, to) = (span.from.max(x0), span.to.min(x1));
+        if from >= to {
+            continue;
+        }
+        let fill = rgb(span.color)
+            .mix(span.opacity.clamp(0.0, 1.0) as f64)
+            .filled();
+        chart
+            .draw_series(std::iter::once(Rectangle::new(
+                [(from, y0), (to, y1)],
+                fill,
+            )))
+            .map_err(area_error)?;
+        if let Some(label) = &span.label {
+            chart
+                .draw_series(std::iter::once(Text::new(
+                    label.clone(),
+                    ((from + to) / 2.0, y1),
+                    label_style(HPos::Center),
+                )))
+                .map_err(area_error)?;
+        }
+    }
+
+    for series in &fig.series {
+        let color = rgb(series.color());
+        let name = series.name().to_string();
+        match series {
+            Series::Line(line) => {
+                let style =
+                    ShapeStyle::from(color).stroke_width(line.style.width.round().max(1.0) as u32);
+                // Non-finite points break the line.
+                let runs = line
+                    .points
+                    .split(|p| !(p[0].is_finite() && p[1].is_finite()))
+                    .filter(|run| !run.is_empty());
+                let mut labelled = false;
+                for run in runs {
+                    let coords = run.iter().map(|p| (p[0], p[1]));
+                    let drawn = match line.style.dash {
+                        Some([on, off]) => chart.draw_series(DashedLineSeries::new(
+                            coords,
+                            on.round().max(1.0) as i32,
+                            off.round().max(1.0) as i32,
+                            style,
+                        )),
+                        None => chart.draw_series(LineSeries::new(coords, style)),
+                    }
+                    .map_err(area_error)?;
+                    if !labelled {
+                        labelled = true;
+                        drawn.label(name.clone()).legend(move |(x, y)| {
+                            PathElement::new(vec![(x, y), (x + 20, y)], style)
+                        });
+                    }
+                }
+            }
+            Series::Scatter(scatter) => {
+                let size = scatter.size.round().max(1.0) as i32;
+                let points = scatter
+                    .points
+                    .iter()
+                    .filter(|p| p[0].is_finite() && p[1].is_finite())
+                    .map(|p| (p[0], p[1]));
+                let fill = color.filled();
+                let drawn = match scatter.shape {
+                    MarkerShape::Circle => {
+                        chart.draw_series(points.map(|c| Circle::new(c, size, fill)))
+                    }
+                    MarkerShape::Square => chart.draw_series(points.map(|c| {
+                        EmptyElement::at(c) + Rectangle::new([(-size, -size), (size, size)], fill)
+                    })),
+                    MarkerShape::Cross => chart
+                        .draw_series(points.map(|c| Cross::new(c, size, color.stroke_width(2)))),
+                    MarkerShape::Triangle => {
+                        chart.draw_series(points.map(|c| TriangleMarker::new(c, size, fill)))
+                    }
+                }
+                .map_err(area_error)?;
+                drawn
+                    .label(name)
+                    .legend(move |(x, y)| Circle::new((x + 10, y), size, fill));
+            }
+            Series::Bars(bars) => {
+                let half = bars.width / 2.0;
+                let fill = color.mix(0.85).filled();
+                chart
+                    .draw_series(
+                        bars.bars
+                            .iter()
+                            .filter(|b| b[0].is_finite() && b[1].is_finite())
+                            .map(|&[x, h]| Rectangle::new([(x - half, 0.0), (x + half, h)], fill)),
+                    )
+                    .map_err(area_error)?
+                    .label(name)
+                    .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], fill));
+            }
+        }
+    }
+
+    for event in &fig.events {
+        if !(x0..=x1).contains(&event.x) {
+            continue;
+        }
+        let style = ShapeStyle::from(rgb(event.style.color))
+            .stroke_width(event.style.width.round().max(1.0) as u32);
+        let line = [(event.x, y0), (event.x, y1)];
+        match event.style.dash {
+            Some([on, off]) => chart.draw_series(DashedLineSeries::new(
+                line,
+                on.round().max(1.0) as i32,
+                off.round().max(1.0) as i32,
+                style,
+            )),
+            None => chart.draw_series(LineSeries::new(line, style)),
+        }
+        .map_err(area_error)?;
+        if let Some(label) = &event.label {
+            chart
+                .draw_series(std::iter::once(Text::new(
+                    format!(" {label}"),
+                    (event.x, y1),
+                    label_style(HPos::Left),
+                )))
+                .map_err(area_error)?;
+        }
+    }
+
+    if fig.series.len() > 1 {
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.85))
+            .border_style(RGBColor(200, 200, 200))
+            .label_font(("sans-serif", 14))
+            .draw()
+            .map_err(area_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::{Axis, BarSeries, Color, EventLine, LineSeries, ScatterSeries, Span, Style};
+
+    fn two_series() -> Figure {
+        let mut fig = Figure::new(Some("Two series".to_string()));
+        fig.x = Axis {
+            label: Some("Time (s)".into()),
+        };
+        fig.y = Axis {
+            label: Some("Amplitude".into()),
+        };
+        for (name, color, dash) in [
+            ("solid", 0x0077FF, None),
+            ("dashed", 0xFF3333, Some([6.0, 4.0])),
+        ] {
+            fig.add_series(Series::Line(LineSeries {
+                name: name.into(),
+                points: (0..50)
+                    .map(|i| [i as f64 * 0.1, (i as f64 * 0.3).sin()])
+                    .collect(),
+                style: Style {
+                    width: 2.0,
+                    dash,
+                    color: Color(color),
+                },
+            }));
+        }
+        fig
+    }
+
+    #[test]
+    fn svg_carries_labels_and_colours() {
+        let svg = figure_to_svg(&two_series(), PlottersBackend::DEFAULT_SIZE).unwrap();
+        for text in ["Two series", "Time (s)", "Amplitude", "solid", "dashed"] {
+            assert!(svg.contains(text), "{text}");
+        }
+        assert!(svg.contains("#0077FF") && svg.contains("#FF3333"));
+    }
+
+    #[test]
+    fn backend_picks_format_from_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["fig.png", "fig.svg"] {
+            let path = dir.path().join(name);
+            PlottersBackend::new(&path)
+                .unwrap()
+                .size(320, 200)
+                .draw(&two_series())
+                .unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        }
+        let png = std::fs::read(dir.path().join("fig.png")).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(PlottersBackend::new(dir.path().join("fig.pdf")).is_err());
+    }
+
+    #[test]
+    fn markers_bars_spans_and_events_are_drawn() {
+        let mut fig = Figure::new(Some("Everything".to_string()));
+        fig.add_series(Series::Bars(BarSeries {
+            name: "bars".into(),
+            bars: vec![[0.5, 2.0], [1.5, 3.0]],
+            width: 1.0,
+            color: Color(0xFFAA00),
+        }));
+        fig.add_series(Series::Scatter(ScatterSeries {
+            name: "peaks".into(),
+            points: vec![[0.5, 2.5], [1.5, 3.5]],
+            shape: MarkerShape::Circle,
+            size: 3.0,
+            color: Color(0x1F4E9E),
+        }));
+        fig.add_span(Span {
+            from: 0.2,
+            to: 0.8,
+            label: Some("band".into()),
+            color: Color(0x4C9BE8),
+            opacity: 0.3,
+        });
+        fig.add_event(EventLine {
+            x: 1.2,
+            label: Some("stimulus".into()),
+            style: Style {
+                width: 1.0,
+                dash: Some([4.0, 2.0]),
+                color: Color(0x888888),
+            },
+        });
+        let svg = figure_to_svg(&fig, (400, 300)).unwrap();
+        for text in ["band", "stimulus", "bars", "peaks", "#FFAA00", "#1F4E9E"] {
+            assert!(svg.contains(text), "{text}");
+        }
+        assert!(svg.contains("<circle"));
+    }
+
+    #[test]
+    fn subplots_share_the_x_axis() {
+        let mut top = two_series();
+        top.title = Some("Top".into());
+        let mut bottom = two_series();
+        bottom.title = Some("Bottom".into());
+        if let Series::Line(line) = &mut bottom.series[0] {
+            line.points.push([10.0, 0.0]);
+        }
+        let mut plots = Subplots::new(Some("Stack".to_string()), true);
+        plots.add_panel(top);
+        plots.add_panel(bottom);
+        assert_eq!(plots.shared_x(), Some([0.0, 10.0]));
+        let svg = subplots_to_svg(&plots, (600, 600)).unwrap();
+        for text in ["Stack", "Top", "Bottom"] {
+            assert!(svg.contains(text), "{text}");
+        }
+        // Only the bottom panel carries the x axis title.
+        assert_eq!(svg.matches("Time (s)").count(), 1);
+    }
+}
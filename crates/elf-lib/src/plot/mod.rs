@@ -1,5 +1,18 @@
+use crate::metrics::hrv::{HRVPsd, HF_BAND, LF_BAND, VLF_BAND};
+use crate::signal::{Events, RRSeries, TimeSeries};
 use serde::{Deserialize, Serialize};
 
+pub mod decimate;
+#[cfg(feature = "plotters")]
+pub mod export;
+
+pub use decimate::{lttb_points, minmax_points, Decimation, SignalPyramid};
+#[cfg(feature = "plotters")]
+pub use export::{figure_to_svg, subplots_to_svg, ImageFormat, PlottersBackend};
+
+/// Points kept per ECG waveform figure.
+pub const ECG_MAX_POINTS: usize = 2048;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Axis {
     pub label: Option<String>,
@@ -22,9 +35,93 @@ pub struct LineSeries {
     pub style: Style,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarkerShape {
+    Circle,
+    Square,
+    Cross,
+    Triangle,
+}
+
+/// Unconnected markers (R-peaks, Poincaré pairs).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScatterSeries {
+    pub name: String,
+    pub points: Vec<[f64; 2]>,
+    pub shape: MarkerShape,
+    /// Marker radius in pixels.
+    pub size: f32,
+    pub color: Color,
+}
+
+/// Bars rising from zero; each entry is `[centre, height]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarSeries {
+    pub name: String,
+    pub bars: Vec<[f64; 2]>,
+    /// Bar width in x units.
+    pub width: f64,
+    pub color: Color,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Series {
     Line(LineSeries),
+    Scatter(ScatterSeries),
+    Bars(BarSeries),
+}
+
+impl Series {
+    pub fn name(&self) -> &str {
+        match self {
+            Series::Line(line) => &line.name,
+            Series::Scatter(scatter) => &scatter.name,
+            Series::Bars(bars) => &bars.name,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Series::Line(line) => line.style.color,
+            Series::Scatter(scatter) => scatter.color,
+            Series::Bars(bars) => bars.color,
+        }
+    }
+
+    /// Data points as drawn: bar outlines contribute their edges and the zero baseline.
+    fn extent_points(&self) -> Vec<[f64; 2]> {
+        match self {
+            Series::Line(line) => line.points.clone(),
+            Series::Scatter(scatter) => scatter.points.clone(),
+            Series::Bars(bars) => bars
+                .bars
+                .iter()
+                .flat_map(|&[x, h]| {
+                    let half = bars.width / 2.0;
+                    [[x - half, 0.0], [x + half, h]]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A shaded x range across the full height of the plot (frequency bands, bad segments).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Span {
+    pub from: f64,
+    pub to: f64,
+    pub label: Option<String>,
+    pub color: Color,
+    /// Fill opacity, 0–1.
+    pub opacity: f32,
+}
+
+/// A vertical line at `x` with an optional label at the top (stimulus onsets, annotations).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLine {
+    pub x: f64,
+    pub label: Option<String>,
+    pub style: Style,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +130,10 @@ pub struct Figure {
     pub x: Axis,
     pub y: Axis,
     pub series: Vec<Series>,
+    #[serde(default)]
+    pub spans: Vec<Span>,
+    #[serde(default)]
+    pub events: Vec<EventLine>,
 }
 
 impl Figure {
@@ -42,18 +143,91 @@ impl Figure {
             x: Axis { label: None },
             y: Axis { label: None },
             series: Vec::new(),
+            spans: Vec::new(),
+            events: Vec::new(),
         }
     }
 
+    pub fn with_axis_labels(mut self, x: impl Into<String>, y: impl Into<String>) -> Self {
+        self.x.label = Some(x.into());
+        self.y.label = Some(y.into());
+        self
+    }
+
     pub fn add_series(&mut self, series: Series) {
         self.series.push(series);
     }
+
+    pub fn add_span(&mut self, span: Span) {
+        self.spans.push(span);
+    }
+
+    pub fn add_event(&mut self, event: EventLine) {
+        self.events.push(event);
+    }
+
+    /// `[min, max]` of the finite series data on x and y, or `None` without any.
+    pub fn data_bounds(&self) -> Option<([f64; 2], [f64; 2])> {
+        let mut bounds: Option<([f64; 2], [f64; 2])> = None;
+        for [x, y] in self.series.iter().flat_map(Series::extent_points) {
+            if !(x.is_finite() && y.is_finite()) {
+                continue;
+            }
+            bounds = Some(match bounds {
+                None => ([x, x], [y, y]),
+                Some(([x0, x1], [y0, y1])) => ([x0.min(x), x1.max(x)], [y0.min(y), y1.max(y)]),
+            });
+        }
+        bounds
+    }
+}
+
+/// Figures stacked top to bottom. With `share_x` every panel uses the union of their x ranges
+/// and only the bottom one labels the x axis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subplots {
+    pub title: Option<String>,
+    pub panels: Vec<Figure>,
+    pub share_x: bool,
+}
+
+impl Subplots {
+    pub fn new(title: impl Into<Option<String>>, share_x: bool) -> Self {
+        Self {
+            title: title.into(),
+            panels: Vec::new(),
+            share_x,
+        }
+    }
+
+    pub fn add_panel(&mut self, figure: Figure) {
+        self.panels.push(figure);
+    }
+
+    /// The x range all panels share, when `share_x` is set and there is data.
+    pub fn shared_x(&self) -> Option<[f64; 2]> {
+        if !self.share_x {
+            return None;
+        }
+        self.panels
+            .iter()
+            .filter_map(|fig| fig.data_bounds())
+            .map(|(x, _)| x)
+            .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+    }
 }
 
 pub trait PlotBackend {
     fn draw(&mut self, fig: &Figure) -> anyhow::Result<()>;
+
+    /// Draws a subplot stack. The default draws each panel in turn with [`Self::draw`];
+    /// backends that can lay the panels out on one canvas override it.
+    fn draw_subplots(&mut self, plots: &Subplots) -> anyhow::Result<()> {
+        plots.panels.iter().try_for_each(|panel| self.draw(panel))
+    }
 }
 
+/// Every Nth point; see [`minmax_points`] for traces with narrow peaks.
 pub fn decimate_points(points: &[[f64; 2]], max_points: usize) -> Vec<[f64; 2]> {
     if points.len() <= max_points {
         return points.to_vec();
@@ -71,7 +245,7 @@ pub fn decimate_points(points: &[[f64; 2]], max_points: usize) -> Vec<[f64; 2]>
     result
 }
 
-pub fn figure_from_rr_limit(rr: &crate::signal::RRSeries, max_points: usize) -> Figure {
+pub fn figure_from_rr_limit(rr: &RRSeries, max_points: usize) -> Figure {
     let mut fig = Figure::new(Some("RR intervals".into()));
     let points: Vec<[f64; 2]> = rr
         .rr
@@ -79,7 +253,7 @@ pub fn figure_from_rr_limit(rr: &crate::signal::RRSeries, max_points: usize) ->
         .enumerate()
         .map(|(i, value)| [i as f64, *value])
         .collect();
-    let decimated = decimate_points(&points, max_points);
+    let decimated = minmax_points(&points, max_points);
     fig.add_series(Series::Line(LineSeries {
         name: "RR".into(),
         points: decimated,
@@ -92,13 +266,13 @@ pub fn figure_from_rr_limit(rr: &crate::signal::RRSeries, max_points: usize) ->
     fig
 }
 
-pub fn figure_from_rr(rr: &crate::signal::RRSeries) -> Figure {
+pub fn figure_from_rr(rr: &RRSeries) -> Figure {
     figure_from_rr_limit(rr, 1024)
 }
 
 pub fn figure_from_timeseries(
     title: &str,
-    series: &crate::signal::TimeSeries,
+    series: &TimeSeries,
     max_points: usize,
     color: u32,
 ) -> Figure {
@@ -109,11 +283,21 @@ pub fn figure_from_timeseries(
         .enumerate()
         .map(|(i, value)| [i as f64 * dt, *value])
         .collect();
-    let decimated = decimate_points(&points, max_points);
-    let mut fig = Figure::new(Some(title.into()));
+    let decimated = minmax_points(&points, max_points);
+    figure_from_points(Some(title.into()), title, decimated, color)
+}
+
+/// Single-series figure with the default 1.4 px stroke.
+pub fn figure_from_points(
+    title: Option<String>,
+    name: &str,
+    points: Vec<[f64; 2]>,
+    color: u32,
+) -> Figure {
+    let mut fig = Figure::new(title);
     fig.add_series(Series::Line(LineSeries {
-        name: title.into(),
-        points: decimated,
+        name: name.into(),
+        points,
         style: Style {
             width: 1.4,
             dash: None,
@@ -122,3 +306,190 @@ pub fn figure_from_timeseries(
     }));
     fig
 }
+
+/// ECG waveform as shown in the HRV tab, with the beats marked at their samples (the markers
+/// are not decimated, so every beat stays visible).
+pub fn figure_from_ecg(series: &TimeSeries, beats: Option<&Events>) -> Figure {
+    let fig = figure_from_timeseries("ECG waveform", series, ECG_MAX_POINTS, 0xFF3333);
+    with_beat_markers(fig, series, beats, None)
+}
+
+/// [`figure_from_ecg`] restricted to `t0..=t1` seconds, read from a pyramid built over
+/// `series` so the cost follows the point budget rather than the recording length.
+pub fn figure_from_ecg_window(
+    series: &TimeSeries,
+    pyramid: &SignalPyramid,
+    beats: Option<&Events>,
+    [t0, t1]: [f64; 2],
+    max_points: usize,
+) -> Figure {
+    let points = pyramid.window(series, t0, t1, max_points);
+    let fig = figure_from_points(
+        Some("ECG waveform".into()),
+        "ECG waveform",
+        points,
+        0xFF3333,
+    );
+    with_beat_markers(fig, series, beats, Some([t0, t1]))
+}
+
+fn with_beat_markers(
+    fig: Figure,
+    series: &TimeSeries,
+    beats: Option<&Events>,
+    range: Option<[f64; 2]>,
+) -> Figure {
+    let mut fig = fig.with_axis_labels("Time (s)", "Amplitude");
+    if let Some(beats) = beats {
+        let fs = series.fs.max(1.0);
+        fig.add_series(Series::Scatter(ScatterSeries {
+            name: "R-peaks".into(),
+            points: beats
+                .indices
+                .iter()
+                .filter_map(|&i| series.data.get(i).map(|&v| [i as f64 / fs, v]))
+                .filter(|p| range.is_none_or(|[t0, t1]| (t0..=t1).contains(&p[0])))
+                .collect(),
+            shape: MarkerShape::Circle,
+            size: 3.0,
+            color: Color(0x1F4E9E),
+        }));
+    }
+    fig
+}
+
+/// Welch spectrum as shown in the HRV tab, with the VLF/LF/HF bands shaded.
+pub fn figure_from_psd(psd: &HRVPsd) -> Figure {
+    let mut fig = figure_from_points(Some("PSD".to_string()), "PSD", psd.points.clone(), 0x0077FF)
+        .with_axis_labels("Frequency (Hz)", "Power");
+    for (name, (from, to), color) in [
+        ("VLF", VLF_BAND, 0xB0B0B0),
+        ("LF", LF_BAND, 0x4C9BE8),
+        ("HF", HF_BAND, 0xE8A33C),
+    ] {
+        fig.add_span(Span {
+            from,
+            to,
+            label: Some(name.into()),
+            color: Color(color),
+            opacity: 0.22,
+        });
+    }
+    fig
+}
+
+/// Share of RR intervals per bin as bars. `None` when there is nothing to bin or every
+/// interval is the same.
+pub fn rr_histogram_figure(rr: &RRSeries, bins: usize) -> Option<Figure> {
+    if rr.rr.is_empty() || bins == 0 {
+        return None;
+    }
+    let min_rr = rr.rr.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_rr = rr.rr.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if (max_rr - min_rr).abs() < f64::EPSILON {
+        return None;
+    }
+    let width = (max_rr - min_rr) / bins as f64;
+    let mut counts = vec![0u32; bins];
+    for &value in &rr.rr {
+        let mut idx = ((value - min_rr) / width).floor() as usize;
+        if idx >= bins {
+            idx = bins - 1;
+        }
+        counts[idx] += 1;
+    }
+    let total = counts.iter().sum::<u32>() as f64;
+    let bars: Vec<[f64; 2]> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| {
+            let bin_center = min_rr + width * (i as f64 + 0.5);
+            [bin_center, count as f64 / total]
+        })
+        .collect();
+    let mut fig = Figure::new(Some("RR histogram".to_string())).with_axis_labels("RR (s)", "Share");
+    fig.add_series(Series::Bars(BarSeries {
+        name: "RR distr".into(),
+        bars,
+        width,
+        color: Color(0xFFAA00),
+    }));
+    Some(fig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_requires_bins() {
+        let rr = RRSeries {
+            rr: vec![1.0, 1.1, 1.2, 1.3],
+        };
+        assert!(rr_histogram_figure(&rr, 4).is_some());
+        assert!(rr_histogram_figure(&rr, 0).is_none());
+    }
+
+    #[test]
+    fn histogram_bars_cover_the_rr_range() {
+        let rr = RRSeries {
+            rr: vec![0.8, 0.8, 0.9, 1.0],
+        };
+        let fig = rr_histogram_figure(&rr, 2).unwrap();
+        let Series::Bars(bars) = &fig.series[0] else {
+            panic!("histogram is not a bar series");
+        };
+        assert_eq!(bars.bars.len(), 2);
+        assert!((bars.bars.iter().map(|b| b[1]).sum::<f64>() - 1.0).abs() < 1e-12);
+        let ([x0, x1], [y0, _]) = fig.data_bounds().unwrap();
+        assert!((x0 - 0.8).abs() < 1e-12 && (x1 - 1.0).abs() < 1e-12);
+        assert_eq!(y0, 0.0);
+    }
+
+    #[test]
+    fn subplots_default_to_one_draw_per_panel() {
+        #[derive(Default)]
+        struct Titles(Vec<Option<String>>);
+        impl PlotBackend for Titles {
+            fn draw(&mut self, fig: &Figure) -> anyhow::Result<()> {
+                self.0.push(fig.title.clone());
+                Ok(())
+            }
+        }
+        let mut plots = Subplots::new(Some("stack".to_string()), true);
+        plots.panels.push(Figure::new(Some("top".to_string())));
+        plots.panels.push(Figure::new(Some("bottom".to_string())));
+        let mut backend = Titles::default();
+        backend.draw_subplots(&plots).unwrap();
+        assert_eq!(
+            backend.0,
+            vec![Some("top".to_string()), Some("bottom".to_string())]
+        );
+    }
+
+    #[test]
+    fn ecg_figure_marks_every_beat() {
+        let ts = TimeSeries {
+            fs: 100.0,
+            data: (0..10_000)
+                .map(|i| if i % 80 == 0 { 1.0 } else { 0.0 })
+                .collect(),
+        };
+        let beats = Events::from_indices((0..10_000).step_by(80).collect());
+        let fig = figure_from_ecg(&ts, Some(&beats));
+        let Series::Scatter(peaks) = &fig.series[1] else {
+            panic!("beats are not a scatter series");
+        };
+        assert_eq!(peaks.points.len(), beats.indices.len());
+        assert!(peaks.points.iter().all(|p| p[1] == 1.0));
+    }
+
+    #[test]
+    fn old_figure_json_still_loads() {
+        let json = r#"{"title":"RR","x":{"label":null},"y":{"label":null},"series":[{"Line":
+            {"name":"RR","points":[[0.0,1.0]],"style":{"width":1.0,"dash":null,"color":255}}}]}"#;
+        let fig: Figure = serde_json::from_str(json).unwrap();
+        assert!(fig.spans.is_empty() && fig.events.is_empty());
+        assert_eq!(fig.series[0].name(), "RR");
+    }
+}
@@ -0,0 +1,986 @@
+use crate::signal::{Events, RRSeries};
+use anyhow::{bail, Result};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use realfft::{num_complex::Complex, RealFftPlanner};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{PI, TAU};
+
+/// Generator settings. Defaults follow ECGSYN (60 bpm, LF 0.1 Hz, HF 0.25 Hz, LF/HF 0.5).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SynthConfig {
+    pub fs: f64,
+    pub duration_s: f64,
+    pub seed: u64,
+    pub heart_rate_bpm: f64,
+    /// Standard deviation of the heart rate, which sets the overall RR variability.
+    pub heart_rate_std_bpm: f64,
+    /// Centre frequencies of the LF and HF peaks of the RR spectrum.
+    pub lf_hz: f64,
+    pub hf_hz: f64,
+    /// Standard deviations (widths) of the two spectral peaks.
+    pub lf_std_hz: f64,
+    pub hf_std_hz: f64,
+    /// Power ratio of the LF peak to the HF peak.
+    pub lf_hf_ratio: f64,
+    /// Probability that a beat is a premature ventricular beat, 0–0.5. Each ectopic beat comes
+    /// 35 % early, has a wide QRS without a P wave, and is followed by a full compensatory
+    /// pause.
+    pub ectopic_rate: f64,
+    pub noise: NoiseConfig,
+    /// Breathing rate; defaults to the HF centre frequency, as in respiratory sinus arrhythmia.
+    pub resp_rate_bpm: Option<f64>,
+    pub scr_per_min: f64,
+    pub blink_per_min: f64,
+    /// Spacing of the stimuli that evoke pupil responses; 0 disables them.
+    pub stimulus_interval_s: f64,
+}
+
+impl Default for SynthConfig {
+    fn default() -> Self {
+        Self {
+            fs: 250.0,
+            duration_s: 60.0,
+            seed: 0,
+            heart_rate_bpm: 60.0,
+            heart_rate_std_bpm: 1.0,
+            lf_hz: 0.1,
+            hf_hz: 0.25,
+            lf_std_hz: 0.01,
+            hf_std_hz: 0.01,
+            lf_hf_ratio: 0.5,
+            ectopic_rate: 0.0,
+            noise: NoiseConfig::default(),
+            resp_rate_bpm: None,
+            scr_per_min: 2.0,
+            blink_per_min: 12.0,
+            stimulus_interval_s: 10.0,
+        }
+    }
+}
+
+/// Noise sources mixed into the ECG. Modelled after the MIT-BIH Noise Stress Test Database
+/// (baseline wander, muscle artefact, electrode motion), plus mains interference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoiseKind {
+    BaselineWander,
+    Emg,
+    ElectrodeMotion,
+    Powerline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseConfig {
+    /// Sources to mix, each at equal power. Empty means a clean signal.
+    pub kinds: Vec<NoiseKind>,
+    /// Signal-to-noise ratio of the mix, in dB of the signal's variance.
+    pub snr_db: f64,
+    pub powerline_hz: f64,
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        Self {
+            kinds: Vec::new(),
+            snr_db: 20.0,
+            powerline_hz: 50.0,
+        }
+    }
+}
+
+/// A beat of the ground truth: the sample of its R peak.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SynthBeat {
+    pub sample: usize,
+    pub ectopic: bool,
+}
+
+/// Sample positions of everything the generator placed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroundTruth {
+    pub beats: Vec<SynthBeat>,
+    /// Systolic peaks of the PPG.
+    pub pulse_peaks: Vec<usize>,
+    /// End of each inhalation in the respiration trace.
+    pub breath_peaks: Vec<usize>,
+    pub scr_onsets: Vec<usize>,
+    pub stimuli: Vec<usize>,
+    /// `[first, end)` samples of each blink; the pupil trace is NaN there.
+    pub blinks: Vec<[usize; 2]>,
+}
+
+/// One ground-truth entry as a timed event, e.g. for a BIDS `events.tsv` or EDF+ annotations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SynthEvent {
+    pub onset_s: f64,
+    pub duration_s: f64,
+    pub sample: usize,
+    /// `beat`, `ectopic_beat`, `pulse_peak`, `breath_peak`, `scr_onset`, `stimulus` or
+    /// `blink`.
+    pub kind: &'static str,
+}
+
+/// The generated channels, all sampled at `fs` for `duration_s`.
+#[derive(Debug, Clone)]
+pub struct SynthRecording {
+    pub fs: f64,
+    /// mV, including the configured noise.
+    pub ecg: Vec<f64>,
+    /// Arbitrary units.
+    pub ppg: Vec<f64>,
+    /// Belt stretch, arbitrary units; rises on inhalation.
+    pub resp: Vec<f64>,
+    /// Skin conductance, µS.
+    pub eda: Vec<f64>,
+    /// Pupil diameter, mm; NaN during blinks.
+    pub pupil: Vec<f64>,
+    pub truth: GroundTruth,
+}
+
+impl SynthRecording {
+    pub fn len(&self) -> usize {
+        self.ecg.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ecg.is_empty()
+    }
+
+    /// `(name, units, samples)` of every channel, ECG first.
+    pub fn channels(&self) -> [(&'static str, &'static str, &[f64]); 5] {
+        [
+            ("ECG", "mV", &self.ecg),
+            ("PPG", "au", &self.ppg),
+            ("RESP", "au", &self.resp),
+            ("EDA", "uS", &self.eda),
+            ("PUPIL", "mm", &self.pupil),
+        ]
+    }
+
+    pub fn beat_events(&self) -> Events {
+        Events::from_indices(self.truth.beats.iter().map(|b| b.sample).collect())
+    }
+
+    /// RR intervals between the true R peaks, in seconds.
+    pub fn rr(&self) -> RRSeries {
+        RRSeries::from_events(&self.beat_events(), self.fs)
+    }
+
+    /// The whole ground truth as events sorted by onset.
+    pub fn events(&self) -> Vec<SynthEvent> {
+        let fs = self.fs;
+        let event = |sample: usize, duration: usize, kind| SynthEvent {
+            onset_s: sample as f64 / fs,
+            duration_s: duration as f64 / fs,
+            sample,
+            kind,
+        };
+        let truth = &self.truth;
+        let mut events: Vec<SynthEvent> = truth
+            .beats
+            .iter()
+            .map(|b| event(b.sample, 0, if b.ectopic { "ectopic_beat" } else { "beat" }))
+            .chain(truth.pulse_peaks.iter().map(|&s| event(s, 0, "pulse_peak")))
+            .chain(
+                truth
+                    .breath_peaks
+                    .iter()
+                    .map(|&s| event(s, 0, "breath_peak")),
+            )
+            .chain(truth.scr_onsets.iter().map(|&s| event(s, 0, "scr_onset")))
+            .chain(truth.stimuli.iter().map(|&s| event(s, 0, "stimulus")))
+            .chain(truth.blinks.iter().map(|&[a, b]| event(a, b - a, "blink")))
+            .collect();
+        events.sort_by_key(|e| e.sample);
+        events
+    }
+}
+
+/// Generates a recording from `config`.
+pub fn generate(config: &SynthConfig) -> Result<SynthRecording> {
+    validate(config)?;
+    let fs = config.fs;
+    let n = (config.duration_s * fs).round() as usize;
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let resp_hz = config.resp_rate_bpm.map_or(config.hf_hz, |bpm| bpm / 60.0);
+    let breathing = Breathing::new(resp_hz, &mut rng);
+    let clock = BeatClock::new(config, &mut rng);
+
+    let clean = ecgsyn(config, &clock, &breathing, n);
+    let search = (0.05 * fs).round() as usize;
+    let beats: Vec<SynthBeat> = clock
+        .times
+        .iter()
+        .zip(&clock.ectopic)
+        .filter(|(&t, _)| t >= 0.0 && ((t * fs).round() as usize) < n)
+        .map(|(&t, &ectopic)| SynthBeat {
+            sample: local_peak(&clean, (t * fs).round() as usize, search),
+            ectopic,
+        })
+        .collect();
+    let mut ecg = clean;
+    add_noise(&mut ecg, fs, &config.noise, config.seed.wrapping_add(1));
+
+    let (resp, breath_peaks) = respiration(&breathing, fs, n, &mut rng);
+    let (ppg, pulse_peaks) = ppg(&clock, &breathing, fs, n, &mut rng);
+    let (eda, scr_onsets) = eda(config, n, &mut rng);
+    let (pupil, stimuli, blinks) = pupil(config, n, &mut rng);
+
+    Ok(SynthRecording {
+        fs,
+        ecg,
+        ppg,
+        resp,
+        eda,
+        pupil,
+        truth: GroundTruth {
+            beats,
+            pulse_peaks,
+            breath_peaks,
+            scr_onsets,
+            stimuli,
+            blinks,
+        },
+    })
+}
+
+fn validate(config: &SynthConfig) -> Result<()> {
+    if !(config.fs > 0.0 && config.fs.is_finite()) {
+        bail!("sampling rate must be positive");
+    }
+    if !(config.duration_s > 0.0 && config.duration_s.is_finite()) {
+        bail!("duration must be positive");
+    }
+    if (config.duration_s * config.fs).round() < 1.0 {
+        bail!(
+            "{} s at {} Hz gives no samples",
+            config.duration_s,
+            config.fs
+        );
+    }
+    if !(20.0..=250.0).contains(&config.heart_rate_bpm) {
+        bail!("heart rate must be between 20 and 250 bpm");
+    }
+    if !(0.0..=0.5).contains(&config.ectopic_rate) {
+        bail!("ectopic rate must be between 0 and 0.5");
+    }
+    if config.heart_rate_std_bpm < 0.0 || config.lf_hf_ratio < 0.0 {
+        bail!("heart rate variability settings must not be negative");
+    }
+    if config.lf_std_hz <= 0.0 || config.hf_std_hz <= 0.0 {
+        bail!("spectral peak widths must be positive");
+    }
+    if config.resp_rate_bpm.is_some_and(|bpm| bpm <= 0.0) {
+        bail!("respiration rate must be positive");
+    }
+    if !config.noise.snr_db.is_finite() {
+        bail!("SNR must be finite");
+    }
+    let nyquist = config.fs / 2.0;
+    if config.noise.kinds.contains(&NoiseKind::Powerline)
+        && !(config.noise.powerline_hz > 0.0 && config.noise.powerline_hz < nyquist)
+    {
+        bail!("powerline frequency must be between 0 and the Nyquist frequency ({nyquist} Hz)");
+    }
+    Ok(())
+}
+
+/// Index of the largest sample within `radius` of `centre`.
+fn local_peak(data: &[f64], centre: usize, radius: usize) -> usize {
+    let lo = centre.saturating_sub(radius);
+    let hi = (centre + radius + 1).min(data.len());
+    (lo..hi)
+        .max_by(|&a, &b| data[a].total_cmp(&data[b]))
+        .unwrap_or(centre)
+}
+
+/// Standard normal draw (Box–Muller).
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+/// Onsets of a Poisson process with `rate_hz` in `[start, end)`, at least `gap` apart.
+fn poisson_onsets(rng: &mut StdRng, rate_hz: f64, start: f64, end: f64, gap: f64) -> Vec<f64> {
+    let mut onsets = Vec::new();
+    if rate_hz <= 0.0 {
+        return onsets;
+    }
+    let mut t = start;
+    loop {
+        t += gap - (1.0 - rng.gen::<f64>()).ln() / rate_hz;
+        if t >= end {
+            return onsets;
+        }
+        onsets.push(t);
+    }
+}
+
+/// Breathing phase with a slowly wandering rate. `phase(t)` grows by 2π per breath.
+struct Breathing {
+    hz: f64,
+    /// `(depth, frequency, phase)` of the rate modulation.
+    wander: [(f64, f64, f64); 3],
+    start: f64,
+}
+
+impl Breathing {
+    fn new(hz: f64, rng: &mut StdRng) -> Self {
+        let mut wander = [(0.0, 0.0, 0.0); 3];
+        for w in &mut wander {
+            *w = (
+                rng.gen_range(0.02..0.06),
+                rng.gen_range(0.005..0.03),
+                rng.gen_range(0.0..TAU),
+            );
+        }
+        Self {
+            hz,
+            wander,
+            start: rng.gen_range(0.0..TAU),
+        }
+    }
+
+    /// Integral of the modulated rate: 2π ∫ f (1 + Σ m sin(2πνt + ψ)) dt.
+    fn phase(&self, t: f64) -> f64 {
+        let wander: f64 = self
+            .wander
+            .iter()
+            .map(|&(m, nu, psi)| m / (TAU * nu) * (psi.cos() - (TAU * nu * t + psi).cos()))
+            .sum();
+        self.start + TAU * self.hz * (t + wander)
+    }
+}
+
+/// R-peak times (seconds, the first one before zero) and which beats are ectopic.
+struct BeatClock {
+    times: Vec<f64>,
+    ectopic: Vec<bool>,
+}
+
+impl BeatClock {
+    fn new(config: &SynthConfig, rng: &mut StdRng) -> Self {
+        let tachogram = rr_tachogram(config, rng);
+        let rr_at = |t: f64| {
+            let pos = t.max(0.0);
+            let i = (pos as usize).min(tachogram.len() - 2);
+            let frac = (pos - i as f64).min(1.0);
+            (tachogram[i] * (1.0 - frac) + tachogram[i + 1] * frac).clamp(0.25, 3.0)
+        };
+        // The first beat sits before the recording so the first P wave is complete.
+        let first = -0.5 * rr_at(0.0);
+        let mut times = vec![first];
+        let mut ectopic = vec![false];
+        let end = config.duration_s + 2.0;
+        let mut t = first;
+        while t < end {
+            let rr = rr_at(t);
+            let previous_ectopic =
+                ectopic.len() >= 2 && ectopic[ectopic.len() - 2..].contains(&true);
+            if times.len() > 2 && !previous_ectopic && rng.gen::<f64>() < config.ectopic_rate {
+                // Premature beat, then a compensatory pause: two intervals still span 2 RR.
+                times.push(t + 0.65 * rr);
+                ectopic.push(true);
+                t += 2.0 * rr;
+            } else {
+                t += rr;
+            }
+            times.push(t);
+            ectopic.push(false);
+        }
+        Self { times, ectopic }
+    }
+
+    /// Limit-cycle angle at `t` (0 at each R peak, ±π halfway between beats) and the beat
+    /// whose cycle `t` belongs to. `cursor` speeds up the lookup for increasing `t`.
+    fn angle(&self, t: f64, cursor: &mut usize) -> (f64, usize) {
+        while *cursor + 2 < self.times.len() && self.times[*cursor + 1] <= t {
+            *cursor += 1;
+        }
+        let k = *cursor;
+        let (t0, t1) = (self.times[k], self.times[k + 1]);
+        let theta = TAU * (t - t0) / (t1 - t0);
+        if theta >= PI {
+            (theta - TAU, k + 1)
+        } else {
+            (theta.max(-PI), k)
+        }
+    }
+}
+
+/// RR intervals at 1 Hz from the ECGSYN spectrum: Gaussian LF and HF peaks, random phases,
+/// scaled to the configured mean and standard deviation.
+fn rr_tachogram(config: &SynthConfig, rng: &mut StdRng) -> Vec<f64> {
+    let len = ((config.duration_s + 16.0) as usize)
+        .next_power_of_two()
+        .max(64);
+    let peak = |f: f64, centre: f64, width: f64| {
+        (-0.5 * ((f - centre) / width).powi(2)).exp() / (TAU * width * width).sqrt()
+    };
+    let mut spectrum: Vec<Complex<f64>> = (0..=len / 2)
+        .map(|k| {
+            let f = k as f64 / len as f64;
+            let power = config.lf_hf_ratio * peak(f, config.lf_hz, config.lf_std_hz)
+                + peak(f, config.hf_hz, config.hf_std_hz);
+            let phase = rng.gen_range(0.0..TAU);
+            Complex::from_polar(power.sqrt(), phase)
+        })
+        .collect();
+    // A real signal has real DC and Nyquist bins.
+    spectrum[0] = Complex::new(0.0, 0.0);
+    spectrum[len / 2].im = 0.0;
+    let mut series = vec![0.0; len];
+    let plan = RealFftPlanner::<f64>::new().plan_fft_inverse(len);
+    if plan.process(&mut spectrum, &mut series).is_err() {
+        series.fill(0.0);
+    }
+    let mean = series.iter().sum::<f64>() / len as f64;
+    let std = (series.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / len as f64).sqrt();
+    let hr = config.heart_rate_bpm;
+    let rr_mean = 60.0 / hr;
+    let rr_std = 60.0 * config.heart_rate_std_bpm / (hr * hr);
+    let scale = if std > 0.0 { rr_std / std } else { 0.0 };
+    series
+        .iter()
+        .map(|v| rr_mean + (v - mean) * scale)
+        .collect()
+}
+
+/// ECGSYN wave `(angle, amplitude, width)` for P, Q, R, S and T at the mean heart rate.
+fn waves(heart_rate_bpm: f64, ectopic: bool) -> [(f64, f64, f64); 5] {
+    let f1 = (heart_rate_bpm / 60.0).sqrt();
+    let f2 = f1.sqrt();
+    let deg = |d: f64| d.to_radians();
+    if ectopic {
+        // No P wave, a wide QRS about 1.5 times the normal R wave and a discordant T wave.
+        [
+            (deg(-70.0) * f2, 0.0, 0.25 * f1),
+            (deg(-25.0) * f1, -1.5, 0.2 * f1),
+            (0.0, 11.0, 0.2 * f1),
+            (deg(30.0) * f1, -3.0, 0.22 * f1),
+            (deg(110.0) * f2, -0.8, 0.45 * f1),
+        ]
+    } else {
+        [
+            (deg(-70.0) * f2, 1.2, 0.25 * f1),
+            (deg(-15.0) * f1, -5.0, 0.1 * f1),
+            (0.0, 30.0, 0.1 * f1),
+            (deg(15.0) * f1, -7.5, 0.1 * f1),
+            (deg(100.0) * f2, 0.75, 0.4 * f1),
+        ]
+    }
+}
+
+/// Integrates the ECGSYN z equation with RK4 (oversampled to at least 1 kHz) and rescales the
+/// result to the −0.4 to 1.2 mV range ECGSYN uses.
+fn ecgsyn(config: &SynthConfig, clock: &BeatClock, breathing: &Breathing, n: usize) -> Vec<f64> {
+    let normal = waves(config.heart_rate_bpm, false);
+    let ectopic = waves(config.heart_rate_bpm, true);
+    let oversample = (1000.0 / config.fs).ceil().max(1.0) as usize;
+    let h = 1.0 / (config.fs * oversample as f64);
+    let mut cursor = 0;
+    let mut dz = |t: f64, z: f64| {
+        let (theta, beat) = clock.angle(t, &mut cursor);
+        let params = if clock.ectopic[beat] {
+            &ectopic
+        } else {
+            &normal
+        };
+        let forcing: f64 = params
+            .iter()
+            .map(|&(angle, a, b)| {
+                let d = (theta - angle + PI).rem_euclid(TAU) - PI;
+                a * d * (-0.5 * (d / b).powi(2)).exp()
+            })
+            .sum();
+        // Respiration moves the baseline (ECGSYN's z0).
+        let z0 = 0.005 * breathing.phase(t).sin();
+        -forcing - (z - z0)
+    };
+    let mut z = 0.0;
+    let mut out = Vec::with_capacity(n);
+    for i in 0..n * oversample {
+        if i % oversample == 0 {
+            out.push(z);
+        }
+        let t = i as f64 * h;
+        let k1 = dz(t, z);
+        let k2 = dz(t + h / 2.0, z + h / 2.0 * k1);
+        let k3 = dz(t + h / 2.0, z + h / 2.0 * k2);
+        let k4 = dz(t + h, z + h * k3);
+        z += h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+    }
+    let lo = out.iter().copied().fold(f64::INFINITY, f64::min);
+    let hi = out.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if hi > lo {
+        for v in &mut out {
+            *v = (*v - lo) * 1.6 / (hi - lo) - 0.4;
+        }
+    }
+    out
+}
+
+/// Adds the configured noise mix to `signal` at `noise.snr_db` relative to the signal's
+/// variance and returns the SNR achieved over the finite samples, i.e. their variance over
+/// the mean power of the noise added to them (`None` when no noise was added). Used by
+/// [`generate`] and for noise-stress tests of real recordings.
+pub fn add_noise(signal: &mut [f64], fs: f64, noise: &NoiseConfig, seed: u64) -> Option<f64> {
+    let n = signal.len();
+    if noise.kinds.is_empty() || n < 2 {
+        return None;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut mix = vec![0.0; n];
+    for kind in &noise.kinds {
+        let mut source = noise_source(*kind, fs, n, noise.powerline_hz, &mut rng);
+        if normalise_rms(&mut source) {
+            for (m, s) in mix.iter_mut().zip(&source) {
+                *m += s;
+            }
+        }
+    }
+    let finite = signal.iter().filter(|v| v.is_finite());
+    let count = finite.clone().count().max(1) as f64;
+    let mean = finite.clone().sum::<f64>() / count;
+    let power = finite.map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+    if !normalise_rms(&mut mix) || power <= 0.0 {
+        return None;
+    }
+    let scale = (power / 10f64.powf(noise.snr_db / 10.0)).sqrt();
+    let mut added = 0.0;
+    for (v, m) in signal.iter_mut().zip(&mix) {
+        if v.is_finite() {
+            added += (m * scale).powi(2);
+        }
+        *v += m * scale;
+    }
+    Some(10.0 * (power / (added / count)).log10())
+}
+
+/// Scales `data` to unit RMS; false when it is all zero.
+fn normalise_rms(data: &mut [f64]) -> bool {
+    let rms = (data.iter().map(|v| v * v).sum::<f64>() / data.len().max(1) as f64).sqrt();
+    if rms <= 0.0 || !rms.is_finite() {
+        return false;
+    }
+    data.iter_mut().for_each(|v| *v /= rms);
+    true
+}
+
+fn noise_source(
+    kind: NoiseKind,
+    fs: f64,
+    n: usize,
+    powerline_hz: f64,
+    rng: &mut StdRng,
+) -> Vec<f64> {
+    let t = |i: usize| i as f64 / fs;
+    match kind {
+        // Sub-hertz drift with a 1/f-like spread of components.
+        NoiseKind::BaselineWander => {
+            let parts: Vec<(f64, f64, f64)> = (0..5)
+                .map(|_| {
+                    let f: f64 = rng.gen_range(0.05..0.5);
+                    (1.0 / f, f, rng.gen_range(0.0..TAU))
+                })
+                .collect();
+            (0..n)
+                .map(|i| {
+                    parts
+                        .iter()
+                        .map(|&(a, f, p)| a * (TAU * f * t(i) + p).sin())
+                        .sum()
+                })
+                .collect()
+        }
+        // Broadband muscle activity whose intensity waxes and wanes.
+        NoiseKind::Emg => {
+            let (f, p) = (rng.gen_range(0.05..0.3), rng.gen_range(0.0..TAU));
+            (0..n)
+                .map(|i| gaussian(rng) * (0.6 + 0.4 * (TAU * f * t(i) + p).sin()))
+                .collect()
+        }
+        // Sporadic transients: a baseline jump plus a decaying 1–8 Hz oscillation.
+        NoiseKind::ElectrodeMotion => {
+            let mut out = vec![0.0; n];
+            let duration = n as f64 / fs;
+            for onset in poisson_onsets(rng, 0.15, 0.0, duration, 0.5) {
+                let jump = gaussian(rng);
+                let amplitude = gaussian(rng) * 2.0;
+                let f = rng.gen_range(1.0..8.0);
+                let first = (onset * fs).ceil() as usize;
+                let last = (((onset + 3.0) * fs) as usize).min(n);
+                for (i, v) in out.iter_mut().enumerate().take(last).skip(first) {
+                    let dt = t(i) - onset;
+                    *v += jump * (-dt / 1.0).exp()
+                        + amplitude * (-dt / 0.3).exp() * (TAU * f * dt).sin();
+                }
+            }
+            out
+        }
+        NoiseKind::Powerline => {
+            let p = rng.gen_range(0.0..TAU);
+            (0..n)
+                .map(|i| {
+                    let w = TAU * powerline_hz * t(i) + p;
+                    w.sin() + 0.2 * (3.0 * w).sin()
+                })
+                .collect()
+        }
+    }
+}
+
+/// Belt signal: an asymmetric cycle (40 % inhalation) with breath-to-breath depth changes.
+fn respiration(
+    breathing: &Breathing,
+    fs: f64,
+    n: usize,
+    rng: &mut StdRng,
+) -> (Vec<f64>, Vec<usize>) {
+    const INHALE: f64 = 0.4;
+    let mut depth = 1.0;
+    let mut breath = i64::MIN;
+    let mut peaks = Vec::new();
+    let mut previous_u = 0.0;
+    let mut out = Vec::with_capacity(n);
+    for i in 0..n {
+        let cycles = breathing.phase(i as f64 / fs) / TAU;
+        if cycles.floor() as i64 != breath {
+            breath = cycles.floor() as i64;
+            depth = 1.0 + 0.15 * gaussian(rng);
+        }
+        let u = cycles.rem_euclid(1.0);
+        if i > 0 && previous_u < INHALE && u >= INHALE {
+            peaks.push(i);
+        }
+        previous_u = u;
+        let shape = if u < INHALE {
+            -(PI * u / INHALE).cos()
+        } else {
+            (PI * (u - INHALE) / (1.0 - INHALE)).cos()
+        };
+        out.push(depth * shape + 0.02 * gaussian(rng));
+    }
+    (out, peaks)
+}
+
+/// Pulse wave: a systolic and a dicrotic Gaussian per beat, arriving after the pulse transit
+/// time, with respiratory amplitude and baseline modulation. Ectopic beats eject less blood,
+/// so their pulses are weaker.
+fn ppg(
+    clock: &BeatClock,
+    breathing: &Breathing,
+    fs: f64,
+    n: usize,
+    rng: &mut StdRng,
+) -> (Vec<f64>, Vec<usize>) {
+    let mut out: Vec<f64> = (0..n)
+        .map(|i| 0.1 * breathing.phase(i as f64 / fs).sin())
+        .collect();
+    let mut peaks = Vec::new();
+    for (k, (&r, &ectopic)) in clock.times.iter().zip(&clock.ectopic).enumerate() {
+        let rr = clock.times.get(k + 1).map_or(1.0, |next| next - r);
+        let arrival = r + 0.25 + 0.005 * gaussian(rng);
+        let modulation = 1.0 + 0.15 * breathing.phase(arrival).sin();
+        let strength = if ectopic { 0.5 } else { 1.0 } * modulation;
+        let (sigma_s, sigma_d) = (0.07 * rr.sqrt(), 0.1 * rr.sqrt());
+        let dicrotic = arrival + 0.3 * rr.sqrt();
+        let first = ((arrival - 4.0 * sigma_s) * fs).floor().max(0.0) as usize;
+        let last = (((dicrotic + 4.0 * sigma_d) * fs).ceil().max(0.0) as usize).min(n);
+        for (i, v) in out.iter_mut().enumerate().take(last).skip(first) {
+            let t = i as f64 / fs;
+            *v += strength
+                * ((-0.5 * ((t - arrival) / sigma_s).powi(2)).exp()
+                    + 0.4 * (-0.5 * ((t - dicrotic) / sigma_d).powi(2)).exp());
+        }
+        let sample = (arrival * fs).round();
+        if sample >= 0.0 && (sample as usize) < n {
+            peaks.push(sample as usize);
+        }
+    }
+    let radius = (0.05 * fs).round() as usize;
+    let peaks = peaks
+        .into_iter()
+        .map(|p| local_peak(&out, p, radius))
+        .collect();
+    for v in &mut out {
+        *v += 0.01 * gaussian(rng);
+    }
+    (out, peaks)
+}
+
+/// Tonic level with slow drift plus phasic responses (Bateman function, 0.75 s rise and 2 s
+/// decay time constants) at Poisson-distributed onsets.
+fn eda(config: &SynthConfig, n: usize, rng: &mut StdRng) -> (Vec<f64>, Vec<usize>) {
+    let fs = config.fs;
+    let level = rng.gen_range(2.0..8.0);
+    let (f, p) = (rng.gen_range(0.002..0.01), rng.gen_range(0.0..TAU));
+    let mut out: Vec<f64> = (0..n)
+        .map(|i| level + 0.5 * (TAU * f * i as f64 / fs + p).sin())
+        .collect();
+    let (rise, decay) = (0.75, 2.0);
+    let bateman = |t: f64| (-t / decay).exp() - (-t / rise).exp();
+    let peak_t = rise * decay / (decay - rise) * (decay / rise).ln();
+    let norm = bateman(peak_t);
+    let onsets = poisson_onsets(
+        rng,
+        config.scr_per_min / 60.0,
+        0.5,
+        config.duration_s - 1.0,
+        2.0,
+    );
+    let mut samples = Vec::with_capacity(onsets.len());
+    for onset in onsets {
+        let amplitude = rng.gen_range(0.1..1.0);
+        let first = (onset * fs).ceil() as usize;
+        samples.push(first.min(n.saturating_sub(1)));
+        for (i, v) in out.iter_mut().enumerate().skip(first) {
+            let dt = i as f64 / fs - onset;
+            if dt > 10.0 * decay {
+                break;
+            }
+            *v += amplitude * bateman(dt) / norm;
+        }
+    }
+    for v in &mut out {
+        *v += 0.005 * gaussian(rng);
+    }
+    (out, samples)
+}
+
+/// Diameter with hippus, task-evoked dilations after each stimulus (Hoeks & Levelt, 1993:
+/// t^n e^(−nt/t_max), n = 10.1, t_max = 0.93 s) and blinks written as NaN.
+fn pupil(
+    config: &SynthConfig,
+    n: usize,
+    rng: &mut StdRng,
+) -> (Vec<f64>, Vec<usize>, Vec<[usize; 2]>) {
+    let fs = config.fs;
+    let hippus: Vec<(f64, f64, f64)> = (0..3)
+        .map(|_| {
+            (
+                rng.gen_range(0.02..0.06),
+                rng.gen_range(0.05..0.3),
+                rng.gen_range(0.0..TAU),
+            )
+        })
+        .collect();
+    let mut out: Vec<f64> = (0..n)
+        .map(|i| {
+            let t = i as f64 / fs;
+            4.0 + hippus
+                .iter()
+                .map(|&(a, f, p)| a * (TAU * f * t + p).sin())
+                .sum::<f64>()
+        })
+        .collect();
+
+    let mut stimuli = Vec::new();
+    if config.stimulus_interval_s > 0.0 {
+        let (shape, t_max) = (10.1, 0.93);
+        let kernel = |t: f64| (t / t_max).powf(shape) * (shape * (1.0 - t / t_max)).exp();
+        let mut onset = 2.0;
+        while onset < config.duration_s {
+            let amplitude = rng.gen_range(0.2..0.4);
+            let first = (onset * fs).ceil() as usize;
+            if first < n {
+                stimuli.push(first);
+            }
+            for (i, v) in out.iter_mut().enumerate().skip(first) {
+                let dt = i as f64 / fs - onset;
+                if dt > 4.0 {
+                    break;
+                }
+                *v += amplitude * kernel(dt);
+            }
+            let jitter = rng.gen_range(-0.2..0.2) * config.stimulus_interval_s;
+            onset += config.stimulus_interval_s + jitter;
+        }
+    }
+
+    let mut blinks = Vec::new();
+    for onset in poisson_onsets(
+        rng,
+        config.blink_per_min / 60.0,
+        0.0,
+        config.duration_s,
+        1.0,
+    ) {
+        let first = (onset * fs).round() as usize;
+        let end = (((onset + rng.gen_range(0.1..0.25)) * fs).round() as usize).min(n);
+        if first < end {
+            out[first..end].fill(f64::NAN);
+            blinks.push([first, end]);
+        }
+    }
+    for v in out.iter_mut().filter(|v| v.is_finite()) {
+        *v += 0.005 * gaussian(rng);
+    }
+    (out, stimuli, blinks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detectors::ecg::{detect_r_peaks_with_config, EcgPipelineConfig};
+    use crate::metrics::hrv::hrv_time;
+
+    #[test]
+    fn same_seed_same_recording() {
+        let config = SynthConfig {
+            duration_s: 20.0,
+            ..SynthConfig::default()
+        };
+        let a = generate(&config).unwrap();
+        let b = generate(&config).unwrap();
+        assert_eq!(a.ecg, b.ecg);
+        assert_eq!(a.truth.beats, b.truth.beats);
+        let c = generate(&SynthConfig { seed: 1, ..config }).unwrap();
+        assert_ne!(a.ecg, c.ecg);
+        assert_eq!(a.len(), 5000);
+    }
+
+    #[test]
+    fn a_duration_shorter_than_one_sample_is_rejected() {
+        let config = SynthConfig {
+            duration_s: 0.001,
+            ..SynthConfig::default()
+        };
+        let err = generate(&config).unwrap_err();
+        assert!(err.to_string().contains("no samples"), "{err}");
+    }
+
+    #[test]
+    fn powerline_noise_above_nyquist_is_rejected() {
+        let mut config = SynthConfig {
+            fs: 80.0,
+            duration_s: 10.0,
+            ..SynthConfig::default()
+        };
+        config.noise.kinds = vec![NoiseKind::Powerline];
+        let err = generate(&config).unwrap_err();
+        assert!(err.to_string().contains("Nyquist"), "{err}");
+        // Without powerline noise the frequency is not used.
+        config.noise.kinds.clear();
+        assert!(generate(&config).is_ok());
+    }
+
+    #[test]
+    fn heart_rate_and_variability_follow_the_config() {
+        let config = SynthConfig {
+            duration_s: 300.0,
+            heart_rate_bpm: 75.0,
+            heart_rate_std_bpm: 3.0,
+            ..SynthConfig::default()
+        };
+        let rec = generate(&config).unwrap();
+        let hrv = hrv_time(&rec.rr());
+        let hr = 60.0 / hrv.avnn;
+        assert!((hr - 75.0).abs() < 1.5, "{hr}");
+        // SDNN ≈ 60 · std / hr² = 32 ms.
+        assert!((0.02..0.045).contains(&hrv.sdnn), "{}", hrv.sdnn);
+    }
+
+    #[test]
+    fn detector_finds_the_true_beats() {
+        let rec = generate(&SynthConfig {
+            duration_s: 60.0,
+            ectopic_rate: 0.05,
+            ..SynthConfig::default()
+        })
+        .unwrap();
+        let ectopic = rec.truth.beats.iter().filter(|b| b.ectopic).count();
+        assert!(ectopic > 0);
+        let ts = crate::signal::TimeSeries {
+            fs: rec.fs,
+            data: rec.ecg.clone(),
+        };
+        let detected = detect_r_peaks_with_config(&ts, &EcgPipelineConfig::default());
+        let tolerance = (0.05 * rec.fs) as usize;
+        let matched = rec
+            .truth
+            .beats
+            .iter()
+            .filter(|b| {
+                detected
+                    .indices
+                    .iter()
+                    .any(|&d| d.abs_diff(b.sample) <= tolerance)
+            })
+            .count();
+        assert!(matched * 100 >= rec.truth.beats.len() * 95, "{matched}");
+    }
+
+    #[test]
+    fn noise_is_mixed_at_the_requested_snr() {
+        let rec = generate(&SynthConfig {
+            duration_s: 30.0,
+            ..SynthConfig::default()
+        })
+        .unwrap();
+        let clean = rec.ecg.clone();
+        let mut noisy = clean.clone();
+        let noise = NoiseConfig {
+            kinds: vec![
+                NoiseKind::BaselineWander,
+                NoiseKind::Emg,
+                NoiseKind::ElectrodeMotion,
+                NoiseKind::Powerline,
+            ],
+            snr_db: 6.0,
+            ..NoiseConfig::default()
+        };
+        let achieved = add_noise(&mut noisy, rec.fs, &noise, 7).unwrap();
+        let var = |d: &[f64]| {
+            let m = d.iter().sum::<f64>() / d.len() as f64;
+            d.iter().map(|v| (v - m).powi(2)).sum::<f64>() / d.len() as f64
+        };
+        let residual: Vec<f64> = noisy.iter().zip(&clean).map(|(a, b)| a - b).collect();
+        let power: f64 = residual.iter().map(|v| v * v).sum::<f64>() / residual.len() as f64;
+        let snr = 10.0 * (var(&clean) / power).log10();
+        assert!((snr - 6.0).abs() < 0.01, "{snr}");
+        assert!((achieved - snr).abs() < 1e-9, "{achieved} vs {snr}");
+
+        // Gaps leave the scale set by the finite samples; the SNR reported is over those.
+        let mut gappy = clean.clone();
+        let gap = gappy.len() / 2;
+        gappy[..gap].iter_mut().for_each(|v| *v = f64::NAN);
+        let mut noisy = gappy.clone();
+        let achieved = add_noise(&mut noisy, rec.fs, &noise, 7).unwrap();
+        let residual: Vec<f64> = noisy[gap..]
+            .iter()
+            .zip(&gappy[gap..])
+            .map(|(a, b)| a - b)
+            .collect();
+        let power: f64 = residual.iter().map(|v| v * v).sum::<f64>() / residual.len() as f64;
+        let snr = 10.0 * (var(&gappy[gap..]) / power).log10();
+        assert!((achieved - snr).abs() < 1e-9, "{achieved} vs {snr}");
+    }
+
+    #[test]
+    fn other_channels_carry_their_ground_truth() {
+        let rec = generate(&SynthConfig {
+            duration_s: 60.0,
+            scr_per_min: 4.0,
+            ..SynthConfig::default()
+        })
+        .unwrap();
+        let truth = &rec.truth;
+        // One pulse per beat, 250 ms or so after the R peak.
+        assert!(truth.pulse_peaks.len().abs_diff(truth.beats.len()) <= 1);
+        // 15 breaths per minute.
+        assert!((13..=17).contains(&truth.breath_peaks.len()));
+        assert!(!truth.scr_onsets.is_empty());
+        assert!(!truth.stimuli.is_empty());
+        for &[first, end] in &truth.blinks {
+            assert!(rec.pupil[first..end].iter().all(|v| v.is_nan()));
+        }
+        let events = rec.events();
+        assert!(events.windows(2).all(|w| w[0].sample <= w[1].sample));
+        assert!(rec.eda.iter().all(|v| *v > 0.0));
+    }
+}
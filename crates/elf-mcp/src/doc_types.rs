@@ -1,5 +1,6 @@
 use crate::catalog::BundleEntry;
 use elf_lib::metrics::hrv::{HRVNonlinear, HRVPsd, HRVTime};
+use elf_lib::plot::Decimation;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -320,6 +321,44 @@ pub struct SignalPreviewParams {
     pub decimate: Option<usize>,
     #[serde(default)]
     pub annotations: Option<Vec<AnnotationEvent>>,
+    /// Recording to preview samples from (any format the loader reads). Without it the
+    /// preview covers the bundle's events only.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Channel name or index; the first channel by default.
+    #[serde(default)]
+    pub signal: Option<String>,
+    /// Sampling rate for formats that do not carry one.
+    #[serde(default)]
+    pub fs: Option<f64>,
+    /// Point budget for the samples between `tmin` and `tmax` (default 1000).
+    #[serde(default)]
+    pub max_points: Option<usize>,
+    #[serde(default)]
+    pub method: Option<PreviewMethod>,
+}
+
+/// How `signal_preview` reduces samples to `max_points`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewMethod {
+    /// Lowest and highest sample per bucket, so peaks and spikes survive.
+    #[default]
+    MinMax,
+    /// Largest-Triangle-Three-Buckets.
+    Lttb,
+    /// Every Nth sample.
+    Stride,
+}
+
+impl From<PreviewMethod> for Decimation {
+    fn from(value: PreviewMethod) -> Self {
+        match value {
+            PreviewMethod::MinMax => Decimation::MinMax,
+            PreviewMethod::Lttb => Decimation::Lttb,
+            PreviewMethod::Stride => Decimation::Stride,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -339,6 +378,18 @@ pub struct SignalPreviewResult {
     pub events: Vec<EventRecord>,
     #[serde(default)]
     pub annotations: Option<Vec<AnnotationEvent>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<SignalSamples>,
+}
+
+/// Decimated `[time_s, value]` samples of a recording channel.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SignalSamples {
+    pub channel: String,
+    pub fs: f64,
+    pub total_samples: usize,
+    pub method: PreviewMethod,
+    pub points: Vec<[f64; 2]>,
 }
 
 pub type ListToolsParams = EmptyObject;
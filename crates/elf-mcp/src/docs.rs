@@ -167,8 +167,8 @@ fn build_tool_docs() -> Vec<ToolDoc> {
         ),
         doc_entry::<SignalPreviewParams, SignalPreviewResult>(
             "signal_preview",
-            "Fetch a downsampled slice of events for plotting.",
-            "Useful for rendering quick signal plots or verifying device data.",
+            "Fetch a downsampled slice of events, or of a recording's samples, for plotting.",
+            "Pass `path` for samples reduced to `max_points` (min/max per bucket by default, so peaks survive); zoomed calls reuse a cached pyramid.",
         ),
         doc_entry::<ListToolsParams, ListToolsResult>(
             "list_tools",
@@ -4,9 +4,10 @@ use crate::{
         BundleDescriptor, BundleManifestParams, CatalogIndexParams, CatalogIndexResult,
         DeriveHrvParams, DeriveHrvResult, DeviceCatalog, DeviceDescriptor, EventRecord,
         ListBundlesParams, ListBundlesResult, ListDevicesParams, ListToolsParams, ListToolsResult,
-        OpenResourceParams, OpenResourceResult, RunManifestDoc, SignalPreviewParams,
-        SignalPreviewResult, SimulateRunParams, SimulateRunResult, SimulatedBundleResources,
-        StartMode, StartRunParams, StartRunResult, TailEventsParams, TailEventsResult,
+        OpenResourceParams, OpenResourceResult, PreviewMethod, RunManifestDoc, SignalPreviewParams,
+        SignalPreviewResult, SignalSamples, SimulateRunParams, SimulateRunResult,
+        SimulatedBundleResources, StartMode, StartRunParams, StartRunResult, TailEventsParams,
+        TailEventsResult,
     },
     docs::DocRegistry,
     resources::{Resource, ResourceResolver},
@@ -15,8 +16,10 @@ use anyhow::{anyhow, Context, Result};
 use base64::{engine::general_purpose, Engine as _};
 use csv::{ReaderBuilder, Trim};
 use elf_lib::{
+    io::loader::{load_recording, LoadOptions},
     metrics::hrv::{hrv_nonlinear, hrv_psd, hrv_time},
-    signal::RRSeries,
+    plot::{Decimation, SignalPyramid},
+    signal::{RRSeries, TimeSeries},
 };
 use elf_run::{
     read_design, read_trials, simulate_run as simulate_bundle, write_events_json, write_events_tsv,
@@ -26,11 +29,14 @@ use log::info;
 use serde_json::{json, Value};
 use std::{
     cell::RefCell,
+    fs,
     path::Path,
+    rc::Rc,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::SystemTime,
 };
 use tempfile::TempDir;
 
@@ -40,8 +46,25 @@ pub struct ToolRegistry<'a> {
     docs: Arc<DocRegistry>,
     temp_dirs: RefCell<Vec<TempDir>>,
     temp_counter: AtomicUsize,
+    /// Recordings opened by `signal_preview`, so later zoomed previews only read the
+    /// requested window from the pyramid. Least recently used first.
+    previews: RefCell<Vec<(PreviewKey, Rc<CachedSignal>)>>,
 }
 
+/// Path, channel selector, fallback sampling rate (as bits) and modification time of a
+/// previewed recording; a different rate or a rewritten file loads it afresh.
+type PreviewKey = (String, Option<String>, Option<u64>, Option<SystemTime>);
+
+struct CachedSignal {
+    channel: String,
+    series: TimeSeries,
+    pyramid: SignalPyramid,
+}
+
+const DEFAULT_PREVIEW_POINTS: usize = 1000;
+/// Recordings kept open for `signal_preview`.
+const PREVIEW_CACHE_SIZE: usize = 8;
+
 impl<'a> ToolRegistry<'a> {
     pub fn new(
         catalog: &'a Catalog,
@@ -54,6 +77,7 @@ impl<'a> ToolRegistry<'a> {
             docs,
             temp_dirs: RefCell::new(Vec::new()),
             temp_counter: AtomicUsize::new(0),
+            previews: RefCell::new(Vec::new()),
         }
     }
 
@@ -231,13 +255,25 @@ impl<'a> ToolRegistry<'a> {
                 let input: SignalPreviewParams = serde_json::from_value(params)?;
                 let stream = input
                     .stream
+                    .clone()
                     .or_else(|| input.run.clone())
+                    .or_else(|| input.path.clone())
                     .unwrap_or_else(|| "events".into());
                 let tmin = input.tmin.unwrap_or(f64::MIN);
                 let tmax = input.tmax.unwrap_or(f64::MAX);
                 let decimate = input.decimate.unwrap_or(1).max(1);
-                let (events, resource_uri) =
-                    self.events_for_source(input.run.as_deref(), input.tmp_id.as_deref())?;
+                let signal = input
+                    .path
+                    .as_deref()
+                    .map(|path| self.preview_samples(path, &input, tmin, tmax))
+                    .transpose()?;
+                // A recording on its own has no bundle events to list.
+                let (events, resource_uri) = match &input.path {
+                    Some(path) if input.run.is_none() && input.tmp_id.is_none() => {
+                        (Vec::new(), path.clone())
+                    }
+                    _ => self.events_for_source(input.run.as_deref(), input.tmp_id.as_deref())?,
+                };
                 let preview: Vec<EventRecord> = events
                     .into_iter()
                     .filter(|event| event.onset >= tmin && event.onset <= tmax)
@@ -253,6 +289,7 @@ impl<'a> ToolRegistry<'a> {
                     decimate,
                     events: preview,
                     annotations: input.annotations.clone(),
+                    signal,
                 };
                 Ok(serde_json::to_value(response)?)
             }
@@ -260,6 +297,78 @@ impl<'a> ToolRegistry<'a> {
         }
     }
 
+    fn preview_samples(
+        &self,
+        path: &str,
+        input: &SignalPreviewParams,
+        tmin: f64,
+        tmax: f64,
+    ) -> Result<SignalSamples> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let key = (
+            path.to_string(),
+            input.signal.clone(),
+            input.fs.map(f64::to_bits),
+            modified,
+        );
+        let hit = {
+            let mut previews = self.previews.borrow_mut();
+            let at = previews.iter().position(|(k, _)| *k == key);
+            at.map(|at| {
+                let entry = previews.remove(at);
+                let cached = entry.1.clone();
+                previews.push(entry);
+                cached
+            })
+        };
+        let cached = match hit {
+            Some(cached) => cached,
+            None => {
+                let recording = load_recording(
+                    Path::new(path),
+                    &LoadOptions {
+                        fallback_fs: input.fs,
+                    },
+                )
+                .with_context(|| format!("loading {}", path))?;
+                let series = recording.time_series(input.signal.as_deref())?;
+                let channel = match input.signal.as_deref() {
+                    Some(selector) => recording.channel(selector).map(|c| c.name.clone()),
+                    None => recording.channels.first().map(|c| c.name.clone()),
+                }
+                .unwrap_or_default();
+                let cached = Rc::new(CachedSignal {
+                    channel,
+                    pyramid: SignalPyramid::new(&series),
+                    series,
+                });
+                let mut previews = self.previews.borrow_mut();
+                if previews.len() >= PREVIEW_CACHE_SIZE {
+                    previews.remove(0);
+                }
+                previews.push((key, cached.clone()));
+                cached
+            }
+        };
+        let max_points = input.max_points.unwrap_or(DEFAULT_PREVIEW_POINTS).max(2);
+        let method = input.method.unwrap_or_default();
+        let series = &cached.series;
+        let points = match method {
+            PreviewMethod::MinMax => cached.pyramid.window(series, tmin, tmax, max_points),
+            other => {
+                let window = cached.pyramid.window(series, tmin, tmax, usize::MAX);
+                Decimation::from(other).apply(&window, max_points)
+            }
+        };
+        Ok(SignalSamples {
+            channel: cached.channel.clone(),
+            fs: series.fs,
+            total_samples: series.len(),
+            method,
+            points,
+        })
+    }
+
     fn device_catalog() -> DeviceCatalog {
         DeviceCatalog {
             devices: vec![
@@ -425,3 +534,43 @@ impl<'a> ToolRegistry<'a> {
         info!("Resource resolver available: {:p}", self.resolver);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_cache_keys_on_fs_and_stays_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let samples: String = (0..400).map(|i| format!("{}\n", i % 7)).collect();
+        let catalog = Catalog::default();
+        let docs = Arc::new(DocRegistry::default());
+        let resolver = ResourceResolver::new(&catalog, docs.clone());
+        let registry = ToolRegistry::new(&catalog, &resolver, docs);
+        let params = |fs: f64| -> SignalPreviewParams {
+            serde_json::from_value(json!({ "fs": fs })).unwrap()
+        };
+
+        let path = dir.path().join("signal.txt");
+        fs::write(&path, &samples).unwrap();
+        let path = path.to_str().unwrap();
+        let slow = registry
+            .preview_samples(path, &params(100.0), 0.0, 4.0)
+            .unwrap();
+        let fast = registry
+            .preview_samples(path, &params(200.0), 0.0, 4.0)
+            .unwrap();
+        assert_eq!(slow.fs, 100.0);
+        assert_eq!(fast.fs, 200.0);
+        assert!(fast.points.last().unwrap()[0] < slow.points.last().unwrap()[0]);
+
+        for i in 0..PREVIEW_CACHE_SIZE + 3 {
+            let other = dir.path().join(format!("other{i}.txt"));
+            fs::write(&other, &samples).unwrap();
+            registry
+                .preview_samples(other.to_str().unwrap(), &params(100.0), 0.0, 1.0)
+                .unwrap();
+        }
+        assert_eq!(registry.previews.borrow().len(), PREVIEW_CACHE_SIZE);
+    }
+}
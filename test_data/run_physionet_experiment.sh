@@ -85,3 +85,6 @@ print(
     f"(permutation p ≈ {p_value:.3f}, {trials} permutations)"
 )
 PY
+
+echo -e "\nDetector leaderboard (clean and noise-stressed)"
+elf_cmd bench-detectors --dir "$RECORD_DIR" --snr-db 12,6,0 --format table